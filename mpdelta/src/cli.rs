@@ -0,0 +1,456 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use mpdelta_core::common::mixed_fraction::MixedFraction;
+use mpdelta_core::component::class::ComponentClass;
+use mpdelta_core::component::parameter::ParameterValueType;
+use mpdelta_core::component::processor::ImageSize;
+use mpdelta_core::core::IdGenerator;
+use mpdelta_core::edit::RootComponentEditCommand;
+use mpdelta_core::encode::{EncodeMonitor, EncodePhase};
+use mpdelta_core::project::{ProjectHandle, RootComponentClassHandle};
+use mpdelta_core::time::{FrameRate, TimelineTime};
+use mpdelta_core::usecase::{EditUsecase, GetRootComponentClassesUsecase, LoadProjectUsecase, RenderRangeUsecase};
+use mpdelta_multimedia::{AudioCodec, CodecImplement, FileFormat, VideoCodec};
+use mpdelta_renderer::EncodePipelineConfig;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+pub struct Cli {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+    /// エンコード時に先行して同時に描画するフレーム数の上限 省略時はCPUのスレッド数
+    #[clap(long, global = true)]
+    encode_concurrency: Option<usize>,
+    /// エンコード待ちのフレームに使ってよいメモリ量の上限(MiB)
+    #[clap(long, global = true)]
+    encode_memory_budget: Option<usize>,
+}
+
+impl Cli {
+    /// GUIからの書き出しとrenderサブコマンドの両方で使う設定
+    pub fn encode_pipeline(&self) -> EncodePipelineConfig {
+        let default = EncodePipelineConfig::default();
+        EncodePipelineConfig {
+            concurrency: self.encode_concurrency.unwrap_or(default.concurrency),
+            memory_budget: self.encode_memory_budget.map_or(default.memory_budget, |mib| mib.saturating_mul(1 << 20)),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// GUIを起動せずにプロジェクトをエンコードする
+    Render(RenderArgs),
+}
+
+/// renderサブコマンドで描画に使う実装
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// Vulkanを使ってGPUで描画する
+    Vulkan,
+    /// GPUを使わずにCPUで描画する 遅いがVulkanを初期化しない
+    Cpu,
+}
+
+#[derive(Args, Debug)]
+pub struct RenderArgs {
+    /// 読み込むプロジェクトファイル(.mpdl)
+    project: PathBuf,
+    /// 出力先 画像の連番は`frame_%05d.png`のように指定する
+    #[clap(long, short)]
+    output: PathBuf,
+    /// ルートコンポーネントの番号(0始まり)またはUUID
+    #[clap(long, default_value = "0")]
+    root: String,
+    /// 出力フォーマット 省略時は出力先の拡張子から決める
+    #[clap(long, value_parser = parse_file_format)]
+    format: Option<FileFormat>,
+    #[clap(long, value_parser = parse_video_codec)]
+    video_codec: Option<VideoCodec>,
+    #[clap(long, value_parser = parse_audio_codec)]
+    audio_codec: Option<AudioCodec>,
+    #[clap(long)]
+    width: Option<u32>,
+    #[clap(long)]
+    height: Option<u32>,
+    /// `60`や`30000/1001`の形式で指定する
+    #[clap(long, value_parser = parse_frame_rate)]
+    fps: Option<FrameRate>,
+    /// 書き出しを始める時刻 `90.5`のような秒数か`1:30.5`のような`[時:]分:秒`で指定する
+    #[clap(long = "in", value_parser = parse_time)]
+    in_point: Option<TimelineTime>,
+    /// 書き出しを終える時刻 形式は`--in`と同じ
+    #[clap(long = "out", value_parser = parse_time)]
+    out_point: Option<TimelineTime>,
+    #[clap(long, value_enum, default_value_t = Backend::Vulkan)]
+    pub backend: Backend,
+}
+
+/// renderサブコマンドの失敗 それぞれ別の終了コードを返す
+///
+/// 引数の誤りはclapが終了コード2で終了させる 描画に使うGPUを初期化できなければ8を返す
+#[derive(Debug, Error)]
+pub enum RenderCommandError {
+    #[error("failed to load project: {0}")]
+    LoadProject(Box<dyn Error + Send>),
+    #[error("root component {0} not found")]
+    RootComponentNotFound(String),
+    #[error("cannot determine output format of {}", .0.display())]
+    UnknownFormat(PathBuf),
+    #[error("no encoder available for {0:?} with video codec {1:?} and audio codec {2:?}")]
+    UnsupportedCodec(FileFormat, Option<VideoCodec>, Option<AudioCodec>),
+    #[error("--in must be earlier than --out")]
+    InvalidRange,
+    #[error("invalid output settings: {0}")]
+    InvalidSettings(Box<dyn Error + Send>),
+    #[error("cannot write to {}: {1}", .0.display())]
+    Output(PathBuf, io::Error),
+    #[error("failed to encode: {0}")]
+    Encode(Box<dyn Error + Send>),
+    #[error("failed to initialize GPU: {0}")]
+    InitializeGpu(Box<dyn Error + Send>),
+}
+
+impl RenderCommandError {
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            RenderCommandError::LoadProject(_) => ExitCode::from(3),
+            RenderCommandError::RootComponentNotFound(_) => ExitCode::from(4),
+            RenderCommandError::UnknownFormat(_) | RenderCommandError::UnsupportedCodec(..) | RenderCommandError::InvalidRange | RenderCommandError::InvalidSettings(_) => ExitCode::from(5),
+            RenderCommandError::Output(..) => ExitCode::from(7),
+            RenderCommandError::Encode(_) => ExitCode::from(6),
+            RenderCommandError::InitializeGpu(_) => ExitCode::from(8),
+        }
+    }
+}
+
+const ALL_FILE_FORMATS: [FileFormat; 10] = [FileFormat::Mp4, FileFormat::Webm, FileFormat::Mov, FileFormat::Mkv, FileFormat::Mp3, FileFormat::Wav, FileFormat::Flac, FileFormat::Png, FileFormat::Jpeg, FileFormat::Webp];
+
+fn parse_file_format(s: &str) -> Result<FileFormat, String> {
+    let s = s.to_ascii_lowercase();
+    match s.as_str() {
+        "jpg" => Ok(FileFormat::Jpeg),
+        "matroska" => Ok(FileFormat::Mkv),
+        _ => ALL_FILE_FORMATS.into_iter().find(|format| format.extension() == s).ok_or_else(|| format!("unknown file format: {s}")),
+    }
+}
+
+fn parse_video_codec(s: &str) -> Result<VideoCodec, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "h264" => Ok(VideoCodec::H264),
+        "h265" | "hevc" => Ok(VideoCodec::H265),
+        "av1" => Ok(VideoCodec::Av1),
+        "vp9" => Ok(VideoCodec::Vp9),
+        "prores" => Ok(VideoCodec::ProRes),
+        "ffv1" => Ok(VideoCodec::Ffv1),
+        "png" => Ok(VideoCodec::Png),
+        "jpeg" | "jpg" => Ok(VideoCodec::Jpeg),
+        "webp" => Ok(VideoCodec::Webp),
+        _ => Err(format!("unknown video codec: {s}")),
+    }
+}
+
+fn parse_audio_codec(s: &str) -> Result<AudioCodec, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "mp3" => Ok(AudioCodec::Mp3),
+        "aac" => Ok(AudioCodec::Aac),
+        "flac" => Ok(AudioCodec::Flac),
+        "opus" => Ok(AudioCodec::Opus),
+        "pcm_s16" => Ok(AudioCodec::PcmS16),
+        "pcm_s24" => Ok(AudioCodec::PcmS24),
+        "pcm_f32" => Ok(AudioCodec::PcmF32),
+        _ => Err(format!("unknown audio codec: {s}")),
+    }
+}
+
+fn parse_frame_rate(s: &str) -> Result<FrameRate, String> {
+    let (numerator, denominator) = s.split_once('/').unwrap_or((s, "1"));
+    let numerator = numerator.trim().parse().map_err(|_| format!("invalid frame rate: {s}"))?;
+    let denominator = denominator.trim().parse().map_err(|_| format!("invalid frame rate: {s}"))?;
+    FrameRate::new(numerator, denominator).ok_or_else(|| format!("invalid frame rate: {s}"))
+}
+
+fn parse_time(s: &str) -> Result<TimelineTime, String> {
+    let invalid = || format!("invalid time: {s}");
+    let mut parts = s.trim().rsplit(':');
+    let seconds = parts.next().ok_or_else(invalid)?.parse::<f64>().map_err(|_| invalid())?;
+    let minutes = parts.next().map_or(Ok(0), str::parse::<u32>).map_err(|_| invalid())?;
+    let hours = parts.next().map_or(Ok(0), str::parse::<u32>).map_err(|_| invalid())?;
+    if parts.next().is_some() || !seconds.is_finite() || seconds < 0. {
+        return Err(invalid());
+    }
+    Ok(TimelineTime::new(MixedFraction::from_f64((hours as f64 * 60. + minutes as f64) * 60. + seconds)))
+}
+
+fn infer_file_format(output: &Path) -> Option<FileFormat> {
+    parse_file_format(output.extension()?.to_str()?).ok()
+}
+
+/// フォーマットごとの既定のコーデック
+fn default_codecs(format: FileFormat) -> (Option<VideoCodec>, Option<AudioCodec>) {
+    match format {
+        FileFormat::Mp4 | FileFormat::Mov | FileFormat::Mkv => (Some(VideoCodec::H264), Some(AudioCodec::Aac)),
+        FileFormat::Webm => (Some(VideoCodec::Vp9), Some(AudioCodec::Opus)),
+        FileFormat::Mp3 => (None, Some(AudioCodec::Mp3)),
+        FileFormat::Wav => (None, Some(AudioCodec::PcmS16)),
+        FileFormat::Flac => (None, Some(AudioCodec::Flac)),
+        FileFormat::Png => (Some(VideoCodec::Png), None),
+        FileFormat::Jpeg => (Some(VideoCodec::Jpeg), None),
+        FileFormat::Webp => (Some(VideoCodec::Webp), None),
+    }
+}
+
+/// 書き出し先に書き込めるかを確かめる 失敗しても書き出し先にファイルを残さない
+///
+/// 連番画像ではディレクトリがあるかだけを見る
+fn check_output(output: &Path, format: FileFormat) -> Result<(), RenderCommandError> {
+    let directory = output.parent().filter(|directory| !directory.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let result = if !directory.is_dir() {
+        Err(io::Error::new(io::ErrorKind::NotFound, format!("directory {} does not exist", directory.display())))
+    } else if format.is_image() {
+        Ok(())
+    } else if output.is_dir() {
+        Err(io::Error::new(io::ErrorKind::Other, "output is a directory"))
+    } else if output.exists() {
+        // 上書きするかどうかはエンコーダが決めるので、既存のファイルは中身を変えずに開けるかだけを見る
+        OpenOptions::new().write(true).open(output).map(drop)
+    } else {
+        // 実際に作れるかを試し、作ったファイルはすぐに消す
+        OpenOptions::new().write(true).create_new(true).open(output).and_then(|file| {
+            drop(file);
+            fs::remove_file(output)
+        })
+    };
+    result.map_err(|err| RenderCommandError::Output(output.to_path_buf(), err))
+}
+
+async fn select_root_component<T, Core>(core: &Core, project: &ProjectHandle<T>, root: &str) -> Option<RootComponentClassHandle<T>>
+where
+    T: ParameterValueType,
+    Core: GetRootComponentClassesUsecase<T>,
+{
+    let root_component_classes = core.get_root_component_classes(project).await;
+    if let Ok(index) = root.parse::<usize>() {
+        return root_component_classes.get(index).cloned();
+    }
+    for handle in root_component_classes.iter() {
+        let Some(root_component_class) = handle.upgrade() else {
+            continue;
+        };
+        if root_component_class.read().await.id().to_string().eq_ignore_ascii_case(root) {
+            return Some(handle.clone());
+        }
+    }
+    None
+}
+
+pub async fn render<T, Core, Encoder>(core: &Core, id_generator: &dyn IdGenerator, video_codecs: &[CodecImplement<VideoCodec, Encoder>], audio_codecs: &[CodecImplement<AudioCodec, Encoder>], args: RenderArgs) -> Result<(), RenderCommandError>
+where
+    T: ParameterValueType,
+    Core: LoadProjectUsecase<T> + GetRootComponentClassesUsecase<T> + EditUsecase<T> + RenderRangeUsecase<T, Encoder>,
+{
+    let RenderArgs {
+        project,
+        output,
+        root,
+        format,
+        video_codec,
+        audio_codec,
+        width,
+        height,
+        fps,
+        in_point,
+        out_point,
+        backend: _,
+    } = args;
+    // 指定がなければ全体を書き出す 長さを超える分は描画時に切り詰められる
+    let range = in_point.unwrap_or(TimelineTime::ZERO)..out_point.unwrap_or(TimelineTime::MAX);
+    if range.start >= range.end {
+        return Err(RenderCommandError::InvalidRange);
+    }
+    let format = format.or_else(|| infer_file_format(&output)).ok_or_else(|| RenderCommandError::UnknownFormat(output.clone()))?;
+    let (default_video_codec, default_audio_codec) = default_codecs(format);
+    let video_codec = video_codec.or(default_video_codec);
+    let audio_codec = audio_codec.or(default_audio_codec);
+
+    let project = core.load_project(&project).await.map_err(|err| RenderCommandError::LoadProject(Box::new(err)))?;
+    let root_component_class = select_root_component(core, &project, &root).await.ok_or_else(|| RenderCommandError::RootComponentNotFound(root.clone()))?;
+    let root_component_class_ref = root_component_class.upgrade().ok_or_else(|| RenderCommandError::RootComponentNotFound(root.clone()))?;
+
+    // サイズとフレームレートの指定はメモリ上のプロジェクトにだけ反映し、ファイルには書き戻さない
+    if width.is_some() || height.is_some() {
+        let (image_size, pixel_aspect_ratio) = {
+            let root_component_class = root_component_class_ref.read().await;
+            let item = root_component_class.get();
+            (item.image_size(), item.pixel_aspect_ratio())
+        };
+        let image_size = ImageSize {
+            width: width.unwrap_or(image_size.width),
+            height: height.unwrap_or(image_size.height),
+        };
+        core.edit(&root_component_class, RootComponentEditCommand::EditImageSize(image_size, pixel_aspect_ratio)).await.map_err(|err| RenderCommandError::InvalidSettings(Box::new(err)))?;
+    }
+    if let Some(fps) = fps {
+        core.edit(&root_component_class, RootComponentEditCommand::EditFrameRate(fps)).await.map_err(|err| RenderCommandError::InvalidSettings(Box::new(err)))?;
+    }
+    let (frame_rate, image_size, pixel_aspect_ratio) = {
+        let root_component_class = root_component_class_ref.read().await;
+        let item = root_component_class.get();
+        (item.frame_rate(), item.image_size(), item.pixel_aspect_ratio())
+    };
+
+    let unsupported = || RenderCommandError::UnsupportedCodec(format, video_codec, audio_codec);
+    let video = match video_codec {
+        Some(codec) => Some(video_codecs.iter().find(|implement| implement.codec() == codec && implement.handler().supports(format, video_codec, audio_codec)).ok_or_else(unsupported)?),
+        None => None,
+    };
+    let audio = match audio_codec {
+        Some(codec) => Some(
+            audio_codecs
+                .iter()
+                .find(|implement| implement.codec() == codec && implement.handler().supports(format, video_codec, audio_codec) && video.is_none_or(|video| video.handler().eq(&**implement.handler())))
+                .ok_or_else(unsupported)?,
+        ),
+        None => None,
+    };
+    let handler = video.map(CodecImplement::handler).or(audio.map(CodecImplement::handler)).ok_or_else(unsupported)?;
+    let video_options = video.map(|video| {
+        let mut options = video.default_codec_options();
+        options.set_width(image_size.width);
+        options.set_height(image_size.height);
+        options.set_frame_rate(frame_rate.numerator(), frame_rate.denominator());
+        options.set_pixel_aspect_ratio(pixel_aspect_ratio.numerator(), pixel_aspect_ratio.denominator());
+        (video.codec(), options)
+    });
+    let audio_options = audio.map(|audio| (audio.codec(), audio.default_codec_options()));
+    check_output(&output, format)?;
+    let encoder = handler.create_encoder(format, video_options, audio_options, &output);
+
+    let instance = root_component_class_ref.read().await.instantiate(&root_component_class.clone().map(|weak| weak as _), id_generator).await;
+    let (monitor, mut progress) = EncodeMonitor::new();
+    let reporter = tokio::spawn(async move {
+        let mut last_reported = None;
+        while progress.changed().await.is_ok() {
+            let Some(progress) = *progress.borrow_and_update() else {
+                continue;
+            };
+            // 1%刻みで表示する
+            let step = (progress.phase, (progress.fraction() * 100.).floor() as u32);
+            if last_reported == Some(step) {
+                continue;
+            }
+            last_reported = Some(step);
+            match progress.phase {
+                EncodePhase::Audio if progress.total_frames == 0 => eprintln!("rendering audio"),
+                EncodePhase::Audio => eprintln!("encoding audio ({}%)", step.1),
+                EncodePhase::Video => eprintln!("{} of {} frames rendered ({}%)", progress.frames_done, progress.total_frames, step.1),
+                EncodePhase::Finalize => eprintln!("finalizing"),
+            }
+        }
+    });
+    let result = core.render_range_and_encode(Arc::new(instance), range, encoder, monitor).await;
+    let _ = reporter.await;
+    result.map_err(|err| RenderCommandError::Encode(Box::new(err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_frame_rate() {
+        assert_eq!(parse_frame_rate("60"), Ok(FrameRate::FPS_60));
+        assert_eq!(parse_frame_rate("30000/1001"), Ok(FrameRate::FPS_29_97));
+        assert_eq!(parse_frame_rate("48/2"), Ok(FrameRate::FPS_24));
+        assert!(parse_frame_rate("0").is_err());
+        assert!(parse_frame_rate("30/0").is_err());
+        assert!(parse_frame_rate("fast").is_err());
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("0"), Ok(TimelineTime::ZERO));
+        assert_eq!(parse_time("2.5"), Ok(TimelineTime::new(MixedFraction::new(2, 1, 2))));
+        assert_eq!(parse_time("1:30"), Ok(TimelineTime::new(MixedFraction::from_integer(90))));
+        assert_eq!(parse_time("1:00:01.25"), Ok(TimelineTime::new(MixedFraction::new(3601, 1, 4))));
+        assert!(parse_time("-1").is_err());
+        assert!(parse_time("1:2:3:4").is_err());
+        assert!(parse_time("a:10").is_err());
+        assert!(parse_time("").is_err());
+    }
+
+    #[test]
+    fn test_check_output() {
+        let directory = std::env::temp_dir().join(format!("mpdelta_check_output_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        assert!(check_output(&directory.join("out.mp4"), FileFormat::Mp4).is_ok());
+        assert!(!directory.join("out.mp4").exists());
+        assert!(check_output(&directory.join("frame_%05d.png"), FileFormat::Png).is_ok());
+        // 既存のファイルは中身を変えない
+        std::fs::write(directory.join("existing.wav"), b"data").unwrap();
+        assert!(check_output(&directory.join("existing.wav"), FileFormat::Wav).is_ok());
+        assert_eq!(std::fs::read(directory.join("existing.wav")).unwrap(), b"data");
+        let missing = directory.join("missing");
+        assert!(matches!(check_output(&missing.join("out.mp4"), FileFormat::Mp4), Err(RenderCommandError::Output(..))));
+        assert!(matches!(check_output(&missing.join("frame_%05d.png"), FileFormat::Png), Err(RenderCommandError::Output(..))));
+        assert!(!missing.exists());
+        // ディレクトリには書き出せない
+        assert!(matches!(check_output(&directory, FileFormat::Wav), Err(RenderCommandError::Output(..))));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_infer_file_format() {
+        assert_eq!(infer_file_format(Path::new("out/video.mp4")), Some(FileFormat::Mp4));
+        assert_eq!(infer_file_format(Path::new("OUT.MKV")), Some(FileFormat::Mkv));
+        assert_eq!(infer_file_format(Path::new("frames/frame_%05d.jpg")), Some(FileFormat::Jpeg));
+        assert_eq!(infer_file_format(Path::new("audio.wav")), Some(FileFormat::Wav));
+        assert_eq!(infer_file_format(Path::new("noext")), None);
+        assert_eq!(infer_file_format(Path::new("video.avi")), None);
+    }
+
+    #[test]
+    fn test_cli_parse() {
+        let cli = Cli::try_parse_from(["mpdelta"]).unwrap();
+        assert!(cli.command.is_none());
+        let cli = Cli::try_parse_from(["mpdelta", "render", "project.mpdl", "-o", "out.webm", "--root", "1", "--video-codec", "av1", "--fps", "24000/1001", "--width", "1280"]).unwrap();
+        let Some(Command::Render(args)) = cli.command else {
+            panic!("expected render subcommand");
+        };
+        assert_eq!(args.project, Path::new("project.mpdl"));
+        assert_eq!(args.output, Path::new("out.webm"));
+        assert_eq!(args.root, "1");
+        assert_eq!(args.video_codec, Some(VideoCodec::Av1));
+        assert_eq!(args.audio_codec, None);
+        assert_eq!(args.fps, FrameRate::new(24000, 1001));
+        assert_eq!(args.width, Some(1280));
+        assert_eq!(args.in_point, None);
+        assert_eq!(args.backend, Backend::Vulkan);
+        let cli = Cli::try_parse_from(["mpdelta", "render", "project.mpdl", "-o", "out.mp4", "--in", "1.5", "--out", "0:04"]).unwrap();
+        let Some(Command::Render(args)) = cli.command else {
+            panic!("expected render subcommand");
+        };
+        assert_eq!(args.in_point, Some(TimelineTime::new(MixedFraction::new(1, 1, 2))));
+        assert_eq!(args.out_point, Some(TimelineTime::new(MixedFraction::from_integer(4))));
+        let cli = Cli::try_parse_from(["mpdelta", "render", "project.mpdl", "-o", "out.mp4", "--backend", "cpu"]).unwrap();
+        let Some(Command::Render(args)) = cli.command else {
+            panic!("expected render subcommand");
+        };
+        assert_eq!(args.backend, Backend::Cpu);
+        assert!(Cli::try_parse_from(["mpdelta", "render", "project.mpdl", "-o", "out.mp4", "--audio-codec", "vorbis"]).is_err());
+    }
+
+    #[test]
+    fn test_encode_pipeline() {
+        let cli = Cli::try_parse_from(["mpdelta"]).unwrap();
+        assert_eq!(cli.encode_pipeline(), EncodePipelineConfig::default());
+        let cli = Cli::try_parse_from(["mpdelta", "--encode-concurrency", "2", "render", "project.mpdl", "-o", "out.mp4", "--encode-memory-budget", "256"]).unwrap();
+        assert_eq!(cli.encode_pipeline(), EncodePipelineConfig { concurrency: 2, memory_budget: 256 << 20 });
+    }
+}
@@ -1,15 +1,23 @@
 use ash::vk;
 use async_trait::async_trait;
+use clap::Parser;
 use cpal::traits::HostTrait;
 use futures::{pin_mut, stream, FutureExt, StreamExt};
 use mpdelta_audio_mixer::MPDeltaAudioMixerBuilder;
+use mpdelta_component_audio_generator::AudioGeneratorClass;
+use mpdelta_component_common::image_uploader::WithImageUploader;
+use mpdelta_component_gradient::GradientClass;
+use mpdelta_component_image_sequence::ImageSequenceClass;
 use mpdelta_component_multimedia_loader::FfmpegMultimediaLoaderClass;
 use mpdelta_component_parameters::file_reader::FileReaderParamManager;
 use mpdelta_component_rectangle::RectangleClass;
+use mpdelta_component_shape::ShapeClass;
 use mpdelta_component_sine_audio::SineAudio;
 use mpdelta_component_text_renderer::TextRendererClass;
+use mpdelta_component_vector_path::VectorPathClass;
 use mpdelta_core::component::class::{ComponentClass, ComponentClassIdentifier};
-use mpdelta_core::component::parameter::value::{DynEditableLerpEasingValueManager, DynEditableSelfValueManager, LinearEasing};
+use mpdelta_core::component::parameter::value::easing::standard_easings;
+use mpdelta_core::component::parameter::value::{DynEditableLerpEasingValueManager, DynEditablePlainValueManager, DynEditableSelfValueManager, Easing, PlainValue};
 use mpdelta_core::component::parameter::{AbstractFile, ParameterAllValues, ParameterValueRaw, ParameterValueType};
 use mpdelta_core::core::{ComponentClassLoader, MPDeltaCore, MPDeltaCoreArgs, NewWithArgs};
 use mpdelta_core::ptr::{StaticPointer, StaticPointerOwned};
@@ -18,10 +26,10 @@ use mpdelta_core_vulkano::ImageType;
 use mpdelta_gui::viewmodel::ViewModelParamsImpl;
 use mpdelta_gui_audio_player_cpal::CpalAudioPlayer;
 use mpdelta_gui_wgpu::MPDeltaGUIWgpu;
-use mpdelta_multimedia_encoder_ffmpeg::{FfmpegEncodeSettings, FfmpegEncoderBuilder};
+use mpdelta_multimedia_encoder_ffmpeg::{CpuReadback, FfmpegEncodeSettings, FfmpegEncoderBuilder};
 use mpdelta_processor_cache_moka::MokaCache;
 use mpdelta_project_serialize::MPDeltaProjectSerializer;
-use mpdelta_renderer::MPDeltaRendererBuilder;
+use mpdelta_renderer::{EncodePipelineConfig, MPDeltaRendererBuilder};
 use mpdelta_rendering_controller::LookaheadRenderingControllerBuilder;
 use mpdelta_services::easing_loader::InMemoryEasingLoader;
 use mpdelta_services::history::InMemoryEditHistoryStore;
@@ -32,25 +40,35 @@ use mpdelta_services::project_store::InMemoryProjectStore;
 use mpdelta_services::value_manager_loader::InMemoryValueManagerLoader;
 use mpdelta_video_renderer_vulkano::ImageCombinerBuilder;
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::CStr;
 use std::fs::File;
+use std::marker::PhantomData;
 use std::os::raw::c_char;
+use std::process::ExitCode;
 use std::sync::Arc;
+use thiserror::Error;
 use tokio::runtime::Runtime;
 use tokio::sync::RwLock;
 use vulkano::command_buffer::allocator::{CommandBufferAllocator, StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo};
 use vulkano::device::physical::PhysicalDeviceType;
 use vulkano::device::{Device, DeviceCreateInfo, DeviceExtensions, DeviceFeatures, QueueCreateInfo, QueueFlags};
 use vulkano::instance::InstanceExtensions;
+use vulkano::library::LoadingError;
 use vulkano::memory::allocator::StandardMemoryAllocator;
-use vulkano::{VulkanLibrary, VulkanObject};
+use vulkano::{Validated, VulkanError, VulkanLibrary, VulkanObject};
 use wgpu::MemoryHints;
 
-struct ValueType;
+mod cli;
 
-impl ParameterValueType for ValueType {
-    type Image = ImageType;
+/// 描画の実装ごとに画像の型だけを差し替える
+struct ValueType<Image>(PhantomData<fn() -> Image>);
+
+impl<Image> ParameterValueType for ValueType<Image>
+where
+    Image: 'static + Clone + Send + Sync,
+{
+    type Image = Image;
     type Audio = AudioType;
     type Binary = ();
     type String = ();
@@ -62,15 +80,14 @@ impl ParameterValueType for ValueType {
     type ComponentClass = ();
 }
 
-#[derive(Default)]
-struct ComponentClassList(Vec<StaticPointerOwned<RwLock<dyn ComponentClass<ValueType>>>>, Vec<StaticPointer<RwLock<dyn ComponentClass<ValueType>>>>);
+struct ComponentClassList<T: ParameterValueType>(Vec<StaticPointerOwned<RwLock<dyn ComponentClass<T>>>>, Vec<StaticPointer<RwLock<dyn ComponentClass<T>>>>);
 
-impl ComponentClassList {
-    fn new() -> ComponentClassList {
-        Default::default()
+impl<T: ParameterValueType> ComponentClassList<T> {
+    fn new() -> ComponentClassList<T> {
+        ComponentClassList(Vec::new(), Vec::new())
     }
 
-    fn add(&mut self, class: impl ComponentClass<ValueType> + 'static) -> &mut Self {
+    fn add(&mut self, class: impl ComponentClass<T> + 'static) -> &mut Self {
         let class = StaticPointerOwned::new(RwLock::new(class)).map(|arc| arc as _, |weak| weak as _);
         let reference = StaticPointerOwned::reference(&class).clone();
         self.0.push(class);
@@ -80,100 +97,90 @@ impl ComponentClassList {
 }
 
 #[async_trait]
-impl ComponentClassLoader<ValueType> for ComponentClassList {
-    async fn get_available_component_classes(&self) -> Cow<[StaticPointer<RwLock<dyn ComponentClass<ValueType>>>]> {
+impl<T: ParameterValueType> ComponentClassLoader<T> for ComponentClassList<T> {
+    async fn get_available_component_classes(&self) -> Cow<[StaticPointer<RwLock<dyn ComponentClass<T>>>]> {
         Cow::Borrowed(&self.1)
     }
 
-    async fn component_class_by_identifier(&self, identifier: ComponentClassIdentifier<'_>) -> Option<StaticPointer<RwLock<dyn ComponentClass<ValueType>>>> {
+    async fn component_class_by_identifier(&self, identifier: ComponentClassIdentifier<'_>) -> Option<StaticPointer<RwLock<dyn ComponentClass<T>>>> {
         let map = stream::iter(self.0.iter()).filter(|&class| class.read().map(|class| class.identifier() == identifier)).map(|class| StaticPointerOwned::reference(class).clone());
         pin_mut!(map);
         map.next().await
     }
 }
 
-struct ValueManagerLoaderTypes;
+struct ValueManagerLoaderTypes<Image>(PhantomData<fn() -> Image>);
 
-impl ParameterValueType for ValueManagerLoaderTypes {
-    type Image = Arc<InMemoryValueManagerLoader<ImageType>>;
+impl<Image> ParameterValueType for ValueManagerLoaderTypes<Image>
+where
+    Image: 'static + Clone + Send + Sync,
+{
+    type Image = Arc<InMemoryValueManagerLoader<Image>>;
     type Audio = Arc<InMemoryValueManagerLoader<AudioType>>;
     type Binary = Arc<InMemoryValueManagerLoader<AbstractFile>>;
     type String = Arc<InMemoryValueManagerLoader<String>>;
     type Integer = Arc<InMemoryValueManagerLoader<i64>>;
     type RealNumber = Arc<InMemoryValueManagerLoader<f64>>;
     type Boolean = Arc<InMemoryValueManagerLoader<bool>>;
-    type Dictionary = Arc<InMemoryValueManagerLoader<HashMap<String, ParameterValueRaw<ImageType, AudioType>>>>;
-    type Array = Arc<InMemoryValueManagerLoader<Vec<ParameterValueRaw<ImageType, AudioType>>>>;
+    type Dictionary = Arc<InMemoryValueManagerLoader<HashMap<String, ParameterValueRaw<Image, AudioType>>>>;
+    type Array = Arc<InMemoryValueManagerLoader<Vec<ParameterValueRaw<Image, AudioType>>>>;
     type ComponentClass = Arc<InMemoryValueManagerLoader<()>>;
 }
 
-fn main() {
-    let GpuHandle {
-        vulkano_instance: _,
-        vulkano_device,
-        vulkano_queue,
-        vulkano_memory_allocator,
-        wgpu_instance,
-        wgpu_adapter,
-        wgpu_device,
-        wgpu_queue,
-    } = initialize_gpu();
-    let runtime = Runtime::new().unwrap();
-    let id_generator = Arc::new(UniqueIdGenerator::new());
-    let project_loader = Arc::new(LocalFSProjectLoader);
-    let project_writer = Arc::new(LocalFSProjectWriter);
-    let project_memory = Arc::new(InMemoryProjectStore::<ValueType>::new());
-    let mut component_class_loader = ComponentClassList::new();
-    let command_buffer_allocator = Arc::new(StandardCommandBufferAllocator::new(Arc::clone(&vulkano_device), StandardCommandBufferAllocatorCreateInfo::default()));
-    component_class_loader.add(RectangleClass::new(Arc::clone(&vulkano_queue), &vulkano_memory_allocator, Arc::clone(&command_buffer_allocator) as Arc<dyn CommandBufferAllocator>));
-    component_class_loader.add(SineAudio::new());
-    component_class_loader.add(FfmpegMultimediaLoaderClass::new(&vulkano_queue, &vulkano_memory_allocator, Arc::clone(&command_buffer_allocator) as Arc<dyn CommandBufferAllocator>));
-    component_class_loader.add(TextRendererClass::new(&vulkano_device, &vulkano_queue, &vulkano_memory_allocator));
-    let component_class_loader = Arc::new(component_class_loader);
-    let value_managers = ParameterAllValues::<ValueManagerLoaderTypes> {
-        image: Arc::new(InMemoryValueManagerLoader::from_iter([], [])),
-        audio: Arc::new(InMemoryValueManagerLoader::from_iter([], [])),
-        binary: Arc::new(InMemoryValueManagerLoader::from_iter([Arc::new(FileReaderParamManager) as _], [])),
-        string: Arc::new(InMemoryValueManagerLoader::from_iter([Arc::new(DynEditableSelfValueManager::default()) as _], [Arc::new(DynEditableSelfValueManager::default()) as _])),
-        integer: Arc::new(InMemoryValueManagerLoader::from_iter([Arc::new(DynEditableSelfValueManager::default()) as _], [Arc::new(DynEditableSelfValueManager::default()) as _])),
-        real_number: Arc::new(InMemoryValueManagerLoader::from_iter(
+/// MPDeltaCoreは型引数が多く名前を書けないので、GUIと描画の実装ごとのrenderサブコマンドで組み立て方をマクロで共有する
+macro_rules! build_core {
+    ($image:ty, $runtime:expr, $component_class_loader:expr, $image_combiner_builder:expr, $encode_pipeline:expr, $available_easing:expr $(,)?) => {{
+        let runtime: &Runtime = $runtime;
+        let available_easing: &Arc<[Arc<dyn Easing>]> = $available_easing;
+        let id_generator = Arc::new(UniqueIdGenerator::new());
+        let project_loader = Arc::new(LocalFSProjectLoader);
+        let project_writer = Arc::new(LocalFSProjectWriter);
+        let project_memory = Arc::new(InMemoryProjectStore::<ValueType<$image>>::new());
+        let component_class_loader = Arc::new($component_class_loader);
+        let quaternion_manager = Arc::new(InMemoryValueManagerLoader::from_iter(
             [Arc::new(DynEditableSelfValueManager::default()) as _],
             [Arc::new(DynEditableSelfValueManager::default()) as _, Arc::new(DynEditableLerpEasingValueManager::default()) as _],
-        )),
-        boolean: Arc::new(InMemoryValueManagerLoader::from_iter([Arc::new(DynEditableSelfValueManager::default()) as _], [Arc::new(DynEditableSelfValueManager::default()) as _])),
-        dictionary: Arc::new(InMemoryValueManagerLoader::from_iter([], [])),
-        array: Arc::new(InMemoryValueManagerLoader::from_iter([], [])),
-        component_class: Arc::new(InMemoryValueManagerLoader::from_iter([], [])),
-    };
-    let quaternion_manager = Arc::new(InMemoryValueManagerLoader::from_iter(
-        [Arc::new(DynEditableSelfValueManager::default()) as _],
-        [Arc::new(DynEditableSelfValueManager::default()) as _, Arc::new(DynEditableLerpEasingValueManager::default()) as _],
-    ));
-    let easing_manager = Arc::new(InMemoryEasingLoader::from_iter([Arc::new(LinearEasing) as _]));
-    let project_serializer = Arc::new(MPDeltaProjectSerializer::new(runtime.handle().clone(), Arc::clone(&id_generator), Arc::clone(&component_class_loader), value_managers, quaternion_manager, easing_manager));
-    let cache = MokaCache::new();
-    let component_renderer_builder = Arc::new(MPDeltaRendererBuilder::new(
-        Arc::new(ImageCombinerBuilder::new(Arc::clone(&vulkano_device), Arc::clone(&vulkano_queue))),
-        Arc::new(LookaheadRenderingControllerBuilder::new()),
-        Arc::new(MPDeltaAudioMixerBuilder::new()),
-        cache,
-        runtime.handle().clone(),
-    ));
-    let editor = Arc::new(ProjectEditor::new(Arc::clone(&id_generator)));
-    let edit_history = Arc::new(InMemoryEditHistoryStore::new(100));
-    let core = Arc::new(MPDeltaCore::new(MPDeltaCoreArgs {
-        id_generator: Arc::clone(&id_generator),
-        project_serializer,
-        project_loader,
-        project_writer,
-        project_memory: Arc::clone(&project_memory),
-        root_component_class_memory: project_memory,
-        component_class_loader,
-        component_renderer_builder: Arc::clone(&component_renderer_builder),
-        video_encoder: component_renderer_builder,
-        editor,
-        edit_history,
-    }));
+        ));
+        let easing_manager = Arc::new(InMemoryEasingLoader::from_iter(available_easing.iter().cloned()));
+        let project_serializer = Arc::new(MPDeltaProjectSerializer::new(
+            runtime.handle().clone(),
+            Arc::clone(&id_generator),
+            Arc::clone(&component_class_loader),
+            value_managers::<$image>(),
+            quaternion_manager,
+            easing_manager,
+        ));
+        let component_renderer_builder = Arc::new(MPDeltaRendererBuilder::new(Arc::new($image_combiner_builder), Arc::new(LookaheadRenderingControllerBuilder::new()), Arc::new(MPDeltaAudioMixerBuilder::new()), MokaCache::new(), runtime.handle().clone()).with_encode_pipeline($encode_pipeline));
+        let editor = Arc::new(ProjectEditor::new(Arc::clone(&id_generator)));
+        let edit_history = Arc::new(InMemoryEditHistoryStore::new(100));
+        let core = Arc::new(MPDeltaCore::new(MPDeltaCoreArgs {
+            id_generator: Arc::clone(&id_generator),
+            project_serializer,
+            project_loader,
+            project_writer,
+            project_memory: Arc::clone(&project_memory),
+            root_component_class_memory: project_memory,
+            component_class_loader,
+            component_renderer_builder: Arc::clone(&component_renderer_builder),
+            video_encoder: component_renderer_builder,
+            editor,
+            edit_history,
+        }));
+        (id_generator, core)
+    }};
+}
+
+fn main() -> ExitCode {
+    let cli = cli::Cli::parse();
+    let encode_pipeline = cli.encode_pipeline();
+    if let Some(cli::Command::Render(args)) = cli.command {
+        return render(args, encode_pipeline);
+    }
+    let (vulkano, wgpu) = initialize_gpu();
+    let runtime = Runtime::new().unwrap();
+    let available_easing = standard_easings().collect::<Arc<[_]>>();
+    let (id_generator, core) = build_core!(ImageType, &runtime, vulkano_component_classes(&vulkano), ImageCombinerBuilder::new(Arc::clone(&vulkano.device), Arc::clone(&vulkano.queue)), encode_pipeline, &available_easing);
+    let encoder_builder = Arc::new(FfmpegEncoderBuilder::new(Arc::clone(&vulkano.device), Arc::clone(&vulkano.queue), Arc::clone(&vulkano.memory_allocator)));
     let audio_player = Arc::new(
         CpalAudioPlayer::new(
             || {
@@ -184,7 +191,6 @@ fn main() {
         )
         .unwrap(),
     );
-    let encoder_builder = Arc::new(FfmpegEncoderBuilder::new(Arc::clone(&vulkano_device), Arc::clone(&vulkano_queue), Arc::clone(&vulkano_memory_allocator)));
     let params = ViewModelParamsImpl {
         runtime: runtime.handle().clone(),
         id: Arc::clone(&id_generator),
@@ -205,58 +211,175 @@ fn main() {
         available_video_codec: encoder_builder.available_video_codec::<FfmpegEncodeSettings<File>>().into_iter().collect::<Vec<_>>().into(),
         available_audio_codec: encoder_builder.available_audio_codec().into_iter().collect::<Vec<_>>().into(),
         encode: Arc::clone(&core),
+        available_easing,
     };
     let gui = mpdelta_gui::new_gui(params);
-    let gui = MPDeltaGUIWgpu::new(wgpu_instance, wgpu_adapter, wgpu_device, wgpu_queue, gui);
+    let gui = MPDeltaGUIWgpu::new(wgpu.instance, wgpu.adapter, wgpu.device, wgpu.queue, gui);
     gui.main();
     drop(core);
     drop(runtime);
+    ExitCode::SUCCESS
+}
+
+/// renderサブコマンド GUIを使わないので、サーフェスやwgpuは用意せず描画に使うデバイスだけを作る
+fn render(args: cli::RenderArgs, encode_pipeline: EncodePipelineConfig) -> ExitCode {
+    let result = match args.backend {
+        cli::Backend::Vulkan => match initialize_vulkano(&ash::Entry::linked(), InstanceExtensions::empty(), DeviceExtensions::empty(), &[1.]) {
+            Ok(vulkano) => {
+                let runtime = Runtime::new().unwrap();
+                let available_easing = standard_easings().collect::<Arc<[_]>>();
+                let (id_generator, core) = build_core!(ImageType, &runtime, vulkano_component_classes(&vulkano), ImageCombinerBuilder::new(Arc::clone(&vulkano.device), Arc::clone(&vulkano.queue)), encode_pipeline, &available_easing);
+                let encoder_builder = Arc::new(FfmpegEncoderBuilder::new(Arc::clone(&vulkano.device), Arc::clone(&vulkano.queue), Arc::clone(&vulkano.memory_allocator)));
+                let available_video_codec = encoder_builder.available_video_codec::<FfmpegEncodeSettings<File>>().into_iter().collect::<Vec<_>>();
+                let available_audio_codec = encoder_builder.available_audio_codec().into_iter().collect::<Vec<_>>();
+                let result = runtime.block_on(cli::render::<ValueType<ImageType>, _, _>(&core, &*id_generator, &available_video_codec, &available_audio_codec, args));
+                drop(core);
+                drop(runtime);
+                result
+            }
+            Err(err) => Err(cli::RenderCommandError::InitializeGpu(Box::new(err))),
+        },
+        cli::Backend::Cpu => {
+            let runtime = Runtime::new().unwrap();
+            let available_easing = standard_easings().collect::<Arc<[_]>>();
+            let (id_generator, core) = build_core!(mpdelta_core_cpu::ImageType, &runtime, cpu_component_classes(), mpdelta_video_renderer_cpu::ImageCombinerBuilder::new(), encode_pipeline, &available_easing);
+            let encoder_builder = Arc::new(FfmpegEncoderBuilder::new_cpu());
+            let available_video_codec = encoder_builder.available_video_codec::<FfmpegEncodeSettings<File, CpuReadback>>().into_iter().collect::<Vec<_>>();
+            let available_audio_codec = encoder_builder.available_audio_codec().into_iter().collect::<Vec<_>>();
+            let result = runtime.block_on(cli::render::<ValueType<mpdelta_core_cpu::ImageType>, _, _>(&core, &*id_generator, &available_video_codec, &available_audio_codec, args));
+            drop(core);
+            drop(runtime);
+            result
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            err.exit_code()
+        }
+    }
+}
+
+fn vulkano_component_classes(vulkano: &VulkanoHandle) -> ComponentClassList<ValueType<ImageType>> {
+    let VulkanoHandle { device, queue, memory_allocator, .. } = vulkano;
+    let command_buffer_allocator = Arc::new(StandardCommandBufferAllocator::new(Arc::clone(device), StandardCommandBufferAllocatorCreateInfo::default()));
+    let mut component_class_loader = ComponentClassList::new();
+    component_class_loader.add(RectangleClass::new(Arc::clone(queue), memory_allocator, Arc::clone(&command_buffer_allocator) as Arc<dyn CommandBufferAllocator>));
+    component_class_loader.add(ShapeClass::new(queue, memory_allocator, Arc::clone(&command_buffer_allocator) as Arc<dyn CommandBufferAllocator>));
+    component_class_loader.add(VectorPathClass::new(queue, memory_allocator, Arc::clone(&command_buffer_allocator) as Arc<dyn CommandBufferAllocator>));
+    component_class_loader.add(GradientClass::new(queue, memory_allocator, Arc::clone(&command_buffer_allocator) as Arc<dyn CommandBufferAllocator>));
+    component_class_loader.add(SineAudio::new());
+    component_class_loader.add(AudioGeneratorClass::new());
+    component_class_loader.add(FfmpegMultimediaLoaderClass::new(queue, memory_allocator, Arc::clone(&command_buffer_allocator) as Arc<dyn CommandBufferAllocator>));
+    component_class_loader.add(ImageSequenceClass::new(queue, memory_allocator, Arc::clone(&command_buffer_allocator) as Arc<dyn CommandBufferAllocator>));
+    component_class_loader.add(TextRendererClass::new(device, queue, memory_allocator));
+    component_class_loader
+}
+
+fn cpu_component_classes() -> ComponentClassList<ValueType<mpdelta_core_cpu::ImageType>> {
+    let mut component_class_loader = ComponentClassList::new();
+    component_class_loader.add(RectangleClass::new_cpu());
+    component_class_loader.add(ShapeClass::new_cpu());
+    component_class_loader.add(VectorPathClass::new_cpu());
+    component_class_loader.add(GradientClass::new_cpu());
+    component_class_loader.add(SineAudio::new());
+    component_class_loader.add(AudioGeneratorClass::new());
+    component_class_loader.add(FfmpegMultimediaLoaderClass::new_cpu());
+    component_class_loader.add(ImageSequenceClass::new_cpu());
+    component_class_loader.add(TextRendererClass::new_cpu());
+    component_class_loader
+}
+
+fn value_managers<Image>() -> ParameterAllValues<ValueManagerLoaderTypes<Image>>
+where
+    Image: 'static + Clone + Send + Sync,
+{
+    ParameterAllValues {
+        image: Arc::new(InMemoryValueManagerLoader::from_iter([], [])),
+        audio: Arc::new(InMemoryValueManagerLoader::from_iter([], [])),
+        binary: Arc::new(InMemoryValueManagerLoader::from_iter([Arc::new(FileReaderParamManager) as _], [])),
+        string: Arc::new(InMemoryValueManagerLoader::from_iter([Arc::new(DynEditableSelfValueManager::default()) as _], [Arc::new(DynEditableSelfValueManager::default()) as _])),
+        integer: Arc::new(InMemoryValueManagerLoader::from_iter([Arc::new(DynEditableSelfValueManager::default()) as _], [Arc::new(DynEditableSelfValueManager::default()) as _])),
+        real_number: Arc::new(InMemoryValueManagerLoader::from_iter(
+            [Arc::new(DynEditableSelfValueManager::default()) as _],
+            [Arc::new(DynEditableSelfValueManager::default()) as _, Arc::new(DynEditableLerpEasingValueManager::default()) as _],
+        )),
+        boolean: Arc::new(InMemoryValueManagerLoader::from_iter([Arc::new(DynEditableSelfValueManager::default()) as _], [Arc::new(DynEditableSelfValueManager::default()) as _])),
+        dictionary: Arc::new(InMemoryValueManagerLoader::from_iter(
+            [Arc::new(DynEditablePlainValueManager::<BTreeMap<String, PlainValue>, Image, AudioType>::default()) as _],
+            [Arc::new(DynEditablePlainValueManager::<BTreeMap<String, PlainValue>, Image, AudioType>::default()) as _],
+        )),
+        array: Arc::new(InMemoryValueManagerLoader::from_iter(
+            [Arc::new(DynEditablePlainValueManager::<Vec<PlainValue>, Image, AudioType>::default()) as _],
+            [Arc::new(DynEditablePlainValueManager::<Vec<PlainValue>, Image, AudioType>::default()) as _],
+        )),
+        component_class: Arc::new(InMemoryValueManagerLoader::from_iter([], [])),
+    }
 }
 
 #[allow(unused)]
-struct GpuHandle {
-    vulkano_instance: Arc<vulkano::instance::Instance>,
-    vulkano_device: Arc<vulkano::device::Device>,
-    vulkano_queue: Arc<vulkano::device::Queue>,
-    vulkano_memory_allocator: Arc<vulkano::memory::allocator::StandardMemoryAllocator>,
-    wgpu_instance: wgpu::Instance,
-    wgpu_adapter: wgpu::Adapter,
-    wgpu_device: wgpu::Device,
-    wgpu_queue: wgpu::Queue,
+struct VulkanoHandle {
+    instance: Arc<vulkano::instance::Instance>,
+    device: Arc<vulkano::device::Device>,
+    queue: Arc<vulkano::device::Queue>,
+    memory_allocator: Arc<vulkano::memory::allocator::StandardMemoryAllocator>,
 }
 
-fn initialize_gpu() -> GpuHandle {
-    let entry = ash::Entry::linked();
+struct WgpuHandle {
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+}
+
+/// 描画に使うVulkanデバイスを用意できなかった
+#[derive(Debug, Error)]
+enum GpuInitError {
+    #[error("failed to load the Vulkan library: {0}")]
+    Library(#[from] LoadingError),
+    #[error("failed to create a Vulkan instance: {0}")]
+    Instance(Validated<VulkanError>),
+    #[error("failed to enumerate Vulkan devices: {0}")]
+    EnumerateDevices(VulkanError),
+    #[error("no Vulkan device with a graphics and compute queue found")]
+    NoDevice,
+    #[error("failed to create a Vulkan device: {0}")]
+    Device(Validated<VulkanError>),
+}
+
+/// `instance_extensions`のうち対応しているものを有効にしてデバイスを作り、`queue_priorities`の数だけキューを確保する
+///
+/// 返すキューは先頭の1つだけで、残りはwgpuなど呼び出し側がインデックスで使う
+fn initialize_vulkano(entry: &ash::Entry, instance_extensions: InstanceExtensions, device_extensions: DeviceExtensions, queue_priorities: &[f32]) -> Result<VulkanoHandle, GpuInitError> {
     struct LibraryLoader(ash::Entry);
     unsafe impl vulkano::library::Loader for LibraryLoader {
         unsafe fn get_instance_proc_addr(&self, instance: vk::Instance, name: *const c_char) -> vk::PFN_vkVoidFunction {
             self.0.get_instance_proc_addr(instance, name)
         }
     }
-    let vulkan_library = VulkanLibrary::with_loader(LibraryLoader(entry.clone())).unwrap();
-    let instance_extensions = vulkan_library.supported_extensions().intersection(&InstanceExtensions {
-        khr_surface: true,
-        khr_xlib_surface: true,
-        khr_xcb_surface: true,
-        khr_wayland_surface: true,
-        khr_android_surface: true,
-        khr_win32_surface: true,
-        mvk_ios_surface: true,
-        mvk_macos_surface: true,
-        ..InstanceExtensions::empty()
-    });
-    let vulkano_instance = vulkano::instance::Instance::new(
+    let vulkan_library = VulkanLibrary::with_loader(LibraryLoader(entry.clone()))?;
+    let instance_extensions = vulkan_library.supported_extensions().intersection(&instance_extensions);
+    let instance = vulkano::instance::Instance::new(
         vulkan_library,
         vulkano::instance::InstanceCreateInfo {
             enabled_extensions: instance_extensions,
             ..vulkano::instance::InstanceCreateInfo::default()
         },
     )
-    .unwrap();
-    let vulkano_physical_device = vulkano_instance
+    .map_err(GpuInitError::Instance)?;
+    let (physical_device, queue_family_index) = instance
         .enumerate_physical_devices()
-        .unwrap()
-        .max_by_key(|physical_device| match physical_device.properties().device_type {
+        .map_err(GpuInitError::EnumerateDevices)?
+        .filter(|physical_device| physical_device.supported_extensions().contains(&device_extensions))
+        .filter_map(|physical_device| {
+            let queue_family_index = physical_device
+                .queue_family_properties()
+                .iter()
+                .position(|q| q.queue_flags.contains(QueueFlags::GRAPHICS | QueueFlags::COMPUTE) && q.queue_count as usize >= queue_priorities.len())?;
+            Some((physical_device, queue_family_index as u32))
+        })
+        .max_by_key(|(physical_device, _)| match physical_device.properties().device_type {
             PhysicalDeviceType::DiscreteGpu => 5,
             PhysicalDeviceType::IntegratedGpu => 4,
             PhysicalDeviceType::VirtualGpu => 3,
@@ -264,35 +387,72 @@ fn initialize_gpu() -> GpuHandle {
             PhysicalDeviceType::Other => 1,
             _ => 0,
         })
-        .unwrap();
-    let queue_index = vulkano_physical_device.queue_family_properties().iter().position(|q| q.queue_flags.contains(QueueFlags::GRAPHICS | QueueFlags::COMPUTE)).unwrap();
+        .ok_or(GpuInitError::NoDevice)?;
 
     let queue_create_infos = vec![QueueCreateInfo {
-        queue_family_index: queue_index as u32,
-        queues: vec![0., 1.],
+        queue_family_index,
+        queues: queue_priorities.to_vec(),
         ..Default::default()
     }];
 
-    let (vulkano_device, mut queues) = {
-        Device::new(
-            vulkano_physical_device.clone(),
-            DeviceCreateInfo {
-                queue_create_infos,
-                enabled_extensions: DeviceExtensions { khr_swapchain: true, ..DeviceExtensions::empty() },
-                enabled_features: DeviceFeatures::empty(),
-                ..Default::default()
-            },
-        )
-        .expect("failed to create device")
+    let (device, mut queues) = Device::new(
+        physical_device,
+        DeviceCreateInfo {
+            queue_create_infos,
+            enabled_extensions: device_extensions,
+            enabled_features: DeviceFeatures::empty(),
+            ..Default::default()
+        },
+    )
+    .map_err(GpuInitError::Device)?;
+    let queue = queues.next().unwrap();
+    let memory_allocator = Arc::new(StandardMemoryAllocator::new_default(Arc::clone(&device)));
+
+    Ok(VulkanoHandle { instance, device, queue, memory_allocator })
+}
+
+fn initialize_gpu() -> (VulkanoHandle, WgpuHandle) {
+    let entry = ash::Entry::linked();
+    let instance_extensions = InstanceExtensions {
+        khr_surface: true,
+        khr_xlib_surface: true,
+        khr_xcb_surface: true,
+        khr_wayland_surface: true,
+        khr_android_surface: true,
+        khr_win32_surface: true,
+        mvk_ios_surface: true,
+        mvk_macos_surface: true,
+        ..InstanceExtensions::empty()
     };
-    let vulkano_queue = queues.next().unwrap();
+    // 2つ目のキューはwgpuが使う
+    let vulkano = initialize_vulkano(&entry, instance_extensions, DeviceExtensions { khr_swapchain: true, ..DeviceExtensions::empty() }, &[0., 1.]).expect("failed to initialize Vulkan");
+    let VulkanoHandle {
+        instance: vulkano_instance,
+        device: vulkano_device,
+        queue: vulkano_queue,
+        ..
+    } = &vulkano;
+    let vulkano_physical_device = vulkano_device.physical_device();
 
     let ash_instance = unsafe { ash::Instance::load(entry.static_fn(), vulkano_instance.handle()) };
-    let wgpu_hal_instance = unsafe { wgpu::hal::vulkan::Instance::from_raw(entry, ash_instance, u32::try_from(vulkano_instance.api_version()).unwrap(), 0, None, instance_extension_into_vec(instance_extensions), wgpu::InstanceFlags::empty(), false, None).unwrap() };
+    let wgpu_hal_instance = unsafe {
+        wgpu::hal::vulkan::Instance::from_raw(
+            entry,
+            ash_instance,
+            u32::try_from(vulkano_instance.api_version()).unwrap(),
+            0,
+            None,
+            instance_extension_into_vec(*vulkano_instance.enabled_extensions()),
+            wgpu::InstanceFlags::empty(),
+            false,
+            None,
+        )
+        .unwrap()
+    };
 
     let wgpu_hal_adapter = wgpu_hal_instance.expose_adapter(vulkano_physical_device.handle()).unwrap();
 
-    let queue_family_index = vulkano_physical_device.queue_family_properties().iter().position(|q| q.queue_flags.contains(QueueFlags::GRAPHICS | QueueFlags::COMPUTE)).unwrap();
+    let queue_family_index = vulkano_queue.queue_family_index();
     let extensions = wgpu_hal_adapter.adapter.required_device_extensions(wgpu_hal_adapter.features);
     let ash_device = unsafe { ash::Device::load(wgpu_hal_instance.shared_instance().raw_instance().fp_v1_0(), vulkano_device.handle()) };
     let wgpu_hal_device = unsafe {
@@ -301,7 +461,7 @@ fn initialize_gpu() -> GpuHandle {
             .device_from_raw(
                 ash_device,
                 Some({
-                    let vulkano_device = Arc::clone(&vulkano_device);
+                    let vulkano_device = Arc::clone(vulkano_device);
                     Box::new(move || {
                         let _ = vulkano_device;
                     })
@@ -309,7 +469,7 @@ fn initialize_gpu() -> GpuHandle {
                 &extensions,
                 wgpu_hal_adapter.features,
                 &MemoryHints::default(),
-                queue_family_index as u32,
+                queue_family_index,
                 1,
             )
             .unwrap()
@@ -336,18 +496,13 @@ fn initialize_gpu() -> GpuHandle {
             .unwrap();
     }
 
-    let vulkano_memory_allocator = Arc::new(StandardMemoryAllocator::new_default(Arc::clone(&vulkano_device)));
-
-    GpuHandle {
-        vulkano_instance,
-        vulkano_device,
-        vulkano_queue,
-        vulkano_memory_allocator,
-        wgpu_instance,
-        wgpu_adapter,
-        wgpu_device,
-        wgpu_queue,
-    }
+    let wgpu = WgpuHandle {
+        instance: wgpu_instance,
+        adapter: wgpu_adapter,
+        device: wgpu_device,
+        queue: wgpu_queue,
+    };
+    (vulkano, wgpu)
 }
 
 fn instance_extension_into_vec(instance_extensions: InstanceExtensions) -> Vec<&'static CStr> {
@@ -1,6 +1,6 @@
 use ffmpeg_next::format::{Pixel, Sample};
 use ffmpeg_next::{codec, Codec, Rational};
-use ffmpeg_sys_next::{avcodec_alloc_context3, AVPixelFormat, AVRational, AVSampleFormat};
+use ffmpeg_sys_next::{av_pix_fmt_desc_get, avcodec_alloc_context3, AVPixelFormat, AVRational, AVSampleFormat};
 use std::marker::PhantomData;
 
 pub fn codec_context_time_base(context: codec::Context) -> Rational {
@@ -21,6 +21,16 @@ pub fn codec_supported_pixel_format(codec: &Codec) -> Option<PixelFormatIterator
     PixelFormatIterator::new(codec)
 }
 
+/// bit depth of the first component of the pixel format, or None if unknown
+pub fn pixel_format_bit_depth(format: Pixel) -> Option<u32> {
+    let descriptor = unsafe { av_pix_fmt_desc_get(AVPixelFormat::from(format)) };
+    if descriptor.is_null() {
+        None
+    } else {
+        Some(unsafe { (*descriptor).comp[0].depth } as u32)
+    }
+}
+
 /// array of supported audio samplerates, or None if unknown
 pub fn codec_supported_sample_rate(codec: &Codec) -> Option<SupportedSampleRateIterator<'_>> {
     SupportedSampleRateIterator::new(codec)
@@ -6,9 +6,9 @@ pub mod codec;
 pub mod io;
 
 pub fn supports(file_format: FileFormat, codec: ffmpeg_next::codec::Id) -> bool {
-    let file_extension = file_format.extension_c();
+    let format_name = file_format.format_name_c();
     let mut output_context: *mut AVFormatContext = ptr::null_mut();
-    let result = unsafe { avformat_alloc_output_context2(&mut output_context, ptr::null(), file_extension.as_ptr(), ptr::null()) };
+    let result = unsafe { avformat_alloc_output_context2(&mut output_context, ptr::null(), format_name.as_ptr(), ptr::null()) };
     if result < 0 {
         return false;
     }
@@ -0,0 +1,294 @@
+use std::f64::consts::TAU;
+use std::str::FromStr;
+
+/// 生成する音声のサンプリングレート
+pub const SAMPLE_RATE: u32 = 48_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    Sine,
+    Square,
+    Saw,
+    Triangle,
+    /// 全ての周波数で同じ強さのノイズ
+    WhiteNoise,
+    /// 周波数に反比例する強さのノイズ
+    PinkNoise,
+    /// 周波数の2乗に反比例する強さのノイズ
+    BrownNoise,
+}
+
+impl Waveform {
+    fn is_noise(self) -> bool {
+        matches!(self, Waveform::WhiteNoise | Waveform::PinkNoise | Waveform::BrownNoise)
+    }
+}
+
+impl FromStr for Waveform {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "sine" => Ok(Waveform::Sine),
+            "square" => Ok(Waveform::Square),
+            "saw" | "sawtooth" => Ok(Waveform::Saw),
+            "triangle" => Ok(Waveform::Triangle),
+            "white" | "white-noise" => Ok(Waveform::WhiteNoise),
+            "pink" | "pink-noise" => Ok(Waveform::PinkNoise),
+            "brown" | "brown-noise" => Ok(Waveform::BrownNoise),
+            _ => Err(()),
+        }
+    }
+}
+
+/// 周波数の掃引のしかた
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sweep {
+    None,
+    /// 周波数が時間に比例して変わる
+    Linear,
+    /// 周波数の対数が時間に比例して変わる
+    Logarithmic,
+}
+
+impl FromStr for Sweep {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" => Ok(Sweep::None),
+            "linear" => Ok(Sweep::Linear),
+            "log" | "logarithmic" => Ok(Sweep::Logarithmic),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratorSettings {
+    pub waveform: Waveform,
+    pub sweep: Sweep,
+    /// 掃引の終わりの周波数(Hz)
+    pub sweep_end_frequency: f64,
+    /// 掃引にかける時間(秒) 掃引が終わったあとは終わりの周波数のままになる
+    pub sweep_duration: f64,
+}
+
+impl GeneratorSettings {
+    /// 時刻`time`(秒)の周波数 `base`は掃引の始まりの周波数
+    ///
+    /// 対数掃引で周波数が正でないときは線形に掃引する
+    pub fn frequency(&self, base: f64, time: f64) -> f64 {
+        let ratio = if self.sweep_duration > 0. { (time / self.sweep_duration).clamp(0., 1.) } else { 1. };
+        match self.sweep {
+            Sweep::None => base,
+            Sweep::Logarithmic if base > 0. && self.sweep_end_frequency > 0. => base * (self.sweep_end_frequency / base).powf(ratio),
+            Sweep::Linear | Sweep::Logarithmic => base + (self.sweep_end_frequency - base) * ratio,
+        }
+    }
+}
+
+/// 1サンプルずつ音声を生成する
+///
+/// 周波数を積分して位相を求めるので、周波数を動かしても波形が途切れない
+/// ホワイトノイズはサンプルの位置から決まり、ピンクノイズとブラウンノイズはそれをフィルタして作る
+/// 生成の途中の状態は`GeneratorState`に持つので、保存しておいた状態から生成をやり直せる
+#[derive(Debug, Clone)]
+pub struct Generator {
+    settings: GeneratorSettings,
+}
+
+/// 次に生成するサンプルの直前までの状態
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GeneratorState {
+    /// 1周期を1とした位相
+    phase: f64,
+    pink: [f64; 7],
+    brown: f64,
+}
+
+impl Generator {
+    pub fn new(settings: GeneratorSettings) -> Generator {
+        Generator { settings }
+    }
+
+    /// 位置`position`のサンプルを`frequency`(Hz)と`amplitude`で生成し、`state`を次のサンプルの状態に進める
+    ///
+    /// `frequency`は掃引の始まりの周波数で、ノイズでは使わない
+    pub fn next_sample(&self, state: &mut GeneratorState, position: u64, frequency: f64, amplitude: f64) -> f64 {
+        let phase = state.phase;
+        let value = match self.settings.waveform {
+            Waveform::Sine => (phase * TAU).sin(),
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.
+                } else {
+                    -1.
+                }
+            }
+            Waveform::Saw => ((phase + 0.5) % 1.) * 2. - 1.,
+            Waveform::Triangle => 1. - 4. * (((phase + 0.25) % 1.) - 0.5).abs(),
+            Waveform::WhiteNoise => white_noise(position),
+            Waveform::PinkNoise => state.pink_noise(white_noise(position)),
+            Waveform::BrownNoise => state.brown_noise(white_noise(position)),
+        };
+        if !self.settings.waveform.is_noise() {
+            let frequency = self.settings.frequency(frequency, position as f64 / SAMPLE_RATE as f64);
+            state.phase = (phase + frequency / SAMPLE_RATE as f64).rem_euclid(1.);
+        }
+        value * amplitude
+    }
+}
+
+impl GeneratorState {
+    /// Paul Kelletのフィルタ
+    fn pink_noise(&mut self, white: f64) -> f64 {
+        let [b0, b1, b2, b3, b4, b5, b6] = &mut self.pink;
+        *b0 = 0.99886 * *b0 + white * 0.0555179;
+        *b1 = 0.99332 * *b1 + white * 0.0750759;
+        *b2 = 0.96900 * *b2 + white * 0.1538520;
+        *b3 = 0.86650 * *b3 + white * 0.3104856;
+        *b4 = 0.55000 * *b4 + white * 0.5329522;
+        *b5 = -0.7616 * *b5 - white * 0.0168980;
+        let pink = *b0 + *b1 + *b2 + *b3 + *b4 + *b5 + *b6 + white * 0.5362;
+        *b6 = white * 0.115926;
+        pink * 0.11
+    }
+
+    /// 少しずつ減衰させながら積分する
+    fn brown_noise(&mut self, white: f64) -> f64 {
+        self.brown = (self.brown + 0.02 * white) / 1.02;
+        self.brown * 3.5
+    }
+}
+
+/// `position`から決まる-1以上1未満の一様乱数
+fn white_noise(position: u64) -> f64 {
+    // SplitMix64
+    let mut z = position.wrapping_add(1).wrapping_mul(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64 * 2. - 1.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(waveform: Waveform) -> GeneratorSettings {
+        GeneratorSettings {
+            waveform,
+            sweep: Sweep::None,
+            sweep_end_frequency: 0.,
+            sweep_duration: 0.,
+        }
+    }
+
+    fn generate(waveform: Waveform, frequency: f64, amplitude: f64, len: usize) -> Vec<f64> {
+        let generator = Generator::new(settings(waveform));
+        let mut state = GeneratorState::default();
+        (0..len as u64).map(|position| generator.next_sample(&mut state, position, frequency, amplitude)).collect()
+    }
+
+    fn assert_samples(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+            assert!((a - e).abs() < 1e-9, "sample {i}: {a} != {e}");
+        }
+    }
+
+    /// 隣り合うサンプルの差の2乗平均を、サンプルの2乗平均で割った値 高い周波数が多いほど大きい
+    fn roughness(samples: &[f64]) -> f64 {
+        let diff = samples.windows(2).map(|w| (w[1] - w[0]).powi(2)).sum::<f64>() / (samples.len() - 1) as f64;
+        let power = samples.iter().map(|s| s * s).sum::<f64>() / samples.len() as f64;
+        diff / power
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(" Sine".parse(), Ok(Waveform::Sine));
+        assert_eq!("sawtooth".parse(), Ok(Waveform::Saw));
+        assert_eq!("pink".parse(), Ok(Waveform::PinkNoise));
+        assert_eq!("brown-noise".parse(), Ok(Waveform::BrownNoise));
+        assert_eq!("pulse".parse::<Waveform>(), Err(()));
+        assert_eq!("none".parse(), Ok(Sweep::None));
+        assert_eq!("Linear".parse(), Ok(Sweep::Linear));
+        assert_eq!("log".parse(), Ok(Sweep::Logarithmic));
+        assert_eq!("exp".parse::<Sweep>(), Err(()));
+    }
+
+    #[test]
+    fn test_oscillator() {
+        // 4サンプルで1周期
+        let frequency = SAMPLE_RATE as f64 / 4.;
+        assert_samples(&generate(Waveform::Sine, frequency, 0.5, 5), &[0., 0.5, 0., -0.5, 0.]);
+        assert_samples(&generate(Waveform::Square, frequency, 1., 5), &[1., 1., -1., -1., 1.]);
+        assert_samples(&generate(Waveform::Saw, frequency, 1., 5), &[0., 0.5, -1., -0.5, 0.]);
+        assert_samples(&generate(Waveform::Triangle, frequency, 1., 5), &[0., 1., 0., -1., 0.]);
+        assert_samples(&generate(Waveform::Sine, 0., 1., 3), &[0., 0., 0.]);
+    }
+
+    #[test]
+    fn test_phase_continuity() {
+        let generator = Generator::new(settings(Waveform::Saw));
+        let mut state = GeneratorState::default();
+        let quarter = SAMPLE_RATE as f64 / 4.;
+        let mut position = 0..;
+        let samples = [quarter, quarter, quarter / 2., quarter / 2., quarter * 2., quarter].map(|frequency| generator.next_sample(&mut state, position.next().unwrap(), frequency, 1.));
+        // 位相は 0, 1/4, 1/2, 5/8, 3/4, 1/4
+        assert_samples(&samples, &[0., 0.5, -1., -0.75, -0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_resume() {
+        for waveform in [Waveform::Sine, Waveform::PinkNoise, Waveform::BrownNoise] {
+            let expected = generate(waveform, 440., 1., 2000);
+            let generator = Generator::new(settings(waveform));
+            let mut state = GeneratorState::default();
+            for position in 0..1000 {
+                generator.next_sample(&mut state, position, 440., 1.);
+            }
+            let saved = state;
+            let resumed = (1000..2000).map(|position| generator.next_sample(&mut state, position, 440., 1.)).collect::<Vec<_>>();
+            assert_eq!(resumed, expected[1000..]);
+            state = saved;
+            assert_eq!(generator.next_sample(&mut state, 1000, 440., 1.), expected[1000]);
+        }
+    }
+
+    #[test]
+    fn test_sweep() {
+        let sweep = |sweep, end| GeneratorSettings {
+            waveform: Waveform::Sine,
+            sweep,
+            sweep_end_frequency: end,
+            sweep_duration: 2.,
+        };
+        assert_eq!(sweep(Sweep::None, 400.).frequency(100., 1.), 100.);
+        assert_eq!(sweep(Sweep::Linear, 300.).frequency(100., 0.), 100.);
+        assert_eq!(sweep(Sweep::Linear, 300.).frequency(100., 1.), 200.);
+        assert_eq!(sweep(Sweep::Linear, 300.).frequency(100., 3.), 300.);
+        assert!((sweep(Sweep::Logarithmic, 400.).frequency(100., 1.) - 200.).abs() < 1e-9);
+        assert!((sweep(Sweep::Logarithmic, 400.).frequency(100., 2.) - 400.).abs() < 1e-9);
+        assert_eq!(sweep(Sweep::Logarithmic, 300.).frequency(-100., 1.), 100.);
+        let mut zero_duration = sweep(Sweep::Linear, 300.);
+        zero_duration.sweep_duration = 0.;
+        assert_eq!(zero_duration.frequency(100., 0.), 300.);
+    }
+
+    #[test]
+    fn test_noise() {
+        let white = generate(Waveform::WhiteNoise, 440., 1., 48_000);
+        assert!(white.iter().all(|s| (-1. ..1.).contains(s)));
+        assert!(white.iter().sum::<f64>().abs() / white.len() as f64 < 0.01);
+        assert_eq!(white, generate(Waveform::WhiteNoise, 880., 1., 48_000));
+        let pink = generate(Waveform::PinkNoise, 440., 1., 48_000);
+        let brown = generate(Waveform::BrownNoise, 440., 1., 48_000);
+        assert!(pink.iter().chain(&brown).all(|s| (-1. ..=1.).contains(s)));
+        let (white, pink, brown) = (roughness(&white), roughness(&pink), roughness(&brown));
+        assert!((white - 2.).abs() < 0.1, "{white}");
+        assert!(pink < white * 0.8, "{pink}");
+        assert!(brown < pink * 0.2, "{brown}");
+    }
+}
@@ -0,0 +1,354 @@
+use crate::generator::{Generator, GeneratorSettings, GeneratorState, Sweep, Waveform, SAMPLE_RATE};
+use async_trait::async_trait;
+use mpdelta_component_common::constant_parameter::ConstantParameter;
+use mpdelta_core::common::mixed_fraction::MixedFraction;
+use mpdelta_core::component::class::{ComponentClass, ComponentClassIdentifier};
+use mpdelta_core::component::instance::ComponentInstance;
+use mpdelta_core::component::marker_pin::{MarkerPin, MarkerPinId, MarkerTime};
+use mpdelta_core::component::parameter::{AudioRequiredParams, Parameter, ParameterNullableValue, ParameterSelect, ParameterType, ParameterValueRaw, ParameterValueType, VariableParameterValue};
+use mpdelta_core::component::processor::{ComponentProcessor, ComponentProcessorGatherNative, ComponentProcessorGatherNativeDyn, ComponentProcessorWrapper, DynGatherNativeParameter, GatherNativeParameter, NativeGatherProcessorInput, NativeProcessorRequest};
+use mpdelta_core::core::IdGenerator;
+use mpdelta_core::ptr::StaticPointer;
+use mpdelta_core::time::TimelineTime;
+use mpdelta_core_audio::multi_channel_audio::{MultiChannelAudioMutOp, MultiChannelAudioSliceMut};
+use mpdelta_core_audio::{AudioProvider, AudioType};
+use std::any::Any;
+use std::borrow::Cow;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+pub mod generator;
+
+pub struct AudioGeneratorClass<T: ParameterValueType> {
+    processor: Arc<dyn ComponentProcessorGatherNativeDyn<T>>,
+}
+
+impl<T> AudioGeneratorClass<T>
+where
+    T: ParameterValueType<Audio = AudioType>,
+{
+    pub fn new() -> AudioGeneratorClass<T> {
+        AudioGeneratorClass { processor: Arc::new(AudioGenerator) }
+    }
+}
+
+impl<T> Default for AudioGeneratorClass<T>
+where
+    T: ParameterValueType<Audio = AudioType>,
+{
+    fn default() -> Self {
+        AudioGeneratorClass::new()
+    }
+}
+
+/// 周期的な波形やノイズ、周波数の掃引を出力する
+///
+/// `frequency`と`amplitude`は`PARAMETER_INTERVAL`ごとに評価して線形に補間し、それ以外のパラメータはコンポーネントの先頭の値を使う
+struct AudioGenerator;
+
+fn variable_parameter_types() -> Vec<(String, ParameterType)> {
+    vec![
+        ("waveform".to_owned(), Parameter::String(())),
+        ("frequency".to_owned(), Parameter::RealNumber(())),
+        ("amplitude".to_owned(), Parameter::RealNumber(())),
+        ("sweep".to_owned(), Parameter::String(())),
+        ("sweep_end_frequency".to_owned(), Parameter::RealNumber(())),
+        ("sweep_duration".to_owned(), Parameter::RealNumber(())),
+    ]
+}
+
+fn variable_parameters<T: ParameterValueType>(left: MarkerPinId, right: MarkerPinId) -> Vec<VariableParameterValue<ParameterNullableValue<T>>> {
+    let constant = ConstantParameter::new(left, right);
+    [constant.string("sine"), constant.real_number(440.), constant.real_number(0.5), constant.string("none"), constant.real_number(880.), constant.real_number(1.)]
+        .into_iter()
+        .map(VariableParameterValue::new)
+        .collect()
+}
+
+#[async_trait]
+impl<T> ComponentClass<T> for AudioGeneratorClass<T>
+where
+    T: ParameterValueType<Audio = AudioType>,
+{
+    fn human_readable_identifier(&self) -> &str {
+        "Audio Generator"
+    }
+
+    fn identifier(&self) -> ComponentClassIdentifier {
+        ComponentClassIdentifier {
+            namespace: Cow::Borrowed("mpdelta"),
+            name: Cow::Borrowed("AudioGenerator"),
+            inner_identifier: Default::default(),
+        }
+    }
+
+    fn processor(&self) -> ComponentProcessorWrapper<T> {
+        ComponentProcessorWrapper::GatherNative(Arc::clone(&self.processor))
+    }
+
+    async fn instantiate(&self, this: &StaticPointer<RwLock<dyn ComponentClass<T>>>, id: &dyn IdGenerator) -> ComponentInstance<T> {
+        let left = MarkerPin::new(id.generate_new(), MarkerTime::ZERO);
+        let right = MarkerPin::new(id.generate_new(), MarkerTime::new(MixedFraction::from_integer(1)).unwrap());
+        let audio_required_params = AudioRequiredParams::new_default(left.id(), right.id(), 1);
+        let variable_parameters = variable_parameters(*left.id(), *right.id());
+        ComponentInstance::builder(this.clone(), left, right, Vec::new(), Arc::clone(&self.processor))
+            .audio_required_params(audio_required_params)
+            .variable_parameters(variable_parameter_types(), variable_parameters.into_iter().collect())
+            .build(id)
+    }
+}
+
+#[async_trait]
+impl<T> ComponentProcessor<T> for AudioGenerator
+where
+    T: ParameterValueType<Audio = AudioType>,
+{
+    async fn fixed_parameter_types(&self) -> &[(String, ParameterType)] {
+        &[]
+    }
+
+    async fn update_variable_parameter(&self, _: &[ParameterValueRaw<T::Image, T::Audio>], variable_parameters: &mut Vec<(String, ParameterType)>) {
+        *variable_parameters = variable_parameter_types();
+    }
+
+    async fn num_interprocess_pins(&self, _: &[ParameterValueRaw<T::Image, T::Audio>]) -> usize {
+        0
+    }
+}
+
+#[async_trait]
+impl<T> ComponentProcessorGatherNative<T> for AudioGenerator
+where
+    T: ParameterValueType<Audio = AudioType>,
+{
+    type WholeComponentCacheKey = ();
+    type WholeComponentCacheValue = ();
+
+    fn whole_component_cache_key(&self, _: &[ParameterValueRaw<T::Image, T::Audio>], _: &[TimelineTime]) -> Option<Self::WholeComponentCacheKey> {
+        None
+    }
+
+    async fn natural_length(&self, _: &[ParameterValueRaw<T::Image, T::Audio>], _: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> Option<MarkerTime> {
+        None
+    }
+
+    async fn supports_output_type(&self, _: &[ParameterValueRaw<T::Image, T::Audio>], out: Parameter<ParameterSelect>, _: &mut Option<Arc<dyn Any + Send + Sync>>) -> bool {
+        matches!(out, Parameter::Audio(_))
+    }
+
+    async fn process(&self, parameters: NativeGatherProcessorInput<'_, T>, time: TimelineTime, _output_type: Parameter<NativeProcessorRequest>, _whole_component_cache: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> ParameterValueRaw<T::Image, T::Audio> {
+        let [Parameter::String(waveform), Parameter::RealNumber(frequency), Parameter::RealNumber(amplitude), Parameter::String(sweep), Parameter::RealNumber(sweep_end_frequency), Parameter::RealNumber(sweep_duration)] = parameters.variable_parameters else {
+            panic!()
+        };
+        let settings = GeneratorSettings {
+            waveform: waveform.get_param(time).await.ok().and_then(|waveform| waveform.parse().ok()).unwrap_or(Waveform::Sine),
+            sweep: sweep.get_param(time).await.ok().and_then(|sweep| sweep.parse().ok()).unwrap_or(Sweep::None),
+            sweep_end_frequency: sweep_end_frequency.get_param(time).await.unwrap_or_default(),
+            sweep_duration: sweep_duration.get_param(time).await.unwrap_or_default(),
+        };
+        let (sec, smp) = parameters.local_time_range.end.value().deconstruct_with_round(SAMPLE_RATE);
+        let end = (sec as i64 * SAMPLE_RATE as i64 + smp as i64).max(0) as u64;
+        Parameter::Audio(AudioType::new(GeneratedAudio::new(Generator::new(settings), ParameterTrack::evaluate(frequency, end).await, ParameterTrack::evaluate(amplitude, end).await)))
+    }
+}
+
+/// `frequency`と`amplitude`を評価する間隔(サンプル数)
+const PARAMETER_INTERVAL: u64 = SAMPLE_RATE as u64 / 100;
+/// 生成の状態を保存する間隔(サンプル数)
+const CHECKPOINT_INTERVAL: u64 = SAMPLE_RATE as u64;
+
+/// `PARAMETER_INTERVAL`ごとに評価したパラメータの値
+struct ParameterTrack(Vec<f64>);
+
+impl ParameterTrack {
+    /// 先頭から`end`サンプル目までを評価する
+    async fn evaluate(param: &DynGatherNativeParameter<f64>, end: u64) -> ParameterTrack {
+        let mut values = Vec::with_capacity(end.div_ceil(PARAMETER_INTERVAL) as usize + 1);
+        for i in 0..=end.div_ceil(PARAMETER_INTERVAL) {
+            let at = TimelineTime::new(MixedFraction::from_fraction((i * PARAMETER_INTERVAL) as i64, SAMPLE_RATE));
+            values.push(param.get_param(at).await.unwrap_or_default());
+        }
+        ParameterTrack(values)
+    }
+
+    /// `position`サンプル目の値 評価した点の間は線形に補間し、範囲の外は端の値を使う
+    fn value_at(&self, position: u64) -> f64 {
+        let index = (position / PARAMETER_INTERVAL) as usize;
+        match self.0.get(index..) {
+            Some(&[a, b, ..]) => {
+                let t = position % PARAMETER_INTERVAL;
+                (a * (PARAMETER_INTERVAL - t) as f64 + b * t as f64) / PARAMETER_INTERVAL as f64
+            }
+            _ => self.0.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// `AudioGenerator`が出力する音声
+///
+/// 生成は先頭からの順に進み、`CHECKPOINT_INTERVAL`ごとに生成の状態を保存しておく
+/// 前に戻って読まれたときは、その位置より前で最も近い保存した状態から生成をやり直す
+#[derive(Clone)]
+struct GeneratedAudio {
+    generator: Generator,
+    frequency: Arc<ParameterTrack>,
+    amplitude: Arc<ParameterTrack>,
+    /// `i`番目は`i * CHECKPOINT_INTERVAL`サンプル目を生成する直前の状態
+    checkpoints: Vec<GeneratorState>,
+    state: GeneratorState,
+    /// 次に生成するサンプルの位置
+    position: u64,
+}
+
+impl GeneratedAudio {
+    fn new(generator: Generator, frequency: ParameterTrack, amplitude: ParameterTrack) -> GeneratedAudio {
+        GeneratedAudio {
+            generator,
+            frequency: Arc::new(frequency),
+            amplitude: Arc::new(amplitude),
+            checkpoints: vec![GeneratorState::default()],
+            state: GeneratorState::default(),
+            position: 0,
+        }
+    }
+
+    fn next_sample(&mut self) -> f32 {
+        let position = self.position;
+        let value = self.generator.next_sample(&mut self.state, position, self.frequency.value_at(position), self.amplitude.value_at(position));
+        self.position += 1;
+        if self.position % CHECKPOINT_INTERVAL == 0 && self.position / CHECKPOINT_INTERVAL == self.checkpoints.len() as u64 {
+            self.checkpoints.push(self.state);
+        }
+        value as f32
+    }
+
+    /// 次に生成するサンプルを`position`サンプル目にする
+    fn seek(&mut self, position: u64) {
+        let index = ((position / CHECKPOINT_INTERVAL) as usize).min(self.checkpoints.len() - 1);
+        let checkpoint = index as u64 * CHECKPOINT_INTERVAL;
+        if position < self.position || self.position < checkpoint {
+            self.state = self.checkpoints[index];
+            self.position = checkpoint;
+        }
+        while self.position < position {
+            self.next_sample();
+        }
+    }
+}
+
+impl AudioProvider for GeneratedAudio {
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn channels(&self) -> usize {
+        1
+    }
+
+    fn compute_audio(&mut self, begin: TimelineTime, mut dst: MultiChannelAudioSliceMut<f32>) -> usize {
+        let (sec, smp) = begin.value().deconstruct_with_round(SAMPLE_RATE);
+        let begin = sec as i64 * SAMPLE_RATE as i64 + smp as i64;
+        self.seek(begin.max(0) as u64);
+        for (i, line) in dst.iter_mut().enumerate() {
+            let value = if begin + (i as i64) < 0 { 0. } else { self.next_sample() };
+            line.fill(value);
+        }
+        dst.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+    use mpdelta_core_audio::multi_channel_audio::{MultiChannelAudio, MultiChannelAudioOp};
+    use mpdelta_core_test_util::golden_audio::render_audio_range;
+    use std::convert::Infallible;
+
+    /// 時刻から値を決めるパラメータ
+    #[derive(Clone)]
+    struct Curve(fn(TimelineTime) -> f64);
+
+    impl GatherNativeParameter<f64> for Curve {
+        type Err = Infallible;
+        async fn get_param(&self, at: TimelineTime) -> Result<f64, Self::Err> {
+            Ok((self.0)(at))
+        }
+    }
+
+    fn sample(i: i64) -> TimelineTime {
+        TimelineTime::new(MixedFraction::from_fraction(i, SAMPLE_RATE))
+    }
+
+    fn track(f: fn(TimelineTime) -> f64, end: u64) -> ParameterTrack {
+        ParameterTrack::evaluate(&DynGatherNativeParameter::new(Curve(f)), end).now_or_never().unwrap()
+    }
+
+    fn audio(waveform: Waveform, frequency: ParameterTrack, amplitude: ParameterTrack) -> GeneratedAudio {
+        let settings = GeneratorSettings {
+            waveform,
+            sweep: Sweep::None,
+            sweep_end_frequency: 0.,
+            sweep_duration: 0.,
+        };
+        GeneratedAudio::new(Generator::new(settings), frequency, amplitude)
+    }
+
+    /// 4サンプルで1周期の矩形波で、振幅はサンプルの位置と同じ値
+    fn square_audio() -> GeneratedAudio {
+        audio(Waveform::Square, track(|_| SAMPLE_RATE as f64 / 4., 16), track(|at| at.value().into_f64() * SAMPLE_RATE as f64, 16))
+    }
+
+    fn compute(audio: &mut GeneratedAudio, begin: i64, len: usize) -> Vec<f32> {
+        let mut buffer = MultiChannelAudio::new(2);
+        buffer.resize(len, f32::NAN);
+        assert_eq!(audio.compute_audio(sample(begin), buffer.slice_mut(..).unwrap()), len);
+        assert!(buffer.iter().all(|line| line[0] == line[1]));
+        buffer.iter().map(|line| line[0]).collect()
+    }
+
+    #[test]
+    fn test_parameter_track() {
+        let track = track(|at| if at.value() < MixedFraction::from_fraction(1, 100) { 1. } else { 3. }, PARAMETER_INTERVAL * 2 - 1);
+        assert_eq!(track.0, [1., 3., 3.]);
+        assert_eq!(track.value_at(0), 1.);
+        assert_eq!(track.value_at(PARAMETER_INTERVAL / 4), 1.5);
+        assert_eq!(track.value_at(PARAMETER_INTERVAL / 2), 2.);
+        assert_eq!(track.value_at(PARAMETER_INTERVAL), 3.);
+        assert_eq!(track.value_at(PARAMETER_INTERVAL * 10), 3.);
+        assert_eq!(ParameterTrack(Vec::new()).value_at(0), 0.);
+    }
+
+    #[test]
+    fn test_sample_accurate_parameters() {
+        let audio = render_audio_range(square_audio(), sample(0)..sample(8));
+        assert_eq!(audio.as_linear(), [0., 1., -2., -3., 4., 5., -6., -7.]);
+    }
+
+    #[test]
+    fn test_seek() {
+        let mut audio = square_audio();
+        assert_eq!(compute(&mut audio, 0, 4), [0., 1., -2., -3.]);
+        assert_eq!(compute(&mut audio, 4, 2), [4., 5.]);
+        assert_eq!(compute(&mut audio, 9, 2), [9., -10.]);
+        assert_eq!(compute(&mut audio, 2, 2), [-2., -3.]);
+        assert_eq!(compute(&mut audio, -2, 4), [0., 0., 0., 1.]);
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let len = CHECKPOINT_INTERVAL as usize * 3;
+        let new_audio = || audio(Waveform::PinkNoise, track(|_| 0., len as u64), track(|at| 1. - at.value().into_f64() / 3., len as u64));
+        let expected = compute(&mut new_audio(), 0, len);
+        let mut audio = new_audio();
+        assert_eq!(compute(&mut audio, CHECKPOINT_INTERVAL as i64 * 2 + 10, 10), expected[len / 3 * 2 + 10..][..10]);
+        assert_eq!(audio.checkpoints.len(), 3);
+        // 直前のチェックポイントから生成し直す
+        assert_eq!(compute(&mut audio, CHECKPOINT_INTERVAL as i64 + 5, 5), expected[len / 3 + 5..][..5]);
+        assert_eq!(audio.position, CHECKPOINT_INTERVAL + 10);
+        assert_eq!(compute(&mut audio, 3, 5), expected[3..8]);
+        // 保存してあるチェックポイントまでは生成せずに進む
+        assert_eq!(compute(&mut audio, CHECKPOINT_INTERVAL as i64 * 2 + 1, 1), expected[len / 3 * 2 + 1..][..1]);
+        assert_eq!(audio.position, CHECKPOINT_INTERVAL * 2 + 2);
+    }
+}
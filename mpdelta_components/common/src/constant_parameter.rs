@@ -0,0 +1,39 @@
+use mpdelta_core::component::marker_pin::MarkerPinId;
+use mpdelta_core::component::parameter::value::{DynEditableLerpEasingValue, DynEditableSelfValue, EasingValue, LinearEasing};
+use mpdelta_core::component::parameter::{ParameterNullableValue, ParameterValueType};
+use mpdelta_core::time_split_value_persistent;
+use std::sync::Arc;
+
+/// コンポーネントの左端から右端まで変化しない、可変パラメータの初期値を作る
+#[derive(Debug, Clone, Copy)]
+pub struct ConstantParameter {
+    left: MarkerPinId,
+    right: MarkerPinId,
+}
+
+impl ConstantParameter {
+    pub fn new(left: MarkerPinId, right: MarkerPinId) -> ConstantParameter {
+        ConstantParameter { left, right }
+    }
+
+    pub fn string<T: ParameterValueType>(&self, value: &str) -> ParameterNullableValue<T> {
+        let ConstantParameter { left, right } = *self;
+        ParameterNullableValue::String(time_split_value_persistent![left, Some(EasingValue::new(DynEditableSelfValue(value.to_owned()), Arc::new(LinearEasing))), right])
+    }
+
+    pub fn integer<T: ParameterValueType>(&self, value: i64) -> ParameterNullableValue<T> {
+        let ConstantParameter { left, right } = *self;
+        ParameterNullableValue::Integer(time_split_value_persistent![left, Some(EasingValue::new(DynEditableSelfValue(value), Arc::new(LinearEasing))), right])
+    }
+
+    /// 後からキーフレームを打ったときに補間できるよう、線形補間の値として作る
+    pub fn real_number<T: ParameterValueType>(&self, value: f64) -> ParameterNullableValue<T> {
+        let ConstantParameter { left, right } = *self;
+        ParameterNullableValue::RealNumber(time_split_value_persistent![left, Some(EasingValue::new(DynEditableLerpEasingValue((value, value)), Arc::new(LinearEasing))), right])
+    }
+
+    pub fn boolean<T: ParameterValueType>(&self, value: bool) -> ParameterNullableValue<T> {
+        let ConstantParameter { left, right } = *self;
+        ParameterNullableValue::Boolean(time_split_value_persistent![left, Some(EasingValue::new(DynEditableSelfValue(value), Arc::new(LinearEasing))), right])
+    }
+}
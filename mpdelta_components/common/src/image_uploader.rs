@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+use mpdelta_core::component::parameter::ParameterValueType;
+use mpdelta_core_cpu::RgbaImage;
+use std::sync::Arc;
+#[cfg(feature = "vulkano")]
+use vulkano::command_buffer::allocator::CommandBufferAllocator;
+#[cfg(feature = "vulkano")]
+use vulkano::device::Queue;
+#[cfg(feature = "vulkano")]
+use vulkano::memory::allocator::{FreeListAllocator, GenericMemoryAllocator};
+
+#[cfg(feature = "vulkano")]
+pub use vulkano_uploader::VulkanoImageUploader;
+
+#[cfg(feature = "vulkano")]
+mod vulkano_uploader;
+
+/// CPUで描いた画像を出力する画像の型に変換する
+#[async_trait]
+pub trait ImageUploader: Send + Sync + 'static {
+    type Image: 'static + Clone + Send + Sync;
+    async fn upload(&self, image: Arc<RgbaImage>) -> Self::Image;
+}
+
+/// CPUで作った画像を[`ImageUploader`]で出力するコンポーネントクラス
+///
+/// `with_uploader`だけ実装すれば、出力する画像の型ごとのコンストラクタが使える
+pub trait WithImageUploader<T: ParameterValueType>: Sized {
+    fn with_uploader<U>(uploader: U) -> Self
+    where
+        U: ImageUploader<Image = T::Image>;
+
+    #[cfg(feature = "vulkano")]
+    fn new(queue: &Arc<Queue>, gpu_memory_allocator: &Arc<GenericMemoryAllocator<FreeListAllocator>>, command_buffer_allocator: Arc<dyn CommandBufferAllocator>) -> Self
+    where
+        T: ParameterValueType<Image = mpdelta_core_vulkano::ImageType>,
+    {
+        Self::with_uploader(VulkanoImageUploader::new(queue, gpu_memory_allocator, command_buffer_allocator))
+    }
+
+    /// 画像をGPUに転送せず、そのまま出力する
+    fn new_cpu() -> Self
+    where
+        T: ParameterValueType<Image = mpdelta_core_cpu::ImageType>,
+    {
+        Self::with_uploader(CpuImageUploader)
+    }
+}
+
+/// 転送せず、そのまま出力する
+pub struct CpuImageUploader;
+
+#[async_trait]
+impl ImageUploader for CpuImageUploader {
+    type Image = mpdelta_core_cpu::ImageType;
+
+    async fn upload(&self, image: Arc<RgbaImage>) -> mpdelta_core_cpu::ImageType {
+        mpdelta_core_cpu::ImageType(image)
+    }
+}
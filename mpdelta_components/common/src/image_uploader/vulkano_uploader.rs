@@ -0,0 +1,91 @@
+use super::ImageUploader;
+use async_trait::async_trait;
+use crossbeam_queue::SegQueue;
+use mpdelta_core_cpu::RgbaImage;
+use mpdelta_core_vulkano::ImageType;
+use std::sync::Arc;
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::command_buffer::allocator::CommandBufferAllocator;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferToImageInfo, PrimaryCommandBufferAbstract};
+use vulkano::device::Queue;
+use vulkano::format::Format;
+use vulkano::image::{Image, ImageCreateInfo, ImageUsage};
+use vulkano::memory::allocator::{AllocationCreateInfo, FreeListAllocator, GenericMemoryAllocator, MemoryTypeFilter};
+use vulkano::sync::{GpuFuture, HostAccessError};
+
+/// GPUのメモリに転送する
+pub struct VulkanoImageUploader {
+    queue: Arc<Queue>,
+    gpu_memory_allocator: Arc<GenericMemoryAllocator<FreeListAllocator>>,
+    command_buffer_allocator: Arc<dyn CommandBufferAllocator>,
+    image_buffer_queue: SegQueue<Subbuffer<[u8]>>,
+}
+
+impl VulkanoImageUploader {
+    pub fn new(queue: &Arc<Queue>, gpu_memory_allocator: &Arc<GenericMemoryAllocator<FreeListAllocator>>, command_buffer_allocator: Arc<dyn CommandBufferAllocator>) -> VulkanoImageUploader {
+        VulkanoImageUploader {
+            queue: Arc::clone(queue),
+            gpu_memory_allocator: Arc::clone(gpu_memory_allocator),
+            command_buffer_allocator,
+            image_buffer_queue: SegQueue::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ImageUploader for VulkanoImageUploader {
+    type Image = ImageType;
+
+    async fn upload(&self, image: Arc<RgbaImage>) -> ImageType {
+        let buffer_len = u64::from(image.width()) * u64::from(image.height()) * 4;
+
+        let mut buffer;
+        let mut buffer_lock = 'lock: {
+            if let Some(b) = self.image_buffer_queue.pop().and_then(|buffer| (buffer.len() >= buffer_len).then_some(buffer)) {
+                buffer = b;
+                match buffer.write() {
+                    Ok(buffer) => break 'lock buffer,
+                    Err(HostAccessError::AccessConflict(_)) => {}
+                    Err(err) => panic!("Unexpected error: {}", err),
+                }
+                self.image_buffer_queue.push(buffer);
+            }
+            buffer = Buffer::new_slice::<u8>(
+                Arc::clone(&self.gpu_memory_allocator) as Arc<_>,
+                BufferCreateInfo {
+                    usage: BufferUsage::TRANSFER_SRC,
+                    ..BufferCreateInfo::default()
+                },
+                AllocationCreateInfo {
+                    memory_type_filter: MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                    ..AllocationCreateInfo::default()
+                },
+                buffer_len,
+            )
+            .unwrap();
+            buffer.write().unwrap()
+        };
+        let flat_samples = image.as_flat_samples();
+        buffer_lock[..buffer_len as usize].copy_from_slice(&flat_samples.samples[..buffer_len as usize]);
+        drop(buffer_lock);
+        let gpu_image = Image::new(
+            Arc::clone(&self.gpu_memory_allocator) as Arc<_>,
+            ImageCreateInfo {
+                format: Format::R8G8B8A8_UNORM,
+                extent: [image.width(), image.height(), 1],
+                usage: ImageUsage::SAMPLED | ImageUsage::TRANSFER_DST,
+                ..ImageCreateInfo::default()
+            },
+            AllocationCreateInfo::default(),
+        )
+        .unwrap();
+        let command_buffer = {
+            let mut builder = AutoCommandBufferBuilder::primary(Arc::clone(&self.command_buffer_allocator), self.queue.queue_family_index(), CommandBufferUsage::OneTimeSubmit).unwrap();
+            builder.copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(buffer.slice(..buffer_len), Arc::clone(&gpu_image))).unwrap();
+            builder.build().unwrap()
+        };
+        command_buffer.execute(Arc::clone(&self.queue)).unwrap().then_signal_fence_and_flush().unwrap().await.unwrap();
+        self.image_buffer_queue.push(buffer);
+        ImageType(gpu_image)
+    }
+}
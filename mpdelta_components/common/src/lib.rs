@@ -1 +1,4 @@
 pub mod color;
+pub mod constant_parameter;
+pub mod image_uploader;
+pub mod triangle_rasterizer;
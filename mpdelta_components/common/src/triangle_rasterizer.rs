@@ -0,0 +1,88 @@
+use mpdelta_core_cpu::RgbaImage;
+
+/// Vulkanの標準サンプル位置での4xMSAA
+const SAMPLE_POSITIONS: [(f32, f32); 4] = [(0.375, 0.125), (0.875, 0.375), (0.125, 0.625), (0.625, 0.875)];
+
+/// テッセレーションした三角形を、GPUで4xMSAAを使って描いたときと同じ結果になるようにCPUで塗る
+///
+/// 後から塗った三角形はサンプル単位で前の色を上書きする
+pub struct TriangleRasterizer {
+    width: u32,
+    height: u32,
+    samples: Vec<[u8; 4]>,
+}
+
+impl TriangleRasterizer {
+    pub fn new(width: u32, height: u32) -> TriangleRasterizer {
+        TriangleRasterizer {
+            width,
+            height,
+            samples: vec![[0u8; 4]; width as usize * height as usize * SAMPLE_POSITIONS.len()],
+        }
+    }
+
+    /// ピクセル座標で与えた三角形を塗る
+    pub fn fill_triangle(&mut self, [p0, p1, p2]: [(f32, f32); 3], color: [u8; 4]) {
+        let TriangleRasterizer { width, height, ref mut samples } = *self;
+        let edge = |(ax, ay): (f32, f32), (bx, by): (f32, f32), (px, py): (f32, f32)| (bx - ax) * (py - ay) - (by - ay) * (px - ax);
+        let area = edge(p0, p1, p2);
+        if area == 0. || !area.is_finite() {
+            return;
+        }
+        let sign = area.signum();
+        let min_x = p0.0.min(p1.0).min(p2.0).floor().max(0.) as u32;
+        let min_y = p0.1.min(p1.1).min(p2.1).floor().max(0.) as u32;
+        let max_x = (p0.0.max(p1.0).max(p2.0).ceil().max(0.) as u32).min(width);
+        let max_y = (p0.1.max(p1.1).max(p2.1).ceil().max(0.) as u32).min(height);
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let pixel = (y as usize * width as usize + x as usize) * SAMPLE_POSITIONS.len();
+                for (sample, &(sx, sy)) in samples[pixel..][..SAMPLE_POSITIONS.len()].iter_mut().zip(&SAMPLE_POSITIONS) {
+                    let p = (x as f32 + sx, y as f32 + sy);
+                    if edge(p1, p2, p) * sign >= 0. && edge(p2, p0, p) * sign >= 0. && edge(p0, p1, p) * sign >= 0. {
+                        *sample = color;
+                    }
+                }
+            }
+        }
+    }
+
+    /// 各ピクセルのサンプルを平均する
+    pub fn resolve(self) -> RgbaImage {
+        let samples_per_pixel = SAMPLE_POSITIONS.len() as u32;
+        let data = self
+            .samples
+            .chunks_exact(SAMPLE_POSITIONS.len())
+            .flat_map(|pixel| {
+                let sum = pixel.iter().fold([0u32; 4], |mut sum, sample| {
+                    sum.iter_mut().zip(sample).for_each(|(sum, &c)| *sum += c as u32);
+                    sum
+                });
+                sum.map(|sum| ((sum + samples_per_pixel / 2) / samples_per_pixel) as u8)
+            })
+            .collect();
+        RgbaImage::from_raw(self.width, self.height, data).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_triangle_rasterizer() {
+        let mut rasterizer = TriangleRasterizer::new(4, 4);
+        // 左上半分の直角三角形
+        rasterizer.fill_triangle([(0., 0.), (4., 0.), (0., 4.)], [255, 0, 0, 255]);
+        // 向きが逆でも塗られ、前の色を上書きする
+        rasterizer.fill_triangle([(0., 0.), (0., 1.), (1., 0.)], [0, 0, 255, 255]);
+        // 面積0の三角形は無視される
+        rasterizer.fill_triangle([(0., 0.), (4., 4.), (2., 2.)], [0, 255, 0, 255]);
+        let image = rasterizer.resolve();
+        assert_eq!(image.dimensions(), (4, 4));
+        assert_eq!(image.get_pixel(1, 1).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(3, 3).0, [0; 4]);
+        assert_eq!(image.get_pixel(2, 1).0, [128, 0, 0, 128]);
+        assert_eq!(image.get_pixel(0, 0).0, [128, 0, 128, 255]);
+    }
+}
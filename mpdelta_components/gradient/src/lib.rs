@@ -0,0 +1,288 @@
+use crate::rasterize::{rasterize, ColorSpace, ColorStop, GradientKind, GradientStyle};
+use async_trait::async_trait;
+use mpdelta_component_common::color::parse_color;
+use mpdelta_component_common::constant_parameter::ConstantParameter;
+use mpdelta_component_common::image_uploader::{ImageUploader, WithImageUploader};
+use mpdelta_core::common::mixed_fraction::MixedFraction;
+use mpdelta_core::component::class::{ComponentClass, ComponentClassIdentifier};
+use mpdelta_core::component::instance::ComponentInstance;
+use mpdelta_core::component::marker_pin::{MarkerPin, MarkerPinId, MarkerTime};
+use mpdelta_core::component::parameter::value::DynEditableSingleValue;
+use mpdelta_core::component::parameter::{ImageRequiredParams, Never, Parameter, ParameterNullableValue, ParameterSelect, ParameterType, ParameterValueRaw, ParameterValueType, VariableParameterValue};
+use mpdelta_core::component::processor::{ComponentProcessor, ComponentProcessorNative, ComponentProcessorNativeDyn, ComponentProcessorWrapper, NativeProcessorInput, NativeProcessorRequest};
+use mpdelta_core::core::IdGenerator;
+use mpdelta_core::ptr::StaticPointer;
+use mpdelta_core::time::TimelineTime;
+use std::borrow::Cow;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+pub mod rasterize;
+
+/// 色の停止点の数の上限
+const MAX_STOPS: i64 = 64;
+
+pub struct GradientClass<T: ParameterValueType> {
+    parameter_type: Arc<[(String, ParameterType)]>,
+    processor: Arc<dyn ComponentProcessorNativeDyn<T>>,
+}
+
+impl<T> WithImageUploader<T> for GradientClass<T>
+where
+    T: ParameterValueType,
+{
+    fn with_uploader<U>(uploader: U) -> GradientClass<T>
+    where
+        U: ImageUploader<Image = T::Image>,
+    {
+        let parameter_type: Arc<[(String, ParameterType)]> = Arc::new([("stops".to_owned(), ParameterType::Integer(()))]);
+        GradientClass {
+            parameter_type: Arc::clone(&parameter_type),
+            processor: Arc::new(Gradient { parameter_type, uploader }),
+        }
+    }
+}
+
+/// 線形、放射状、円錐状のグラデーション
+///
+/// 固定パラメータ`stops`で色の停止点の数を決め、停止点ごとに位置と色のパラメータを持つ
+/// 色を解釈できない停止点は無視する
+/// CPUで描いてから`U`で出力する画像の型に変換する
+struct Gradient<U> {
+    parameter_type: Arc<[(String, ParameterType)]>,
+    uploader: U,
+}
+
+fn variable_parameter_types(stops: usize) -> Vec<(String, ParameterType)> {
+    let head = [
+        ("kind".to_owned(), Parameter::String(())),
+        ("interpolation".to_owned(), Parameter::String(())),
+        ("dither".to_owned(), Parameter::Boolean(())),
+        ("start_x".to_owned(), Parameter::RealNumber(())),
+        ("start_y".to_owned(), Parameter::RealNumber(())),
+        ("end_x".to_owned(), Parameter::RealNumber(())),
+        ("end_y".to_owned(), Parameter::RealNumber(())),
+    ];
+    let stops = (0..stops).flat_map(|i| [(format!("stop{i}_position"), Parameter::RealNumber(())), (format!("stop{i}_color"), Parameter::String(()))]);
+    head.into_iter().chain(stops).collect()
+}
+
+fn variable_parameters<T: ParameterValueType>(left: MarkerPinId, right: MarkerPinId) -> Vec<VariableParameterValue<ParameterNullableValue<T>>> {
+    let constant = ConstantParameter::new(left, right);
+    [
+        constant.string("linear"),
+        constant.string("oklab"),
+        constant.boolean(true),
+        constant.real_number(0.),
+        constant.real_number(0.5),
+        constant.real_number(1.),
+        constant.real_number(0.5),
+        constant.real_number(0.),
+        constant.string("black"),
+        constant.real_number(1.),
+        constant.string("white"),
+    ]
+    .into_iter()
+    .map(VariableParameterValue::new)
+    .collect()
+}
+
+#[async_trait]
+impl<T> ComponentClass<T> for GradientClass<T>
+where
+    T: ParameterValueType,
+{
+    fn human_readable_identifier(&self) -> &str {
+        "Gradient"
+    }
+
+    fn identifier(&self) -> ComponentClassIdentifier {
+        ComponentClassIdentifier {
+            namespace: Cow::Borrowed("mpdelta"),
+            name: Cow::Borrowed("Gradient"),
+            inner_identifier: Default::default(),
+        }
+    }
+
+    fn processor(&self) -> ComponentProcessorWrapper<T> {
+        ComponentProcessorWrapper::Native(Arc::clone(&self.processor))
+    }
+
+    async fn instantiate(&self, this: &StaticPointer<RwLock<dyn ComponentClass<T>>>, id: &dyn IdGenerator) -> ComponentInstance<T> {
+        let left = MarkerPin::new(id.generate_new(), MarkerTime::ZERO);
+        let right = MarkerPin::new(id.generate_new(), MarkerTime::new(MixedFraction::from_integer(1)).unwrap());
+        let image_required_params = ImageRequiredParams::new_default(left.id(), right.id());
+        let variable_parameters = variable_parameters(*left.id(), *right.id());
+        ComponentInstance::builder(this.clone(), left, right, Vec::new(), Arc::clone(&self.processor))
+            .image_required_params(image_required_params)
+            .fixed_parameters(Arc::clone(&self.parameter_type), Arc::new([Parameter::Integer(DynEditableSingleValue::new_self(2))]))
+            .variable_parameters(variable_parameter_types(2), variable_parameters.into_iter().collect())
+            .build(id)
+    }
+}
+
+#[async_trait]
+impl<T, U> ComponentProcessor<T> for Gradient<U>
+where
+    T: ParameterValueType,
+    U: ImageUploader<Image = T::Image>,
+{
+    async fn fixed_parameter_types(&self) -> &[(String, ParameterType)] {
+        &self.parameter_type
+    }
+
+    async fn update_variable_parameter(&self, fixed_params: &[ParameterValueRaw<T::Image, T::Audio>], variable_parameters: &mut Vec<(String, ParameterType)>) {
+        let [Parameter::Integer(stops)] = fixed_params else { panic!() };
+        *variable_parameters = variable_parameter_types((*stops).clamp(0, MAX_STOPS) as usize);
+    }
+
+    async fn num_interprocess_pins(&self, _: &[ParameterValueRaw<T::Image, T::Audio>]) -> usize {
+        0
+    }
+}
+
+#[async_trait]
+impl<T, U> ComponentProcessorNative<T> for Gradient<U>
+where
+    T: ParameterValueType,
+    U: ImageUploader<Image = T::Image>,
+{
+    type WholeComponentCacheKey = ();
+    type WholeComponentCacheValue = ();
+    type FramedCacheKey = GradientStyleKey;
+    /// 描いた画像とその大きさ 大きさが違えば描き直す
+    type FramedCacheValue = ((u32, u32), U::Image);
+
+    fn whole_component_cache_key(&self, _: &[ParameterValueRaw<T::Image, T::Audio>], _: &[TimelineTime]) -> Option<Self::WholeComponentCacheKey> {
+        None
+    }
+
+    fn framed_cache_key(&self, parameters: NativeProcessorInput<'_, T>, _: TimelineTime, _: Parameter<ParameterSelect>) -> Option<Self::FramedCacheKey> {
+        Some(GradientStyleKey::new(&gradient_style(parameters.variable_parameters)))
+    }
+
+    async fn natural_length(&self, _: &[ParameterValueRaw<T::Image, T::Audio>], _: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> Option<MarkerTime> {
+        None
+    }
+
+    async fn supports_output_type(&self, _: &[ParameterValueRaw<T::Image, T::Audio>], out: Parameter<ParameterSelect>, _: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> bool {
+        matches!(out, Parameter::Image(_))
+    }
+
+    async fn process(
+        &self,
+        parameters: NativeProcessorInput<'_, T>,
+        _time: TimelineTime,
+        output_type: Parameter<NativeProcessorRequest>,
+        _whole_component_cache: &mut Option<Arc<Self::WholeComponentCacheValue>>,
+        framed_cache: &mut Option<Arc<Self::FramedCacheValue>>,
+    ) -> ParameterValueRaw<T::Image, T::Audio> {
+        let Parameter::Image((width, height)) = output_type else { panic!() };
+        let size = (width.max(1), height.max(1));
+        if let Some((cached_size, image)) = framed_cache.as_deref() {
+            if *cached_size == size {
+                return Parameter::Image(image.clone());
+            }
+        }
+        let style = gradient_style(parameters.variable_parameters);
+        let image = tokio::task::spawn_blocking(move || rasterize(size.0, size.1, &style)).await.unwrap();
+        let image = self.uploader.upload(Arc::new(image)).await;
+        *framed_cache = Some(Arc::new((size, image.clone())));
+        Parameter::Image(image)
+    }
+}
+
+/// 同じグラデーションを描き直さないためのキャッシュのキー 実数はビット列で比べる
+#[derive(PartialEq, Eq, Hash)]
+struct GradientStyleKey {
+    kind: GradientKind,
+    color_space: ColorSpace,
+    start: (u64, u64),
+    end: (u64, u64),
+    stops: Vec<(u64, [u8; 4])>,
+    dither: bool,
+}
+
+impl GradientStyleKey {
+    fn new(style: &GradientStyle) -> GradientStyleKey {
+        GradientStyleKey {
+            kind: style.kind,
+            color_space: style.color_space,
+            start: (style.start.0.to_bits(), style.start.1.to_bits()),
+            end: (style.end.0.to_bits(), style.end.1.to_bits()),
+            stops: style.stops.iter().map(|stop| (stop.position.to_bits(), stop.color)).collect(),
+            dither: style.dither,
+        }
+    }
+}
+
+/// 知らない種類は線形、知らない補間の色空間はOklabにする 停止点は並び順のまま渡し、位置の並べ替えは描くときに行う
+fn gradient_style<Image>(parameters: &[ParameterValueRaw<Image, Never>]) -> GradientStyle
+where
+    Image: Send + Sync + Clone + 'static,
+{
+    let [Parameter::String(kind), Parameter::String(interpolation), Parameter::Boolean(dither), Parameter::RealNumber(start_x), Parameter::RealNumber(start_y), Parameter::RealNumber(end_x), Parameter::RealNumber(end_y), stops @ ..] = parameters else {
+        panic!()
+    };
+    let stops = stops
+        .chunks_exact(2)
+        .filter_map(|stop| {
+            let [Parameter::RealNumber(position), Parameter::String(color)] = stop else { panic!() };
+            Some(ColorStop { position: *position, color: parse_color(color)? })
+        })
+        .collect();
+    GradientStyle {
+        kind: kind.parse().unwrap_or(GradientKind::Linear),
+        color_space: interpolation.parse().unwrap_or(ColorSpace::Oklab),
+        start: (*start_x, *start_y),
+        end: (*end_x, *end_y),
+        stops,
+        dither: *dither,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variable_parameter_types() {
+        let types = variable_parameter_types(3);
+        assert_eq!(types.len(), 7 + 3 * 2);
+        assert_eq!(types[7].0, "stop0_position");
+        assert_eq!(types[12].0, "stop2_color");
+        assert!(matches!(types[12].1, Parameter::String(())));
+    }
+
+    #[test]
+    fn test_gradient_style() {
+        let parameters = |kind: &str, interpolation: &str, colors: &[&str]| -> Vec<ParameterValueRaw<(), Never>> {
+            let head = [
+                Parameter::String(kind.to_owned()),
+                Parameter::String(interpolation.to_owned()),
+                Parameter::Boolean(false),
+                Parameter::RealNumber(0.1),
+                Parameter::RealNumber(0.2),
+                Parameter::RealNumber(0.3),
+                Parameter::RealNumber(0.4),
+            ];
+            let stops = colors.iter().enumerate().flat_map(|(i, color)| [Parameter::RealNumber(i as f64), Parameter::String((*color).to_owned())]);
+            head.into_iter().chain(stops).collect()
+        };
+        assert_eq!(
+            gradient_style(&parameters("radial", "srgb", &["#f00", "blue"])),
+            GradientStyle {
+                kind: GradientKind::Radial,
+                color_space: ColorSpace::Srgb,
+                start: (0.1, 0.2),
+                end: (0.3, 0.4),
+                stops: vec![ColorStop { position: 0., color: [255, 0, 0, 255] }, ColorStop { position: 1., color: [0, 0, 255, 255] }],
+                dither: false,
+            }
+        );
+        let style = gradient_style(&parameters("unknown", "hsl", &["", "white", "not a color"]));
+        assert_eq!(style.kind, GradientKind::Linear);
+        assert_eq!(style.color_space, ColorSpace::Oklab);
+        assert_eq!(style.stops, vec![ColorStop { position: 1., color: [255; 4] }]);
+    }
+}
@@ -0,0 +1,278 @@
+use mpdelta_core_cpu::{Rgba, RgbaImage};
+use std::f64::consts::TAU;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GradientKind {
+    /// 始点から終点へ向かって変化する
+    Linear,
+    /// 始点を中心に、終点までの距離を半径として変化する
+    Radial,
+    /// 始点を中心に、終点の方向から時計回りに一周して変化する
+    Conic,
+}
+
+impl FromStr for GradientKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "linear" => Ok(GradientKind::Linear),
+            "radial" => Ok(GradientKind::Radial),
+            "conic" => Ok(GradientKind::Conic),
+            _ => Err(()),
+        }
+    }
+}
+
+/// 色を補間する色空間
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    Srgb,
+    LinearSrgb,
+    Oklab,
+}
+
+impl FromStr for ColorSpace {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "srgb" => Ok(ColorSpace::Srgb),
+            "linear" | "linear-srgb" => Ok(ColorSpace::LinearSrgb),
+            "oklab" => Ok(ColorSpace::Oklab),
+            _ => Err(()),
+        }
+    }
+}
+
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f64) -> f64 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    }
+}
+
+fn linear_srgb_to_oklab([r, g, b]: [f64; 3]) -> [f64; 3] {
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+    [0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s, 1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s, 0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s]
+}
+
+fn oklab_to_linear_srgb([l, a, b]: [f64; 3]) -> [f64; 3] {
+    let l_ = (l + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m_ = (l - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s_ = (l - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+    [4.0767416621 * l_ - 3.3077115913 * m_ + 0.2309699292 * s_, -1.2684380046 * l_ + 2.6097574011 * m_ - 0.3413193965 * s_, -0.0041960863 * l_ - 0.7034186147 * m_ + 1.7076147010 * s_]
+}
+
+impl ColorSpace {
+    /// 8bitのsRGBの色を、この色空間でアルファを乗算した値にする
+    fn premultiplied(self, [r, g, b, a]: [u8; 4]) -> [f64; 4] {
+        let srgb = [r, g, b].map(|c| c as f64 / 255.);
+        let [x, y, z] = match self {
+            ColorSpace::Srgb => srgb,
+            ColorSpace::LinearSrgb => srgb.map(srgb_to_linear),
+            ColorSpace::Oklab => linear_srgb_to_oklab(srgb.map(srgb_to_linear)),
+        };
+        let a = a as f64 / 255.;
+        [x * a, y * a, z * a, a]
+    }
+
+    /// アルファを乗算した値を、アルファを乗算していない0から1のsRGBの値に戻す
+    fn to_srgb(self, [x, y, z, a]: [f64; 4]) -> [f64; 4] {
+        if a <= 0. {
+            return [0.; 4];
+        }
+        let color = [x / a, y / a, z / a];
+        let [r, g, b] = match self {
+            ColorSpace::Srgb => color,
+            ColorSpace::LinearSrgb => color.map(linear_to_srgb),
+            ColorSpace::Oklab => oklab_to_linear_srgb(color).map(linear_to_srgb),
+        };
+        [r, g, b, a].map(|c| c.clamp(0., 1.))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorStop {
+    /// 0が始点、1が終点
+    pub position: f64,
+    pub color: [u8; 4],
+}
+
+/// グラデーションの形と色 座標は画像の幅と高さを1とした値
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradientStyle {
+    pub kind: GradientKind,
+    pub color_space: ColorSpace,
+    pub start: (f64, f64),
+    pub end: (f64, f64),
+    /// 位置の順に並んでいなくてもよい
+    pub stops: Vec<ColorStop>,
+    /// 8bitに量子化するときに順序付きディザリングをかけて縞模様を目立たなくする
+    pub dither: bool,
+}
+
+/// 4x4のベイヤー行列
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// 0から1の値を8bitにする ディザリングしても、ちょうど8bitで表せる値は変わらない
+fn quantize(value: f64, (x, y): (u32, u32), dither: bool) -> u8 {
+    let value = value * 255.;
+    let value = if dither { (value + (BAYER[y as usize % 4][x as usize % 4] as f64 + 0.5) / 16.).floor() } else { value.round() };
+    value.clamp(0., 255.) as u8
+}
+
+/// `width`x`height`の画像いっぱいにグラデーションを描く
+pub fn rasterize(width: u32, height: u32, style: &GradientStyle) -> RgbaImage {
+    let mut stops = style.stops.iter().map(|stop| (stop.position, style.color_space.premultiplied(stop.color))).collect::<Vec<_>>();
+    stops.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+    let (width_f, height_f) = (width as f64, height as f64);
+    let start = (style.start.0 * width_f, style.start.1 * height_f);
+    let (dx, dy) = (style.end.0 * width_f - start.0, style.end.1 * height_f - start.1);
+    let length_squared = dx * dx + dy * dy;
+    RgbaImage::from_fn(width, height, |x, y| {
+        let (px, py) = (x as f64 + 0.5 - start.0, y as f64 + 0.5 - start.1);
+        let t = match style.kind {
+            GradientKind::Linear if length_squared > 0. => (px * dx + py * dy) / length_squared,
+            GradientKind::Radial if length_squared > 0. => (px * px + py * py).sqrt() / length_squared.sqrt(),
+            GradientKind::Linear | GradientKind::Radial => 0.,
+            GradientKind::Conic => (py.atan2(px) - dy.atan2(dx)).rem_euclid(TAU) / TAU,
+        };
+        let Some(color) = color_at(&stops, t) else {
+            return Rgba([0; 4]);
+        };
+        Rgba(style.color_space.to_srgb(color).map(|c| quantize(c, (x, y), style.dither)))
+    })
+}
+
+/// 位置の順に並んだ色の停止点から、位置`t`の色を求める
+fn color_at(stops: &[(f64, [f64; 4])], t: f64) -> Option<[f64; 4]> {
+    let &(first_position, first_color) = stops.first()?;
+    if t <= first_position {
+        return Some(first_color);
+    }
+    let i = stops.partition_point(|&(position, _)| position <= t);
+    let Some(&(next_position, next_color)) = stops.get(i) else {
+        return stops.last().map(|&(_, color)| color);
+    };
+    let (position, color) = stops[i - 1];
+    let ratio = (t - position) / (next_position - position);
+    Some(std::array::from_fn(|c| color[c] + (next_color[c] - color[c]) * ratio))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+    const WHITE: [u8; 4] = [255; 4];
+
+    fn style(kind: GradientKind, color_space: ColorSpace, stops: &[(f64, [u8; 4])]) -> GradientStyle {
+        GradientStyle {
+            kind,
+            color_space,
+            start: (0., 0.5),
+            end: (1., 0.5),
+            stops: stops.iter().map(|&(position, color)| ColorStop { position, color }).collect(),
+            dither: false,
+        }
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(" Linear".parse(), Ok(GradientKind::Linear));
+        assert_eq!("radial".parse(), Ok(GradientKind::Radial));
+        assert_eq!("conic".parse(), Ok(GradientKind::Conic));
+        assert_eq!("diamond".parse::<GradientKind>(), Err(()));
+        assert_eq!("sRGB".parse(), Ok(ColorSpace::Srgb));
+        assert_eq!("linear".parse(), Ok(ColorSpace::LinearSrgb));
+        assert_eq!("linear-srgb".parse(), Ok(ColorSpace::LinearSrgb));
+        assert_eq!("oklab".parse(), Ok(ColorSpace::Oklab));
+        assert_eq!("hsl".parse::<ColorSpace>(), Err(()));
+    }
+
+    #[test]
+    fn test_color_space_round_trip() {
+        for color_space in [ColorSpace::Srgb, ColorSpace::LinearSrgb, ColorSpace::Oklab] {
+            for color in [[0, 0, 0, 255], [255, 255, 255, 255], [255, 0, 0, 128], [12, 200, 90, 255]] {
+                let srgb = color_space.to_srgb(color_space.premultiplied(color));
+                assert_eq!(srgb.map(|c| quantize(c, (0, 0), false)), color, "{color_space:?}");
+            }
+        }
+        let [l, a, b] = linear_srgb_to_oklab([1., 1., 1.]);
+        assert!((l - 1.).abs() < 1e-6 && a.abs() < 1e-6 && b.abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_rasterize_linear() {
+        let pixels = |color_space| rasterize(4, 1, &style(GradientKind::Linear, color_space, &[(0., BLACK), (1., WHITE)])).pixels().map(|p| p.0[0]).collect::<Vec<_>>();
+        assert_eq!(pixels(ColorSpace::Srgb), [32, 96, 159, 223]);
+        // 線形の空間で補間すると中間が明るくなる
+        let pixels = |color_space| rasterize(2, 1, &style(GradientKind::Linear, color_space, &[(0., BLACK), (0.5, BLACK), (0.5, WHITE), (1., WHITE)])).pixels().map(|p| p.0).collect::<Vec<_>>();
+        assert_eq!(pixels(ColorSpace::Srgb), [BLACK, WHITE]);
+        let mid = |color_space| rasterize(1, 1, &style(GradientKind::Linear, color_space, &[(0., BLACK), (1., WHITE)])).get_pixel(0, 0).0;
+        assert_eq!(mid(ColorSpace::Srgb), [128, 128, 128, 255]);
+        assert_eq!(mid(ColorSpace::LinearSrgb), [188, 188, 188, 255]);
+        assert_eq!(mid(ColorSpace::Oklab), [99, 99, 99, 255]);
+
+        // 範囲外は端の色になり、停止点は位置の順に並べ替えられる
+        let image = rasterize(4, 1, &style(GradientKind::Linear, ColorSpace::Srgb, &[(0.75, WHITE), (0.25, BLACK)]));
+        assert_eq!(image.get_pixel(0, 0).0, BLACK);
+        assert_eq!(image.get_pixel(3, 0).0, WHITE);
+
+        // 透明との補間はアルファを乗算して行うので色が暗くならない
+        let image = rasterize(1, 1, &style(GradientKind::Linear, ColorSpace::Srgb, &[(0., [255, 0, 0, 255]), (1., [0, 0, 255, 0])]));
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 128]);
+
+        assert!(rasterize(2, 2, &style(GradientKind::Linear, ColorSpace::Oklab, &[])).pixels().all(|p| p.0 == [0; 4]));
+    }
+
+    #[test]
+    fn test_rasterize_radial_and_conic() {
+        let radial = GradientStyle {
+            start: (0.5, 0.5),
+            end: (1., 0.5),
+            ..style(GradientKind::Radial, ColorSpace::Srgb, &[(0., BLACK), (1., WHITE)])
+        };
+        let image = rasterize(10, 10, &radial);
+        assert!(image.get_pixel(5, 5).0[0] < 40);
+        assert_eq!(image.get_pixel(0, 0).0, WHITE);
+        assert_eq!(image.get_pixel(5, 4).0, image.get_pixel(4, 5).0);
+
+        // 右向きから時計回りに一周する
+        let conic = GradientStyle {
+            start: (0.5, 0.5),
+            end: (1., 0.5),
+            ..style(GradientKind::Conic, ColorSpace::Srgb, &[(0., BLACK), (1., WHITE)])
+        };
+        let image = rasterize(2, 2, &conic);
+        assert_eq!(image.get_pixel(1, 1).0, [32, 32, 32, 255]);
+        assert_eq!(image.get_pixel(0, 1).0, [96, 96, 96, 255]);
+        assert_eq!(image.get_pixel(0, 0).0, [159, 159, 159, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [223, 223, 223, 255]);
+    }
+
+    #[test]
+    fn test_dither() {
+        // 8bitで表せない値は4x4の範囲で平均すると元の値になる
+        let sum = (0..4).flat_map(|y| (0..4).map(move |x| quantize(100.25 / 255., (x, y), true) as u32)).sum::<u32>();
+        assert_eq!(sum, 1604);
+        assert_eq!(quantize(100.25 / 255., (0, 0), false), 100);
+        // 8bitで表せる値は変わらない
+        assert!((0..4).all(|x| quantize(100. / 255., (x, x), true) == 100));
+        assert_eq!(quantize(1., (3, 0), true), 255);
+        assert_eq!(quantize(0., (3, 3), true), 0);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::sequence::{frame_index, frame_number, EndBehavior, FrameSource};
+use async_trait::async_trait;
+use mpdelta_component_common::image_uploader::{ImageUploader, WithImageUploader};
+use mpdelta_core::common::mixed_fraction::MixedFraction;
+use mpdelta_core::component::class::{ComponentClass, ComponentClassIdentifier};
+use mpdelta_core::component::instance::ComponentInstance;
+use mpdelta_core::component::marker_pin::{MarkerPin, MarkerTime};
+use mpdelta_core::component::parameter::value::{DynEditableSelfValue, DynEditableSingleValue};
+use mpdelta_core::component::parameter::{ImageRequiredParams, Parameter, ParameterSelect, ParameterType, ParameterValueRaw, ParameterValueType};
+use mpdelta_core::component::processor::{ComponentProcessor, ComponentProcessorNative, ComponentProcessorNativeDyn, ComponentProcessorWrapper, NativeProcessorInput, NativeProcessorRequest};
+use mpdelta_core::core::IdGenerator;
+use mpdelta_core::ptr::StaticPointer;
+use mpdelta_core::time::TimelineTime;
+use mpdelta_core_cpu::RgbaImage;
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+pub mod sequence;
+
+/// `frame_rate`が正でなく、ファイルにも表示時間が書かれていないときのフレームレート
+const DEFAULT_FRAME_RATE: f64 = 24.;
+
+pub struct ImageSequenceClass<T: ParameterValueType> {
+    parameter_type: Arc<[(String, ParameterType)]>,
+    processor: Arc<dyn ComponentProcessorNativeDyn<T>>,
+}
+
+impl<T> WithImageUploader<T> for ImageSequenceClass<T>
+where
+    T: ParameterValueType,
+{
+    fn with_uploader<U>(uploader: U) -> ImageSequenceClass<T>
+    where
+        U: ImageUploader<Image = T::Image>,
+    {
+        let parameter_type: Arc<[(String, ParameterType)]> = Arc::new([("pattern".to_owned(), ParameterType::String(())), ("frame_rate".to_owned(), ParameterType::RealNumber(())), ("end_behavior".to_owned(), ParameterType::String(()))]);
+        ImageSequenceClass {
+            parameter_type: Arc::clone(&parameter_type),
+            processor: Arc::new(ImageSequence { parameter_type, uploader }),
+        }
+    }
+}
+
+/// 連番画像やアニメーション画像を`frame_rate`で再生する
+///
+/// `pattern`は`frame_####.png`や`frame_%04d.exr`のような連番のパターン、ディレクトリ、またはGIFやAPNGのファイル
+/// `frame_rate`が正でなければアニメーション画像に書かれている表示時間を使う
+/// `end_behavior`は`hold`、`loop`、`ping-pong`のいずれかで、最後のフレームより後の時刻の振る舞いを決める
+struct ImageSequence<U> {
+    parameter_type: Arc<[(String, ParameterType)]>,
+    uploader: U,
+}
+
+/// 同じフレームを読み直さないためのキャッシュのキー
+///
+/// フレームの枚数を知らなくても決まるように、`end_behavior`を適用する前の通し番号を使う
+#[derive(PartialEq, Eq, Hash)]
+struct FrameKey {
+    pattern: String,
+    frame_rate: u64,
+    end_behavior: EndBehavior,
+    frame: u64,
+}
+
+#[async_trait]
+impl<T> ComponentClass<T> for ImageSequenceClass<T>
+where
+    T: ParameterValueType,
+{
+    fn human_readable_identifier(&self) -> &str {
+        "Image Sequence"
+    }
+
+    fn identifier(&self) -> ComponentClassIdentifier {
+        ComponentClassIdentifier {
+            namespace: Cow::Borrowed("mpdelta"),
+            name: Cow::Borrowed("ImageSequence"),
+            inner_identifier: Default::default(),
+        }
+    }
+
+    fn processor(&self) -> ComponentProcessorWrapper<T> {
+        ComponentProcessorWrapper::Native(Arc::clone(&self.processor))
+    }
+
+    async fn instantiate(&self, this: &StaticPointer<RwLock<dyn ComponentClass<T>>>, id: &dyn IdGenerator) -> ComponentInstance<T> {
+        let left = MarkerPin::new(id.generate_new(), MarkerTime::ZERO);
+        let right = MarkerPin::new(id.generate_new(), MarkerTime::new(MixedFraction::from_integer(1)).unwrap());
+        let image_required_params = ImageRequiredParams::new_default(left.id(), right.id());
+        ComponentInstance::builder(this.clone(), left, right, Vec::new(), Arc::clone(&self.processor))
+            .image_required_params(image_required_params)
+            .fixed_parameters(
+                Arc::clone(&self.parameter_type),
+                Arc::new([
+                    Parameter::String(DynEditableSingleValue::new(DynEditableSelfValue(String::new()))),
+                    Parameter::RealNumber(DynEditableSingleValue::new_self(DEFAULT_FRAME_RATE)),
+                    Parameter::String(DynEditableSingleValue::new(DynEditableSelfValue("hold".to_owned()))),
+                ]),
+            )
+            .build(id)
+    }
+}
+
+#[async_trait]
+impl<T, U> ComponentProcessor<T> for ImageSequence<U>
+where
+    T: ParameterValueType,
+    U: ImageUploader<Image = T::Image>,
+{
+    async fn fixed_parameter_types(&self) -> &[(String, ParameterType)] {
+        &self.parameter_type
+    }
+
+    async fn update_variable_parameter(&self, _: &[ParameterValueRaw<T::Image, T::Audio>], variable_parameters: &mut Vec<(String, ParameterType)>) {
+        variable_parameters.clear();
+    }
+
+    async fn num_interprocess_pins(&self, _: &[ParameterValueRaw<T::Image, T::Audio>]) -> usize {
+        0
+    }
+}
+
+#[async_trait]
+impl<T, U> ComponentProcessorNative<T> for ImageSequence<U>
+where
+    T: ParameterValueType,
+    U: ImageUploader<Image = T::Image>,
+{
+    type WholeComponentCacheKey = String;
+    type WholeComponentCacheValue = FrameSource;
+    type FramedCacheKey = FrameKey;
+    type FramedCacheValue = U::Image;
+
+    fn whole_component_cache_key(&self, fixed_parameters: &[ParameterValueRaw<T::Image, T::Audio>], _: &[TimelineTime]) -> Option<Self::WholeComponentCacheKey> {
+        let [Parameter::String(pattern), ..] = fixed_parameters else { panic!() };
+        Some(pattern.clone())
+    }
+
+    /// `frame_rate`が正でなければファイルを読むまでフレームの長さが分からないので、キャッシュしない
+    fn framed_cache_key(&self, parameters: NativeProcessorInput<'_, T>, time: TimelineTime, _: Parameter<ParameterSelect>) -> Option<Self::FramedCacheKey> {
+        let [Parameter::String(pattern), Parameter::RealNumber(frame_rate), Parameter::String(end_behavior)] = parameters.fixed_parameters else { panic!() };
+        (*frame_rate > 0.).then(|| FrameKey {
+            pattern: pattern.clone(),
+            frame_rate: frame_rate.to_bits(),
+            end_behavior: end_behavior.parse().unwrap_or(EndBehavior::Hold),
+            frame: frame_number(time.value().into_f64(), *frame_rate),
+        })
+    }
+
+    async fn natural_length(&self, fixed_params: &[ParameterValueRaw<T::Image, T::Audio>], cache: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> Option<MarkerTime> {
+        let source = setup_cache(cache, fixed_params).await;
+        let (frame_rate, end_behavior) = playback(fixed_params, &source);
+        if end_behavior != EndBehavior::Hold || source.is_empty() {
+            return None;
+        }
+        MarkerTime::new(MixedFraction::from_f64(source.len() as f64 / frame_rate))
+    }
+
+    async fn supports_output_type(&self, fixed_params: &[ParameterValueRaw<T::Image, T::Audio>], out: Parameter<ParameterSelect>, cache: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> bool {
+        let source = setup_cache(cache, fixed_params).await;
+        matches!(out, Parameter::Image(_)) && !source.is_empty()
+    }
+
+    async fn process(
+        &self,
+        parameters: NativeProcessorInput<'_, T>,
+        time: TimelineTime,
+        output_type: Parameter<NativeProcessorRequest>,
+        whole_component_cache: &mut Option<Arc<Self::WholeComponentCacheValue>>,
+        framed_cache: &mut Option<Arc<Self::FramedCacheValue>>,
+    ) -> ParameterValueRaw<T::Image, T::Audio> {
+        if let Some(image) = framed_cache.as_deref() {
+            return Parameter::Image(image.clone());
+        }
+        let Parameter::Image((width, height)) = output_type else { panic!() };
+        let source = setup_cache(whole_component_cache, parameters.fixed_parameters).await;
+        let (frame_rate, end_behavior) = playback(parameters.fixed_parameters, &source);
+        let frame = match frame_index(time.value().into_f64(), frame_rate, source.len(), end_behavior) {
+            Some(index) => tokio::task::spawn_blocking(move || source.frame(index)).await.unwrap(),
+            None => None,
+        };
+        let Some(frame) = frame else {
+            // 読めなかったときの透明な画像は要求された大きさで作るので、キャッシュしない
+            return Parameter::Image(self.uploader.upload(Arc::new(RgbaImage::new(width.max(1), height.max(1)))).await);
+        };
+        let image = self.uploader.upload(frame).await;
+        *framed_cache = Some(Arc::new(image.clone()));
+        Parameter::Image(image)
+    }
+}
+
+/// ディレクトリを読んだりアニメーション画像を展開したりするので、ブロックしてよいスレッドで行う
+async fn setup_cache<Image, Audio>(cache: &mut Option<Arc<FrameSource>>, fixed_parameters: &[ParameterValueRaw<Image, Audio>]) -> Arc<FrameSource>
+where
+    Image: Send + Sync + Clone + 'static,
+    Audio: Send + Sync + Clone + 'static,
+{
+    if let Some(source) = cache {
+        return Arc::clone(source);
+    }
+    let [Parameter::String(pattern), ..] = fixed_parameters else { panic!() };
+    let pattern = PathBuf::from(pattern);
+    let source = Arc::new(tokio::task::spawn_blocking(move || FrameSource::resolve(&pattern)).await.unwrap());
+    *cache = Some(Arc::clone(&source));
+    source
+}
+
+/// フレームレートと最後のフレームより後の振る舞い `end_behavior`が読めなければ最後のフレームを出し続ける
+fn playback<Image, Audio>(fixed_parameters: &[ParameterValueRaw<Image, Audio>], source: &FrameSource) -> (f64, EndBehavior)
+where
+    Image: Send + Sync + Clone + 'static,
+    Audio: Send + Sync + Clone + 'static,
+{
+    let [Parameter::String(_), Parameter::RealNumber(frame_rate), Parameter::String(end_behavior)] = fixed_parameters else { panic!() };
+    let frame_rate = if *frame_rate > 0. { *frame_rate } else { source.frame_duration().map_or(DEFAULT_FRAME_RATE, |duration| 1. / duration) };
+    (frame_rate, end_behavior.parse().unwrap_or(EndBehavior::Hold))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playback() {
+        let parameters = |frame_rate: f64, end_behavior: &str| -> [ParameterValueRaw<(), ()>; 3] { [Parameter::String(String::new()), Parameter::RealNumber(frame_rate), Parameter::String(end_behavior.to_owned())] };
+        let files = FrameSource::Files(Vec::new());
+        let animation = FrameSource::Decoded { frames: Vec::new(), frame_duration: Some(0.1) };
+        assert_eq!(playback(&parameters(30., "loop"), &files), (30., EndBehavior::Loop));
+        assert_eq!(playback(&parameters(30., "ping-pong"), &animation), (30., EndBehavior::PingPong));
+        assert_eq!(playback(&parameters(0., "hold"), &animation), (10., EndBehavior::Hold));
+        assert_eq!(playback(&parameters(-1., "unknown"), &files), (DEFAULT_FRAME_RATE, EndBehavior::Hold));
+    }
+}
@@ -0,0 +1,376 @@
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::{AnimationDecoder, DynamicImage, Frame, ImageFormat, ImageReader, ImageResult, Rgba};
+use mpdelta_core_cpu::RgbaImage;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// ディレクトリから連番画像として読むファイルの拡張子
+const IMAGE_EXTENSIONS: [&str; 4] = ["png", "exr", "jpg", "jpeg"];
+
+/// 最後のフレームより後の時刻の振る舞い
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EndBehavior {
+    /// 最後のフレームを表示し続ける
+    Hold,
+    /// 最初のフレームに戻って繰り返す
+    Loop,
+    /// 逆再生と再生を交互に繰り返す
+    PingPong,
+}
+
+impl FromStr for EndBehavior {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "hold" | "hold-last-frame" => Ok(EndBehavior::Hold),
+            "loop" => Ok(EndBehavior::Loop),
+            "ping-pong" | "pingpong" => Ok(EndBehavior::PingPong),
+            _ => Err(()),
+        }
+    }
+}
+
+/// 番号の位置を指定した連番画像のファイル名
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequencePattern {
+    prefix: String,
+    /// 0埋めした番号の桁数
+    width: usize,
+    suffix: String,
+}
+
+impl SequencePattern {
+    /// `frame_####.png`の`#`の並び、または`frame_%04d.png`の`%04d`や`%d`を番号の位置として解釈する
+    pub fn parse(file_name: &str) -> Option<SequencePattern> {
+        if let Some(start) = file_name.find('#') {
+            let width = file_name[start..].bytes().take_while(|&b| b == b'#').count();
+            return Some(SequencePattern {
+                prefix: file_name[..start].to_owned(),
+                width,
+                suffix: file_name[start + width..].to_owned(),
+            });
+        }
+        file_name.match_indices('%').find_map(|(start, _)| {
+            let spec = &file_name[start + 1..];
+            let digits = spec.bytes().take_while(u8::is_ascii_digit).count();
+            let rest = spec[digits..].strip_prefix('d')?;
+            let width = if digits == 0 { 1 } else { spec[..digits].parse().ok()? };
+            Some(SequencePattern {
+                prefix: file_name[..start].to_owned(),
+                width,
+                suffix: rest.to_owned(),
+            })
+        })
+    }
+
+    /// ファイル名がパターンに一致すれば、その番号を返す
+    ///
+    /// 番号は指定された桁数まで0埋めされている必要があり、それより長いときは0から始まってはいけない
+    pub fn match_file_name(&self, file_name: &str) -> Option<u64> {
+        let number = file_name.strip_prefix(&self.prefix)?.strip_suffix(&self.suffix)?;
+        if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) || number.len() < self.width || (number.len() > self.width && number.starts_with('0')) {
+            return None;
+        }
+        number.parse().ok()
+    }
+}
+
+/// 読み込むフレームの一覧
+pub enum FrameSource {
+    /// フレームごとの画像ファイル 必要になったときに読む
+    Files(Vec<PathBuf>),
+    /// アニメーション画像から展開したフレーム
+    Decoded {
+        frames: Vec<Arc<RgbaImage>>,
+        /// ファイルに書かれている1フレームの平均の表示時間(秒)
+        frame_duration: Option<f64>,
+    },
+}
+
+impl FrameSource {
+    /// `pattern`が指すフレームの一覧を作る
+    ///
+    /// ファイル名に番号の位置があれば一致するファイルを番号順に、ディレクトリならその中の画像をファイル名順に並べる
+    /// それ以外は1つの画像ファイルとして扱い、GIFとAPNGは全てのフレームを展開する
+    /// 番号が飛んでいても詰めて並べる
+    pub fn resolve(pattern: &Path) -> FrameSource {
+        if pattern.is_dir() {
+            let mut files = fs::read_dir(pattern)
+                .into_iter()
+                .flatten()
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().and_then(|extension| extension.to_str()).is_some_and(|extension| IMAGE_EXTENSIONS.iter().any(|e| extension.eq_ignore_ascii_case(e))))
+                .map(|path| (natural_key(&path.file_name().unwrap_or_default().to_string_lossy()), path))
+                .collect::<Vec<_>>();
+            files.sort();
+            return FrameSource::Files(files.into_iter().map(|(_, path)| path).collect());
+        }
+        if let Some(sequence) = pattern.file_name().and_then(|name| name.to_str()).and_then(SequencePattern::parse) {
+            let directory = pattern.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            let mut files = fs::read_dir(directory)
+                .into_iter()
+                .flatten()
+                .filter_map(|entry| {
+                    let entry = entry.ok()?;
+                    let number = sequence.match_file_name(entry.file_name().to_str()?)?;
+                    Some((number, entry.path()))
+                })
+                .collect::<Vec<_>>();
+            files.sort();
+            return FrameSource::Files(files.into_iter().map(|(_, path)| path).collect());
+        }
+        if !pattern.is_file() {
+            return FrameSource::Files(Vec::new());
+        }
+        decode_animation(pattern).unwrap_or_else(|_| FrameSource::Files(vec![pattern.to_owned()]))
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            FrameSource::Files(files) => files.len(),
+            FrameSource::Decoded { frames, .. } => frames.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// ファイルに書かれている1フレームの平均の表示時間(秒)
+    pub fn frame_duration(&self) -> Option<f64> {
+        match self {
+            FrameSource::Files(_) => None,
+            FrameSource::Decoded { frame_duration, .. } => *frame_duration,
+        }
+    }
+
+    /// `index`番目のフレーム 読めなければNone
+    pub fn frame(&self, index: usize) -> Option<Arc<RgbaImage>> {
+        match self {
+            FrameSource::Files(files) => load_frame(files.get(index)?).map(Arc::new),
+            FrameSource::Decoded { frames, .. } => frames.get(index).cloned(),
+        }
+    }
+}
+
+/// 数字の並びを数値の順に比べられるように、桁数を揃えたファイル名
+fn natural_key(name: &str) -> String {
+    let mut key = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_digit()) {
+        let len = rest[start..].bytes().take_while(u8::is_ascii_digit).count();
+        key.push_str(&rest[..start]);
+        key.push_str(&"0".repeat(20usize.saturating_sub(len)));
+        key.push_str(&rest[start..start + len]);
+        rest = &rest[start + len..];
+    }
+    key.push_str(rest);
+    key
+}
+
+/// GIFとAPNGを展開する それ以外の画像はそのまま1フレームとして扱う
+fn decode_animation(path: &Path) -> ImageResult<FrameSource> {
+    let format = ImageReader::open(path)?.with_guessed_format()?.format();
+    let frames = match format {
+        Some(ImageFormat::Gif) => GifDecoder::new(BufReader::new(File::open(path)?))?.into_frames().collect_frames()?,
+        Some(ImageFormat::Png) => {
+            let decoder = PngDecoder::new(BufReader::new(File::open(path)?))?;
+            if !decoder.is_apng()? {
+                return Ok(FrameSource::Files(vec![path.to_owned()]));
+            }
+            decoder.apng()?.into_frames().collect_frames()?
+        }
+        _ => return Ok(FrameSource::Files(vec![path.to_owned()])),
+    };
+    let total_ms = frames
+        .iter()
+        .map(|frame| {
+            let (numerator, denominator) = frame.delay().numer_denom_ms();
+            numerator as f64 / denominator as f64
+        })
+        .sum::<f64>();
+    let frame_duration = (total_ms > 0.).then(|| total_ms / frames.len() as f64 / 1000.);
+    let frames = frames.into_iter().map(Frame::into_buffer).map(Arc::new).collect();
+    Ok(FrameSource::Decoded { frames, frame_duration })
+}
+
+/// 画像ファイルを読む 浮動小数点数の画像はリニアなsRGBとして扱い、ガンマをかけて8bitにする
+pub fn load_frame(path: &Path) -> Option<RgbaImage> {
+    let image = ImageReader::open(path).ok()?.with_guessed_format().ok()?.decode().ok()?;
+    match image {
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            let image = image.into_rgba32f();
+            let to_u8 = |c: f32| (c.clamp(0., 1.) * 255.).round() as u8;
+            Some(RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+                let [r, g, b, a] = image.get_pixel(x, y).0;
+                Rgba([to_u8(linear_to_srgb(r)), to_u8(linear_to_srgb(g)), to_u8(linear_to_srgb(b)), to_u8(a)])
+            }))
+        }
+        image => Some(image.into_rgba8()),
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    }
+}
+
+/// `frame_rate`で並べたフレームのうち、時刻`time`(秒)が何番目のフレームの中にあるか 最後のフレームより後でもそのまま数える
+pub fn frame_number(time: f64, frame_rate: f64) -> u64 {
+    // フレームの境界ちょうどの時刻が、浮動小数点数の誤差で前のフレームにならないように少しずらす
+    (time * frame_rate + 1e-6).floor().max(0.) as u64
+}
+
+/// `frame_rate`で並べた`frame_count`枚のフレームのうち、時刻`time`(秒)に表示するフレームの番号
+pub fn frame_index(time: f64, frame_rate: f64, frame_count: usize, end_behavior: EndBehavior) -> Option<usize> {
+    if frame_count == 0 {
+        return None;
+    }
+    let frame = frame_number(time, frame_rate);
+    let count = frame_count as u64;
+    let index = match end_behavior {
+        EndBehavior::Hold => frame.min(count - 1),
+        EndBehavior::Loop => frame % count,
+        EndBehavior::PingPong if count == 1 => 0,
+        EndBehavior::PingPong => {
+            let period = count * 2 - 2;
+            let frame = frame % period;
+            if frame < count {
+                frame
+            } else {
+                period - frame
+            }
+        }
+    };
+    Some(index as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifEncoder;
+    use image::{Delay, Rgba32FImage};
+
+    const TEST_OUTPUT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../test_output/", env!("CARGO_PKG_NAME"));
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = Path::new(TEST_OUTPUT_DIR).join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn solid(value: u8) -> RgbaImage {
+        RgbaImage::from_pixel(2, 2, Rgba([value, 0, 0, 255]))
+    }
+
+    fn red_values(source: &FrameSource) -> Vec<u8> {
+        (0..source.len()).map(|i| source.frame(i).unwrap().get_pixel(0, 0).0[0]).collect()
+    }
+
+    #[test]
+    fn test_sequence_pattern() {
+        let pattern = |prefix: &str, width, suffix: &str| {
+            Some(SequencePattern {
+                prefix: prefix.to_owned(),
+                width,
+                suffix: suffix.to_owned(),
+            })
+        };
+        assert_eq!(SequencePattern::parse("frame_####.png"), pattern("frame_", 4, ".png"));
+        assert_eq!(SequencePattern::parse("shot.%04d.exr"), pattern("shot.", 4, ".exr"));
+        assert_eq!(SequencePattern::parse("100%_%d.png"), pattern("100%_", 1, ".png"));
+        assert_eq!(SequencePattern::parse("frame.png"), None);
+        assert_eq!(SequencePattern::parse("frame_%x.png"), None);
+
+        let pattern = SequencePattern::parse("frame_####.png").unwrap();
+        assert_eq!(pattern.match_file_name("frame_0012.png"), Some(12));
+        assert_eq!(pattern.match_file_name("frame_12345.png"), Some(12345));
+        assert_eq!(pattern.match_file_name("frame_12.png"), None);
+        assert_eq!(pattern.match_file_name("frame_01234.png"), None);
+        assert_eq!(pattern.match_file_name("frame_00a1.png"), None);
+        assert_eq!(pattern.match_file_name("frame_0001.exr"), None);
+        let pattern = SequencePattern::parse("%d.png").unwrap();
+        assert_eq!(pattern.match_file_name("0.png"), Some(0));
+        assert_eq!(pattern.match_file_name("10.png"), Some(10));
+        assert_eq!(pattern.match_file_name("01.png"), None);
+    }
+
+    #[test]
+    fn test_end_behavior() {
+        assert_eq!("Hold".parse(), Ok(EndBehavior::Hold));
+        assert_eq!("hold-last-frame".parse(), Ok(EndBehavior::Hold));
+        assert_eq!("loop".parse(), Ok(EndBehavior::Loop));
+        assert_eq!("ping-pong".parse(), Ok(EndBehavior::PingPong));
+        assert_eq!("bounce".parse::<EndBehavior>(), Err(()));
+    }
+
+    #[test]
+    fn test_frame_index() {
+        let indices = |end_behavior, frames| (0..10).map(|i| frame_index(i as f64 / 24., 24., frames, end_behavior).unwrap()).collect::<Vec<_>>();
+        assert_eq!(indices(EndBehavior::Hold, 4), [0, 1, 2, 3, 3, 3, 3, 3, 3, 3]);
+        assert_eq!(indices(EndBehavior::Loop, 4), [0, 1, 2, 3, 0, 1, 2, 3, 0, 1]);
+        assert_eq!(indices(EndBehavior::PingPong, 4), [0, 1, 2, 3, 2, 1, 0, 1, 2, 3]);
+        assert_eq!(indices(EndBehavior::PingPong, 1), [0; 10]);
+        assert_eq!(frame_index(0.99 / 24., 24., 4, EndBehavior::Hold), Some(0));
+        assert_eq!(frame_index(-1., 24., 4, EndBehavior::Loop), Some(0));
+        assert_eq!(frame_index(1., 0.5, 4, EndBehavior::Hold), Some(0));
+        assert_eq!(frame_index(0., 24., 0, EndBehavior::Hold), None);
+        assert_eq!(frame_number(10. / 24., 24.), 10);
+        assert_eq!(frame_number(-1., 24.), 0);
+    }
+
+    #[test]
+    fn test_natural_key() {
+        let mut names = ["f10.png", "f2.png", "g1.png", "f1.png"];
+        names.sort_by_key(|name| natural_key(name));
+        assert_eq!(names, ["f1.png", "f2.png", "f10.png", "g1.png"]);
+    }
+
+    #[test]
+    fn test_resolve_sequence() {
+        let dir = test_dir("resolve_sequence");
+        for (name, value) in [("frame_0003.png", 3), ("frame_0001.png", 1), ("frame_0010.png", 10), ("frame_0002.jpg", 2), ("other.png", 100)] {
+            solid(value).save(dir.join(name)).unwrap();
+        }
+        let source = FrameSource::resolve(&dir.join("frame_####.png"));
+        assert_eq!(red_values(&source), [1, 3, 10]);
+        assert_eq!(source.frame_duration(), None);
+        assert_eq!(FrameSource::resolve(&dir.join("frame_%04d.png")).len(), 3);
+        let source = FrameSource::resolve(&dir);
+        assert_eq!(source.len(), 5);
+        assert_eq!(red_values(&source)[4], 100);
+        let source = FrameSource::resolve(&dir.join("other.png"));
+        assert_eq!(red_values(&source), [100]);
+        assert!(FrameSource::resolve(&dir.join("missing.png")).is_empty());
+        assert!(source.frame(1).is_none());
+    }
+
+    #[test]
+    fn test_resolve_animation() {
+        let dir = test_dir("resolve_animation");
+        let path = dir.join("animation.gif");
+        let mut encoder = GifEncoder::new(File::create(&path).unwrap());
+        encoder.encode_frames([solid(0), solid(255)].map(|image| Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(100, 1)))).unwrap();
+        drop(encoder);
+        let source = FrameSource::resolve(&path);
+        assert_eq!(red_values(&source), [0, 255]);
+        assert_eq!(source.frame_duration(), Some(0.1));
+    }
+
+    #[test]
+    fn test_load_float_frame() {
+        let dir = test_dir("load_float_frame");
+        let path = dir.join("linear.exr");
+        DynamicImage::ImageRgba32F(Rgba32FImage::from_pixel(1, 1, image::Rgba([0.5, 0., 1., 1.]))).save(&path).unwrap();
+        assert_eq!(load_frame(&path).unwrap().get_pixel(0, 0).0, [188, 0, 255, 255]);
+    }
+}
@@ -1,6 +1,6 @@
 use async_trait::async_trait;
-use crossbeam_queue::SegQueue;
 use media_loader::{AudioReader, VideoReader};
+use mpdelta_component_common::image_uploader::{ImageUploader, WithImageUploader};
 use mpdelta_component_parameters::file_reader::FileReaderParam;
 use mpdelta_core::common::mixed_fraction::MixedFraction;
 use mpdelta_core::component::class::{ComponentClass, ComponentClassIdentifier};
@@ -13,53 +13,44 @@ use mpdelta_core::core::IdGenerator;
 use mpdelta_core::ptr::StaticPointer;
 use mpdelta_core::time::TimelineTime;
 use mpdelta_core_audio::AudioType;
-use mpdelta_core_vulkano::ImageType;
 use std::borrow::Cow;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::{Mutex as TokioMutex, RwLock};
 use uuid::Uuid;
-use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
-use vulkano::command_buffer::allocator::CommandBufferAllocator;
-use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferToImageInfo, PrimaryCommandBufferAbstract};
-use vulkano::device::Queue;
-use vulkano::format::Format;
-use vulkano::image::{Image, ImageCreateInfo, ImageUsage};
-use vulkano::memory::allocator::{AllocationCreateInfo, FreeListAllocator, GenericMemoryAllocator, MemoryTypeFilter};
-use vulkano::sync::{GpuFuture, HostAccessError};
 
 mod media_loader;
 
-pub struct FfmpegMultimediaLoaderClass {
-    processor: Arc<FfmpegMultimediaLoader>,
-}
-
-struct FfmpegMultimediaLoader {
+pub struct FfmpegMultimediaLoaderClass<T: ParameterValueType> {
     parameter_type: Arc<[(String, ParameterType)]>,
-    queue: Arc<Queue>,
-    gpu_memory_allocator: Arc<GenericMemoryAllocator<FreeListAllocator>>,
-    command_buffer_allocator: Arc<dyn CommandBufferAllocator>,
-    image_buffer_queue: SegQueue<Subbuffer<[u8]>>,
+    processor: Arc<dyn ComponentProcessorNativeDyn<T>>,
 }
 
-impl FfmpegMultimediaLoaderClass {
-    pub fn new(queue: &Arc<Queue>, gpu_memory_allocator: &Arc<GenericMemoryAllocator<FreeListAllocator>>, command_buffer_allocator: Arc<dyn CommandBufferAllocator>) -> FfmpegMultimediaLoaderClass {
+impl<T> WithImageUploader<T> for FfmpegMultimediaLoaderClass<T>
+where
+    T: ParameterValueType<Audio = AudioType>,
+{
+    fn with_uploader<U>(uploader: U) -> FfmpegMultimediaLoaderClass<T>
+    where
+        U: ImageUploader<Image = T::Image>,
+    {
+        let parameter_type: Arc<[(String, ParameterType)]> = Arc::new([("media_file".to_owned(), ParameterType::Binary(()))]);
         FfmpegMultimediaLoaderClass {
-            processor: Arc::new(FfmpegMultimediaLoader {
-                parameter_type: Arc::new([("media_file".to_owned(), ParameterType::Binary(()))]),
-                queue: Arc::clone(queue),
-                gpu_memory_allocator: Arc::clone(gpu_memory_allocator) as Arc<_>,
-                command_buffer_allocator,
-                image_buffer_queue: SegQueue::new(),
-            }),
+            parameter_type: Arc::clone(&parameter_type),
+            processor: Arc::new(FfmpegMultimediaLoader { parameter_type, uploader }),
         }
     }
 }
 
+struct FfmpegMultimediaLoader<U> {
+    parameter_type: Arc<[(String, ParameterType)]>,
+    uploader: U,
+}
+
 #[async_trait]
-impl<T> ComponentClass<T> for FfmpegMultimediaLoaderClass
+impl<T> ComponentClass<T> for FfmpegMultimediaLoaderClass<T>
 where
-    T: ParameterValueType<Image = ImageType, Audio = AudioType>,
+    T: ParameterValueType<Audio = AudioType>,
 {
     fn human_readable_identifier(&self) -> &str {
         "MultiMedia Loader (FFmpeg)"
@@ -74,7 +65,7 @@ where
     }
 
     fn processor(&self) -> ComponentProcessorWrapper<T> {
-        ComponentProcessorWrapper::Native(Arc::clone(&self.processor) as _)
+        ComponentProcessorWrapper::Native(Arc::clone(&self.processor))
     }
 
     async fn instantiate(&self, this: &StaticPointer<RwLock<dyn ComponentClass<T>>>, id: &dyn IdGenerator) -> ComponentInstance<T> {
@@ -83,18 +74,19 @@ where
         // TODO: Imageを含むかどうかや音声のチャンネル数はFixedParameterが決まらないと取得できないので良い感じにする
         let image_required_params = ImageRequiredParams::new_default(left.id(), right.id());
         let audio_required_params = AudioRequiredParams::new_default(left.id(), right.id(), 2);
-        ComponentInstance::builder(this.clone(), left, right, Vec::new(), Arc::clone(&self.processor) as Arc<dyn ComponentProcessorNativeDyn<T>>)
+        ComponentInstance::builder(this.clone(), left, right, Vec::new(), Arc::clone(&self.processor))
             .image_required_params(image_required_params)
             .audio_required_params(audio_required_params)
-            .fixed_parameters(Arc::clone(&self.processor.parameter_type), Arc::new([Parameter::Binary(DynEditableSingleValue::new(FileReaderParam::new(PathBuf::new())))]))
+            .fixed_parameters(Arc::clone(&self.parameter_type), Arc::new([Parameter::Binary(DynEditableSingleValue::new(FileReaderParam::new(PathBuf::new())))]))
             .build(id)
     }
 }
 
 #[async_trait]
-impl<T> ComponentProcessor<T> for FfmpegMultimediaLoader
+impl<T, U> ComponentProcessor<T> for FfmpegMultimediaLoader<U>
 where
-    T: ParameterValueType<Image = ImageType, Audio = AudioType>,
+    T: ParameterValueType<Audio = AudioType>,
+    U: ImageUploader<Image = T::Image>,
 {
     async fn fixed_parameter_types(&self) -> &[(String, ParameterType)] {
         &self.parameter_type
@@ -110,9 +102,10 @@ where
 }
 
 #[async_trait]
-impl<T> ComponentProcessorNative<T> for FfmpegMultimediaLoader
+impl<T, U> ComponentProcessorNative<T> for FfmpegMultimediaLoader<U>
 where
-    T: ParameterValueType<Image = ImageType, Audio = AudioType>,
+    T: ParameterValueType<Audio = AudioType>,
+    U: ImageUploader<Image = T::Image>,
 {
     type WholeComponentCacheKey = Uuid;
     type WholeComponentCacheValue = CachePair;
@@ -168,55 +161,7 @@ where
                 let mut guard = cache.video_reader.as_ref().unwrap().lock().await;
                 let image = guard.read_image_at(time);
                 drop(guard);
-                let buffer_len = u64::from(image.width()) * u64::from(image.height()) * 4;
-
-                let mut buffer;
-                let mut buffer_lock = 'lock: {
-                    if let Some(b) = self.image_buffer_queue.pop().and_then(|buffer| (buffer.len() >= buffer_len).then_some(buffer)) {
-                        buffer = b;
-                        match buffer.write() {
-                            Ok(buffer) => break 'lock buffer,
-                            Err(HostAccessError::AccessConflict(_)) => {}
-                            Err(err) => panic!("Unexpected error: {}", err),
-                        }
-                        self.image_buffer_queue.push(buffer);
-                    }
-                    buffer = Buffer::new_slice::<u8>(
-                        Arc::clone(&self.gpu_memory_allocator) as Arc<_>,
-                        BufferCreateInfo {
-                            usage: BufferUsage::TRANSFER_SRC,
-                            ..BufferCreateInfo::default()
-                        },
-                        AllocationCreateInfo {
-                            memory_type_filter: MemoryTypeFilter::HOST_RANDOM_ACCESS,
-                            ..AllocationCreateInfo::default()
-                        },
-                        buffer_len,
-                    )
-                    .unwrap();
-                    buffer.write().unwrap()
-                };
-                let flat_samples = image.as_flat_samples();
-                buffer_lock[..buffer_len as usize].copy_from_slice(&flat_samples.samples[..buffer_len as usize]);
-                drop(buffer_lock);
-                let gpu_image = Image::new(
-                    Arc::clone(&self.gpu_memory_allocator) as Arc<_>,
-                    ImageCreateInfo {
-                        format: Format::R8G8B8A8_UNORM,
-                        extent: [image.width(), image.height(), 1],
-                        usage: ImageUsage::SAMPLED | ImageUsage::TRANSFER_DST,
-                        ..ImageCreateInfo::default()
-                    },
-                    AllocationCreateInfo::default(),
-                )
-                .unwrap();
-                let command_buffer = {
-                    let mut builder = AutoCommandBufferBuilder::primary(Arc::clone(&self.command_buffer_allocator), self.queue.queue_family_index(), CommandBufferUsage::OneTimeSubmit).unwrap();
-                    builder.copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(buffer.slice(..buffer_len), Arc::clone(&gpu_image))).unwrap();
-                    builder.build().unwrap()
-                };
-                command_buffer.execute(Arc::clone(&self.queue)).unwrap().then_signal_fence_and_flush().unwrap().await.unwrap();
-                Parameter::Image(ImageType(gpu_image))
+                Parameter::Image(self.uploader.upload(image).await)
             }
             Parameter::Audio(()) => Parameter::Audio(cache.audio_reader.clone().map(AudioType::new).unwrap()),
             _ => unreachable!(),
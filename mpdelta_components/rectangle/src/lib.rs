@@ -16,25 +16,32 @@ use vulkano::command_buffer::allocator::CommandBufferAllocator;
 use vulkano::command_buffer::{AutoCommandBufferBuilder, ClearColorImageInfo, CommandBufferUsage, PrimaryCommandBufferAbstract};
 use vulkano::device::Queue;
 use vulkano::format::{ClearColorValue, Format};
-use vulkano::image::{Image, ImageCreateInfo, ImageUsage};
+use vulkano::image::{Image as VulkanoImage, ImageCreateInfo, ImageUsage};
 use vulkano::memory::allocator::{AllocationCreateInfo, FreeListAllocator, GenericMemoryAllocator, MemoryAllocator};
 use vulkano::sync::GpuFuture;
 
 #[derive(Debug, Clone)]
-pub struct RectangleClass(Arc<Rectangle>);
+pub struct RectangleClass<Image = ImageType>(Arc<Rectangle<Image>>);
 
+/// 1x1の白い画像を出力するだけのコンポーネント
 #[derive(Debug, Clone)]
-pub struct Rectangle(Arc<Image>);
+pub struct Rectangle<Image = ImageType>(Image);
 
-impl RectangleClass {
-    pub fn new(queue: Arc<Queue>, allocator: &Arc<GenericMemoryAllocator<FreeListAllocator>>, command_buffer_allocator: Arc<dyn CommandBufferAllocator>) -> RectangleClass {
+impl RectangleClass<ImageType> {
+    pub fn new(queue: Arc<Queue>, allocator: &Arc<GenericMemoryAllocator<FreeListAllocator>>, command_buffer_allocator: Arc<dyn CommandBufferAllocator>) -> RectangleClass<ImageType> {
         RectangleClass(Arc::new(Rectangle::new(queue, allocator, command_buffer_allocator)))
     }
 }
 
-impl Rectangle {
-    pub fn new(queue: Arc<Queue>, allocator: &Arc<GenericMemoryAllocator<FreeListAllocator>>, command_buffer_allocator: Arc<dyn CommandBufferAllocator>) -> Rectangle {
-        let image = Image::new(
+impl RectangleClass<mpdelta_core_cpu::ImageType> {
+    pub fn new_cpu() -> RectangleClass<mpdelta_core_cpu::ImageType> {
+        RectangleClass(Arc::new(Rectangle::new_cpu()))
+    }
+}
+
+impl Rectangle<ImageType> {
+    pub fn new(queue: Arc<Queue>, allocator: &Arc<GenericMemoryAllocator<FreeListAllocator>>, command_buffer_allocator: Arc<dyn CommandBufferAllocator>) -> Rectangle<ImageType> {
+        let image = VulkanoImage::new(
             Arc::clone(allocator) as Arc<dyn MemoryAllocator>,
             ImageCreateInfo {
                 format: Format::R8G8B8A8_UNORM,
@@ -53,12 +60,22 @@ impl Rectangle {
             })
             .unwrap();
         builder.build().unwrap().execute(queue).unwrap().then_signal_fence_and_flush().unwrap().wait(None).unwrap();
-        Rectangle(image)
+        Rectangle(ImageType(image))
+    }
+}
+
+impl Rectangle<mpdelta_core_cpu::ImageType> {
+    pub fn new_cpu() -> Rectangle<mpdelta_core_cpu::ImageType> {
+        Rectangle(mpdelta_core_cpu::ImageType::filled(1, 1, [255; 4]))
     }
 }
 
 #[async_trait]
-impl<T: ParameterValueType<Image = ImageType>> ComponentClass<T> for RectangleClass {
+impl<T, Image> ComponentClass<T> for RectangleClass<Image>
+where
+    T: ParameterValueType<Image = Image>,
+    Image: Clone + Send + Sync + 'static,
+{
     fn human_readable_identifier(&self) -> &str {
         "Rectangle"
     }
@@ -86,7 +103,11 @@ impl<T: ParameterValueType<Image = ImageType>> ComponentClass<T> for RectangleCl
 }
 
 #[async_trait]
-impl<T: ParameterValueType<Image = ImageType>> ComponentProcessor<T> for Rectangle {
+impl<T, Image> ComponentProcessor<T> for Rectangle<Image>
+where
+    T: ParameterValueType<Image = Image>,
+    Image: Clone + Send + Sync + 'static,
+{
     async fn fixed_parameter_types(&self) -> &[(String, ParameterType)] {
         &[]
     }
@@ -101,7 +122,11 @@ impl<T: ParameterValueType<Image = ImageType>> ComponentProcessor<T> for Rectang
 }
 
 #[async_trait]
-impl<T: ParameterValueType<Image = ImageType>> ComponentProcessorNative<T> for Rectangle {
+impl<T, Image> ComponentProcessorNative<T> for Rectangle<Image>
+where
+    T: ParameterValueType<Image = Image>,
+    Image: Clone + Send + Sync + 'static,
+{
     type WholeComponentCacheKey = ();
     type WholeComponentCacheValue = ();
     type FramedCacheKey = ();
@@ -131,6 +156,6 @@ impl<T: ParameterValueType<Image = ImageType>> ComponentProcessorNative<T> for R
         _whole_component_cache: &mut Option<Arc<Self::WholeComponentCacheValue>>,
         _framed_cache: &mut Option<Arc<Self::FramedCacheValue>>,
     ) -> ParameterValueRaw<T::Image, T::Audio> {
-        ParameterValueRaw::Image(ImageType(Arc::clone(&self.0)))
+        ParameterValueRaw::Image(self.0.clone())
     }
 }
@@ -0,0 +1,261 @@
+use crate::rasterize::{rasterize, ShapeKind, ShapeStyle, SIDES};
+use async_trait::async_trait;
+use mpdelta_component_common::color::parse_color;
+use mpdelta_component_common::constant_parameter::ConstantParameter;
+use mpdelta_component_common::image_uploader::{ImageUploader, WithImageUploader};
+use mpdelta_core::common::mixed_fraction::MixedFraction;
+use mpdelta_core::component::class::{ComponentClass, ComponentClassIdentifier};
+use mpdelta_core::component::instance::ComponentInstance;
+use mpdelta_core::component::marker_pin::{MarkerPin, MarkerPinId, MarkerTime};
+use mpdelta_core::component::parameter::{ImageRequiredParams, Never, Parameter, ParameterNullableValue, ParameterSelect, ParameterType, ParameterValueRaw, ParameterValueType, VariableParameterValue};
+use mpdelta_core::component::processor::{ComponentProcessor, ComponentProcessorNative, ComponentProcessorNativeDyn, ComponentProcessorWrapper, NativeProcessorInput, NativeProcessorRequest};
+use mpdelta_core::core::IdGenerator;
+use mpdelta_core::ptr::StaticPointer;
+use mpdelta_core::time::TimelineTime;
+use std::borrow::Cow;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+pub mod rasterize;
+
+pub struct ShapeClass<T: ParameterValueType> {
+    processor: Arc<dyn ComponentProcessorNativeDyn<T>>,
+}
+
+impl<T> WithImageUploader<T> for ShapeClass<T>
+where
+    T: ParameterValueType,
+{
+    fn with_uploader<U>(uploader: U) -> ShapeClass<T>
+    where
+        U: ImageUploader<Image = T::Image>,
+    {
+        ShapeClass { processor: Arc::new(Shape { uploader }) }
+    }
+}
+
+/// 塗りと線の色、角の丸み、形を指定できる図形
+///
+/// 図形は出力画像いっぱいに描かれ、大きさは画像の大きさで決まる
+/// 形をCPUで描いてから`U`で出力する画像の型に変換する
+struct Shape<U> {
+    uploader: U,
+}
+
+fn variable_parameter_types() -> Vec<(String, ParameterType)> {
+    vec![
+        ("shape".to_owned(), Parameter::String(())),
+        ("fill".to_owned(), Parameter::String(())),
+        ("stroke".to_owned(), Parameter::String(())),
+        ("stroke_width".to_owned(), Parameter::RealNumber(())),
+        ("corner_radius".to_owned(), Parameter::RealNumber(())),
+        ("sides".to_owned(), Parameter::Integer(())),
+        ("inner_radius".to_owned(), Parameter::RealNumber(())),
+    ]
+}
+
+fn variable_parameters<T: ParameterValueType>(left: MarkerPinId, right: MarkerPinId) -> Vec<VariableParameterValue<ParameterNullableValue<T>>> {
+    let constant = ConstantParameter::new(left, right);
+    [
+        constant.string("rectangle"),
+        constant.string("white"),
+        constant.string("transparent"),
+        constant.real_number(0.),
+        constant.real_number(0.),
+        constant.integer(5),
+        constant.real_number(0.5),
+    ]
+    .into_iter()
+    .map(VariableParameterValue::new)
+    .collect()
+}
+
+#[async_trait]
+impl<T> ComponentClass<T> for ShapeClass<T>
+where
+    T: ParameterValueType,
+{
+    fn human_readable_identifier(&self) -> &str {
+        "Shape"
+    }
+
+    fn identifier(&self) -> ComponentClassIdentifier {
+        ComponentClassIdentifier {
+            namespace: Cow::Borrowed("mpdelta"),
+            name: Cow::Borrowed("Shape"),
+            inner_identifier: Default::default(),
+        }
+    }
+
+    fn processor(&self) -> ComponentProcessorWrapper<T> {
+        ComponentProcessorWrapper::Native(Arc::clone(&self.processor))
+    }
+
+    async fn instantiate(&self, this: &StaticPointer<RwLock<dyn ComponentClass<T>>>, id: &dyn IdGenerator) -> ComponentInstance<T> {
+        let left = MarkerPin::new(id.generate_new(), MarkerTime::ZERO);
+        let right = MarkerPin::new(id.generate_new(), MarkerTime::new(MixedFraction::from_integer(1)).unwrap());
+        let image_required_params = ImageRequiredParams::new_default(left.id(), right.id());
+        let variable_parameters = variable_parameters(*left.id(), *right.id());
+        ComponentInstance::builder(this.clone(), left, right, Vec::new(), Arc::clone(&self.processor))
+            .image_required_params(image_required_params)
+            .variable_parameters(variable_parameter_types(), variable_parameters.into_iter().collect())
+            .build(id)
+    }
+}
+
+#[async_trait]
+impl<T, U> ComponentProcessor<T> for Shape<U>
+where
+    T: ParameterValueType,
+    U: ImageUploader<Image = T::Image>,
+{
+    async fn fixed_parameter_types(&self) -> &[(String, ParameterType)] {
+        &[]
+    }
+
+    async fn update_variable_parameter(&self, _: &[ParameterValueRaw<T::Image, T::Audio>], variable_parameters: &mut Vec<(String, ParameterType)>) {
+        *variable_parameters = variable_parameter_types();
+    }
+
+    async fn num_interprocess_pins(&self, _: &[ParameterValueRaw<T::Image, T::Audio>]) -> usize {
+        0
+    }
+}
+
+#[async_trait]
+impl<T, U> ComponentProcessorNative<T> for Shape<U>
+where
+    T: ParameterValueType,
+    U: ImageUploader<Image = T::Image>,
+{
+    type WholeComponentCacheKey = ();
+    type WholeComponentCacheValue = ();
+    type FramedCacheKey = ShapeStyleKey;
+    /// 描いた画像とその大きさ 大きさが違えば描き直す
+    type FramedCacheValue = ((u32, u32), U::Image);
+
+    fn whole_component_cache_key(&self, _: &[ParameterValueRaw<T::Image, T::Audio>], _: &[TimelineTime]) -> Option<Self::WholeComponentCacheKey> {
+        None
+    }
+
+    fn framed_cache_key(&self, parameters: NativeProcessorInput<'_, T>, _: TimelineTime, _: Parameter<ParameterSelect>) -> Option<Self::FramedCacheKey> {
+        Some(ShapeStyleKey::new(&shape_style(parameters.variable_parameters)))
+    }
+
+    async fn natural_length(&self, _: &[ParameterValueRaw<T::Image, T::Audio>], _: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> Option<MarkerTime> {
+        None
+    }
+
+    async fn supports_output_type(&self, _: &[ParameterValueRaw<T::Image, T::Audio>], out: Parameter<ParameterSelect>, _: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> bool {
+        matches!(out, Parameter::Image(_))
+    }
+
+    async fn process(
+        &self,
+        parameters: NativeProcessorInput<'_, T>,
+        _time: TimelineTime,
+        output_type: Parameter<NativeProcessorRequest>,
+        _whole_component_cache: &mut Option<Arc<Self::WholeComponentCacheValue>>,
+        framed_cache: &mut Option<Arc<Self::FramedCacheValue>>,
+    ) -> ParameterValueRaw<T::Image, T::Audio> {
+        let Parameter::Image((width, height)) = output_type else { panic!() };
+        let size = (width.max(1), height.max(1));
+        if let Some((cached_size, image)) = framed_cache.as_deref() {
+            if *cached_size == size {
+                return Parameter::Image(image.clone());
+            }
+        }
+        let style = shape_style(parameters.variable_parameters);
+        let image = tokio::task::spawn_blocking(move || rasterize(size.0, size.1, &style)).await.unwrap();
+        let image = self.uploader.upload(Arc::new(image)).await;
+        *framed_cache = Some(Arc::new((size, image.clone())));
+        Parameter::Image(image)
+    }
+}
+
+/// 同じ見た目の図形を描き直さないためのキャッシュのキー 実数はビット列で比べる
+#[derive(PartialEq, Eq, Hash)]
+struct ShapeStyleKey {
+    kind: ShapeKind,
+    fill: [u8; 4],
+    stroke: [u8; 4],
+    stroke_width: u64,
+    corner_radius: u64,
+    sides: u32,
+    inner_radius: u64,
+}
+
+impl ShapeStyleKey {
+    fn new(style: &ShapeStyle) -> ShapeStyleKey {
+        ShapeStyleKey {
+            kind: style.kind,
+            fill: style.fill,
+            stroke: style.stroke,
+            stroke_width: style.stroke_width.to_bits(),
+            corner_radius: style.corner_radius.to_bits(),
+            sides: style.sides,
+            inner_radius: style.inner_radius.to_bits(),
+        }
+    }
+}
+
+/// 知らない形は矩形、読めない塗りの色は白、線の色は透明として扱い、頂点数は[`SIDES`]の範囲に丸める
+fn shape_style<Image>(parameters: &[ParameterValueRaw<Image, Never>]) -> ShapeStyle
+where
+    Image: Send + Sync + Clone + 'static,
+{
+    let [Parameter::String(kind), Parameter::String(fill), Parameter::String(stroke), Parameter::RealNumber(stroke_width), Parameter::RealNumber(corner_radius), Parameter::Integer(sides), Parameter::RealNumber(inner_radius)] = parameters else {
+        panic!()
+    };
+    ShapeStyle {
+        kind: kind.parse().unwrap_or(ShapeKind::Rectangle),
+        fill: parse_color(fill).unwrap_or([255; 4]),
+        stroke: parse_color(stroke).unwrap_or([0; 4]),
+        stroke_width: *stroke_width,
+        corner_radius: *corner_radius,
+        sides: (*sides).clamp(*SIDES.start() as i64, *SIDES.end() as i64) as u32,
+        inner_radius: *inner_radius,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shape_style() {
+        let parameters = |kind: &str, fill: &str, stroke: &str| -> [ParameterValueRaw<(), Never>; 7] {
+            [
+                Parameter::String(kind.to_owned()),
+                Parameter::String(fill.to_owned()),
+                Parameter::String(stroke.to_owned()),
+                Parameter::RealNumber(2.),
+                Parameter::RealNumber(3.),
+                Parameter::Integer(6),
+                Parameter::RealNumber(0.4),
+            ]
+        };
+        assert_eq!(
+            shape_style(&parameters("star", "#f00", "blue")),
+            ShapeStyle {
+                kind: ShapeKind::Star,
+                fill: [255, 0, 0, 255],
+                stroke: [0, 0, 255, 255],
+                stroke_width: 2.,
+                corner_radius: 3.,
+                sides: 6,
+                inner_radius: 0.4,
+            }
+        );
+        let style = shape_style(&parameters("unknown", "not a color", "#zzz"));
+        assert_eq!(style.kind, ShapeKind::Rectangle);
+        assert_eq!(style.fill, [255; 4]);
+        assert_eq!(style.stroke, [0; 4]);
+
+        let mut parameters = parameters("polygon", "white", "black");
+        parameters[5] = Parameter::Integer(-1);
+        assert_eq!(shape_style(&parameters).sides, *SIDES.start());
+        parameters[5] = Parameter::Integer(i64::MAX);
+        assert_eq!(shape_style(&parameters).sides, *SIDES.end());
+    }
+}
@@ -0,0 +1,253 @@
+use mpdelta_core_cpu::{Rgba, RgbaImage};
+use std::f64::consts::{FRAC_PI_2, PI};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+/// `ShapeStyle::sides`として扱う範囲 範囲外の値はこの範囲に丸める
+pub const SIDES: RangeInclusive<u32> = 3..=1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShapeKind {
+    Rectangle,
+    Ellipse,
+    Polygon,
+    Star,
+}
+
+impl FromStr for ShapeKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "rectangle" | "rect" => Ok(ShapeKind::Rectangle),
+            "ellipse" | "circle" => Ok(ShapeKind::Ellipse),
+            "polygon" => Ok(ShapeKind::Polygon),
+            "star" => Ok(ShapeKind::Star),
+            _ => Err(()),
+        }
+    }
+}
+
+/// 図形の形と色 長さの単位は出力画像のピクセル
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapeStyle {
+    pub kind: ShapeKind,
+    pub fill: [u8; 4],
+    pub stroke: [u8; 4],
+    /// 線は図形の内側に引く
+    pub stroke_width: f64,
+    /// Rectangleの角の半径
+    pub corner_radius: f64,
+    /// Polygonの頂点数、Starのとがった部分の数 [`SIDES`]の範囲に丸めて使う
+    pub sides: u32,
+    /// Starの内側の頂点の、外側の頂点に対する半径の比
+    pub inner_radius: f64,
+}
+
+/// 画素ごとに作り直さずに済むよう、大きさの決まった図形の輪郭をまとめておく
+enum Outline {
+    Rectangle { corner_radius: f64 },
+    Ellipse,
+    Polygon(Vec<(f64, f64)>),
+}
+
+impl ShapeStyle {
+    fn outline(&self, (half_width, half_height): (f64, f64)) -> Outline {
+        let sides = self.sides.clamp(*SIDES.start(), *SIDES.end());
+        match self.kind {
+            ShapeKind::Rectangle => Outline::Rectangle {
+                corner_radius: self.corner_radius.clamp(0., half_width.min(half_height)),
+            },
+            ShapeKind::Ellipse => Outline::Ellipse,
+            ShapeKind::Polygon => {
+                let vertices = (0..sides).map(|i| {
+                    let angle = -FRAC_PI_2 + 2. * PI * i as f64 / sides as f64;
+                    (angle.cos() * half_width, angle.sin() * half_height)
+                });
+                Outline::Polygon(vertices.collect())
+            }
+            ShapeKind::Star => {
+                let n = sides * 2;
+                let inner = self.inner_radius.clamp(0., 1.);
+                let vertices = (0..n).map(|i| {
+                    let angle = -FRAC_PI_2 + 2. * PI * i as f64 / n as f64;
+                    let r = if i % 2 == 0 { 1. } else { inner };
+                    (angle.cos() * half_width * r, angle.sin() * half_height * r)
+                });
+                Outline::Polygon(vertices.collect())
+            }
+        }
+    }
+}
+
+impl Outline {
+    /// 画像の中心を原点とした点`(x, y)`から図形の輪郭までの符号付き距離 内側が負
+    fn signed_distance(&self, (half_width, half_height): (f64, f64), (x, y): (f64, f64)) -> f64 {
+        match self {
+            &Outline::Rectangle { corner_radius: r } => {
+                let qx = x.abs() - half_width + r;
+                let qy = y.abs() - half_height + r;
+                qx.max(0.).hypot(qy.max(0.)) + qx.max(qy).min(0.) - r
+            }
+            Outline::Ellipse => {
+                if half_width <= 0. || half_height <= 0. {
+                    return f64::INFINITY;
+                }
+                // 陰関数を勾配の大きさで割った近似
+                let k0 = (x / half_width).hypot(y / half_height);
+                let k1 = (x / (half_width * half_width)).hypot(y / (half_height * half_height));
+                if k1 == 0. {
+                    return -half_width.min(half_height);
+                }
+                k0 * (k0 - 1.) / k1
+            }
+            Outline::Polygon(vertices) => polygon_signed_distance(vertices, (x, y)),
+        }
+    }
+}
+
+/// 自己交差しない多角形からの符号付き距離
+fn polygon_signed_distance(vertices: &[(f64, f64)], (px, py): (f64, f64)) -> f64 {
+    let Some(&(x0, y0)) = vertices.first() else {
+        return f64::INFINITY;
+    };
+    let mut distance = (px - x0).powi(2) + (py - y0).powi(2);
+    let mut sign = 1.;
+    for (i, &(vx, vy)) in vertices.iter().enumerate() {
+        let (ux, uy) = vertices[(i + vertices.len() - 1) % vertices.len()];
+        let (ex, ey) = (ux - vx, uy - vy);
+        let (wx, wy) = (px - vx, py - vy);
+        let t = ((wx * ex + wy * ey) / (ex * ex + ey * ey)).clamp(0., 1.);
+        let t = if t.is_nan() { 0. } else { t };
+        distance = distance.min((wx - ex * t).powi(2) + (wy - ey * t).powi(2));
+        let c = [py >= vy, py < uy, ex * wy > ey * wx];
+        if c.iter().all(|&c| c) || c.iter().all(|&c| !c) {
+            sign = -sign;
+        }
+    }
+    sign * distance.sqrt()
+}
+
+/// `width`x`height`の画像いっぱいに図形を描く 縁は画素を覆う面積で近似してアンチエイリアスする
+pub fn rasterize(width: u32, height: u32, style: &ShapeStyle) -> RgbaImage {
+    let half = (width as f64 / 2., height as f64 / 2.);
+    let premultiplied = |[r, g, b, a]: [u8; 4]| {
+        let a = a as f64 / 255.;
+        [r as f64 / 255. * a, g as f64 / 255. * a, b as f64 / 255. * a, a]
+    };
+    let fill = premultiplied(style.fill);
+    let stroke = premultiplied(style.stroke);
+    let stroke_width = style.stroke_width.max(0.);
+    let outline = style.outline(half);
+    RgbaImage::from_fn(width, height, |x, y| {
+        let distance = outline.signed_distance(half, (x as f64 + 0.5 - half.0, y as f64 + 0.5 - half.1));
+        let outer = (0.5 - distance).clamp(0., 1.);
+        let inner = (0.5 - (distance + stroke_width)).clamp(0., 1.);
+        let color: [f64; 4] = std::array::from_fn(|i| fill[i] * inner + stroke[i] * (outer - inner));
+        let alpha = color[3];
+        if alpha <= 0. {
+            return Rgba([0; 4]);
+        }
+        let to_u8 = |c: f64| (c * 255.).round().clamp(0., 255.) as u8;
+        Rgba([to_u8(color[0] / alpha), to_u8(color[1] / alpha), to_u8(color[2] / alpha), to_u8(alpha)])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    fn style(kind: ShapeKind) -> ShapeStyle {
+        ShapeStyle {
+            kind,
+            fill: RED,
+            stroke: [0; 4],
+            stroke_width: 0.,
+            corner_radius: 0.,
+            sides: 5,
+            inner_radius: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_shape_kind_from_str() {
+        assert_eq!("rectangle".parse(), Ok(ShapeKind::Rectangle));
+        assert_eq!(" Ellipse ".parse(), Ok(ShapeKind::Ellipse));
+        assert_eq!("polygon".parse(), Ok(ShapeKind::Polygon));
+        assert_eq!("star".parse(), Ok(ShapeKind::Star));
+        assert_eq!("triangle".parse::<ShapeKind>(), Err(()));
+    }
+
+    #[test]
+    fn test_rasterize_rectangle() {
+        let image = rasterize(10, 6, &style(ShapeKind::Rectangle));
+        assert!(image.pixels().all(|p| p.0 == RED));
+
+        // 線は内側に引かれる
+        let image = rasterize(
+            10,
+            10,
+            &ShapeStyle {
+                stroke: BLUE,
+                stroke_width: 2.,
+                ..style(ShapeKind::Rectangle)
+            },
+        );
+        assert_eq!(image.get_pixel(0, 0).0, BLUE);
+        assert_eq!(image.get_pixel(1, 5).0, BLUE);
+        assert_eq!(image.get_pixel(2, 5).0, RED);
+        assert_eq!(image.get_pixel(5, 5).0, RED);
+
+        let image = rasterize(10, 10, &ShapeStyle { corner_radius: 4., ..style(ShapeKind::Rectangle) });
+        assert_eq!(image.get_pixel(0, 0).0, [0; 4]);
+        assert_eq!(image.get_pixel(0, 5).0, RED);
+        assert_eq!(image.get_pixel(5, 0).0, RED);
+        let [r, g, b, a] = image.get_pixel(1, 1).0;
+        assert_eq!([r, g, b], [255, 0, 0]);
+        assert!(0 < a && a < 255);
+    }
+
+    #[test]
+    fn test_rasterize_ellipse() {
+        let image = rasterize(20, 10, &style(ShapeKind::Ellipse));
+        assert_eq!(image.get_pixel(0, 0).0, [0; 4]);
+        assert_eq!(image.get_pixel(19, 9).0, [0; 4]);
+        assert_eq!(image.get_pixel(10, 5).0, RED);
+        assert_eq!(image.get_pixel(1, 5).0, RED);
+        assert!(image.get_pixel(10, 0).0[3] > 250);
+        assert_eq!(image.get_pixel(1, 1).0, [0; 4]);
+        assert_eq!(image.get_pixel(2, 0).0, [0; 4]);
+    }
+
+    #[test]
+    fn test_rasterize_polygon() {
+        // 上向きの三角形
+        let image = rasterize(20, 20, &ShapeStyle { sides: 3, ..style(ShapeKind::Polygon) });
+        assert_eq!(image.get_pixel(10, 10).0, RED);
+        assert_eq!(image.get_pixel(10, 3).0, RED);
+        assert_eq!(image.get_pixel(2, 2).0, [0; 4]);
+        assert_eq!(image.get_pixel(17, 2).0, [0; 4]);
+        assert_eq!(image.get_pixel(10, 16).0, [0; 4]);
+
+        let image = rasterize(20, 20, &style(ShapeKind::Star));
+        assert_eq!(image.get_pixel(10, 10).0, RED);
+        assert_eq!(image.get_pixel(10, 3).0, RED);
+        // 上のとがった部分と右上のとがった部分の間はくぼんでいる
+        assert_eq!(image.get_pixel(14, 3).0, [0; 4]);
+        // 内側の半径を1にすると正十角形になる
+        let image = rasterize(20, 20, &ShapeStyle { inner_radius: 1., ..style(ShapeKind::Star) });
+        assert_eq!(image.get_pixel(14, 3).0, RED);
+    }
+
+    #[test]
+    fn test_rasterize_sides_out_of_range() {
+        // 範囲外の頂点数は丸めて描く
+        for kind in [ShapeKind::Polygon, ShapeKind::Star] {
+            assert_eq!(rasterize(20, 20, &ShapeStyle { sides: 0, ..style(kind) }), rasterize(20, 20, &ShapeStyle { sides: *SIDES.start(), ..style(kind) }));
+            assert_eq!(rasterize(8, 8, &ShapeStyle { sides: u32::MAX, ..style(kind) }), rasterize(8, 8, &ShapeStyle { sides: *SIDES.end(), ..style(kind) }));
+        }
+    }
+}
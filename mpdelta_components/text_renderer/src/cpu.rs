@@ -0,0 +1,74 @@
+use crate::TextRasterizer;
+use async_trait::async_trait;
+use lyon_tessellation::VertexBuffers;
+use mpdelta_component_common::triangle_rasterizer::TriangleRasterizer;
+use mpdelta_core_cpu::ImageType;
+use shader_font_rendering::{FontVertex, GlyphStyle};
+
+/// VulkanoTextRasterizerと同じ結果になるようにCPUで三角形を塗る
+pub(crate) struct CpuTextRasterizer;
+
+#[async_trait]
+impl TextRasterizer for CpuTextRasterizer {
+    type Image = ImageType;
+
+    async fn rasterize(&self, width: u32, height: u32, buffers: &[VertexBuffers<FontVertex, u32>], glyph_style: &[GlyphStyle]) -> ImageType {
+        let mut rasterizer = TriangleRasterizer::new(width, height);
+        for VertexBuffers { vertices, indices } in buffers.iter().rev() {
+            for triangle in indices.chunks_exact(3) {
+                let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|i| vertices[i as usize]);
+                let style = glyph_style[a.glyph as usize];
+                let position = |vertex: FontVertex| (vertex.x * style.scale + style.offset_x, -vertex.y * style.scale + style.offset_y);
+                rasterizer.fill_triangle([position(a), position(b), position(c)], style.color.to_be_bytes());
+            }
+        }
+        ImageType::from(rasterizer.resolve())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(glyph: u32, size: f32) -> VertexBuffers<FontVertex, u32> {
+        let vertex = |x, y| FontVertex { x, y, glyph };
+        VertexBuffers {
+            vertices: vec![vertex(0., 0.), vertex(size, 0.), vertex(size, size), vertex(0., size)],
+            indices: vec![0, 1, 2, 0, 2, 3],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cpu_text_rasterizer() {
+        let glyph_style = [
+            GlyphStyle {
+                scale: 1.,
+                offset_x: 3.,
+                offset_y: 7.,
+                color: u32::from_be_bytes([255, 0, 0, 255]),
+            },
+            GlyphStyle {
+                scale: 1.,
+                offset_x: 2.,
+                offset_y: 8.,
+                color: u32::from_be_bytes([0, 0, 255, 255]),
+            },
+        ];
+        // 縁取りの上に塗りを上書きする
+        let ImageType(image) = CpuTextRasterizer.rasterize(10, 10, &[square(0, 4.), square(1, 6.)], &glyph_style).await;
+        assert_eq!(image.dimensions(), (10, 10));
+        assert_eq!(image.get_pixel(0, 0).0, [0; 4]);
+        assert_eq!(image.get_pixel(2, 2).0, [0, 0, 255, 255]);
+        assert_eq!(image.get_pixel(7, 7).0, [0, 0, 255, 255]);
+        assert_eq!(image.get_pixel(3, 3).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(6, 6).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(8, 8).0, [0; 4]);
+
+        // 半分だけ覆う画素はサンプルの平均になる
+        let ImageType(image) = CpuTextRasterizer.rasterize(4, 4, &[square(0, 1.5)], &[GlyphStyle { offset_y: 1.5, ..glyph_style[0] }]).await;
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [128, 0, 0, 128]);
+        assert_eq!(image.get_pixel(1, 1).0, [64, 0, 0, 64]);
+        assert_eq!(image.get_pixel(2, 2).0, [0; 4]);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::cpu::CpuTextRasterizer;
 use crate::rich_text::{RichTextParser, RichTextToken};
 use crate::shaping::{GlyphData, ShapingBuilder, ShapingBuilderSegment};
 use async_trait::async_trait;
@@ -62,6 +63,7 @@ use vulkano::shader::{ShaderModule, ShaderModuleCreateInfo};
 use vulkano::single_pass_renderpass;
 use vulkano::sync::{GpuFuture, HostAccessError};
 
+mod cpu;
 mod rich_text;
 mod shaping;
 
@@ -75,7 +77,19 @@ where
 {
     pub fn new(device: &Arc<Device>, queue: &Arc<Queue>, memory_allocator: &Arc<StandardMemoryAllocator>) -> TextRendererClass<T> {
         TextRendererClass {
-            processor: ComponentProcessorWrapper::Native(Arc::new(TextRenderer::new(device, queue, memory_allocator))),
+            processor: ComponentProcessorWrapper::Native(Arc::new(TextRenderer::new(VulkanoTextRasterizer::new(device, queue, memory_allocator)))),
+        }
+    }
+}
+
+impl<T> TextRendererClass<T>
+where
+    T: ParameterValueType<Image = mpdelta_core_cpu::ImageType>,
+{
+    /// GPUを使わずに描画する
+    pub fn new_cpu() -> TextRendererClass<T> {
+        TextRendererClass {
+            processor: ComponentProcessorWrapper::Native(Arc::new(TextRenderer::new(CpuTextRasterizer))),
         }
     }
 }
@@ -83,7 +97,7 @@ where
 #[async_trait]
 impl<T> ComponentClass<T> for TextRendererClass<T>
 where
-    T: ParameterValueType<Image = ImageType>,
+    T: ParameterValueType,
 {
     fn human_readable_identifier(&self) -> &str {
         "Text"
@@ -122,7 +136,26 @@ where
     }
 }
 
-struct TextRenderer {
+/// テッセレーションした文字を画像に描く
+///
+/// `buffers[0]`が文字の塗り、`buffers[1..]`が内側から順に縁取りで、外側のものから順に上書きして描く
+#[async_trait]
+trait TextRasterizer: Send + Sync + 'static {
+    type Image;
+    async fn rasterize(&self, width: u32, height: u32, buffers: &[VertexBuffers<FontVertex, u32>], glyph_style: &[GlyphStyle]) -> Self::Image;
+}
+
+struct TextRenderer<R> {
+    rasterizer: R,
+}
+
+impl<R> TextRenderer<R> {
+    fn new(rasterizer: R) -> TextRenderer<R> {
+        TextRenderer { rasterizer }
+    }
+}
+
+struct VulkanoTextRasterizer {
     queue: Arc<Queue>,
     render_pass: Arc<RenderPass>,
     pipeline: Arc<GraphicsPipeline>,
@@ -136,8 +169,8 @@ struct TextRenderer {
 
 const MULTISAMPLE: u32 = 4;
 
-impl TextRenderer {
-    fn new(device: &Arc<Device>, queue: &Arc<Queue>, memory_allocator: &Arc<StandardMemoryAllocator>) -> TextRenderer {
+impl VulkanoTextRasterizer {
+    fn new(device: &Arc<Device>, queue: &Arc<Queue>, memory_allocator: &Arc<StandardMemoryAllocator>) -> VulkanoTextRasterizer {
         let render_pass = single_pass_renderpass!(
             Arc::clone(device),
             attachments: {
@@ -228,7 +261,7 @@ impl TextRenderer {
         .unwrap();
         let command_buffer_allocator = StandardCommandBufferAllocator::new(Arc::clone(device), StandardCommandBufferAllocatorCreateInfo::default());
         let descriptor_set_allocator = StandardDescriptorSetAllocator::new(Arc::clone(device), StandardDescriptorSetAllocatorCreateInfo::default());
-        TextRenderer {
+        VulkanoTextRasterizer {
             queue: Arc::clone(queue),
             render_pass,
             pipeline,
@@ -243,9 +276,10 @@ impl TextRenderer {
 }
 
 #[async_trait]
-impl<T> ComponentProcessor<T> for TextRenderer
+impl<T, R> ComponentProcessor<T> for TextRenderer<R>
 where
-    T: ParameterValueType<Image = ImageType>,
+    T: ParameterValueType<Image = R::Image>,
+    R: TextRasterizer,
 {
     async fn fixed_parameter_types(&self) -> &[(String, ParameterType)] {
         &[]
@@ -292,9 +326,10 @@ macro_rules! get_or_create_buffer {
 }
 
 #[async_trait]
-impl<T> ComponentProcessorNative<T> for TextRenderer
+impl<T, R> ComponentProcessorNative<T> for TextRenderer<R>
 where
-    T: ParameterValueType<Image = ImageType>,
+    T: ParameterValueType<Image = R::Image>,
+    R: TextRasterizer,
 {
     type WholeComponentCacheKey = ();
     type WholeComponentCacheValue = ();
@@ -327,74 +362,87 @@ where
     ) -> ParameterValueRaw<T::Image, T::Audio> {
         let Parameter::Image((width, height)) = output_type else { panic!() };
         let [Parameter::String(text)] = parameters.variable_parameters else { panic!() };
-        let (builder, font_list) = parse(text).await;
-        let fonts = font_list.iter().map(|&(ref binary, index)| FontRef::from_index(binary, index as usize).unwrap()).collect::<Vec<_>>();
-        let result = builder.shape(&fonts, width as f32);
-        let mut fill = FillTessellator::new();
-        let mut stroke = StrokeTessellator::new();
-        let mut buffers = SmallVec::<[_; 4]>::new();
-        let mut scaler_context = ScaleContext::new();
-        let mut scaler = scaler_context.builder(fonts[0]).build();
-        let mut scaler_font_id = 0;
-        let mut units_per_em = fonts[0].metrics(&[]).units_per_em as f32;
-        let mut glyph_style = Vec::new();
-        for (GlyphData { x, y, font_id, font_size, glyph_id }, &TextData { color, ref outline }) in result.glyphs() {
-            if buffers.len() < outline.len() + 2 {
-                buffers.resize(outline.len() + 2, VertexBuffers::<_, u32>::new());
-            }
-            if scaler_font_id != font_id {
-                scaler = scaler_context.builder(fonts[font_id]).build();
-                scaler_font_id = font_id;
-                units_per_em = fonts[font_id].metrics(&[]).units_per_em as f32;
+        let (buffers, glyph_style) = tessellate(text, width).await;
+        Parameter::Image(self.rasterizer.rasterize(width, height, &buffers, &glyph_style).await)
+    }
+}
+
+async fn tessellate(text: &str, width: u32) -> (SmallVec<[VertexBuffers<FontVertex, u32>; 4]>, Vec<GlyphStyle>) {
+    let (builder, font_list) = parse(text).await;
+    let fonts = font_list.iter().map(|&(ref binary, index)| FontRef::from_index(binary, index as usize).unwrap()).collect::<Vec<_>>();
+    let result = builder.shape(&fonts, width as f32);
+    let mut fill = FillTessellator::new();
+    let mut stroke = StrokeTessellator::new();
+    let mut buffers = SmallVec::<[_; 4]>::new();
+    let mut scaler_context = ScaleContext::new();
+    let mut scaler = scaler_context.builder(fonts[0]).build();
+    let mut scaler_font_id = 0;
+    let mut units_per_em = fonts[0].metrics(&[]).units_per_em as f32;
+    let mut glyph_style = Vec::new();
+    for (GlyphData { x, y, font_id, font_size, glyph_id }, &TextData { color, ref outline }) in result.glyphs() {
+        if buffers.len() < outline.len() + 2 {
+            buffers.resize(outline.len() + 2, VertexBuffers::<_, u32>::new());
+        }
+        if scaler_font_id != font_id {
+            scaler = scaler_context.builder(fonts[font_id]).build();
+            scaler_font_id = font_id;
+            units_per_em = fonts[font_id].metrics(&[]).units_per_em as f32;
+        }
+        let glyph_style_template = GlyphStyle {
+            scale: font_size / units_per_em,
+            offset_x: x,
+            offset_y: y,
+            color: 0,
+        };
+        if let Some(glyph_outline) = scaler.scale_outline(glyph_id) {
+            if glyph_outline.verbs().is_empty() {
+                continue;
             }
-            let glyph_style_template = GlyphStyle {
-                scale: font_size / units_per_em,
-                offset_x: x,
-                offset_y: y,
-                color: 0,
-            };
-            if let Some(glyph_outline) = scaler.scale_outline(glyph_id) {
-                if glyph_outline.verbs().is_empty() {
-                    continue;
-                }
-                let one_px = units_per_em / font_size;
-                let tolerance = one_px / 2.;
-                fill.tessellate_with_ids(
-                    IdEventIter::new(glyph_outline.verbs()),
-                    &Points::new(glyph_outline.points()),
-                    None,
-                    &FillOptions::even_odd().with_tolerance(tolerance),
-                    &mut BuffersBuilder::new(&mut buffers[0], VertexCtor::new(glyph_style.len() as u32)),
-                )
-                .unwrap();
+            let one_px = units_per_em / font_size;
+            let tolerance = one_px / 2.;
+            fill.tessellate_with_ids(
+                IdEventIter::new(glyph_outline.verbs()),
+                &Points::new(glyph_outline.points()),
+                None,
+                &FillOptions::even_odd().with_tolerance(tolerance),
+                &mut BuffersBuilder::new(&mut buffers[0], VertexCtor::new(glyph_style.len() as u32)),
+            )
+            .unwrap();
+            glyph_style.push(GlyphStyle {
+                color: u32::from_be_bytes(color),
+                ..glyph_style_template
+            });
+            // outlineを一つ増やしているのは、そうしないと一番外側の透過部分といっしょにresolveされる部分が透明な黒(#00000000)とブレンドされてくすんでしまうため
+            // depth/stencilを上手く使えばもっと簡単に解決できる気がしている(TODO)
+            let outline_iter = outline.iter().copied().chain(iter::once((2., outline.last().map_or([color[0], color[1], color[2], 0], |&(_, [r, g, b, _])| [r, g, b, 0])))).scan(0., |sum, (width, color)| {
+                *sum += width;
+                Some((*sum, color))
+            });
+            for (buffer, (outline_width, outline_color)) in buffers[1..].iter_mut().zip(outline_iter) {
+                stroke
+                    .tessellate_with_ids(
+                        IdEventIter::new(glyph_outline.verbs()),
+                        &Points::new(glyph_outline.points()),
+                        None,
+                        &StrokeOptions::tolerance(tolerance).with_line_join(LineJoin::Round).with_line_width(outline_width * one_px),
+                        &mut BuffersBuilder::new(buffer, VertexCtor::new(glyph_style.len() as u32)),
+                    )
+                    .unwrap();
                 glyph_style.push(GlyphStyle {
-                    color: u32::from_be_bytes(color),
+                    color: u32::from_be_bytes(outline_color),
                     ..glyph_style_template
                 });
-                // outlineを一つ増やしているのは、そうしないと一番外側の透過部分といっしょにresolveされる部分が透明な黒(#00000000)とブレンドされてくすんでしまうため
-                // depth/stencilを上手く使えばもっと簡単に解決できる気がしている(TODO)
-                let outline_iter = outline.iter().copied().chain(iter::once((2., outline.last().map_or([color[0], color[1], color[2], 0], |&(_, [r, g, b, _])| [r, g, b, 0])))).scan(0., |sum, (width, color)| {
-                    *sum += width;
-                    Some((*sum, color))
-                });
-                for (buffer, (outline_width, outline_color)) in buffers[1..].iter_mut().zip(outline_iter) {
-                    stroke
-                        .tessellate_with_ids(
-                            IdEventIter::new(glyph_outline.verbs()),
-                            &Points::new(glyph_outline.points()),
-                            None,
-                            &StrokeOptions::tolerance(tolerance).with_line_join(LineJoin::Round).with_line_width(outline_width * one_px),
-                            &mut BuffersBuilder::new(buffer, VertexCtor::new(glyph_style.len() as u32)),
-                        )
-                        .unwrap();
-                    glyph_style.push(GlyphStyle {
-                        color: u32::from_be_bytes(outline_color),
-                        ..glyph_style_template
-                    });
-                }
             }
         }
+    }
+    (buffers, glyph_style)
+}
+
+#[async_trait]
+impl TextRasterizer for VulkanoTextRasterizer {
+    type Image = ImageType;
 
+    async fn rasterize(&self, width: u32, height: u32, buffers: &[VertexBuffers<FontVertex, u32>], glyph_style: &[GlyphStyle]) -> ImageType {
         let color_image = Image::new(
             Arc::clone(&self.memory_allocator) as Arc<dyn MemoryAllocator>,
             ImageCreateInfo {
@@ -425,7 +473,7 @@ where
         let index_buffer_len = buffers.iter().map(|buffer| buffer.indices.len()).sum::<usize>() as u64;
 
         if index_buffer_len == 0 {
-            return Parameter::Image(ImageType(color_resolve_image));
+            return ImageType(color_resolve_image);
         }
 
         get_or_create_buffer!(vertex_buffer, vertex_buffer_lock, self.vertex_buffer_queue, self.memory_allocator, vertex_buffer_len, BufferUsage::VERTEX_BUFFER);
@@ -446,7 +494,7 @@ where
         }
 
         get_or_create_buffer!(glyph_style_buffer, glyph_style_buffer_lock, self.glyph_style_buffer_queue, self.memory_allocator, glyph_style.len() as u64, BufferUsage::STORAGE_BUFFER);
-        glyph_style_buffer_lock[..glyph_style.len()].copy_from_slice(&glyph_style);
+        glyph_style_buffer_lock[..glyph_style.len()].copy_from_slice(glyph_style);
 
         drop(vertex_buffer_lock);
         drop(index_buffer_lock);
@@ -536,7 +584,7 @@ where
         self.index_buffer_queue.push(index_buffer);
         self.glyph_style_buffer_queue.push(glyph_style_buffer);
 
-        Parameter::Image(ImageType(color_resolve_image))
+        ImageType(color_resolve_image)
     }
 }
 
@@ -881,7 +929,7 @@ mod tests {
             },
             ..VulkanoConfig::default()
         });
-        let renderer = TextRenderer::new(context.device(), context.graphics_queue(), context.memory_allocator());
+        let renderer = TextRenderer::new(VulkanoTextRasterizer::new(context.device(), context.graphics_queue(), context.memory_allocator()));
         let Parameter::Image(ImageType(image)) = ComponentProcessorNative::<T>::process(
             &renderer,
             NativeProcessorInput {
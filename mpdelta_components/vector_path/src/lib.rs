@@ -0,0 +1,316 @@
+use crate::path_data::{parse_path_data, parse_svg};
+use crate::render::{render, PathStyle};
+use async_trait::async_trait;
+use lyon_tessellation::path::Path;
+use mpdelta_component_common::color::parse_color;
+use mpdelta_component_common::constant_parameter::ConstantParameter;
+use mpdelta_component_common::image_uploader::{ImageUploader, WithImageUploader};
+use mpdelta_component_parameters::file_reader::FileReaderParam;
+use mpdelta_core::common::mixed_fraction::MixedFraction;
+use mpdelta_core::component::class::{ComponentClass, ComponentClassIdentifier};
+use mpdelta_core::component::instance::ComponentInstance;
+use mpdelta_core::component::marker_pin::{MarkerPin, MarkerPinId, MarkerTime};
+use mpdelta_core::component::parameter::value::DynEditableSingleValue;
+use mpdelta_core::component::parameter::{AbstractFile, FileAbstraction, ImageRequiredParams, Never, Parameter, ParameterNullableValue, ParameterSelect, ParameterType, ParameterValueRaw, ParameterValueType, VariableParameterValue};
+use mpdelta_core::component::processor::{ComponentProcessor, ComponentProcessorNative, ComponentProcessorNativeDyn, ComponentProcessorWrapper, NativeProcessorInput, NativeProcessorRequest};
+use mpdelta_core::core::IdGenerator;
+use mpdelta_core::ptr::StaticPointer;
+use mpdelta_core::time::TimelineTime;
+use std::borrow::Cow;
+use std::io::{Read, Seek};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+pub mod path_data;
+pub mod render;
+
+pub struct VectorPathClass<T: ParameterValueType> {
+    parameter_type: Arc<[(String, ParameterType)]>,
+    processor: Arc<dyn ComponentProcessorNativeDyn<T>>,
+}
+
+impl<T> WithImageUploader<T> for VectorPathClass<T>
+where
+    T: ParameterValueType,
+{
+    fn with_uploader<U>(uploader: U) -> VectorPathClass<T>
+    where
+        U: ImageUploader<Image = T::Image>,
+    {
+        let parameter_type: Arc<[(String, ParameterType)]> = Arc::new([("svg_file".to_owned(), ParameterType::Binary(()))]);
+        VectorPathClass {
+            parameter_type: Arc::clone(&parameter_type),
+            processor: Arc::new(VectorPath { parameter_type, uploader }),
+        }
+    }
+}
+
+/// SVGのパスデータ、またはSVGファイルの`<path>`要素を塗りと線で描く
+///
+/// `svg_file`のパスと`path`のパスの両方を描く
+/// `trim_start`と`trim_end`で線を描く範囲を絞れるので、動かすと線を描いていくアニメーションになる
+/// テキストと同様にlyonでテッセレーションし、CPUで塗ってから`U`で出力する画像の型に変換する
+struct VectorPath<U> {
+    parameter_type: Arc<[(String, ParameterType)]>,
+    uploader: U,
+}
+
+/// `svg_file`から読んだパスと、最後に`path`パラメータのパス
+struct SvgFile {
+    view_box: Option<[f32; 4]>,
+    /// `paths`の最後の要素の元になったパスデータ
+    path_data: String,
+    paths: Vec<Path>,
+}
+
+impl SvgFile {
+    fn new(view_box: Option<[f32; 4]>, mut paths: Vec<Path>, path_data: &str) -> SvgFile {
+        paths.push(parse_path_data(path_data));
+        SvgFile { view_box, path_data: path_data.to_owned(), paths }
+    }
+}
+
+fn variable_parameter_types() -> Vec<(String, ParameterType)> {
+    vec![
+        ("path".to_owned(), Parameter::String(())),
+        ("fill".to_owned(), Parameter::String(())),
+        ("stroke".to_owned(), Parameter::String(())),
+        ("stroke_width".to_owned(), Parameter::RealNumber(())),
+        ("trim_start".to_owned(), Parameter::RealNumber(())),
+        ("trim_end".to_owned(), Parameter::RealNumber(())),
+    ]
+}
+
+fn variable_parameters<T: ParameterValueType>(left: MarkerPinId, right: MarkerPinId) -> Vec<VariableParameterValue<ParameterNullableValue<T>>> {
+    let constant = ConstantParameter::new(left, right);
+    [constant.string(""), constant.string("white"), constant.string("transparent"), constant.real_number(1.), constant.real_number(0.), constant.real_number(1.)]
+        .into_iter()
+        .map(VariableParameterValue::new)
+        .collect()
+}
+
+#[async_trait]
+impl<T> ComponentClass<T> for VectorPathClass<T>
+where
+    T: ParameterValueType,
+{
+    fn human_readable_identifier(&self) -> &str {
+        "Vector Path"
+    }
+
+    fn identifier(&self) -> ComponentClassIdentifier {
+        ComponentClassIdentifier {
+            namespace: Cow::Borrowed("mpdelta"),
+            name: Cow::Borrowed("VectorPath"),
+            inner_identifier: Default::default(),
+        }
+    }
+
+    fn processor(&self) -> ComponentProcessorWrapper<T> {
+        ComponentProcessorWrapper::Native(Arc::clone(&self.processor))
+    }
+
+    async fn instantiate(&self, this: &StaticPointer<RwLock<dyn ComponentClass<T>>>, id: &dyn IdGenerator) -> ComponentInstance<T> {
+        let left = MarkerPin::new(id.generate_new(), MarkerTime::ZERO);
+        let right = MarkerPin::new(id.generate_new(), MarkerTime::new(MixedFraction::from_integer(1)).unwrap());
+        let image_required_params = ImageRequiredParams::new_default(left.id(), right.id());
+        let variable_parameters = variable_parameters(*left.id(), *right.id());
+        ComponentInstance::builder(this.clone(), left, right, Vec::new(), Arc::clone(&self.processor))
+            .image_required_params(image_required_params)
+            .fixed_parameters(Arc::clone(&self.parameter_type), Arc::new([Parameter::Binary(DynEditableSingleValue::new(FileReaderParam::new(PathBuf::new())))]))
+            .variable_parameters(variable_parameter_types(), variable_parameters.into_iter().collect())
+            .build(id)
+    }
+}
+
+#[async_trait]
+impl<T, U> ComponentProcessor<T> for VectorPath<U>
+where
+    T: ParameterValueType,
+    U: ImageUploader<Image = T::Image>,
+{
+    async fn fixed_parameter_types(&self) -> &[(String, ParameterType)] {
+        &self.parameter_type
+    }
+
+    async fn update_variable_parameter(&self, _: &[ParameterValueRaw<T::Image, T::Audio>], variable_parameters: &mut Vec<(String, ParameterType)>) {
+        *variable_parameters = variable_parameter_types();
+    }
+
+    async fn num_interprocess_pins(&self, _: &[ParameterValueRaw<T::Image, T::Audio>]) -> usize {
+        0
+    }
+}
+
+#[async_trait]
+impl<T, U> ComponentProcessorNative<T> for VectorPath<U>
+where
+    T: ParameterValueType,
+    U: ImageUploader<Image = T::Image>,
+{
+    type WholeComponentCacheKey = Uuid;
+    type WholeComponentCacheValue = SvgFile;
+    type FramedCacheKey = VectorPathKey;
+    /// 描いた画像とその大きさ 大きさが違えば描き直す
+    type FramedCacheValue = ((u32, u32), U::Image);
+
+    fn whole_component_cache_key(&self, fixed_parameters: &[ParameterValueRaw<T::Image, T::Audio>], _: &[TimelineTime]) -> Option<Self::WholeComponentCacheKey> {
+        let [Parameter::Binary(file)] = fixed_parameters else { panic!() };
+        Some(file.identifier())
+    }
+
+    fn framed_cache_key(&self, parameters: NativeProcessorInput<'_, T>, _: TimelineTime, _: Parameter<ParameterSelect>) -> Option<Self::FramedCacheKey> {
+        let [Parameter::Binary(file)] = parameters.fixed_parameters else { panic!() };
+        let (path_data, style) = path_style(parameters.variable_parameters);
+        Some(VectorPathKey::new(file.identifier(), path_data, &style))
+    }
+
+    async fn natural_length(&self, _: &[ParameterValueRaw<T::Image, T::Audio>], _: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> Option<MarkerTime> {
+        None
+    }
+
+    async fn supports_output_type(&self, _: &[ParameterValueRaw<T::Image, T::Audio>], out: Parameter<ParameterSelect>, _: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> bool {
+        matches!(out, Parameter::Image(_))
+    }
+
+    async fn process(
+        &self,
+        parameters: NativeProcessorInput<'_, T>,
+        _time: TimelineTime,
+        output_type: Parameter<NativeProcessorRequest>,
+        whole_component_cache: &mut Option<Arc<Self::WholeComponentCacheValue>>,
+        framed_cache: &mut Option<Arc<Self::FramedCacheValue>>,
+    ) -> ParameterValueRaw<T::Image, T::Audio> {
+        let NativeProcessorInput {
+            fixed_parameters: [Parameter::Binary(file)],
+            variable_parameters,
+            ..
+        } = parameters
+        else {
+            panic!()
+        };
+        let Parameter::Image((width, height)) = output_type else { panic!() };
+        let size = (width.max(1), height.max(1));
+        if let Some((cached_size, image)) = framed_cache.as_deref() {
+            if *cached_size == size {
+                return Parameter::Image(image.clone());
+            }
+        }
+        let (path_data, style) = path_style(variable_parameters);
+        let svg_file = setup_cache(whole_component_cache, file, path_data);
+        let image = tokio::task::spawn_blocking(move || render(size.0, size.1, &svg_file.paths, svg_file.view_box, &style)).await.unwrap();
+        let image = self.uploader.upload(Arc::new(image)).await;
+        *framed_cache = Some(Arc::new((size, image.clone())));
+        Parameter::Image(image)
+    }
+}
+
+/// 同じパスを同じ見た目で描き直さないためのキャッシュのキー 実数はビット列で比べる
+#[derive(PartialEq, Eq, Hash)]
+struct VectorPathKey {
+    file: Uuid,
+    path_data: String,
+    fill: [u8; 4],
+    stroke: [u8; 4],
+    stroke_width: u32,
+    trim_start: u32,
+    trim_end: u32,
+}
+
+impl VectorPathKey {
+    fn new(file: Uuid, path_data: &str, style: &PathStyle) -> VectorPathKey {
+        VectorPathKey {
+            file,
+            path_data: path_data.to_owned(),
+            fill: style.fill,
+            stroke: style.stroke,
+            stroke_width: style.stroke_width.to_bits(),
+            trim_start: style.trim_start.to_bits(),
+            trim_end: style.trim_end.to_bits(),
+        }
+    }
+}
+
+/// 読めないファイルはパスを含まないものとして扱う `path_data`は前回と変わったときだけ解釈し直す
+fn setup_cache(cache: &mut Option<Arc<SvgFile>>, file: &AbstractFile, path_data: &str) -> Arc<SvgFile> {
+    let svg_file = match cache.take() {
+        Some(svg_file) if svg_file.path_data == path_data => svg_file,
+        Some(svg_file) => Arc::new(SvgFile::new(svg_file.view_box, svg_file.paths[..svg_file.paths.len() - 1].to_vec(), path_data)),
+        None => {
+            let mut file = file.clone();
+            let mut source = String::new();
+            if file.rewind().and_then(|_| file.read_to_string(&mut source)).is_err() {
+                source.clear();
+            }
+            let document = parse_svg(&source);
+            Arc::new(SvgFile::new(document.view_box, document.paths.iter().map(|path| parse_path_data(&path.data).transformed(&path.transform)).collect(), path_data))
+        }
+    };
+    *cache = Some(Arc::clone(&svg_file));
+    svg_file
+}
+
+/// 読めない塗りの色は白、線の色は透明として扱う パスデータはそのまま返し、解釈は`parse_path_data`に任せる
+fn path_style<Image>(parameters: &[ParameterValueRaw<Image, Never>]) -> (&str, PathStyle)
+where
+    Image: Send + Sync + Clone + 'static,
+{
+    let [Parameter::String(path_data), Parameter::String(fill), Parameter::String(stroke), Parameter::RealNumber(stroke_width), Parameter::RealNumber(trim_start), Parameter::RealNumber(trim_end)] = parameters else {
+        panic!()
+    };
+    let style = PathStyle {
+        fill: parse_color(fill).unwrap_or([255; 4]),
+        stroke: parse_color(stroke).unwrap_or([0; 4]),
+        stroke_width: *stroke_width as f32,
+        trim_start: *trim_start as f32,
+        trim_end: *trim_end as f32,
+    };
+    (path_data.as_str(), style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_style() {
+        let parameters: [ParameterValueRaw<(), Never>; 6] = [
+            Parameter::String("M0 0 H1".to_owned()),
+            Parameter::String("#f00".to_owned()),
+            Parameter::String("not a color".to_owned()),
+            Parameter::RealNumber(2.),
+            Parameter::RealNumber(0.25),
+            Parameter::RealNumber(0.75),
+        ];
+        assert_eq!(
+            path_style(&parameters),
+            (
+                "M0 0 H1",
+                PathStyle {
+                    fill: [255, 0, 0, 255],
+                    stroke: [0; 4],
+                    stroke_width: 2.,
+                    trim_start: 0.25,
+                    trim_end: 0.75,
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_setup_cache() {
+        let mut cache = None;
+        let svg_file = setup_cache(&mut cache, &AbstractFile::default(), "M0 0 H1");
+        assert_eq!(svg_file.view_box, None);
+        assert_eq!(svg_file.paths.len(), 1);
+        // パスデータが変わらなければ解釈し直さない
+        assert!(Arc::ptr_eq(&setup_cache(&mut cache, &AbstractFile::default(), "M0 0 H1"), &svg_file));
+        let changed = setup_cache(&mut cache, &AbstractFile::default(), "M0 0 V1");
+        assert!(!Arc::ptr_eq(&changed, &svg_file));
+        assert_eq!(changed.path_data, "M0 0 V1");
+        assert_eq!(changed.paths.len(), 1);
+        assert!(Arc::ptr_eq(cache.as_ref().unwrap(), &changed));
+    }
+}
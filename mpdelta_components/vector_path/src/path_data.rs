@@ -0,0 +1,538 @@
+use lyon_tessellation::math::{point, vector, Angle, Point, Transform};
+use lyon_tessellation::path::geom::{ArcFlags, SvgArc};
+use lyon_tessellation::path::path::Builder;
+use lyon_tessellation::path::Path;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::borrow::Cow;
+
+/// SVGのパスデータ(`d`属性)を解釈する
+///
+/// SVGの仕様と同様に、解釈できない箇所があればその手前までのパスを返す
+pub fn parse_path_data(data: &str) -> Path {
+    let mut parser = Parser { data: data.as_bytes(), position: 0 };
+    let mut builder = PathDataBuilder {
+        builder: Path::builder(),
+        current: point(0., 0.),
+        subpath_start: point(0., 0.),
+        in_subpath: false,
+        previous_cubic_control: None,
+        previous_quadratic_control: None,
+    };
+    let mut command = None;
+    let mut started = false;
+    loop {
+        parser.skip_separators();
+        let Some(&c) = parser.data.get(parser.position) else { break };
+        if c.is_ascii_alphabetic() {
+            parser.position += 1;
+            if c.eq_ignore_ascii_case(&b'z') {
+                builder.close();
+                command = None;
+                continue;
+            }
+            command = Some(c);
+        }
+        let Some(c) = command else { break };
+        // パスデータはmovetoから始まらなければならない
+        if !started && !c.eq_ignore_ascii_case(&b'm') {
+            break;
+        }
+        started = true;
+        if builder.command(c, &mut parser).is_none() {
+            break;
+        }
+        // moveto の後に続く座標は lineto として扱う
+        command = match c {
+            b'M' => Some(b'L'),
+            b'm' => Some(b'l'),
+            c => Some(c),
+        };
+    }
+    builder.finish()
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn skip_separators(&mut self) {
+        while self.data.get(self.position).is_some_and(|c| c.is_ascii_whitespace() || *c == b',') {
+            self.position += 1;
+        }
+    }
+
+    fn number(&mut self) -> Option<f32> {
+        self.skip_separators();
+        let start = self.position;
+        let digits = |parser: &mut Parser| {
+            let start = parser.position;
+            while parser.data.get(parser.position).is_some_and(u8::is_ascii_digit) {
+                parser.position += 1;
+            }
+            parser.position > start
+        };
+        if matches!(self.data.get(self.position), Some(b'+' | b'-')) {
+            self.position += 1;
+        }
+        let mut has_digits = digits(self);
+        if self.data.get(self.position) == Some(&b'.') {
+            self.position += 1;
+            has_digits |= digits(self);
+        }
+        if !has_digits {
+            self.position = start;
+            return None;
+        }
+        if matches!(self.data.get(self.position), Some(b'e' | b'E')) {
+            let mantissa_end = self.position;
+            self.position += 1;
+            if matches!(self.data.get(self.position), Some(b'+' | b'-')) {
+                self.position += 1;
+            }
+            if !digits(self) {
+                self.position = mantissa_end;
+            }
+        }
+        std::str::from_utf8(&self.data[start..self.position]).ok()?.parse().ok()
+    }
+
+    /// 円弧のフラグ 区切りなしで続けて書かれることがある
+    fn flag(&mut self) -> Option<bool> {
+        self.skip_separators();
+        let flag = match self.data.get(self.position)? {
+            b'0' => false,
+            b'1' => true,
+            _ => return None,
+        };
+        self.position += 1;
+        Some(flag)
+    }
+
+    fn point(&mut self) -> Option<Point> {
+        Some(point(self.number()?, self.number()?))
+    }
+}
+
+struct PathDataBuilder {
+    builder: Builder,
+    current: Point,
+    subpath_start: Point,
+    in_subpath: bool,
+    previous_cubic_control: Option<Point>,
+    previous_quadratic_control: Option<Point>,
+}
+
+impl PathDataBuilder {
+    fn begin_if_needed(&mut self) {
+        if !self.in_subpath {
+            self.builder.begin(self.current);
+            self.subpath_start = self.current;
+            self.in_subpath = true;
+        }
+    }
+
+    fn close(&mut self) {
+        if self.in_subpath {
+            self.builder.end(true);
+            self.in_subpath = false;
+        }
+        self.current = self.subpath_start;
+        self.previous_cubic_control = None;
+        self.previous_quadratic_control = None;
+    }
+
+    fn line_to(&mut self, to: Point) {
+        self.begin_if_needed();
+        self.builder.line_to(to);
+        self.current = to;
+    }
+
+    /// コマンド`command`の引数を1組読んでパスに追加する 読めなければNoneを返す
+    fn command(&mut self, command: u8, parser: &mut Parser) -> Option<()> {
+        let origin = if command.is_ascii_lowercase() { self.current.to_vector() } else { vector(0., 0.) };
+        let mut cubic_control = None;
+        let mut quadratic_control = None;
+        match command.to_ascii_uppercase() {
+            b'M' => {
+                let to = parser.point()? + origin;
+                if self.in_subpath {
+                    self.builder.end(false);
+                    self.in_subpath = false;
+                }
+                self.current = to;
+                self.begin_if_needed();
+            }
+            b'L' => {
+                let to = parser.point()? + origin;
+                self.line_to(to);
+            }
+            b'H' => {
+                let x = parser.number()? + origin.x;
+                self.line_to(point(x, self.current.y));
+            }
+            b'V' => {
+                let y = parser.number()? + origin.y;
+                self.line_to(point(self.current.x, y));
+            }
+            b'C' | b'S' => {
+                let control1 = if command.eq_ignore_ascii_case(&b'C') {
+                    parser.point()? + origin
+                } else {
+                    self.previous_cubic_control.map_or(self.current, |control| self.current + (self.current - control))
+                };
+                let control2 = parser.point()? + origin;
+                let to = parser.point()? + origin;
+                self.begin_if_needed();
+                self.builder.cubic_bezier_to(control1, control2, to);
+                self.current = to;
+                cubic_control = Some(control2);
+            }
+            b'Q' | b'T' => {
+                let control = if command.eq_ignore_ascii_case(&b'Q') {
+                    parser.point()? + origin
+                } else {
+                    self.previous_quadratic_control.map_or(self.current, |control| self.current + (self.current - control))
+                };
+                let to = parser.point()? + origin;
+                self.begin_if_needed();
+                self.builder.quadratic_bezier_to(control, to);
+                self.current = to;
+                quadratic_control = Some(control);
+            }
+            b'A' => {
+                let (rx, ry) = (parser.number()?, parser.number()?);
+                let x_rotation = parser.number()?;
+                let (large_arc, sweep) = (parser.flag()?, parser.flag()?);
+                let to = parser.point()? + origin;
+                // 始点と終点が同じ円弧は描かない
+                if to != self.current {
+                    if rx == 0. || ry == 0. {
+                        self.line_to(to);
+                    } else {
+                        self.begin_if_needed();
+                        let arc = SvgArc {
+                            from: self.current,
+                            to,
+                            radii: vector(rx.abs(), ry.abs()),
+                            x_rotation: Angle::degrees(x_rotation),
+                            flags: ArcFlags { large_arc, sweep },
+                        };
+                        arc.for_each_quadratic_bezier(&mut |segment| {
+                            self.builder.quadratic_bezier_to(segment.ctrl, segment.to);
+                        });
+                        self.current = to;
+                    }
+                }
+            }
+            _ => return None,
+        }
+        self.previous_cubic_control = cubic_control;
+        self.previous_quadratic_control = quadratic_control;
+        Some(())
+    }
+
+    fn finish(mut self) -> Path {
+        if self.in_subpath {
+            self.builder.end(false);
+        }
+        self.builder.build()
+    }
+}
+
+/// SVGファイルから取り出した、描画に必要な情報
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SvgDocument {
+    /// ルート要素の`viewBox`属性 (x, y, 幅, 高さ)
+    pub view_box: Option<[f32; 4]>,
+    pub paths: Vec<SvgPath>,
+}
+
+/// `<path>`要素
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgPath {
+    /// `d`属性
+    pub data: String,
+    /// 祖先の要素から自身までの`transform`属性を合成した変換
+    pub transform: Transform,
+}
+
+/// SVGファイルから`viewBox`と`<path>`要素のパスデータ、その変換だけを取り出す
+///
+/// スタイルや`<path>`以外の図形要素は無視する XMLとして読めない箇所があればその手前までを返す
+pub fn parse_svg(source: &str) -> SvgDocument {
+    let mut document = SvgDocument::default();
+    let mut reader = Reader::from_str(source);
+    // 開いている要素ごとの変換
+    let mut transforms = Vec::new();
+    loop {
+        let (element, empty) = match reader.read_event() {
+            Ok(Event::Start(element)) => (element, false),
+            Ok(Event::Empty(element)) => (element, true),
+            Ok(Event::End(_)) => {
+                transforms.pop();
+                continue;
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            // コメント、CDATA、処理命令などは描画に関係しない
+            Ok(_) => continue,
+        };
+        let parent = transforms.last().copied().unwrap_or_else(Transform::identity);
+        let transform = attribute(&element, b"transform").map_or(parent, |transform| parse_transform(&transform).then(&parent));
+        match element.local_name().as_ref() {
+            b"svg" if transforms.is_empty() => {
+                document.view_box = attribute(&element, b"viewBox").and_then(|view_box| {
+                    let mut parser = Parser { data: view_box.as_bytes(), position: 0 };
+                    let view_box = [parser.number()?, parser.number()?, parser.number()?, parser.number()?];
+                    (view_box[2] > 0. && view_box[3] > 0.).then_some(view_box)
+                });
+            }
+            b"path" => document.paths.extend(attribute(&element, b"d").map(|data| SvgPath { data: data.into_owned(), transform })),
+            _ => {}
+        }
+        if !empty {
+            transforms.push(transform);
+        }
+    }
+    document
+}
+
+fn attribute<'a>(element: &'a BytesStart, name: &[u8]) -> Option<Cow<'a, str>> {
+    element.try_get_attribute(name).ok()??.unescape_value().ok()
+}
+
+/// `transform`属性を解釈する
+///
+/// 左に書かれたものほど後に適用する 解釈できない箇所があればその手前までの変換を返す
+fn parse_transform(transform: &str) -> Transform {
+    let mut parser = Parser { data: transform.as_bytes(), position: 0 };
+    let mut result = Transform::identity();
+    while let Some(transform) = parser.transform_function() {
+        result = transform.then(&result);
+    }
+    result
+}
+
+impl Parser<'_> {
+    /// `translate(10 20)`のような変換関数を1つ読む
+    fn transform_function(&mut self) -> Option<Transform> {
+        self.skip_separators();
+        let name_start = self.position;
+        while self.data.get(self.position).is_some_and(u8::is_ascii_alphabetic) {
+            self.position += 1;
+        }
+        let name = &self.data[name_start..self.position];
+        self.skip_separators();
+        if self.data.get(self.position) != Some(&b'(') {
+            return None;
+        }
+        self.position += 1;
+        let mut arguments = Vec::new();
+        while let Some(argument) = self.number() {
+            arguments.push(argument);
+        }
+        self.skip_separators();
+        if self.data.get(self.position) != Some(&b')') {
+            return None;
+        }
+        self.position += 1;
+        let transform = match (name, arguments.as_slice()) {
+            (b"matrix", &[a, b, c, d, e, f]) => Transform::new(a, b, c, d, e, f),
+            (b"translate", &[x]) => Transform::translation(x, 0.),
+            (b"translate", &[x, y]) => Transform::translation(x, y),
+            (b"scale", &[s]) => Transform::scale(s, s),
+            (b"scale", &[x, y]) => Transform::scale(x, y),
+            (b"rotate", &[angle]) => Transform::rotation(Angle::degrees(angle)),
+            (b"rotate", &[angle, cx, cy]) => Transform::translation(-cx, -cy).then_rotate(Angle::degrees(angle)).then_translate(vector(cx, cy)),
+            (b"skewX", &[angle]) => Transform::new(1., 0., Angle::degrees(angle).radians.tan(), 1., 0., 0.),
+            (b"skewY", &[angle]) => Transform::new(1., Angle::degrees(angle).radians.tan(), 0., 1., 0., 0.),
+            _ => return None,
+        };
+        Some(transform)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lyon_tessellation::path::PathEvent;
+
+    fn events(data: &str) -> Vec<PathEvent> {
+        parse_path_data(data).iter().collect()
+    }
+
+    #[test]
+    fn test_parse_path_data() {
+        assert_eq!(
+            events("M10 20 L30,40 h-10 v5 z"),
+            [
+                PathEvent::Begin { at: point(10., 20.) },
+                PathEvent::Line { from: point(10., 20.), to: point(30., 40.) },
+                PathEvent::Line { from: point(30., 40.), to: point(20., 40.) },
+                PathEvent::Line { from: point(20., 40.), to: point(20., 45.) },
+                PathEvent::End {
+                    last: point(20., 45.),
+                    first: point(10., 20.),
+                    close: true
+                },
+            ]
+        );
+        // moveto の後の座標は lineto になり、数値は区切りなしで続けて書ける
+        assert_eq!(
+            events("m1-2 3.5.5e1"),
+            [
+                PathEvent::Begin { at: point(1., -2.) },
+                PathEvent::Line { from: point(1., -2.), to: point(4.5, 3.) },
+                PathEvent::End {
+                    last: point(4.5, 3.),
+                    first: point(1., -2.),
+                    close: false
+                },
+            ]
+        );
+        // 滑らかな曲線は直前の制御点を反転する
+        assert_eq!(
+            events("M0 0 C0 1 1 1 1 0 S2 -1 2 0"),
+            [
+                PathEvent::Begin { at: point(0., 0.) },
+                PathEvent::Cubic {
+                    from: point(0., 0.),
+                    ctrl1: point(0., 1.),
+                    ctrl2: point(1., 1.),
+                    to: point(1., 0.)
+                },
+                PathEvent::Cubic {
+                    from: point(1., 0.),
+                    ctrl1: point(1., -1.),
+                    ctrl2: point(2., -1.),
+                    to: point(2., 0.)
+                },
+                PathEvent::End {
+                    last: point(2., 0.),
+                    first: point(0., 0.),
+                    close: false
+                },
+            ]
+        );
+        assert_eq!(
+            events("M0 0 Q1 1 2 0 T4 0"),
+            [
+                PathEvent::Begin { at: point(0., 0.) },
+                PathEvent::Quadratic {
+                    from: point(0., 0.),
+                    ctrl: point(1., 1.),
+                    to: point(2., 0.)
+                },
+                PathEvent::Quadratic {
+                    from: point(2., 0.),
+                    ctrl: point(3., -1.),
+                    to: point(4., 0.)
+                },
+                PathEvent::End {
+                    last: point(4., 0.),
+                    first: point(0., 0.),
+                    close: false
+                },
+            ]
+        );
+        // closepath の後は部分パスの始点から続ける
+        assert_eq!(
+            events("M1 1 L2 1 Z l0 1"),
+            [
+                PathEvent::Begin { at: point(1., 1.) },
+                PathEvent::Line { from: point(1., 1.), to: point(2., 1.) },
+                PathEvent::End { last: point(2., 1.), first: point(1., 1.), close: true },
+                PathEvent::Begin { at: point(1., 1.) },
+                PathEvent::Line { from: point(1., 1.), to: point(1., 2.) },
+                PathEvent::End {
+                    last: point(1., 2.),
+                    first: point(1., 1.),
+                    close: false
+                },
+            ]
+        );
+        // 不正な箇所の手前までを返す
+        assert_eq!(
+            events("M0 0 L1 1 L2 x L3 3"),
+            [
+                PathEvent::Begin { at: point(0., 0.) },
+                PathEvent::Line { from: point(0., 0.), to: point(1., 1.) },
+                PathEvent::End {
+                    last: point(1., 1.),
+                    first: point(0., 0.),
+                    close: false
+                },
+            ]
+        );
+        assert!(events("").is_empty());
+        assert!(events("L1 1").is_empty());
+    }
+
+    #[test]
+    fn test_parse_path_data_arc() {
+        // 半径0の円弧は直線になる
+        assert_eq!(events("M0 0 A0 0 0 0 1 2 0")[1], PathEvent::Line { from: point(0., 0.), to: point(2., 0.) });
+        // 区切りなしのフラグ
+        let events = events("M0 0 a1 1 0 0110 0");
+        let Some(&PathEvent::End { last, close: false, .. }) = events.last() else { panic!("{events:?}") };
+        assert!((last - point(10., 0.)).length() < 1e-4);
+        // 半径が足りない場合は拡大され、y座標が負(時計回り)の半円になる
+        assert!(events.iter().all(|event| match *event {
+            PathEvent::Quadratic { to, .. } => to.y <= 1e-4 && (to - point(5., 0.)).length() <= 5. + 1e-3,
+            PathEvent::Begin { .. } | PathEvent::End { .. } => true,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn test_parse_svg() {
+        let document = parse_svg(
+            r#"<?xml version="1.0"?>
+<!-- <path d="M9 9"/> -->
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:svg="http://www.w3.org/2000/svg" width="100" height="50" viewBox="0 0 10,5">
+  <g><path fill="red" d="M0 0 L10 5"/></g>
+  <path data-note="a > b" d='M1 1 h2' />
+  <svg:path d="M2 2 v1"/>
+  <![CDATA[<path d="M9 9"/>]]>
+  <?process <path d="M9 9"/> ?>
+  <rect x="0" y="0" width="1" height="1"/>
+</svg>"#,
+        );
+        assert_eq!(
+            document,
+            SvgDocument {
+                view_box: Some([0., 0., 10., 5.]),
+                paths: ["M0 0 L10 5", "M1 1 h2", "M2 2 v1"]
+                    .map(|data| SvgPath {
+                        data: data.to_owned(),
+                        transform: Transform::identity()
+                    })
+                    .to_vec(),
+            }
+        );
+        assert_eq!(parse_svg(r#"<svg viewBox="0 0 0 5"><path/></svg>"#), SvgDocument::default());
+        // 親の要素の変換を後に適用する
+        let document = parse_svg(r#"<svg><g transform="translate(10 0)"><path transform="scale(2)" d="M1 1"/></g><path d="M1 1"/></svg>"#);
+        assert_eq!(document.paths[0].transform.transform_point(point(1., 1.)), point(12., 2.));
+        assert_eq!(document.paths[1].transform, Transform::identity());
+    }
+
+    #[test]
+    fn test_parse_transform() {
+        let apply = |transform: &str, p: Point| parse_transform(transform).transform_point(p);
+        assert_eq!(apply("matrix(1 2 3 4 5 6)", point(1., 1.)), point(9., 12.));
+        assert_eq!(apply("translate(1)", point(1., 1.)), point(2., 1.));
+        assert_eq!(apply("scale(2, 3)", point(1., 1.)), point(2., 3.));
+        assert!((apply("rotate(90)", point(1., 0.)) - point(0., 1.)).length() < 1e-5);
+        assert!((apply("rotate(180 1 1)", point(0., 0.)) - point(2., 2.)).length() < 1e-5);
+        assert!((apply("skewX(45)", point(0., 1.)) - point(1., 1.)).length() < 1e-5);
+        assert!((apply("skewY(45)", point(1., 0.)) - point(1., 1.)).length() < 1e-5);
+        // 右に書かれたものから適用する
+        assert_eq!(apply("translate(10 0) scale(2)", point(1., 1.)), point(12., 2.));
+        // 不正な箇所の手前までを返す
+        assert_eq!(apply("translate(1 1) scale(1 2 3) translate(5 5)", point(0., 0.)), point(1., 1.));
+        assert_eq!(parse_transform(""), Transform::identity());
+    }
+}
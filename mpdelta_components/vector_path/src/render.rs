@@ -0,0 +1,250 @@
+use lyon_tessellation::math::{point, Point};
+use lyon_tessellation::path::iterator::PathIterator;
+use lyon_tessellation::path::{Path, PathEvent};
+use lyon_tessellation::{BuffersBuilder, FillOptions, FillTessellator, FillVertex, FillVertexConstructor, LineJoin, StrokeOptions, StrokeTessellator, StrokeVertex, StrokeVertexConstructor, VertexBuffers};
+use mpdelta_component_common::triangle_rasterizer::TriangleRasterizer;
+use mpdelta_core_cpu::RgbaImage;
+use std::mem;
+
+/// 出力画像のピクセル単位での曲線の近似の許容誤差
+const TOLERANCE: f32 = 0.1;
+
+/// パスの描き方 長さの単位はパスの座標系
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathStyle {
+    pub fill: [u8; 4],
+    pub stroke: [u8; 4],
+    pub stroke_width: f32,
+    /// 線を描く範囲の始まり 全てのパスをつなげた長さに対する割合
+    pub trim_start: f32,
+    /// 線を描く範囲の終わり 全てのパスをつなげた長さに対する割合
+    pub trim_end: f32,
+}
+
+/// 折れ線で近似した部分パス
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polyline {
+    pub points: Vec<Point>,
+    pub closed: bool,
+}
+
+impl Polyline {
+    fn segments(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+        let closing = self.closed.then(|| (*self.points.last()?, *self.points.first()?)).flatten();
+        self.points.windows(2).map(|w| (w[0], w[1])).chain(closing)
+    }
+}
+
+fn flatten(path: &Path, tolerance: f32, polylines: &mut Vec<Polyline>) {
+    let mut points = Vec::new();
+    for event in path.iter().flattened(tolerance) {
+        match event {
+            PathEvent::Begin { at } => points.push(at),
+            PathEvent::Line { to, .. } => points.push(to),
+            PathEvent::End { close, .. } => polylines.push(Polyline { points: mem::take(&mut points), closed: close }),
+            PathEvent::Quadratic { .. } | PathEvent::Cubic { .. } => unreachable!(),
+        }
+    }
+}
+
+/// 全ての折れ線をつなげた長さのうち`start`から`end`の割合の部分を取り出す 取り出した部分は閉じない
+pub fn trim(polylines: &[Polyline], start: f32, end: f32) -> Vec<Polyline> {
+    let (start, end) = (start.clamp(0., 1.), end.clamp(0., 1.));
+    if start <= 0. && end >= 1. {
+        return polylines.to_vec();
+    }
+    let total = polylines.iter().flat_map(Polyline::segments).map(|(a, b)| (b - a).length()).sum::<f32>();
+    let (from, to) = (start * total, end * total);
+    let mut offset = 0.;
+    let mut result = Vec::new();
+    for polyline in polylines {
+        let mut points = Vec::new();
+        for (a, b) in polyline.segments() {
+            let length = (b - a).length();
+            let segment_start = offset;
+            offset += length;
+            if length <= 0. || offset <= from || to <= segment_start {
+                continue;
+            }
+            if points.is_empty() {
+                points.push(a.lerp(b, ((from - segment_start) / length).max(0.)));
+            }
+            points.push(a.lerp(b, ((to - segment_start) / length).min(1.)));
+        }
+        if points.len() >= 2 {
+            result.push(Polyline { points, closed: false });
+        }
+    }
+    result
+}
+
+fn control_point_bounds(paths: &[Path]) -> Option<[f32; 4]> {
+    let points = paths.iter().flat_map(|path| path.iter()).flat_map(|event| match event {
+        PathEvent::Begin { at } => vec![at],
+        PathEvent::Line { to, .. } => vec![to],
+        PathEvent::Quadratic { ctrl, to, .. } => vec![ctrl, to],
+        PathEvent::Cubic { ctrl1, ctrl2, to, .. } => vec![ctrl1, ctrl2, to],
+        PathEvent::End { .. } => vec![],
+    });
+    points.fold(None, |bounds, p| match bounds {
+        None => Some([p.x, p.y, p.x, p.y]),
+        Some([min_x, min_y, max_x, max_y]) => Some([min_x.min(p.x), min_y.min(p.y), max_x.max(p.x), max_y.max(p.y)]),
+    })
+}
+
+struct PositionCtor;
+
+impl FillVertexConstructor<Point> for PositionCtor {
+    fn new_vertex(&mut self, vertex: FillVertex) -> Point {
+        vertex.position()
+    }
+}
+
+impl StrokeVertexConstructor<Point> for PositionCtor {
+    fn new_vertex(&mut self, vertex: StrokeVertex) -> Point {
+        vertex.position()
+    }
+}
+
+fn polylines_to_path<'a>(polylines: impl IntoIterator<Item = &'a Polyline>, close_all: bool) -> Path {
+    let mut builder = Path::builder();
+    for Polyline { points, closed } in polylines {
+        let Some((&first, rest)) = points.split_first() else { continue };
+        builder.begin(first);
+        rest.iter().for_each(|&p| {
+            builder.line_to(p);
+        });
+        builder.end(close_all || *closed);
+    }
+    builder.build()
+}
+
+/// `paths`を`width`x`height`の画像に描く
+///
+/// `view_box`(x, y, 幅, 高さ)の範囲を縦横比を保って画像の中央に収める
+/// `view_box`がなければ線の太さを含めたパスの範囲を使う
+/// 塗りは常にパス全体に対して行い、トリムは線にだけ効く
+pub fn render(width: u32, height: u32, paths: &[Path], view_box: Option<[f32; 4]>, style: &PathStyle) -> RgbaImage {
+    let mut rasterizer = TriangleRasterizer::new(width, height);
+    let stroke_width = style.stroke_width.max(0.);
+    let view_box = view_box.or_else(|| {
+        let [min_x, min_y, max_x, max_y] = control_point_bounds(paths)?;
+        let margin = stroke_width / 2.;
+        Some([min_x - margin, min_y - margin, max_x - min_x + stroke_width, max_y - min_y + stroke_width])
+    });
+    let Some([view_x, view_y, view_width, view_height]) = view_box else {
+        return rasterizer.resolve();
+    };
+    let scale = (width as f32 / view_width).min(height as f32 / view_height);
+    if !scale.is_finite() || scale <= 0. {
+        return rasterizer.resolve();
+    }
+    let offset_x = (width as f32 - view_width * scale) / 2. - view_x * scale;
+    let offset_y = (height as f32 - view_height * scale) / 2. - view_y * scale;
+
+    let mut polylines = Vec::new();
+    paths.iter().for_each(|path| flatten(path, TOLERANCE / scale, &mut polylines));
+    polylines.iter_mut().flat_map(|polyline| &mut polyline.points).for_each(|p| *p = point(p.x * scale + offset_x, p.y * scale + offset_y));
+
+    let mut fill_buffers = VertexBuffers::<Point, u32>::new();
+    if style.fill[3] > 0 {
+        // 塗りの規則はSVGの既定値であるnonzero
+        let path = polylines_to_path(&polylines, true);
+        let _ = FillTessellator::new().tessellate_path(&path, &FillOptions::non_zero().with_tolerance(TOLERANCE), &mut BuffersBuilder::new(&mut fill_buffers, PositionCtor));
+    }
+    let mut stroke_buffers = VertexBuffers::<Point, u32>::new();
+    if style.stroke[3] > 0 && stroke_width > 0. {
+        let path = polylines_to_path(&trim(&polylines, style.trim_start, style.trim_end), false);
+        let options = StrokeOptions::tolerance(TOLERANCE).with_line_join(LineJoin::Round).with_line_width(stroke_width * scale);
+        let _ = StrokeTessellator::new().tessellate_path(&path, &options, &mut BuffersBuilder::new(&mut stroke_buffers, PositionCtor));
+    }
+    for (VertexBuffers { vertices, indices }, color) in [(fill_buffers, style.fill), (stroke_buffers, style.stroke)] {
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|i| vertices[i as usize]);
+            rasterizer.fill_triangle([(a.x, a.y), (b.x, b.y), (c.x, c.y)], color);
+        }
+    }
+    rasterizer.resolve()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path_data::parse_path_data;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    fn polyline(points: &[(f32, f32)], closed: bool) -> Polyline {
+        Polyline {
+            points: points.iter().map(|&(x, y)| point(x, y)).collect(),
+            closed,
+        }
+    }
+
+    fn style(fill: [u8; 4], stroke: [u8; 4], stroke_width: f32) -> PathStyle {
+        PathStyle {
+            fill,
+            stroke,
+            stroke_width,
+            trim_start: 0.,
+            trim_end: 1.,
+        }
+    }
+
+    #[test]
+    fn test_trim() {
+        // 長さ4の正方形と長さ4の直線
+        let polylines = [polyline(&[(0., 0.), (1., 0.), (1., 1.), (0., 1.)], true), polyline(&[(2., 0.), (6., 0.)], false)];
+        assert_eq!(trim(&polylines, 0., 1.), polylines);
+        assert!(trim(&polylines, 0.5, 0.25).is_empty());
+        assert_eq!(trim(&polylines, 0., 0.25), [polyline(&[(0., 0.), (1., 0.), (1., 1.)], false)]);
+        // 閉じた部分パスは最後の辺も含めて切り出す
+        assert_eq!(trim(&polylines, 0.3125, 0.5), [polyline(&[(0.5, 1.), (0., 1.), (0., 0.)], false)]);
+        assert_eq!(trim(&polylines, 0.375, 0.75), [polyline(&[(0., 1.), (0., 0.)], false), polyline(&[(2., 0.), (4., 0.)], false)]);
+    }
+
+    #[test]
+    fn test_render_fill_and_stroke() {
+        // viewBoxの範囲を縦横比を保って中央に収める
+        let square = parse_path_data("M0 0 H10 V10 H0 Z");
+        let image = render(20, 10, &[square.clone()], Some([0., 0., 10., 10.]), &style(RED, [0; 4], 0.));
+        assert_eq!(image.get_pixel(4, 5).0, [0; 4]);
+        assert_eq!(image.get_pixel(5, 0).0, RED);
+        assert_eq!(image.get_pixel(14, 9).0, RED);
+        assert_eq!(image.get_pixel(15, 5).0, [0; 4]);
+
+        // viewBoxがなければ線を含めたパスの範囲を画像いっぱいに描く
+        let image = render(12, 12, &[square.clone()], None, &style(RED, BLUE, 2.));
+        assert_eq!(image.get_pixel(6, 0).0, BLUE);
+        assert_eq!(image.get_pixel(0, 6).0, BLUE);
+        assert_eq!(image.get_pixel(1, 6).0, BLUE);
+        assert_eq!(image.get_pixel(2, 6).0, RED);
+        assert_eq!(image.get_pixel(11, 6).0, BLUE);
+        assert_eq!(image.get_pixel(6, 6).0, RED);
+
+        // 交差する部分パスはnonzeroで塗る
+        let image = render(10, 10, &[parse_path_data("M0 0 H10 V10 H0 Z M2 2 H8 V8 H2 Z")], None, &style(RED, [0; 4], 0.));
+        assert_eq!(image.get_pixel(5, 5).0, RED);
+        let image = render(10, 10, &[parse_path_data("M0 0 H10 V10 H0 Z M2 2 V8 H8 V2 Z")], None, &style(RED, [0; 4], 0.));
+        assert_eq!(image.get_pixel(5, 5).0, [0; 4]);
+        assert_eq!(image.get_pixel(1, 5).0, RED);
+
+        assert!(render(4, 4, &[], None, &style(RED, BLUE, 1.)).pixels().all(|p| p.0 == [0; 4]));
+    }
+
+    #[test]
+    fn test_render_trim() {
+        // 左から右への水平線の左半分だけを描く
+        let line = parse_path_data("M0 5 H10");
+        let image = render(10, 10, &[line.clone()], Some([0., 0., 10., 10.]), &PathStyle { trim_end: 0.5, ..style([0; 4], BLUE, 2.) });
+        assert_eq!(image.get_pixel(2, 5).0, BLUE);
+        assert_eq!(image.get_pixel(4, 4).0, BLUE);
+        assert_eq!(image.get_pixel(6, 5).0, [0; 4]);
+        assert_eq!(image.get_pixel(2, 7).0, [0; 4]);
+
+        let image = render(10, 10, &[line], Some([0., 0., 10., 10.]), &PathStyle { trim_start: 0.5, ..style([0; 4], BLUE, 2.) });
+        assert_eq!(image.get_pixel(2, 5).0, [0; 4]);
+        assert_eq!(image.get_pixel(8, 5).0, BLUE);
+    }
+}
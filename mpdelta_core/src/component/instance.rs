@@ -281,6 +281,10 @@ impl<T: ParameterValueType> ComponentInstance<T> {
     pub fn variable_parameters_mut(&mut self) -> &mut VectorSync<VariableParameterValue<ParameterNullableValue<T>>> {
         &mut self.variable_parameters
     }
+    pub fn set_variable_parameters(&mut self, types: impl Into<Arc<Vec<(String, Parameter<Type>)>>>, values: VectorSync<VariableParameterValue<ParameterNullableValue<T>>>) {
+        self.variable_parameters_type = types.into();
+        self.variable_parameters = values;
+    }
     pub fn processor(&self) -> &ComponentProcessorWrapper<T> {
         &self.processor
     }
@@ -1,8 +1,10 @@
 use crate::common::time_split_value_persistent::TimeSplitValuePersistent;
+use crate::component::class::ComponentClass;
 use crate::component::instance::ComponentInstanceId;
 use crate::component::marker_pin::MarkerPinId;
 use crate::component::parameter::placeholder::{Placeholder, TagAudio, TagImage};
 use crate::component::parameter::value::{DynEditableLerpEasingValue, DynEditableSingleValue, EasingValue, LinearEasing};
+use crate::ptr::StaticPointer;
 use cgmath::{One, Quaternion, Vector3};
 use rpds::{Vector, VectorSync};
 use serde::{Deserialize, Serialize};
@@ -16,6 +18,7 @@ use std::marker::PhantomData;
 use std::ops::Range;
 use std::sync::Arc;
 use std::{io, mem};
+use tokio::sync::RwLock;
 use uuid::Uuid;
 
 pub mod placeholder;
@@ -686,9 +689,9 @@ impl<T: ParameterValueType> ParameterValueType for NullableValue<T> {
     type Integer = TimeSplitValuePersistent<MarkerPinId, Option<EasingValue<i64>>>;
     type RealNumber = TimeSplitValuePersistent<MarkerPinId, Option<EasingValue<f64>>>;
     type Boolean = TimeSplitValuePersistent<MarkerPinId, Option<EasingValue<bool>>>;
-    type Dictionary = Never;
-    type Array = Never;
-    type ComponentClass = Option<()>;
+    type Dictionary = TimeSplitValuePersistent<MarkerPinId, Option<EasingValue<HashMap<String, ParameterValueRaw<T::Image, T::Audio>>>>>;
+    type Array = TimeSplitValuePersistent<MarkerPinId, Option<EasingValue<Vec<ParameterValueRaw<T::Image, T::Audio>>>>>;
+    type ComponentClass = Option<StaticPointer<RwLock<dyn ComponentClass<T>>>>;
 }
 
 pub struct TypedValue<K>(PhantomData<K>);
@@ -981,6 +984,7 @@ pub enum ImageRequiredParamsTransform {
         scale_center: Arc<Vector3Params>,
         rotate_center: Arc<Vector3Params>,
     },
+    /// 画像の4隅を出力画像上の座標(左上が(0, 0)、右下が(1, 1))に直接指定する
     Free {
         left_top: Arc<Vector3Params>,
         right_top: Arc<Vector3Params>,
@@ -1,3 +1,4 @@
+use crate::component::parameter::{Parameter, ParameterValueRaw};
 use cgmath::Quaternion;
 use erased_serde::Error;
 use serde::de::DeserializeOwned;
@@ -5,12 +6,15 @@ use serde::{Deserialize, Serialize, Serializer};
 use std::any::Any;
 use std::borrow::Cow;
 use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{Debug, Formatter};
 use std::marker::PhantomData;
 use std::sync::Arc;
 use std::{any, fmt, ptr};
 use thiserror::Error;
 
+pub mod easing;
+
 #[derive(Debug, Clone, Copy)]
 pub struct EasingInput(f64);
 
@@ -94,6 +98,24 @@ impl EasingIdentifier<'_> {
 pub trait Easing: Send + Sync {
     fn identifier(&self) -> EasingIdentifier;
     fn easing(&self, from: EasingInput) -> f64;
+    /// パラメータを持つイージングであれば、シリアライズするためにそのパラメータを返す
+    fn parameters(&self) -> Option<&dyn erased_serde::Serialize> {
+        None
+    }
+    /// 同じ種類で、パラメータを差し替えたイージングを作る
+    fn with_parameters(&self, deserializer: &mut dyn erased_serde::Deserializer) -> Result<Arc<dyn Easing>, Error> {
+        let _ = deserializer;
+        Err(serde::de::Error::custom(format_args!("easing {:?} does not take parameters", self.identifier())))
+    }
+    /// GUIから編集できる数値パラメータの名前と値
+    fn numeric_parameters(&self) -> Vec<(&'static str, f64)> {
+        Vec::new()
+    }
+    /// [`Easing::numeric_parameters`]と同じ順に並べた値で、パラメータを差し替えたイージングを作る
+    fn with_numeric_parameters(&self, values: &[f64]) -> Option<Arc<dyn Easing>> {
+        let _ = values;
+        None
+    }
 }
 
 pub struct LinearEasing;
@@ -474,6 +496,191 @@ where
     }
 }
 
+/// Image/Audio/Binaryを含まず、そのままシリアライズできるParameterValueRaw
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PlainValue {
+    String(String),
+    Integer(i64),
+    RealNumber(f64),
+    Boolean(bool),
+    Dictionary(BTreeMap<String, PlainValue>),
+    Array(Vec<PlainValue>),
+}
+
+impl PlainValue {
+    pub fn to_raw<Image, Audio>(&self) -> ParameterValueRaw<Image, Audio>
+    where
+        Image: Send + Sync + Clone + 'static,
+        Audio: Send + Sync + Clone + 'static,
+    {
+        match self {
+            PlainValue::String(value) => Parameter::String(value.clone()),
+            &PlainValue::Integer(value) => Parameter::Integer(value),
+            &PlainValue::RealNumber(value) => Parameter::RealNumber(value),
+            &PlainValue::Boolean(value) => Parameter::Boolean(value),
+            PlainValue::Dictionary(value) => Parameter::Dictionary(PlainValueContainer::<Image, Audio>::to_raw(value)),
+            PlainValue::Array(value) => Parameter::Array(PlainValueContainer::<Image, Audio>::to_raw(value)),
+        }
+    }
+
+    pub fn from_raw<Image, Audio>(value: &ParameterValueRaw<Image, Audio>) -> Option<PlainValue>
+    where
+        Image: Send + Sync + Clone + 'static,
+        Audio: Send + Sync + Clone + 'static,
+    {
+        match value {
+            Parameter::String(value) => Some(PlainValue::String(value.clone())),
+            &Parameter::Integer(value) => Some(PlainValue::Integer(value)),
+            &Parameter::RealNumber(value) => Some(PlainValue::RealNumber(value)),
+            &Parameter::Boolean(value) => Some(PlainValue::Boolean(value)),
+            Parameter::Dictionary(value) => value.iter().map(|(key, value)| Some((key.clone(), PlainValue::from_raw(value)?))).collect::<Option<_>>().map(PlainValue::Dictionary),
+            Parameter::Array(value) => value.iter().map(PlainValue::from_raw).collect::<Option<_>>().map(PlainValue::Array),
+            Parameter::None | Parameter::Image(_) | Parameter::Audio(_) | Parameter::Binary(_) | Parameter::ComponentClass(_) => None,
+        }
+    }
+}
+
+/// [`PlainValue`]の集まりで、Dictionary/Arrayの値になるもの
+pub trait PlainValueContainer<Image, Audio>: Clone + Send + Sync + Serialize + DeserializeOwned + 'static {
+    type Raw: 'static;
+    fn to_raw(&self) -> Self::Raw;
+}
+
+impl<Image, Audio> PlainValueContainer<Image, Audio> for BTreeMap<String, PlainValue>
+where
+    Image: Send + Sync + Clone + 'static,
+    Audio: Send + Sync + Clone + 'static,
+{
+    type Raw = HashMap<String, ParameterValueRaw<Image, Audio>>;
+
+    fn to_raw(&self) -> Self::Raw {
+        self.iter().map(|(key, value)| (key.clone(), value.to_raw())).collect()
+    }
+}
+
+impl<Image, Audio> PlainValueContainer<Image, Audio> for Vec<PlainValue>
+where
+    Image: Send + Sync + Clone + 'static,
+    Audio: Send + Sync + Clone + 'static,
+{
+    type Raw = Vec<ParameterValueRaw<Image, Audio>>;
+
+    fn to_raw(&self) -> Self::Raw {
+        self.iter().map(PlainValue::to_raw).collect()
+    }
+}
+
+pub struct DynEditablePlainValue<T, Image, Audio>(pub T, PhantomData<fn() -> (Image, Audio)>);
+
+impl<T, Image, Audio> DynEditablePlainValue<T, Image, Audio> {
+    pub fn new(value: T) -> DynEditablePlainValue<T, Image, Audio> {
+        DynEditablePlainValue(value, PhantomData)
+    }
+}
+
+impl<T: Clone, Image, Audio> Clone for DynEditablePlainValue<T, Image, Audio> {
+    fn clone(&self) -> Self {
+        DynEditablePlainValue(self.0.clone(), PhantomData)
+    }
+}
+
+impl<T: Serialize, Image, Audio> Serialize for DynEditablePlainValue<T, Image, Audio> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+pub struct DynEditablePlainValueManager<T, Image, Audio>(PhantomData<fn() -> (T, Image, Audio)>);
+
+impl<T, Image, Audio> Default for DynEditablePlainValueManager<T, Image, Audio> {
+    fn default() -> Self {
+        DynEditablePlainValueManager(PhantomData)
+    }
+}
+
+impl<T, Image, Audio> DynEditableSingleValueManager<T::Raw> for DynEditablePlainValueManager<T, Image, Audio>
+where
+    T: PlainValueContainer<Image, Audio>,
+    Image: 'static,
+    Audio: 'static,
+{
+    fn identifier(&self) -> DynEditableSingleValueIdentifier {
+        DynEditableSingleValueIdentifier {
+            namespace: Cow::Borrowed("mpdelta"),
+            name: Cow::Borrowed("PlainValue"),
+        }
+    }
+
+    fn deserialize(&self, deserializer: &mut dyn erased_serde::Deserializer) -> Result<DynEditableSingleValue<T::Raw>, Error> {
+        let value: T = erased_serde::deserialize(deserializer)?;
+        Ok(DynEditableSingleValue::new(DynEditablePlainValue::<T, Image, Audio>::new(value)))
+    }
+}
+
+impl<T, Image, Audio> DynEditableEasingValueManager<T::Raw> for DynEditablePlainValueManager<T, Image, Audio>
+where
+    T: PlainValueContainer<Image, Audio>,
+    Image: 'static,
+    Audio: 'static,
+{
+    fn identifier(&self) -> DynEditableEasingValueIdentifier {
+        DynEditableEasingValueIdentifier {
+            namespace: Cow::Borrowed("mpdelta"),
+            name: Cow::Borrowed("PlainEasingValue"),
+        }
+    }
+
+    fn deserialize(&self, deserializer: &mut dyn erased_serde::Deserializer) -> Result<DynEditableEasingValue<T::Raw>, Error> {
+        let value: T = erased_serde::deserialize(deserializer)?;
+        Ok(DynEditableEasingValue::new(DynEditablePlainValue::<T, Image, Audio>::new(value)))
+    }
+}
+
+impl<T, Image, Audio> DynEditableSingleValueMarker for DynEditablePlainValue<T, Image, Audio>
+where
+    T: PlainValueContainer<Image, Audio>,
+    Image: 'static,
+    Audio: 'static,
+{
+    type Out = T::Raw;
+
+    fn manager(&self) -> &dyn DynEditableSingleValueManager<Self::Out> {
+        &DynEditablePlainValueManager::<T, Image, Audio>(PhantomData)
+    }
+
+    fn get_raw_value_mut(&mut self) -> &mut dyn NamedAny {
+        &mut self.0
+    }
+
+    fn get_value(&self) -> Self::Out {
+        self.0.to_raw()
+    }
+}
+
+impl<T, Image, Audio> DynEditableEasingValueMarker for DynEditablePlainValue<T, Image, Audio>
+where
+    T: PlainValueContainer<Image, Audio>,
+    Image: 'static,
+    Audio: 'static,
+{
+    type Out = T::Raw;
+
+    fn manager(&self) -> &dyn DynEditableEasingValueManager<Self::Out> {
+        &DynEditablePlainValueManager::<T, Image, Audio>(PhantomData)
+    }
+
+    fn get_raw_value_mut(&mut self) -> &mut dyn NamedAny {
+        &mut self.0
+    }
+
+    fn get_value(&self, _: f64) -> Self::Out {
+        self.0.to_raw()
+    }
+}
+
 pub trait DynEditableEasingValueMarkerCloneable: DynEditableEasingValueMarker {
     fn clone_dyn(&self) -> DynEditableEasingValue<Self::Out>;
 }
@@ -697,4 +904,37 @@ mod tests {
         assert_eq!(DynEditableEasingValueMarker::get_value(&value, 0.5), 25);
         assert_eq!(DynEditableEasingValueMarker::get_value(&value, 1.0), 30);
     }
+
+    #[test]
+    fn test_plain_value() {
+        type Raw = ParameterValueRaw<(), ()>;
+        let palette = BTreeMap::from([
+            ("name".to_owned(), PlainValue::String("palette".to_owned())),
+            ("colors".to_owned(), PlainValue::Array(vec![PlainValue::Integer(0xff0000), PlainValue::Integer(0x00ff00)])),
+            ("alpha".to_owned(), PlainValue::RealNumber(0.5)),
+            ("enabled".to_owned(), PlainValue::Boolean(true)),
+        ]);
+        let raw: HashMap<String, Raw> = PlainValueContainer::<(), ()>::to_raw(&palette);
+        assert_eq!(raw["name"].as_string(), Some(&"palette".to_owned()));
+        assert_eq!(raw["colors"].as_array().map(Vec::len), Some(2));
+        assert_eq!(PlainValue::from_raw(&Raw::Dictionary(raw)), Some(PlainValue::Dictionary(palette.clone())));
+        assert_eq!(PlainValue::from_raw(&Raw::Array(vec![Raw::Integer(1), Raw::Image(())])), None);
+
+        let mut value = DynEditableSingleValue::new(DynEditablePlainValue::<_, (), ()>::new(palette.clone()));
+        assert_eq!(value.manager().identifier().name, "PlainValue");
+        assert_eq!(PlainValue::from_raw(&Raw::Dictionary(value.get_value())), Some(PlainValue::Dictionary(palette.clone())));
+        SingleValueEdit::edit_value::<BTreeMap<String, PlainValue>, _>(&mut value, |value| value.insert("alpha".to_owned(), PlainValue::RealNumber(1.))).unwrap();
+        assert_eq!(value.get_value()["alpha"].as_real_number(), Some(&1.));
+
+        let json = serde_json::to_string(&value).unwrap();
+        // キーの順に並ぶので、保存するたびに内容が変わることはない
+        assert_eq!(json, r#"{"alpha":{"RealNumber":1.0},"colors":{"Array":[{"Integer":16711680},{"Integer":65280}]},"enabled":{"Boolean":true},"name":{"String":"palette"}}"#);
+        let manager = DynEditablePlainValueManager::<BTreeMap<String, PlainValue>, (), ()>::default();
+        let deserialized = DynEditableSingleValueManager::deserialize(&manager, &mut <dyn erased_serde::Deserializer>::erase(&mut serde_json::Deserializer::from_str(&json))).unwrap();
+        assert_eq!(PlainValue::from_raw(&Raw::Dictionary(deserialized.get_value())), PlainValue::from_raw(&Raw::Dictionary(value.get_value())));
+
+        let cues = vec![PlainValue::String("cue".to_owned()), PlainValue::Dictionary(BTreeMap::new())];
+        let value = DynEditableEasingValue::new(DynEditablePlainValue::<_, (), ()>::new(cues.clone()));
+        assert_eq!(PlainValue::from_raw(&Raw::Array(value.get_value(0.5))), Some(PlainValue::Array(cues)));
+    }
 }
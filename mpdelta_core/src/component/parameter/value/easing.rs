@@ -0,0 +1,470 @@
+use crate::component::parameter::value::{Easing, EasingIdentifier, EasingInput, LinearEasing};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// 標準で用意するイージングの曲線の種類
+///
+/// ref: https://easings.net/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EasingCurve {
+    Quad,
+    Cubic,
+    Quart,
+    Quint,
+    Sine,
+    Expo,
+    Circ,
+    Back,
+    Elastic,
+    Bounce,
+}
+
+impl EasingCurve {
+    pub const ALL: [EasingCurve; 10] = [
+        EasingCurve::Quad,
+        EasingCurve::Cubic,
+        EasingCurve::Quart,
+        EasingCurve::Quint,
+        EasingCurve::Sine,
+        EasingCurve::Expo,
+        EasingCurve::Circ,
+        EasingCurve::Back,
+        EasingCurve::Elastic,
+        EasingCurve::Bounce,
+    ];
+
+    /// ease-inの曲線 ease-out, ease-in-outはこれから導出する
+    fn ease_in(self, x: f64) -> f64 {
+        const BACK_C1: f64 = 1.70158;
+        const BACK_C3: f64 = BACK_C1 + 1.;
+        const ELASTIC_C4: f64 = 2. * PI / 3.;
+        match self {
+            EasingCurve::Quad => x.powi(2),
+            EasingCurve::Cubic => x.powi(3),
+            EasingCurve::Quart => x.powi(4),
+            EasingCurve::Quint => x.powi(5),
+            EasingCurve::Sine => 1. - (x * PI / 2.).cos(),
+            EasingCurve::Expo if x <= 0. => 0.,
+            EasingCurve::Expo => 2f64.powf(10. * x - 10.),
+            EasingCurve::Circ => 1. - (1. - x * x).max(0.).sqrt(),
+            EasingCurve::Back => BACK_C3 * x.powi(3) - BACK_C1 * x.powi(2),
+            EasingCurve::Elastic if x <= 0. => 0.,
+            EasingCurve::Elastic if x >= 1. => 1.,
+            EasingCurve::Elastic => -(2f64.powf(10. * x - 10.)) * ((x * 10. - 10.75) * ELASTIC_C4).sin(),
+            EasingCurve::Bounce => 1. - bounce_out(1. - x),
+        }
+    }
+}
+
+fn bounce_out(x: f64) -> f64 {
+    const N1: f64 = 7.5625;
+    const D1: f64 = 2.75;
+    if x < 1. / D1 {
+        N1 * x * x
+    } else if x < 2. / D1 {
+        let x = x - 1.5 / D1;
+        N1 * x * x + 0.75
+    } else if x < 2.5 / D1 {
+        let x = x - 2.25 / D1;
+        N1 * x * x + 0.9375
+    } else {
+        let x = x - 2.625 / D1;
+        N1 * x * x + 0.984375
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EasingDirection {
+    In,
+    Out,
+    InOut,
+}
+
+impl EasingDirection {
+    pub const ALL: [EasingDirection; 3] = [EasingDirection::In, EasingDirection::Out, EasingDirection::InOut];
+}
+
+/// `EaseInQuad`などの標準的なイージング
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StandardEasing {
+    pub curve: EasingCurve,
+    pub direction: EasingDirection,
+}
+
+impl StandardEasing {
+    pub const fn new(curve: EasingCurve, direction: EasingDirection) -> StandardEasing {
+        StandardEasing { curve, direction }
+    }
+
+    pub fn all() -> impl Iterator<Item = StandardEasing> {
+        EasingCurve::ALL.into_iter().flat_map(|curve| EasingDirection::ALL.into_iter().map(move |direction| StandardEasing::new(curve, direction)))
+    }
+
+    fn name(&self) -> &'static str {
+        // プロジェクトファイルに保存される名前なので変更しないこと
+        const NAMES: [[&str; 3]; 10] = [
+            ["EaseInQuad", "EaseOutQuad", "EaseInOutQuad"],
+            ["EaseInCubic", "EaseOutCubic", "EaseInOutCubic"],
+            ["EaseInQuart", "EaseOutQuart", "EaseInOutQuart"],
+            ["EaseInQuint", "EaseOutQuint", "EaseInOutQuint"],
+            ["EaseInSine", "EaseOutSine", "EaseInOutSine"],
+            ["EaseInExpo", "EaseOutExpo", "EaseInOutExpo"],
+            ["EaseInCirc", "EaseOutCirc", "EaseInOutCirc"],
+            ["EaseInBack", "EaseOutBack", "EaseInOutBack"],
+            ["EaseInElastic", "EaseOutElastic", "EaseInOutElastic"],
+            ["EaseInBounce", "EaseOutBounce", "EaseInOutBounce"],
+        ];
+        NAMES[self.curve as usize][self.direction as usize]
+    }
+}
+
+impl Easing for StandardEasing {
+    fn identifier(&self) -> EasingIdentifier {
+        EasingIdentifier {
+            namespace: Cow::Borrowed("mpdelta"),
+            name: Cow::Borrowed(self.name()),
+        }
+    }
+
+    fn easing(&self, from: EasingInput) -> f64 {
+        let x = from.value();
+        let curve = self.curve;
+        match self.direction {
+            EasingDirection::In => curve.ease_in(x),
+            EasingDirection::Out => 1. - curve.ease_in(1. - x),
+            EasingDirection::InOut if x < 0.5 => curve.ease_in(x * 2.) / 2.,
+            EasingDirection::InOut => 1. - curve.ease_in(2. - x * 2.) / 2.,
+        }
+    }
+}
+
+/// 区間の開始直後に終点の値へ切り替わるイージング
+pub struct StepEasing;
+
+impl Easing for StepEasing {
+    fn identifier(&self) -> EasingIdentifier {
+        EasingIdentifier {
+            namespace: Cow::Borrowed("mpdelta"),
+            name: Cow::Borrowed("Step"),
+        }
+    }
+
+    fn easing(&self, from: EasingInput) -> f64 {
+        if from.value() > 0. {
+            1.
+        } else {
+            0.
+        }
+    }
+}
+
+/// 区間の終端まで始点の値を保持するイージング
+pub struct HoldEasing;
+
+impl Easing for HoldEasing {
+    fn identifier(&self) -> EasingIdentifier {
+        EasingIdentifier {
+            namespace: Cow::Borrowed("mpdelta"),
+            name: Cow::Borrowed("Hold"),
+        }
+    }
+
+    fn easing(&self, from: EasingInput) -> f64 {
+        if from.value() < 1. {
+            0.
+        } else {
+            1.
+        }
+    }
+}
+
+/// CSSの`cubic-bezier(x1, y1, x2, y2)`と同じ曲線
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CubicBezierEasing {
+    pub x1: f64,
+    pub y1: f64,
+    pub x2: f64,
+    pub y2: f64,
+}
+
+impl Default for CubicBezierEasing {
+    /// CSSの`ease`
+    fn default() -> Self {
+        CubicBezierEasing::new(0.25, 0.1, 0.25, 1.)
+    }
+}
+
+impl CubicBezierEasing {
+    /// xが単調増加になるよう、`x1`, `x2`は[0, 1]に丸める
+    pub fn new(x1: f64, y1: f64, x2: f64, y2: f64) -> CubicBezierEasing {
+        let sanitize = |v: f64| if v.is_finite() { v } else { 0. };
+        CubicBezierEasing {
+            x1: sanitize(x1).clamp(0., 1.),
+            y1: sanitize(y1),
+            x2: sanitize(x2).clamp(0., 1.),
+            y2: sanitize(y2),
+        }
+    }
+
+    fn bezier(t: f64, p1: f64, p2: f64) -> f64 {
+        let s = 1. - t;
+        3. * s * s * t * p1 + 3. * s * t * t * p2 + t * t * t
+    }
+
+    fn bezier_derivative(t: f64, p1: f64, p2: f64) -> f64 {
+        let s = 1. - t;
+        3. * s * s * p1 + 6. * s * t * (p2 - p1) + 3. * t * t * (1. - p2)
+    }
+
+    /// x(t) = x となるtを求める
+    fn solve_t(&self, x: f64) -> f64 {
+        let mut t = x;
+        for _ in 0..8 {
+            let error = CubicBezierEasing::bezier(t, self.x1, self.x2) - x;
+            if error.abs() < 1e-9 {
+                return t;
+            }
+            let derivative = CubicBezierEasing::bezier_derivative(t, self.x1, self.x2);
+            if derivative.abs() < 1e-9 {
+                break;
+            }
+            t -= error / derivative;
+        }
+        // Newton法が収束しなければ二分法
+        let (mut low, mut high) = (0., 1.);
+        t = x;
+        for _ in 0..64 {
+            let value = CubicBezierEasing::bezier(t, self.x1, self.x2);
+            if (value - x).abs() < 1e-9 {
+                break;
+            }
+            if value < x {
+                low = t;
+            } else {
+                high = t;
+            }
+            t = (low + high) / 2.;
+        }
+        t
+    }
+}
+
+impl Easing for CubicBezierEasing {
+    fn identifier(&self) -> EasingIdentifier {
+        EasingIdentifier {
+            namespace: Cow::Borrowed("mpdelta"),
+            name: Cow::Borrowed("CubicBezier"),
+        }
+    }
+
+    fn easing(&self, from: EasingInput) -> f64 {
+        let x = from.value();
+        if x <= 0. || x >= 1. {
+            return x;
+        }
+        CubicBezierEasing::bezier(self.solve_t(x), self.y1, self.y2)
+    }
+
+    fn parameters(&self) -> Option<&dyn erased_serde::Serialize> {
+        Some(self)
+    }
+
+    fn with_parameters(&self, deserializer: &mut dyn erased_serde::Deserializer) -> Result<Arc<dyn Easing>, erased_serde::Error> {
+        let CubicBezierEasing { x1, y1, x2, y2 } = erased_serde::deserialize(deserializer)?;
+        Ok(Arc::new(CubicBezierEasing::new(x1, y1, x2, y2)))
+    }
+
+    fn numeric_parameters(&self) -> Vec<(&'static str, f64)> {
+        vec![("x1", self.x1), ("y1", self.y1), ("x2", self.x2), ("y2", self.y2)]
+    }
+
+    fn with_numeric_parameters(&self, values: &[f64]) -> Option<Arc<dyn Easing>> {
+        let &[x1, y1, x2, y2] = values else { return None };
+        Some(Arc::new(CubicBezierEasing::new(x1, y1, x2, y2)))
+    }
+}
+
+/// 質量`mass`の物体を、ばね定数`stiffness`、減衰係数`damping`のばねで0から1へ引っ張ったときの位置
+///
+/// 区間の長さを単位時間とし、終端で必ず1になるよう残差を線形に補正する
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SpringEasing {
+    pub stiffness: f64,
+    pub damping: f64,
+    pub mass: f64,
+}
+
+impl Default for SpringEasing {
+    fn default() -> Self {
+        SpringEasing::new(100., 10., 1.)
+    }
+}
+
+impl SpringEasing {
+    pub fn new(stiffness: f64, damping: f64, mass: f64) -> SpringEasing {
+        let positive = |v: f64| if v.is_finite() { v.max(f64::EPSILON) } else { 1. };
+        SpringEasing {
+            stiffness: positive(stiffness),
+            damping: if damping.is_finite() { damping.max(0.) } else { 0. },
+            mass: positive(mass),
+        }
+    }
+
+    fn position(&self, t: f64) -> f64 {
+        let SpringEasing { stiffness, damping, mass } = *self;
+        let omega = (stiffness / mass).sqrt();
+        let zeta = damping / (2. * (stiffness * mass).sqrt());
+        if zeta < 1. {
+            let omega_d = omega * (1. - zeta * zeta).sqrt();
+            1. - (-zeta * omega * t).exp() * ((omega_d * t).cos() + zeta * omega / omega_d * (omega_d * t).sin())
+        } else if zeta == 1. {
+            1. - (-omega * t).exp() * (1. + omega * t)
+        } else {
+            let root = (zeta * zeta - 1.).sqrt();
+            let r1 = -omega * (zeta - root);
+            let r2 = -omega * (zeta + root);
+            1. - (r2 * (r1 * t).exp() - r1 * (r2 * t).exp()) / (r2 - r1)
+        }
+    }
+}
+
+impl Easing for SpringEasing {
+    fn identifier(&self) -> EasingIdentifier {
+        EasingIdentifier {
+            namespace: Cow::Borrowed("mpdelta"),
+            name: Cow::Borrowed("Spring"),
+        }
+    }
+
+    fn easing(&self, from: EasingInput) -> f64 {
+        let x = from.value();
+        self.position(x) + (1. - self.position(1.)) * x
+    }
+
+    fn parameters(&self) -> Option<&dyn erased_serde::Serialize> {
+        Some(self)
+    }
+
+    fn with_parameters(&self, deserializer: &mut dyn erased_serde::Deserializer) -> Result<Arc<dyn Easing>, erased_serde::Error> {
+        let SpringEasing { stiffness, damping, mass } = erased_serde::deserialize(deserializer)?;
+        Ok(Arc::new(SpringEasing::new(stiffness, damping, mass)))
+    }
+
+    fn numeric_parameters(&self) -> Vec<(&'static str, f64)> {
+        vec![("stiffness", self.stiffness), ("damping", self.damping), ("mass", self.mass)]
+    }
+
+    fn with_numeric_parameters(&self, values: &[f64]) -> Option<Arc<dyn Easing>> {
+        let &[stiffness, damping, mass] = values else { return None };
+        Some(Arc::new(SpringEasing::new(stiffness, damping, mass)))
+    }
+}
+
+/// 標準で登録するイージングの一覧
+///
+/// パラメータを持つイージングはデフォルト値のものが登録され、読み込み時に[`Easing::with_parameters`]でパラメータが差し替えられる
+pub fn standard_easings() -> impl Iterator<Item = Arc<dyn Easing>> {
+    [Arc::new(LinearEasing) as Arc<dyn Easing>]
+        .into_iter()
+        .chain(StandardEasing::all().map(|easing| Arc::new(easing) as Arc<dyn Easing>))
+        .chain([Arc::new(StepEasing) as Arc<dyn Easing>, Arc::new(HoldEasing) as Arc<dyn Easing>])
+        .chain([Arc::new(CubicBezierEasing::default()) as Arc<dyn Easing>, Arc::new(SpringEasing::default()) as Arc<dyn Easing>])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_standard_easing_endpoints() {
+        for easing in StandardEasing::all() {
+            assert!(easing.easing(EasingInput::new(0.)).abs() < 1e-9, "{easing:?}");
+            assert!((easing.easing(EasingInput::new(1.)) - 1.).abs() < 1e-9, "{easing:?}");
+        }
+        for easing in StandardEasing::all().filter(|easing| easing.direction == EasingDirection::InOut) {
+            assert!((easing.easing(EasingInput::new(0.5)) - 0.5).abs() < 1e-9, "{easing:?}");
+        }
+    }
+
+    #[test]
+    fn test_standard_easing_values() {
+        let at = |curve, direction, x| StandardEasing::new(curve, direction).easing(EasingInput::new(x));
+        assert!((at(EasingCurve::Quad, EasingDirection::In, 0.5) - 0.25).abs() < 1e-9);
+        assert!((at(EasingCurve::Quad, EasingDirection::Out, 0.5) - 0.75).abs() < 1e-9);
+        assert!((at(EasingCurve::Cubic, EasingDirection::InOut, 0.25) - 0.0625).abs() < 1e-9);
+        assert!((at(EasingCurve::Sine, EasingDirection::Out, 0.5) - (PI / 4.).sin()).abs() < 1e-9);
+        assert!((at(EasingCurve::Bounce, EasingDirection::Out, 0.5) - 0.765625).abs() < 1e-9);
+        // Back, Elasticは範囲外に振れる
+        assert!(at(EasingCurve::Back, EasingDirection::In, 0.2) < 0.);
+        assert!(at(EasingCurve::Back, EasingDirection::Out, 0.8) > 1.);
+        assert!(at(EasingCurve::Elastic, EasingDirection::Out, 0.1) > 1.);
+    }
+
+    #[test]
+    fn test_step_and_hold() {
+        assert_eq!(StepEasing.easing(EasingInput::new(0.)), 0.);
+        assert_eq!(StepEasing.easing(EasingInput::new(0.01)), 1.);
+        assert_eq!(HoldEasing.easing(EasingInput::new(0.99)), 0.);
+        assert_eq!(HoldEasing.easing(EasingInput::new(1.)), 1.);
+    }
+
+    #[test]
+    fn test_cubic_bezier_easing() {
+        let linear = CubicBezierEasing::new(0., 0., 1., 1.);
+        for x in [0., 0.1, 0.25, 0.5, 0.75, 1.] {
+            assert!((linear.easing(EasingInput::new(x)) - x).abs() < 1e-6);
+        }
+        let ease = CubicBezierEasing::default();
+        assert!((ease.easing(EasingInput::new(0.5)) - 0.8024033877399112).abs() < 1e-6);
+        let clamped = CubicBezierEasing::new(-1., 0., 2., 1.);
+        assert_eq!((clamped.x1, clamped.x2), (0., 1.));
+        // 範囲外のyは許容する
+        let overshoot = CubicBezierEasing::new(0.3, 1.5, 0.7, 1.5);
+        assert!(overshoot.easing(EasingInput::new(0.5)) > 1.);
+    }
+
+    #[test]
+    fn test_spring_easing() {
+        for spring in [SpringEasing::default(), SpringEasing::new(100., 20., 1.), SpringEasing::new(100., 50., 1.), SpringEasing::new(1., 0., 1.)] {
+            assert!(spring.easing(EasingInput::new(0.)).abs() < 1e-9, "{spring:?}");
+            assert!((spring.easing(EasingInput::new(1.)) - 1.).abs() < 1e-9, "{spring:?}");
+        }
+        // 減衰が弱ければ行き過ぎる
+        assert!((0..100).map(|i| SpringEasing::default().easing(EasingInput::new(i as f64 / 100.))).any(|v| v > 1.));
+    }
+
+    #[test]
+    fn test_easing_parameters_roundtrip() {
+        let easing = CubicBezierEasing::new(0.1, 0.7, 0.3, 1.2);
+        let json = serde_json::to_value(easing.parameters().unwrap()).unwrap();
+        let restored = CubicBezierEasing::default().with_parameters(&mut <dyn erased_serde::Deserializer>::erase(json)).unwrap();
+        assert_eq!(restored.identifier(), easing.identifier());
+        assert_eq!(restored.easing(EasingInput::new(0.4)), easing.easing(EasingInput::new(0.4)));
+        let json = serde_json::to_value(SpringEasing::new(50., 3., 2.).parameters().unwrap()).unwrap();
+        let restored = SpringEasing::default().with_parameters(&mut <dyn erased_serde::Deserializer>::erase(json)).unwrap();
+        assert_eq!(restored.easing(EasingInput::new(0.4)), SpringEasing::new(50., 3., 2.).easing(EasingInput::new(0.4)));
+        assert!(LinearEasing.with_parameters(&mut <dyn erased_serde::Deserializer>::erase(serde_json::Value::Null)).is_err());
+    }
+
+    #[test]
+    fn test_numeric_parameters() {
+        let easing = CubicBezierEasing::default().with_numeric_parameters(&[0.1, 0.7, 1.5, 1.2]).unwrap();
+        // x2は[0, 1]に丸められる
+        assert_eq!(easing.numeric_parameters(), [("x1", 0.1), ("y1", 0.7), ("x2", 1.), ("y2", 1.2)]);
+        let easing = SpringEasing::default().with_numeric_parameters(&[50., 3., 2.]).unwrap();
+        assert_eq!(easing.numeric_parameters(), [("stiffness", 50.), ("damping", 3.), ("mass", 2.)]);
+        assert!(SpringEasing::default().with_numeric_parameters(&[1.]).is_none());
+        assert!(LinearEasing.numeric_parameters().is_empty());
+        assert!(LinearEasing.with_numeric_parameters(&[]).is_none());
+    }
+
+    #[test]
+    fn test_standard_easings_identifier_unique() {
+        let identifiers = standard_easings().map(|easing| easing.identifier().into_static()).collect::<Vec<_>>();
+        assert_eq!(identifiers.len(), 1 + 30 + 2 + 2);
+        assert_eq!(identifiers.iter().collect::<HashSet<_>>().len(), identifiers.len());
+    }
+}
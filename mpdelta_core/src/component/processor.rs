@@ -4,33 +4,160 @@ use crate::component::link::MarkerLink;
 use crate::component::marker_pin::{MarkerPin, MarkerPinId, MarkerTime};
 use crate::component::parameter::{Never, Parameter, ParameterSelect, ParameterType, ParameterValueRaw, ParameterValueType, ValueRaw};
 use crate::ptr::StaticPointer;
-use crate::time::TimelineTime;
+use crate::time::{FrameRate, TimelineTime};
 use async_trait::async_trait;
 use dyn_eq::DynEq;
 use dyn_hash::DynHash;
 use futures::TryFutureExt;
+use num::Integer;
+use serde::{Deserialize, Serialize};
 use std::any::{Any, TypeId};
 use std::error::Error;
 use std::fmt::{Debug, Display};
 use std::future::Future;
 use std::hash::Hash;
 use std::marker::PhantomData;
+use std::ops::Range;
 use std::pin::Pin;
 use std::sync::Arc;
+use thiserror::Error;
 use tokio::sync::RwLock;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "ImageSizeUnchecked")]
+#[cfg_attr(any(feature = "proptest", test), derive(proptest_derive::Arbitrary))]
 pub struct ImageSize {
+    #[serde(rename = "w")]
+    #[cfg_attr(any(feature = "proptest", test), proptest(strategy = "1u32..=8192"))]
     pub width: u32,
+    #[serde(rename = "h")]
+    #[cfg_attr(any(feature = "proptest", test), proptest(strategy = "1u32..=8192"))]
     pub height: u32,
 }
 
+impl ImageSize {
+    pub const FULL_HD: ImageSize = ImageSize { width: 1920, height: 1080 };
+
+    /// 幅か高さが0の画像は出力できないので、有効なサイズかどうかを返す
+    pub fn is_valid(&self) -> bool {
+        self.width > 0 && self.height > 0
+    }
+}
+
+impl Default for ImageSize {
+    fn default() -> Self {
+        ImageSize::FULL_HD
+    }
+}
+
+#[derive(Deserialize)]
+struct ImageSizeUnchecked {
+    #[serde(rename = "w")]
+    width: u32,
+    #[serde(rename = "h")]
+    height: u32,
+}
+
+#[derive(Debug, Error)]
+#[error("invalid image size: {0}x{1}")]
+pub struct InvalidImageSize(u32, u32);
+
+impl TryFrom<ImageSizeUnchecked> for ImageSize {
+    type Error = InvalidImageSize;
+
+    fn try_from(ImageSizeUnchecked { width, height }: ImageSizeUnchecked) -> Result<Self, Self::Error> {
+        let size = ImageSize { width, height };
+        size.is_valid().then_some(size).ok_or(InvalidImageSize(width, height))
+    }
+}
+
+/// ピクセルアスペクト比(1ピクセルの幅/高さ)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "(u32, u32)", into = "(u32, u32)")]
+pub struct PixelAspectRatio {
+    numerator: u32,
+    denominator: u32,
+}
+
+#[cfg(any(feature = "proptest", test))]
+const _: () = {
+    use proptest::prelude::*;
+    impl Arbitrary for PixelAspectRatio {
+        type Parameters = ();
+
+        fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+            (1u32..256, 1u32..256).prop_filter_map("invalid pixel aspect ratio", |(n, d)| PixelAspectRatio::new(n, d))
+        }
+
+        type Strategy = proptest::strategy::FilterMap<(Range<u32>, Range<u32>), fn((u32, u32)) -> Option<PixelAspectRatio>>;
+    }
+};
+
+#[derive(Debug, Error)]
+#[error("invalid pixel aspect ratio: {0}/{1}")]
+pub struct InvalidPixelAspectRatio(u32, u32);
+
+impl PixelAspectRatio {
+    pub const SQUARE: PixelAspectRatio = PixelAspectRatio { numerator: 1, denominator: 1 };
+
+    /// `numerator / denominator` のPixelAspectRatioを作る
+    ///
+    /// どちらかが0の場合はNoneを返す
+    pub fn new(numerator: u32, denominator: u32) -> Option<PixelAspectRatio> {
+        if numerator == 0 || denominator == 0 {
+            return None;
+        }
+        let gcd = numerator.gcd(&denominator);
+        Some(PixelAspectRatio {
+            numerator: numerator / gcd,
+            denominator: denominator / gcd,
+        })
+    }
+
+    pub fn numerator(self) -> u32 {
+        self.numerator
+    }
+
+    pub fn denominator(self) -> u32 {
+        self.denominator
+    }
+
+    pub fn into_f64(self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    /// `size`の画像を表示するときの幅/高さ
+    pub fn display_aspect_ratio(self, size: ImageSize) -> f64 {
+        size.width as f64 * self.numerator as f64 / (size.height as f64 * self.denominator as f64)
+    }
+}
+
+impl Default for PixelAspectRatio {
+    fn default() -> Self {
+        PixelAspectRatio::SQUARE
+    }
+}
+
+impl TryFrom<(u32, u32)> for PixelAspectRatio {
+    type Error = InvalidPixelAspectRatio;
+
+    fn try_from((numerator, denominator): (u32, u32)) -> Result<Self, Self::Error> {
+        PixelAspectRatio::new(numerator, denominator).ok_or(InvalidPixelAspectRatio(numerator, denominator))
+    }
+}
+
+impl From<PixelAspectRatio> for (u32, u32) {
+    fn from(value: PixelAspectRatio) -> Self {
+        (value.numerator, value.denominator)
+    }
+}
+
 pub trait ComponentsLinksPair<T>: Send + Sync
 where
     T: ParameterValueType,
 {
     fn default_image_size(&self) -> ImageSize;
-    fn frames_per_second(&self) -> u32;
+    fn frames_per_second(&self) -> FrameRate;
     fn components(&self) -> impl DoubleEndedIterator<Item = &'_ Arc<ComponentInstance<T>>> + Send + Sync + '_
     where
         Self: Sized;
@@ -52,7 +179,7 @@ where
         C::default_image_size(self)
     }
 
-    fn frames_per_second(&self) -> u32 {
+    fn frames_per_second(&self) -> FrameRate {
         C::frames_per_second(self)
     }
 
@@ -95,7 +222,7 @@ where
         (*self).default_image_size()
     }
 
-    fn frames_per_second(&self) -> u32 {
+    fn frames_per_second(&self) -> FrameRate {
         (*self).frames_per_second()
     }
 
@@ -358,6 +485,8 @@ where
 pub struct NativeGatherProcessorInput<'a, T: ParameterValueType> {
     pub fixed_parameters: &'a [ParameterValueRaw<T::Image, T::Audio>],
     pub interprocess_pins: &'a [TimelineTime],
+    /// コンポーネントの左端から右端までのローカル時刻の範囲
+    pub local_time_range: &'a Range<TimelineTime>,
     pub variable_parameters: &'a [Parameter<GatherNativeProcessorParam<T::Image, T::Audio>>],
     pub variable_parameter_type: &'a [(String, ParameterType)],
 }
@@ -555,6 +684,24 @@ mod tests {
         type ComponentClass = ();
     }
 
+    #[test]
+    fn test_pixel_aspect_ratio() {
+        assert_eq!(PixelAspectRatio::new(1, 1), Some(PixelAspectRatio::SQUARE));
+        assert_eq!(PixelAspectRatio::new(8, 6), PixelAspectRatio::new(4, 3));
+        assert_eq!(PixelAspectRatio::new(0, 1), None);
+        assert_eq!(PixelAspectRatio::new(1, 0), None);
+        assert!(PixelAspectRatio::try_from((10, 11)).is_ok());
+        assert!(PixelAspectRatio::try_from((10, 0)).is_err());
+        assert_eq!(PixelAspectRatio::SQUARE.display_aspect_ratio(ImageSize { width: 1080, height: 1920 }), 1080. / 1920.);
+        assert_eq!(PixelAspectRatio::new(4, 3).unwrap().display_aspect_ratio(ImageSize { width: 1440, height: 1080 }), 16. / 9.);
+        assert!(!ImageSize { width: 0, height: 1080 }.is_valid());
+        assert!(ImageSize::default().is_valid());
+        assert_eq!(serde_json::from_str::<ImageSize>(r#"{"w":1080,"h":1920}"#).unwrap(), ImageSize { width: 1080, height: 1920 });
+        assert_eq!(serde_json::to_string(&ImageSize::FULL_HD).unwrap(), r#"{"w":1920,"h":1080}"#);
+        assert!(serde_json::from_str::<ImageSize>(r#"{"w":0,"h":1080}"#).is_err());
+        assert!(serde_json::from_str::<ImageSize>(r#"{"w":1920,"h":0}"#).is_err());
+    }
+
     #[test]
     fn test_components_links_pair() {
         struct TestComponentsLinksPair {
@@ -566,7 +713,7 @@ mod tests {
                 unimplemented!()
             }
 
-            fn frames_per_second(&self) -> u32 {
+            fn frames_per_second(&self) -> FrameRate {
                 unimplemented!()
             }
 
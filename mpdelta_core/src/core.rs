@@ -3,8 +3,10 @@ use crate::component::instance::{ComponentInstance, ComponentInstanceId};
 use crate::component::parameter::value::{DynEditableEasingValueIdentifier, DynEditableEasingValueManager, DynEditableSingleValueIdentifier, DynEditableSingleValueManager, Easing, EasingIdentifier};
 use crate::component::parameter::ParameterValueType;
 use crate::edit::{InstanceEditCommand, InstanceEditEvent, RootComponentEditCommand, RootComponentEditEvent};
+use crate::encode::EncodeMonitor;
 use crate::project::{Project, ProjectHandle, ProjectHandleOwned, RootComponentClass, RootComponentClassHandle, RootComponentClassHandleOwned};
 use crate::ptr::{StaticPointer, StaticPointerOwned};
+use crate::time::TimelineTime;
 use crate::usecase::*;
 use async_trait::async_trait;
 use std::borrow::Cow;
@@ -12,7 +14,7 @@ use std::error::Error;
 use std::fmt::Debug;
 use std::future::Future;
 use std::io::{Read, Write};
-use std::ops::Deref;
+use std::ops::{Deref, Range};
 use std::path::Path;
 use std::pin::Pin;
 use std::sync::Arc;
@@ -413,7 +415,13 @@ where
 
 pub trait ComponentEncoder<T: ParameterValueType, Encoder>: Send + Sync {
     type Err: Error + Send + 'static;
-    fn render_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, encoder: Encoder) -> impl Future<Output = Result<(), Self::Err>> + Send + 'async_trait
+    fn render_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, encoder: Encoder, monitor: EncodeMonitor) -> impl Future<Output = Result<(), Self::Err>> + Send + 'async_trait
+    where
+        'life0: 'async_trait;
+    fn render_range_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, range: Range<TimelineTime>, encoder: Encoder, monitor: EncodeMonitor) -> impl Future<Output = Result<(), Self::Err>> + Send + 'async_trait
+    where
+        'life0: 'async_trait;
+    fn render_frame_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, at: TimelineTime, encoder: Encoder) -> impl Future<Output = Result<(), Self::Err>> + Send + 'async_trait
     where
         'life0: 'async_trait;
 }
@@ -425,11 +433,41 @@ where
 {
     type Err = VE::Err;
 
-    fn render_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, encoder: Encoder) -> impl Future<Output = Result<(), Self::Err>> + Send + 'async_trait
+    fn render_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, encoder: Encoder, monitor: EncodeMonitor) -> impl Future<Output = Result<(), Self::Err>> + Send + 'async_trait
+    where
+        'life0: 'async_trait,
+    {
+        self.video_encoder.render_and_encode(component, encoder, monitor)
+    }
+}
+
+impl<T: ParameterValueType, T0, T1, T2, T3, T4, T5, T6, T7, VE, T9, T10, Encoder> RenderRangeUsecase<T, Encoder> for MPDeltaCore<T0, T1, T2, T3, T4, T5, T6, T7, VE, T9, T10>
+where
+    Self: Send + Sync,
+    VE: ComponentEncoder<T, Encoder>,
+{
+    type Err = VE::Err;
+
+    fn render_range_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, range: Range<TimelineTime>, encoder: Encoder, monitor: EncodeMonitor) -> impl Future<Output = Result<(), Self::Err>> + Send + 'async_trait
+    where
+        'life0: 'async_trait,
+    {
+        self.video_encoder.render_range_and_encode(component, range, encoder, monitor)
+    }
+}
+
+impl<T: ParameterValueType, T0, T1, T2, T3, T4, T5, T6, T7, VE, T9, T10, Encoder> RenderFrameUsecase<T, Encoder> for MPDeltaCore<T0, T1, T2, T3, T4, T5, T6, T7, VE, T9, T10>
+where
+    Self: Send + Sync,
+    VE: ComponentEncoder<T, Encoder>,
+{
+    type Err = VE::Err;
+
+    fn render_frame_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, at: TimelineTime, encoder: Encoder) -> impl Future<Output = Result<(), Self::Err>> + Send + 'async_trait
     where
         'life0: 'async_trait,
     {
-        self.video_encoder.render_and_encode(component, encoder)
+        self.video_encoder.render_frame_and_encode(component, at, encoder)
     }
 }
 
@@ -2,7 +2,8 @@ use crate::component::instance::{ComponentInstance, ComponentInstanceId};
 use crate::component::link::MarkerLink;
 use crate::component::marker_pin::{MarkerPinId, MarkerTime};
 use crate::component::parameter::{ImageRequiredParams, ParameterNullableValue, ParameterValueFixed, ParameterValueType, VariableParameterValue};
-use crate::time::TimelineTime;
+use crate::component::processor::{ImageSize, PixelAspectRatio};
+use crate::time::{FrameRate, TimelineTime};
 
 pub enum RootComponentEditCommand<T: ParameterValueType> {
     AddComponentInstance(ComponentInstance<T>),
@@ -12,6 +13,8 @@ pub enum RootComponentEditCommand<T: ParameterValueType> {
     DeleteComponentInstance(ComponentInstanceId),
     EditComponentLength(MarkerTime),
     ConnectMarkerPins(MarkerPinId, MarkerPinId),
+    EditFrameRate(FrameRate),
+    EditImageSize(ImageSize, PixelAspectRatio),
 }
 
 pub enum InstanceEditCommand<T: ParameterValueType> {
@@ -35,6 +38,8 @@ pub enum RootComponentEditEvent<'a> {
     DeleteComponentInstance(&'a ComponentInstanceId),
     EditComponentLength(MarkerTime),
     ConnectMarkerPins(&'a MarkerPinId, &'a MarkerPinId),
+    EditFrameRate(FrameRate),
+    EditImageSize(ImageSize, PixelAspectRatio),
 }
 
 pub enum InstanceEditEvent<'a, T: ParameterValueType> {
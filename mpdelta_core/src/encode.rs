@@ -0,0 +1,169 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// エンコードの処理段階
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EncodePhase {
+    Audio,
+    Video,
+    Finalize,
+}
+
+/// エンコードの進捗
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodeProgress {
+    pub phase: EncodePhase,
+    /// `Video`では描画したフレーム数、`Audio`ではエンコードした音声のサンプル数
+    pub frames_done: u64,
+    /// 全体の量 分からなければ0
+    pub total_frames: u64,
+    pub elapsed: Duration,
+}
+
+impl EncodeProgress {
+    /// 0.0から1.0の範囲の進捗率
+    pub fn fraction(&self) -> f64 {
+        match self.phase {
+            EncodePhase::Audio | EncodePhase::Video if self.total_frames == 0 => 0.,
+            EncodePhase::Audio | EncodePhase::Video => (self.frames_done as f64 / self.total_frames as f64).clamp(0., 1.),
+            EncodePhase::Finalize => 1.,
+        }
+    }
+
+    /// これまでの速度から推定した残り時間 まだ1フレームも終わっていなければNone
+    pub fn eta(&self) -> Option<Duration> {
+        match self.phase {
+            EncodePhase::Audio | EncodePhase::Video if self.frames_done > 0 && self.total_frames > 0 => {
+                let remaining = self.total_frames.saturating_sub(self.frames_done);
+                Some(self.elapsed.mul_f64(remaining as f64 / self.frames_done as f64))
+            }
+            EncodePhase::Finalize => Some(Duration::ZERO),
+            _ => None,
+        }
+    }
+}
+
+/// エンコードを中断するためのトークン
+#[derive(Debug, Clone)]
+pub struct EncodeCancellationToken(Arc<watch::Sender<bool>>);
+
+impl Default for EncodeCancellationToken {
+    fn default() -> Self {
+        EncodeCancellationToken(Arc::new(watch::Sender::new(false)))
+    }
+}
+
+impl EncodeCancellationToken {
+    pub fn new() -> EncodeCancellationToken {
+        EncodeCancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.send_replace(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// 中断されるまで待つ
+    pub async fn cancelled(&self) {
+        // Senderは自分が持っているので、閉じてエラーになることはない
+        let _ = self.0.subscribe().wait_for(|&cancelled| cancelled).await;
+    }
+}
+
+/// エンコード処理側が進捗を報告し、中断要求を確認するためのハンドル
+///
+/// `Default`で作ったものは進捗をどこにも報告せず、中断もされない
+#[derive(Debug, Clone, Default)]
+pub struct EncodeMonitor {
+    progress: Option<Arc<watch::Sender<Option<EncodeProgress>>>>,
+    cancellation: EncodeCancellationToken,
+}
+
+impl EncodeMonitor {
+    /// モニタと、進捗を受け取る`watch::Receiver`を作る
+    pub fn new() -> (EncodeMonitor, watch::Receiver<Option<EncodeProgress>>) {
+        let (sender, receiver) = watch::channel(None);
+        let monitor = EncodeMonitor {
+            progress: Some(Arc::new(sender)),
+            cancellation: EncodeCancellationToken::new(),
+        };
+        (monitor, receiver)
+    }
+
+    pub fn with_cancellation_token(self, cancellation: EncodeCancellationToken) -> EncodeMonitor {
+        EncodeMonitor { cancellation, ..self }
+    }
+
+    pub fn cancellation_token(&self) -> &EncodeCancellationToken {
+        &self.cancellation
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    /// 中断されるまで待つ
+    pub async fn cancelled(&self) {
+        self.cancellation.cancelled().await
+    }
+
+    pub fn report(&self, progress: EncodeProgress) {
+        if let Some(sender) = &self.progress {
+            sender.send_replace(Some(progress));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    #[test]
+    fn test_encode_progress() {
+        let progress = EncodeProgress {
+            phase: EncodePhase::Video,
+            frames_done: 25,
+            total_frames: 100,
+            elapsed: Duration::from_secs(5),
+        };
+        assert_eq!(progress.fraction(), 0.25);
+        assert_eq!(progress.eta(), Some(Duration::from_secs(15)));
+        assert_eq!(EncodeProgress { frames_done: 0, ..progress }.eta(), None);
+        assert_eq!(EncodeProgress { phase: EncodePhase::Audio, ..progress }.fraction(), 0.25);
+        assert_eq!(EncodeProgress { phase: EncodePhase::Audio, ..progress }.eta(), Some(Duration::from_secs(15)));
+        assert_eq!(EncodeProgress { phase: EncodePhase::Audio, total_frames: 0, ..progress }.eta(), None);
+        assert_eq!(EncodeProgress { phase: EncodePhase::Finalize, ..progress }.fraction(), 1.);
+        assert_eq!(EncodeProgress { total_frames: 0, ..progress }.fraction(), 0.);
+    }
+
+    #[test]
+    fn test_encode_monitor() {
+        let (monitor, receiver) = EncodeMonitor::new();
+        assert_eq!(*receiver.borrow(), None);
+        let progress = EncodeProgress {
+            phase: EncodePhase::Audio,
+            frames_done: 0,
+            total_frames: 10,
+            elapsed: Duration::ZERO,
+        };
+        monitor.clone().report(progress);
+        assert_eq!(*receiver.borrow(), Some(progress));
+
+        let token = EncodeCancellationToken::new();
+        let monitor = monitor.with_cancellation_token(token.clone());
+        assert!(!monitor.is_cancelled());
+        assert_eq!(monitor.cancelled().now_or_never(), None);
+        token.cancel();
+        assert!(monitor.is_cancelled());
+        assert_eq!(monitor.cancelled().now_or_never(), Some(()));
+        assert!(monitor.cancellation_token().is_cancelled());
+
+        // Defaultは報告先がなくてもpanicしない
+        EncodeMonitor::default().report(progress);
+    }
+}
@@ -2,6 +2,7 @@ pub mod common;
 pub mod component;
 pub mod core;
 pub mod edit;
+pub mod encode;
 pub mod project;
 pub mod ptr;
 pub mod time;
@@ -6,10 +6,10 @@ use crate::component::link::MarkerLink;
 use crate::component::marker_pin::{MarkerPin, MarkerPinId, MarkerTime};
 use crate::component::parameter::value::{DynEditableLerpEasingValue, EasingValue, LinearEasing};
 use crate::component::parameter::{AudioRequiredParams, ImageRequiredParams, ImageRequiredParamsTransform, ParameterType, ParameterValueRaw, ParameterValueType, VariableParameterValue};
-use crate::component::processor::{ComponentProcessor, ComponentProcessorComponent, ComponentProcessorWrapper, ComponentsLinksPair, ImageSize};
+use crate::component::processor::{ComponentProcessor, ComponentProcessorComponent, ComponentProcessorWrapper, ComponentsLinksPair, ImageSize, PixelAspectRatio};
 use crate::core::IdGenerator;
 use crate::ptr::{StaticPointer, StaticPointerCow, StaticPointerOwned};
-use crate::time::TimelineTime;
+use crate::time::{FrameRate, TimelineTime};
 use arc_swap::ArcSwap;
 use async_trait::async_trait;
 use cgmath::{One, Quaternion, Vector3};
@@ -102,10 +102,16 @@ pub struct RootComponentClassItem<T: ParameterValueType> {
     link_map: HashTrieMapSync<MarkerPinId, HashTrieSetSync<MarkerPinId>>,
     pin_time_map: Arc<HashMap<MarkerPinId, TimelineTime>>,
     length: MarkerTime,
+    frame_rate: FrameRate,
+    image_size: ImageSize,
+    pixel_aspect_ratio: PixelAspectRatio,
 }
 
 pub struct RootComponentClassItemViewBase<'a> {
     length: &'a mut MarkerTime,
+    frame_rate: &'a mut FrameRate,
+    image_size: &'a mut ImageSize,
+    pixel_aspect_ratio: &'a mut PixelAspectRatio,
 }
 
 pub struct RootComponentClassItemViewStructure<'a, T: ParameterValueType> {
@@ -137,6 +143,9 @@ impl<T: ParameterValueType> Debug for RootComponentClassItem<T> {
             .field("components", &DebugFn(|f: &mut Formatter| f.debug_list().entries(self.components.keys()).finish()))
             .field("links", &DebugFn(|f: &mut Formatter| f.debug_list().entries(self.links.values()).finish()))
             .field("length", &self.length)
+            .field("frame_rate", &self.frame_rate)
+            .field("image_size", &self.image_size)
+            .field("pixel_aspect_ratio", &self.pixel_aspect_ratio)
             .finish_non_exhaustive()
     }
 }
@@ -154,6 +163,9 @@ impl<T: ParameterValueType> Clone for RootComponentClassItem<T> {
             link_map,
             pin_time_map,
             length,
+            frame_rate,
+            image_size,
+            pixel_aspect_ratio,
         } = self;
         RootComponentClassItem {
             left: left.clone(),
@@ -166,17 +178,20 @@ impl<T: ParameterValueType> Clone for RootComponentClassItem<T> {
             link_map: link_map.clone(),
             pin_time_map: pin_time_map.clone(),
             length: *length,
+            frame_rate: *frame_rate,
+            image_size: *image_size,
+            pixel_aspect_ratio: *pixel_aspect_ratio,
         }
     }
 }
 
 impl<T: ParameterValueType> ComponentsLinksPair<T> for RootComponentClassItem<T> {
     fn default_image_size(&self) -> ImageSize {
-        ImageSize { width: 1920, height: 1080 }
+        self.image_size
     }
 
-    fn frames_per_second(&self) -> u32 {
-        60
+    fn frames_per_second(&self) -> FrameRate {
+        self.frame_rate
     }
 
     fn components(&self) -> impl DoubleEndedIterator<Item = &Arc<ComponentInstance<T>>> + Send + Sync + '_
@@ -227,9 +242,12 @@ impl<T: ParameterValueType> RootComponentClassItem<T> {
             link_map,
             pin_time_map,
             length,
+            frame_rate,
+            image_size,
+            pixel_aspect_ratio,
         } = self;
         (
-            RootComponentClassItemViewBase { length },
+            RootComponentClassItemViewBase { length, frame_rate, image_size, pixel_aspect_ratio },
             RootComponentClassItemViewStructure {
                 left,
                 right,
@@ -310,6 +328,24 @@ impl<T: ParameterValueType> RootComponentClassItem<T> {
     pub fn set_length(&mut self, length: MarkerTime) {
         self.length = length;
     }
+    pub fn frame_rate(&self) -> FrameRate {
+        self.frame_rate
+    }
+    pub fn set_frame_rate(&mut self, frame_rate: FrameRate) {
+        self.frame_rate = frame_rate;
+    }
+    pub fn image_size(&self) -> ImageSize {
+        self.image_size
+    }
+    pub fn set_image_size(&mut self, image_size: ImageSize) {
+        self.image_size = image_size;
+    }
+    pub fn pixel_aspect_ratio(&self) -> PixelAspectRatio {
+        self.pixel_aspect_ratio
+    }
+    pub fn set_pixel_aspect_ratio(&mut self, pixel_aspect_ratio: PixelAspectRatio) {
+        self.pixel_aspect_ratio = pixel_aspect_ratio;
+    }
 }
 
 impl RootComponentClassItemViewBase<'_> {
@@ -319,6 +355,24 @@ impl RootComponentClassItemViewBase<'_> {
     pub fn set_length(&mut self, length: MarkerTime) {
         *self.length = length;
     }
+    pub fn frame_rate(&self) -> &FrameRate {
+        self.frame_rate
+    }
+    pub fn set_frame_rate(&mut self, frame_rate: FrameRate) {
+        *self.frame_rate = frame_rate;
+    }
+    pub fn image_size(&self) -> &ImageSize {
+        self.image_size
+    }
+    pub fn set_image_size(&mut self, image_size: ImageSize) {
+        *self.image_size = image_size;
+    }
+    pub fn pixel_aspect_ratio(&self) -> &PixelAspectRatio {
+        self.pixel_aspect_ratio
+    }
+    pub fn set_pixel_aspect_ratio(&mut self, pixel_aspect_ratio: PixelAspectRatio) {
+        *self.pixel_aspect_ratio = pixel_aspect_ratio;
+    }
 }
 
 impl<T> RootComponentClassItemViewStructure<'_, T>
@@ -653,6 +707,9 @@ impl<T: ParameterValueType> RootComponentClass<T> {
                 link_map: HashTrieMap::new_sync(),
                 pin_time_map,
                 length: MarkerTime::new(MixedFraction::from_integer(10)).unwrap(),
+                frame_rate: FrameRate::default(),
+                image_size: ImageSize::default(),
+                pixel_aspect_ratio: PixelAspectRatio::default(),
             }))),
         }))
     }
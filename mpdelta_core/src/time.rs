@@ -1,9 +1,11 @@
 use crate::common::mixed_fraction::MixedFraction;
 use crate::component::marker_pin::MarkerTime;
+use num::Integer;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::ops::{Add, Div, Neg, Sub};
+use thiserror::Error;
 
 /// タイムライン上での時間(秒)
 /// (-∞, ∞)
@@ -62,3 +64,192 @@ impl Div for TimelineTime {
         self.0 / rhs.0
     }
 }
+
+/// フレームレート(フレーム/秒)
+///
+/// 29.97fps(30000/1001)のような値も正確に扱えるよう、既約分数で保持する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "(u32, u32)", into = "(u32, u32)")]
+pub struct FrameRate {
+    numerator: u32,
+    denominator: u32,
+}
+
+#[cfg(any(feature = "proptest", test))]
+const _: () = {
+    use proptest::prelude::*;
+    use std::ops::Range;
+    impl Arbitrary for FrameRate {
+        type Parameters = ();
+
+        fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+            (1u32..FrameRate::MAX_NUMERATOR + 1, 1u32..1002).prop_filter_map("invalid frame rate", |(n, d)| FrameRate::new(n, d))
+        }
+
+        type Strategy = proptest::strategy::FilterMap<(Range<u32>, Range<u32>), fn((u32, u32)) -> Option<FrameRate>>;
+    }
+};
+
+#[derive(Debug, Error)]
+#[error("invalid frame rate: {0}/{1}")]
+pub struct InvalidFrameRate(u32, u32);
+
+impl FrameRate {
+    /// フレームの時刻がMixedFractionで表せる範囲(分母が18bitに収まる範囲)に制限する
+    const MAX_NUMERATOR: u32 = (1 << 18) - 1;
+    /// 1フレームの長さが整数部に収まるよう、分子と同じ範囲に制限する
+    const MAX_DENOMINATOR: u32 = (1 << 18) - 1;
+
+    pub const FPS_24: FrameRate = FrameRate::new_inner(24, 1);
+    pub const FPS_25: FrameRate = FrameRate::new_inner(25, 1);
+    pub const FPS_29_97: FrameRate = FrameRate::new_inner(30000, 1001);
+    pub const FPS_30: FrameRate = FrameRate::new_inner(30, 1);
+    pub const FPS_59_94: FrameRate = FrameRate::new_inner(60000, 1001);
+    pub const FPS_60: FrameRate = FrameRate::new_inner(60, 1);
+
+    const fn new_inner(numerator: u32, denominator: u32) -> FrameRate {
+        FrameRate { numerator, denominator }
+    }
+
+    /// `numerator / denominator` fpsのFrameRateを作る
+    ///
+    /// どちらかが0であるか、約分後の分子か分母が大きすぎる場合はNoneを返す
+    pub fn new(numerator: u32, denominator: u32) -> Option<FrameRate> {
+        if numerator == 0 || denominator == 0 {
+            return None;
+        }
+        let gcd = numerator.gcd(&denominator);
+        let (numerator, denominator) = (numerator / gcd, denominator / gcd);
+        (numerator <= FrameRate::MAX_NUMERATOR && denominator <= FrameRate::MAX_DENOMINATOR).then_some(FrameRate { numerator, denominator })
+    }
+
+    pub fn from_integer(fps: u32) -> Option<FrameRate> {
+        FrameRate::new(fps, 1)
+    }
+
+    pub fn numerator(self) -> u32 {
+        self.numerator
+    }
+
+    pub fn denominator(self) -> u32 {
+        self.denominator
+    }
+
+    pub fn into_f64(self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    /// `frame`番目のフレームの時刻 MixedFractionで表せない時刻はその範囲に丸める
+    pub fn time_of_frame(self, frame: i64) -> TimelineTime {
+        let (integer, numerator) = (frame as i128 * self.denominator as i128).div_mod_floor(&(self.numerator as i128));
+        let (numerator, denominator) = (numerator as u32, self.numerator);
+        let gcd = numerator.gcd(&denominator);
+        let time = i32::try_from(integer).ok().and_then(|integer| MixedFraction::new_checked(integer, numerator / gcd, denominator / gcd));
+        TimelineTime::new(time.unwrap_or(if integer < 0 { MixedFraction::MIN } else { MixedFraction::MAX }))
+    }
+
+    /// `time`に最も近いフレームの番号
+    pub fn frame_round(self, time: TimelineTime) -> i64 {
+        let (n, d) = self.frames_fraction(time);
+        (2 * n + d).div_euclid(2 * d) as i64
+    }
+
+    /// `time`以前で最も遅いフレームの番号
+    pub fn frame_floor(self, time: TimelineTime) -> i64 {
+        let (n, d) = self.frames_fraction(time);
+        n.div_euclid(d) as i64
+    }
+
+    /// `time`以降で最も早いフレームの番号
+    pub fn frame_ceil(self, time: TimelineTime) -> i64 {
+        let (n, d) = self.frames_fraction(time);
+        (-(-n).div_euclid(d)) as i64
+    }
+
+    fn frames_fraction(self, time: TimelineTime) -> (i128, i128) {
+        let (i, n, d) = time.value().deconstruct();
+        let numerator = (i as i128 * d as i128 + n as i128) * self.numerator as i128;
+        let denominator = d as i128 * self.denominator as i128;
+        (numerator, denominator)
+    }
+}
+
+impl Default for FrameRate {
+    fn default() -> Self {
+        FrameRate::FPS_60
+    }
+}
+
+impl TryFrom<(u32, u32)> for FrameRate {
+    type Error = InvalidFrameRate;
+
+    fn try_from((numerator, denominator): (u32, u32)) -> Result<Self, Self::Error> {
+        FrameRate::new(numerator, denominator).ok_or(InvalidFrameRate(numerator, denominator))
+    }
+}
+
+impl From<FrameRate> for (u32, u32) {
+    fn from(value: FrameRate) -> Self {
+        (value.numerator, value.denominator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_frame_rate_new() {
+        assert_eq!(FrameRate::new(60, 1), Some(FrameRate::FPS_60));
+        assert_eq!(FrameRate::new(120, 2), Some(FrameRate::FPS_60));
+        assert_eq!(FrameRate::new(60000, 2002), Some(FrameRate::FPS_29_97));
+        assert_eq!(FrameRate::from_integer(24), Some(FrameRate::FPS_24));
+        assert_eq!(FrameRate::new(0, 1), None);
+        assert_eq!(FrameRate::new(1, 0), None);
+        assert_eq!(FrameRate::new(1 << 18, 1), None);
+        assert_eq!(FrameRate::new(1, 1 << 18), None);
+        assert_eq!(FrameRate::new(1, 4_000_000_000), None);
+        assert_eq!(FrameRate::try_from((30000, 1001)).unwrap(), FrameRate::FPS_29_97);
+        assert!(FrameRate::try_from((30, 0)).is_err());
+    }
+
+    #[test]
+    fn test_frame_rate_time_of_frame() {
+        assert_eq!(FrameRate::FPS_60.time_of_frame(0), TimelineTime::ZERO);
+        assert_eq!(FrameRate::FPS_60.time_of_frame(90), TimelineTime::new(MixedFraction::new(1, 1, 2)));
+        assert_eq!(FrameRate::FPS_25.time_of_frame(-1), TimelineTime::new(MixedFraction::new(-1, 24, 25)));
+        assert_eq!(FrameRate::FPS_29_97.time_of_frame(30), TimelineTime::new(MixedFraction::new(1, 1, 1000)));
+        assert_eq!(FrameRate::FPS_29_97.time_of_frame(30000), TimelineTime::new(MixedFraction::from_integer(1001)));
+        let slowest = FrameRate::new(1, (1 << 18) - 1).unwrap();
+        assert_eq!(slowest.time_of_frame(1), TimelineTime::new(MixedFraction::from_integer((1 << 18) - 1)));
+        assert_eq!(slowest.time_of_frame(i64::MAX), TimelineTime::new(MixedFraction::MAX));
+        assert_eq!(slowest.time_of_frame(i64::MIN), TimelineTime::new(MixedFraction::MIN));
+    }
+
+    #[test]
+    fn test_frame_rate_frame_of_time() {
+        let time = TimelineTime::new(MixedFraction::new(1, 1, 100));
+        assert_eq!(FrameRate::FPS_60.frame_round(time), 61);
+        assert_eq!(FrameRate::FPS_60.frame_floor(time), 60);
+        assert_eq!(FrameRate::FPS_60.frame_ceil(time), 61);
+        let time = TimelineTime::new(MixedFraction::from_integer(10));
+        assert_eq!(FrameRate::FPS_29_97.frame_round(time), 300);
+        assert_eq!(FrameRate::FPS_29_97.frame_floor(time), 299);
+        assert_eq!(FrameRate::FPS_29_97.frame_ceil(time), 300);
+        let time = TimelineTime::new(MixedFraction::new(-1, 1, 2));
+        assert_eq!(FrameRate::FPS_24.frame_round(time), -12);
+        assert_eq!(FrameRate::FPS_24.frame_floor(time), -12);
+        assert_eq!(FrameRate::FPS_24.frame_ceil(time), -12);
+    }
+
+    proptest! {
+        #[test]
+        fn test_frame_rate_round_trip(frame_rate: FrameRate, frame in -100_000i64..100_000) {
+            let time = frame_rate.time_of_frame(frame);
+            prop_assert_eq!(frame_rate.frame_round(time), frame);
+            prop_assert_eq!(frame_rate.frame_floor(time), frame);
+            prop_assert_eq!(frame_rate.frame_ceil(time), frame);
+        }
+    }
+}
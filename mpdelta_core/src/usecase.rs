@@ -1,16 +1,18 @@
 use crate::component::class::ComponentClass;
 use crate::component::instance::{ComponentInstance, ComponentInstanceId};
 use crate::component::marker_pin::MarkerTime;
-use crate::component::parameter::{Parameter, ParameterSelect, ParameterValueType};
+use crate::component::parameter::{ParameterType, ParameterValueRaw, ParameterValueType};
 use crate::core::EditEventListener;
 use crate::edit::{InstanceEditCommand, RootComponentEditCommand};
+use crate::encode::EncodeMonitor;
 use crate::project::{ProjectHandle, RootComponentClassHandle};
 use crate::ptr::StaticPointer;
+use crate::time::{FrameRate, TimelineTime};
 use async_trait::async_trait;
 use std::borrow::Cow;
 use std::error::Error;
 use std::future::Future;
-use std::ops::Deref;
+use std::ops::{Deref, Range};
 use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -160,10 +162,14 @@ where
 pub trait RealtimeComponentRenderer<T: ParameterValueType>: Send + Sync {
     type Err: Error + Send + 'static;
     fn get_component_length(&self) -> Option<MarkerTime>;
+    fn frame_rate(&self) -> FrameRate;
     fn render_frame(&self, frame: usize) -> Result<T::Image, Self::Err>;
     fn sampling_rate(&self) -> u32;
     fn mix_audio(&self, offset: usize, length: usize) -> impl Future<Output = Result<T::Audio, Self::Err>> + Send + '_;
-    fn render_param(&self, param: Parameter<ParameterSelect>) -> Result<Parameter<T>, Self::Err>;
+    /// `at`の時点でのコンポーネントの出力を`ty`の型で評価する
+    ///
+    /// `target`が`None`ならレンダリング対象のコンポーネント自身、`Some`ならその直下にあるコンポーネントを評価する
+    fn render_param(&self, target: Option<ComponentInstanceId>, at: TimelineTime, ty: ParameterType) -> impl Future<Output = Result<ParameterValueRaw<T::Image, T::Audio>, Self::Err>> + Send + '_;
 }
 
 #[async_trait]
@@ -179,6 +185,10 @@ where
         self.deref().get_component_length()
     }
 
+    fn frame_rate(&self) -> FrameRate {
+        self.deref().frame_rate()
+    }
+
     fn render_frame(&self, frame: usize) -> Result<T::Image, Self::Err> {
         self.deref().render_frame(frame)
     }
@@ -191,8 +201,8 @@ where
         self.deref().mix_audio(offset, length)
     }
 
-    fn render_param(&self, param: Parameter<ParameterSelect>) -> Result<Parameter<T>, Self::Err> {
-        self.deref().render_param(param)
+    fn render_param(&self, target: Option<ComponentInstanceId>, at: TimelineTime, ty: ParameterType) -> impl Future<Output = Result<ParameterValueRaw<T::Image, T::Audio>, Self::Err>> + Send + '_ {
+        self.deref().render_param(target, at, ty)
     }
 }
 
@@ -220,7 +230,8 @@ where
 
 pub trait RenderWholeComponentUsecase<T: ParameterValueType, Encoder>: Send + Sync {
     type Err: Error + Send + 'static;
-    fn render_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, encoder: Encoder) -> impl Future<Output = Result<(), Self::Err>> + Send + 'async_trait
+    /// 進捗は`monitor`へ報告する `monitor`が中断された場合は途中までの出力を破棄してエラーを返す
+    fn render_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, encoder: Encoder, monitor: EncodeMonitor) -> impl Future<Output = Result<(), Self::Err>> + Send + 'async_trait
     where
         'life0: 'async_trait;
 }
@@ -233,11 +244,59 @@ where
 {
     type Err = <O::Target as RenderWholeComponentUsecase<T, Encoder>>::Err;
 
-    fn render_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, encoder: Encoder) -> impl Future<Output = Result<(), Self::Err>> + Send + 'async_trait
+    fn render_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, encoder: Encoder, monitor: EncodeMonitor) -> impl Future<Output = Result<(), Self::Err>> + Send + 'async_trait
+    where
+        'life0: 'async_trait,
+    {
+        self.deref().render_and_encode(component, encoder, monitor)
+    }
+}
+
+pub trait RenderRangeUsecase<T: ParameterValueType, Encoder>: Send + Sync {
+    type Err: Error + Send + 'static;
+    /// `range`の範囲だけを描画し、`range.start`を先頭としてエンコードする
+    fn render_range_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, range: Range<TimelineTime>, encoder: Encoder, monitor: EncodeMonitor) -> impl Future<Output = Result<(), Self::Err>> + Send + 'async_trait
+    where
+        'life0: 'async_trait;
+}
+
+impl<T, Encoder, O> RenderRangeUsecase<T, Encoder> for O
+where
+    T: ParameterValueType,
+    O: Deref + Send + Sync,
+    O::Target: RenderRangeUsecase<T, Encoder>,
+{
+    type Err = <O::Target as RenderRangeUsecase<T, Encoder>>::Err;
+
+    fn render_range_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, range: Range<TimelineTime>, encoder: Encoder, monitor: EncodeMonitor) -> impl Future<Output = Result<(), Self::Err>> + Send + 'async_trait
+    where
+        'life0: 'async_trait,
+    {
+        self.deref().render_range_and_encode(component, range, encoder, monitor)
+    }
+}
+
+pub trait RenderFrameUsecase<T: ParameterValueType, Encoder>: Send + Sync {
+    type Err: Error + Send + 'static;
+    /// `at`の時点の1フレームだけを描画してエンコードする
+    fn render_frame_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, at: TimelineTime, encoder: Encoder) -> impl Future<Output = Result<(), Self::Err>> + Send + 'async_trait
+    where
+        'life0: 'async_trait;
+}
+
+impl<T, Encoder, O> RenderFrameUsecase<T, Encoder> for O
+where
+    T: ParameterValueType,
+    O: Deref + Send + Sync,
+    O::Target: RenderFrameUsecase<T, Encoder>,
+{
+    type Err = <O::Target as RenderFrameUsecase<T, Encoder>>::Err;
+
+    fn render_frame_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, at: TimelineTime, encoder: Encoder) -> impl Future<Output = Result<(), Self::Err>> + Send + 'async_trait
     where
         'life0: 'async_trait,
     {
-        self.deref().render_and_encode(component, encoder)
+        self.deref().render_frame_and_encode(component, at, encoder)
     }
 }
 
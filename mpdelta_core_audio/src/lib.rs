@@ -1,5 +1,6 @@
 use mpdelta_core::time::TimelineTime;
-use multi_channel_audio::MultiChannelAudioSliceMut;
+use multi_channel_audio::{MultiChannelAudioMutOp, MultiChannelAudioOp, MultiChannelAudioSliceMut};
+use std::ops::Range;
 
 pub mod multi_channel_audio;
 
@@ -52,3 +53,77 @@ impl AudioType {
         AudioType(Box::new(audio))
     }
 }
+
+/// 元の音声の`range`の範囲だけを切り出し、`range.start`が時刻0になるようにした音声
+#[derive(Clone)]
+pub struct TrimmedAudio {
+    audio: AudioType,
+    range: Range<TimelineTime>,
+}
+
+impl TrimmedAudio {
+    pub fn new(audio: AudioType, range: Range<TimelineTime>) -> TrimmedAudio {
+        TrimmedAudio { audio, range }
+    }
+}
+
+impl AudioProvider for TrimmedAudio {
+    fn sample_rate(&self) -> u32 {
+        self.audio.sample_rate()
+    }
+
+    fn channels(&self) -> usize {
+        self.audio.channels()
+    }
+
+    fn compute_audio(&mut self, begin: TimelineTime, mut dst: MultiChannelAudioSliceMut<f32>) -> usize {
+        let remaining = (self.range.end - self.range.start - begin).value().into_f64();
+        let remaining_len = (remaining * self.sample_rate() as f64).round().max(0.) as usize;
+        let len = dst.len().min(remaining_len);
+        if len == 0 {
+            return 0;
+        }
+        self.audio.compute_audio(self.range.start + begin, dst.slice_mut(..len).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multi_channel_audio::MultiChannelAudio;
+    use mpdelta_core::common::mixed_fraction::MixedFraction;
+
+    #[derive(Clone)]
+    struct Ramp;
+
+    impl AudioProvider for Ramp {
+        fn sample_rate(&self) -> u32 {
+            10
+        }
+
+        fn channels(&self) -> usize {
+            1
+        }
+
+        fn compute_audio(&mut self, begin: TimelineTime, mut dst: MultiChannelAudioSliceMut<f32>) -> usize {
+            let offset = (begin.value().into_f64() * 10.).round() as usize;
+            for (i, sample) in dst.iter_mut().enumerate() {
+                sample[0] = (offset + i) as f32;
+            }
+            dst.len()
+        }
+    }
+
+    #[test]
+    fn test_trimmed_audio() {
+        let time = |v: i32| TimelineTime::new(MixedFraction::from_integer(v));
+        let mut audio = TrimmedAudio::new(AudioType::new(Ramp), time(2)..time(5));
+        let mut buffer = MultiChannelAudio::new(1);
+        buffer.resize(20, 0.);
+        assert_eq!(audio.compute_audio(TimelineTime::ZERO, buffer.slice_mut(..).unwrap()), 20);
+        assert_eq!(buffer.as_linear(), (20..40).map(|v| v as f32).collect::<Vec<_>>());
+        assert_eq!(audio.compute_audio(time(2), buffer.slice_mut(..).unwrap()), 10);
+        assert_eq!(&buffer.as_linear()[..10], (40..50).map(|v| v as f32).collect::<Vec<_>>());
+        assert_eq!(audio.compute_audio(time(3), buffer.slice_mut(..).unwrap()), 0);
+    }
+}
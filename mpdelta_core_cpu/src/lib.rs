@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+pub use image::{Rgba, RgbaImage};
+
+/// GPUを使わずに描画するための、メモリ上のRGBA8画像
+///
+/// 色はpremultiplyしない
+#[derive(Debug, Clone)]
+pub struct ImageType(pub Arc<RgbaImage>);
+
+impl ImageType {
+    /// 1色で塗りつぶした画像
+    pub fn filled(width: u32, height: u32, color: [u8; 4]) -> ImageType {
+        ImageType(Arc::new(RgbaImage::from_pixel(width, height, Rgba(color))))
+    }
+}
+
+impl From<RgbaImage> for ImageType {
+    fn from(value: RgbaImage) -> Self {
+        ImageType(Arc::new(value))
+    }
+}
+
+impl From<Arc<RgbaImage>> for ImageType {
+    fn from(value: Arc<RgbaImage>) -> Self {
+        ImageType(value)
+    }
+}
+
+impl From<ImageType> for Arc<RgbaImage> {
+    fn from(ImageType(value): ImageType) -> Self {
+        value
+    }
+}
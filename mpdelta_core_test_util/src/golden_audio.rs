@@ -0,0 +1,303 @@
+use crate::golden_image::UPDATE_GOLDEN_ENV;
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use mpdelta_core::common::mixed_fraction::MixedFraction;
+use mpdelta_core::time::TimelineTime;
+use mpdelta_core_audio::multi_channel_audio::{MultiChannelAudio, MultiChannelAudioMutOp, MultiChannelAudioOp};
+use mpdelta_core_audio::AudioProvider;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+/// 音声比較の許容誤差
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioTolerance {
+    /// 1サンプルあたりの差の絶対値の許容値
+    pub peak: f64,
+    /// 全サンプルの差の二乗平均平方根の許容値
+    pub rms: f64,
+}
+
+impl AudioTolerance {
+    pub const EXACT: AudioTolerance = AudioTolerance { peak: 0., rms: 0. };
+}
+
+impl Default for AudioTolerance {
+    fn default() -> Self {
+        AudioTolerance { peak: 1. / 1024., rms: 1. / 8192. }
+    }
+}
+
+/// 許容値を超えて異なる最初のサンプル
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampleDivergence {
+    pub index: usize,
+    pub channel: usize,
+    pub expected: f32,
+    pub actual: f32,
+}
+
+/// チャンネル数と長さが同じ2つの音声の比較結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioComparison {
+    pub peak_error: f64,
+    pub rms_error: f64,
+    pub first_divergence: Option<SampleDivergence>,
+}
+
+impl AudioComparison {
+    pub fn is_within(&self, tolerance: &AudioTolerance) -> bool {
+        self.peak_error <= tolerance.peak && self.rms_error <= tolerance.rms
+    }
+}
+
+/// チャンネル数と長さが同じ2つの音声を比較する そうでない場合はNoneを返す
+pub fn compare_audio(expected: &MultiChannelAudio<f32>, actual: &MultiChannelAudio<f32>, tolerance: &AudioTolerance) -> Option<AudioComparison> {
+    if expected.channels() != actual.channels() || expected.len() != actual.len() {
+        return None;
+    }
+    let mut peak_error = 0f64;
+    let mut square_sum = 0f64;
+    let mut first_divergence = None;
+    for (index, (e, a)) in expected.iter().zip(actual.iter()).enumerate() {
+        for (channel, (&e, &a)) in e.iter().zip(a).enumerate() {
+            let error = (e as f64 - a as f64).abs();
+            peak_error = peak_error.max(error);
+            square_sum += error * error;
+            if first_divergence.is_none() && error > tolerance.peak {
+                first_divergence = Some(SampleDivergence { index, channel, expected: e, actual: a });
+            }
+        }
+    }
+    let samples = expected.as_linear().len();
+    let rms_error = if samples == 0 { 0. } else { (square_sum / samples as f64).sqrt() };
+    Some(AudioComparison { peak_error, rms_error, first_divergence })
+}
+
+/// `audio`の`range`の範囲を`audio`のサンプリングレートで描画する
+pub fn render_audio_range(mut audio: impl AudioProvider, range: Range<TimelineTime>) -> MultiChannelAudio<f32> {
+    let sample_rate = audio.sample_rate();
+    let (sec, smp) = (range.end - range.start).value().deconstruct_with_round(sample_rate);
+    let len = sec.max(0) as usize * sample_rate as usize + smp as usize;
+    let mut buffer = MultiChannelAudio::new(audio.channels());
+    buffer.resize(len, 0.);
+    let mut offset = 0;
+    while offset < len {
+        let begin = range.start + TimelineTime::new(MixedFraction::from_fraction(offset as i64, sample_rate));
+        let written = audio.compute_audio(begin, buffer.slice_mut(offset..).unwrap());
+        if written == 0 {
+            break;
+        }
+        offset += written;
+    }
+    buffer
+}
+
+fn read_wav(path: &Path) -> (u32, MultiChannelAudio<f32>) {
+    let mut reader = WavReader::open(path).unwrap();
+    let WavSpec { channels, sample_rate, bits_per_sample, sample_format } = reader.spec();
+    let samples = match sample_format {
+        SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<_>, _>>().unwrap(),
+        SampleFormat::Int => {
+            let scale = (1u64 << (bits_per_sample - 1)) as f32;
+            reader.samples::<i32>().map(|s| s.map(|s| s as f32 / scale)).collect::<Result<Vec<_>, _>>().unwrap()
+        }
+    };
+    let mut audio = MultiChannelAudio::new(channels as usize);
+    samples.chunks_exact(channels as usize).for_each(|sample| audio.push(sample));
+    (sample_rate, audio)
+}
+
+fn write_wav(path: &Path, sample_rate: u32, audio: &MultiChannelAudio<f32>) {
+    let spec = WavSpec {
+        channels: audio.channels() as u16,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+    let mut writer = WavWriter::create(path, spec).unwrap();
+    audio.as_linear().iter().for_each(|&s| writer.write_sample(s).unwrap());
+    writer.finalize().unwrap();
+}
+
+/// 描画結果を`reference_dir`の参照音声(`{name}.wav`)と比較する
+///
+/// 一致しなかった場合は`output_dir`に描画結果と、描画結果から参照音声を引いた差分を書き出す
+/// 環境変数`MPDELTA_UPDATE_GOLDEN`が設定されていると、比較せずに参照音声を更新する
+#[derive(Debug, Clone)]
+pub struct GoldenAudio {
+    reference_dir: PathBuf,
+    output_dir: PathBuf,
+    tolerance: AudioTolerance,
+}
+
+impl GoldenAudio {
+    pub fn new(reference_dir: impl Into<PathBuf>, output_dir: impl Into<PathBuf>) -> GoldenAudio {
+        GoldenAudio {
+            reference_dir: reference_dir.into(),
+            output_dir: output_dir.into(),
+            tolerance: AudioTolerance::default(),
+        }
+    }
+
+    pub fn with_tolerance(self, tolerance: AudioTolerance) -> GoldenAudio {
+        GoldenAudio { tolerance, ..self }
+    }
+
+    pub fn reference_path(&self, name: &str) -> PathBuf {
+        self.reference_dir.join(format!("{name}.wav"))
+    }
+
+    fn save(&self, name: &str, suffix: &str, sample_rate: u32, audio: &MultiChannelAudio<f32>) -> PathBuf {
+        fs::create_dir_all(&self.output_dir).unwrap();
+        let path = self.output_dir.join(format!("{name}.{suffix}.wav"));
+        write_wav(&path, sample_rate, audio);
+        path
+    }
+
+    /// 一致すればOk、しなければ失敗の説明をErrで返す
+    pub fn check(&self, name: &str, sample_rate: u32, actual: &MultiChannelAudio<f32>) -> Result<(), String> {
+        if env::var_os(UPDATE_GOLDEN_ENV).is_some_and(|v| !v.is_empty()) {
+            fs::create_dir_all(&self.reference_dir).unwrap();
+            write_wav(&self.reference_path(name), sample_rate, actual);
+            return Ok(());
+        }
+        self.compare(name, sample_rate, actual)
+    }
+
+    fn compare(&self, name: &str, sample_rate: u32, actual: &MultiChannelAudio<f32>) -> Result<(), String> {
+        let reference_path = self.reference_path(name);
+        if !reference_path.exists() {
+            let actual_path = self.save(name, "actual", sample_rate, actual);
+            return Err(format!("{name}: reference audio {} not found (actual: {}); run with {UPDATE_GOLDEN_ENV}=1 to create it", reference_path.display(), actual_path.display()));
+        }
+        let (expected_sample_rate, expected) = read_wav(&reference_path);
+        let comparison = (expected_sample_rate == sample_rate).then(|| compare_audio(&expected, actual, &self.tolerance)).flatten();
+        let Some(comparison) = comparison else {
+            let actual_path = self.save(name, "actual", sample_rate, actual);
+            return Err(format!(
+                "{name}: audio format mismatch, expected {} samples x {}ch @ {expected_sample_rate}Hz but got {} samples x {}ch @ {sample_rate}Hz (actual: {})",
+                expected.len(),
+                expected.channels(),
+                actual.len(),
+                actual.channels(),
+                actual_path.display(),
+            ));
+        };
+        if comparison.is_within(&self.tolerance) {
+            return Ok(());
+        }
+        let actual_path = self.save(name, "actual", sample_rate, actual);
+        let mut diff = actual.clone();
+        diff.as_linear_mut().iter_mut().zip(expected.as_linear()).for_each(|(a, e)| *a -= e);
+        let diff_path = self.save(name, "diff", sample_rate, &diff);
+        let first_divergence = match comparison.first_divergence {
+            Some(SampleDivergence { index, channel, expected, actual }) => format!("first divergent sample is #{index} ({:.6}s) ch{channel}: expected {expected} but got {actual}", index as f64 / sample_rate as f64),
+            None => "no single sample exceeds the peak tolerance".to_owned(),
+        };
+        Err(format!(
+            "{name}: peak error {:.6} (tolerance {}), rms error {:.6} (tolerance {}); {first_divergence}; actual: {}, diff: {}",
+            comparison.peak_error,
+            self.tolerance.peak,
+            comparison.rms_error,
+            self.tolerance.rms,
+            actual_path.display(),
+            diff_path.display(),
+        ))
+    }
+
+    pub fn assert_matches(&self, name: &str, sample_rate: u32, actual: &MultiChannelAudio<f32>) {
+        if let Err(message) = self.check(name, sample_rate, actual) {
+            panic!("golden audio mismatch\n{message}");
+        }
+    }
+
+    /// `audio`の`range`の範囲を描画して参照音声と比較する
+    pub fn assert_range(&self, name: &str, audio: impl AudioProvider, range: Range<TimelineTime>) {
+        let sample_rate = audio.sample_rate();
+        let actual = render_audio_range(audio, range);
+        self.assert_matches(name, sample_rate, &actual);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mpdelta_core::mfrac;
+    use mpdelta_core_audio::multi_channel_audio::MultiChannelAudioSliceMut;
+
+    #[derive(Clone)]
+    struct Ramp;
+
+    impl AudioProvider for Ramp {
+        fn sample_rate(&self) -> u32 {
+            100
+        }
+
+        fn channels(&self) -> usize {
+            2
+        }
+
+        fn compute_audio(&mut self, begin: TimelineTime, mut dst: MultiChannelAudioSliceMut<f32>) -> usize {
+            let (sec, smp) = begin.value().deconstruct_with_round(100);
+            let begin = sec as usize * 100 + smp as usize;
+            // 一度に最大10サンプルしか返さない
+            let len = dst.len().min(10);
+            dst.slice_mut(..len).unwrap().iter_mut().enumerate().for_each(|(i, s)| s.copy_from_slice(&[(begin + i) as f32, -((begin + i) as f32)]));
+            len
+        }
+    }
+
+    fn audio(samples: &[[f32; 2]]) -> MultiChannelAudio<f32> {
+        let mut audio = MultiChannelAudio::new(2);
+        samples.iter().for_each(|s| audio.push(s));
+        audio
+    }
+
+    #[test]
+    fn test_render_audio_range() {
+        let audio = render_audio_range(Ramp, TimelineTime::new(mfrac!(1, 2))..TimelineTime::new(mfrac!(3, 4)));
+        assert_eq!(audio.channels(), 2);
+        assert_eq!(audio.len(), 25);
+        assert_eq!(audio.get(0), Some(&[50., -50.][..]));
+        assert_eq!(audio.get(24), Some(&[74., -74.][..]));
+    }
+
+    #[test]
+    fn test_compare_audio() {
+        let expected = audio(&[[0., 0.], [0.5, -0.5], [1., -1.], [0.5, -0.5]]);
+        let actual = audio(&[[0., 0.], [0.5, -0.5], [1., -0.9], [0.5, -0.45]]);
+        let comparison = compare_audio(&expected, &actual, &AudioTolerance::default()).unwrap();
+        assert!((comparison.peak_error - 0.1).abs() < 1e-6);
+        assert!((comparison.rms_error - (0.0125f64 / 8.).sqrt()).abs() < 1e-6);
+        assert_eq!(comparison.first_divergence, Some(SampleDivergence { index: 2, channel: 1, expected: -1., actual: -0.9 }));
+        assert!(!comparison.is_within(&AudioTolerance::default()));
+        assert!(comparison.is_within(&AudioTolerance { peak: 0.1, rms: 0.1 }));
+        assert_eq!(compare_audio(&expected, &expected, &AudioTolerance::EXACT).unwrap().first_divergence, None);
+
+        assert!(compare_audio(&expected, &audio(&[[0., 0.]]), &AudioTolerance::default()).is_none());
+        assert!(compare_audio(&expected, &MultiChannelAudio::new(1), &AudioTolerance::default()).is_none());
+    }
+
+    #[test]
+    fn test_golden_audio() {
+        const TEST_OUTPUT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test_output/", env!("CARGO_PKG_NAME"));
+        let dir = Path::new(TEST_OUTPUT_DIR).join("golden_audio");
+        let _ = fs::remove_dir_all(&dir);
+        let golden = GoldenAudio::new(dir.join("reference"), dir.join("output"));
+
+        let expected = audio(&[[0., 0.], [0.5, -0.5], [1., -1.]]);
+        assert!(golden.compare("missing", 100, &expected).is_err());
+        assert!(dir.join("output/missing.actual.wav").exists());
+
+        fs::create_dir_all(dir.join("reference")).unwrap();
+        write_wav(&golden.reference_path("ramp"), 100, &expected);
+        assert_eq!(golden.compare("ramp", 100, &expected), Ok(()));
+        assert!(golden.compare("ramp", 200, &expected).is_err());
+
+        let actual = audio(&[[0., 0.], [0.25, -0.5], [1., -1.]]);
+        let message = golden.compare("ramp", 100, &actual).unwrap_err();
+        assert!(message.contains("#1 (0.010000s) ch0"), "{message}");
+        let (_, diff) = read_wav(&dir.join("output/ramp.diff.wav"));
+        assert_eq!(diff.get(1), Some(&[-0.25, 0.][..]));
+    }
+}
@@ -0,0 +1,270 @@
+use image::{Rgba, RgbaImage};
+use mpdelta_core::time::TimelineTime;
+use std::fmt::Write;
+use std::future::Future;
+use std::path::PathBuf;
+use std::{env, fs};
+
+/// 空でない値が設定されていると、比較せずに参照画像や参照音声を描画結果で上書きする
+pub const UPDATE_GOLDEN_ENV: &str = "MPDELTA_UPDATE_GOLDEN";
+
+// YIQ空間での色差の取りうる最大値
+const MAX_YIQ_DELTA: f64 = 35215.;
+
+/// 画像比較の許容誤差
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageTolerance {
+    /// 1ピクセルあたりの色差の許容値
+    ///
+    /// 色差は白背景に合成した色のYIQ空間での距離を、取りうる最大の差が1になるように正規化したもの
+    pub color_delta: f64,
+    /// 色差が許容値を超えたピクセルが全体に占める割合の許容値
+    pub mismatched_ratio: f64,
+}
+
+impl ImageTolerance {
+    pub const EXACT: ImageTolerance = ImageTolerance { color_delta: 0., mismatched_ratio: 0. };
+}
+
+impl Default for ImageTolerance {
+    fn default() -> Self {
+        // 1段階程度の丸め誤差は許容する
+        ImageTolerance { color_delta: 0.05, mismatched_ratio: 0. }
+    }
+}
+
+/// 2枚の同じ大きさの画像の比較結果
+#[derive(Debug, Clone)]
+pub struct ImageComparison {
+    pub mismatched_pixels: usize,
+    pub total_pixels: usize,
+    pub max_color_delta: f64,
+    /// 許容値を超えたピクセルを赤、許容値以内で異なるピクセルを黄で示し、それ以外は期待値を薄く描いた画像
+    pub diff: RgbaImage,
+}
+
+impl ImageComparison {
+    pub fn mismatched_ratio(&self) -> f64 {
+        if self.total_pixels == 0 {
+            0.
+        } else {
+            self.mismatched_pixels as f64 / self.total_pixels as f64
+        }
+    }
+
+    pub fn is_within(&self, tolerance: &ImageTolerance) -> bool {
+        self.mismatched_ratio() <= tolerance.mismatched_ratio
+    }
+}
+
+fn blend_with_white(Rgba([r, g, b, a]): Rgba<u8>) -> [f64; 3] {
+    let a = a as f64 / 255.;
+    [r, g, b].map(|c| 255. + (c as f64 - 255.) * a)
+}
+
+fn yiq([r, g, b]: [f64; 3]) -> [f64; 3] {
+    [r * 0.29889531 + g * 0.58662247 + b * 0.11448223, r * 0.59597799 - g * 0.27417610 - b * 0.32180189, r * 0.21147017 - g * 0.52261711 + b * 0.31114694]
+}
+
+/// 2色の知覚的な差を0..=1で返す
+///
+/// ref: Y. Kotsarenko, F. Ramos, "Measuring perceived color difference using YIQ NTSC transmission color space in mobile applications"
+pub fn color_delta(a: Rgba<u8>, b: Rgba<u8>) -> f64 {
+    if a == b {
+        return 0.;
+    }
+    let [y1, i1, q1] = yiq(blend_with_white(a));
+    let [y2, i2, q2] = yiq(blend_with_white(b));
+    let (y, i, q) = (y1 - y2, i1 - i2, q1 - q2);
+    ((0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q) / MAX_YIQ_DELTA).sqrt().min(1.)
+}
+
+/// 同じ大きさの2枚の画像を比較する 大きさが異なる場合はNoneを返す
+pub fn compare_images(expected: &RgbaImage, actual: &RgbaImage, tolerance: &ImageTolerance) -> Option<ImageComparison> {
+    if expected.dimensions() != actual.dimensions() {
+        return None;
+    }
+    let mut mismatched_pixels = 0;
+    let mut max_color_delta = 0f64;
+    let diff = RgbaImage::from_fn(expected.width(), expected.height(), |x, y| {
+        let e = *expected.get_pixel(x, y);
+        let delta = color_delta(e, *actual.get_pixel(x, y));
+        max_color_delta = max_color_delta.max(delta);
+        if delta > tolerance.color_delta {
+            mismatched_pixels += 1;
+            Rgba([255, 0, 0, 255])
+        } else if delta > 0. {
+            Rgba([255, 255, 0, 255])
+        } else {
+            let [y, _, _] = yiq(blend_with_white(e));
+            let gray = (255. + (y - 255.) * 0.1).round() as u8;
+            Rgba([gray, gray, gray, 255])
+        }
+    });
+    Some(ImageComparison {
+        mismatched_pixels,
+        total_pixels: expected.width() as usize * expected.height() as usize,
+        max_color_delta,
+        diff,
+    })
+}
+
+/// 描画結果を`reference_dir`の参照画像(`{name}.png`)と比較する
+///
+/// 一致しなかった場合は`output_dir`に描画結果、参照画像、差分画像を書き出す
+/// 環境変数`MPDELTA_UPDATE_GOLDEN`が設定されていると、比較せずに参照画像を更新する
+#[derive(Debug, Clone)]
+pub struct GoldenImages {
+    reference_dir: PathBuf,
+    output_dir: PathBuf,
+    tolerance: ImageTolerance,
+}
+
+impl GoldenImages {
+    pub fn new(reference_dir: impl Into<PathBuf>, output_dir: impl Into<PathBuf>) -> GoldenImages {
+        GoldenImages {
+            reference_dir: reference_dir.into(),
+            output_dir: output_dir.into(),
+            tolerance: ImageTolerance::default(),
+        }
+    }
+
+    pub fn with_tolerance(self, tolerance: ImageTolerance) -> GoldenImages {
+        GoldenImages { tolerance, ..self }
+    }
+
+    pub fn reference_path(&self, name: &str) -> PathBuf {
+        self.reference_dir.join(format!("{name}.png"))
+    }
+
+    fn save(&self, name: &str, suffix: &str, image: &RgbaImage) -> PathBuf {
+        fs::create_dir_all(&self.output_dir).unwrap();
+        let path = self.output_dir.join(format!("{name}.{suffix}.png"));
+        image.save(&path).unwrap();
+        path
+    }
+
+    /// 一致すればOk、しなければ失敗の説明をErrで返す
+    pub fn check(&self, name: &str, actual: &RgbaImage) -> Result<(), String> {
+        if env::var_os(UPDATE_GOLDEN_ENV).is_some_and(|v| !v.is_empty()) {
+            fs::create_dir_all(&self.reference_dir).unwrap();
+            actual.save(self.reference_path(name)).unwrap();
+            return Ok(());
+        }
+        self.compare(name, actual)
+    }
+
+    fn compare(&self, name: &str, actual: &RgbaImage) -> Result<(), String> {
+        let reference_path = self.reference_path(name);
+        if !reference_path.exists() {
+            let actual_path = self.save(name, "actual", actual);
+            return Err(format!("{name}: reference image {} not found (actual: {}); run with {UPDATE_GOLDEN_ENV}=1 to create it", reference_path.display(), actual_path.display()));
+        }
+        let expected = image::open(&reference_path).unwrap().into_rgba8();
+        let Some(comparison) = compare_images(&expected, actual, &self.tolerance) else {
+            let actual_path = self.save(name, "actual", actual);
+            return Err(format!("{name}: image size mismatch, expected {:?} but got {:?} (actual: {})", expected.dimensions(), actual.dimensions(), actual_path.display()));
+        };
+        if comparison.is_within(&self.tolerance) {
+            return Ok(());
+        }
+        let actual_path = self.save(name, "actual", actual);
+        self.save(name, "expected", &expected);
+        let diff_path = self.save(name, "diff", &comparison.diff);
+        Err(format!(
+            "{name}: {} of {} pixels ({:.3}%) differ by more than {} (max color delta {:.4}); actual: {}, diff: {}",
+            comparison.mismatched_pixels,
+            comparison.total_pixels,
+            comparison.mismatched_ratio() * 100.,
+            self.tolerance.color_delta,
+            comparison.max_color_delta,
+            actual_path.display(),
+            diff_path.display(),
+        ))
+    }
+
+    pub fn assert_matches(&self, name: &str, actual: &RgbaImage) {
+        if let Err(message) = self.check(name, actual) {
+            panic!("golden image mismatch\n{message}");
+        }
+    }
+
+    /// `times`の各時刻で`render`した結果を`{name}_{index}`の参照画像と比較する
+    ///
+    /// すべての時刻を比較してから、一致しなかったものをまとめて報告する
+    pub async fn assert_frames<F, Fut>(&self, name: &str, times: impl IntoIterator<Item = TimelineTime>, mut render: F)
+    where
+        F: FnMut(TimelineTime) -> Fut,
+        Fut: Future<Output = RgbaImage>,
+    {
+        let mut failures = String::new();
+        for (i, time) in times.into_iter().enumerate() {
+            let actual = render(time).await;
+            if let Err(message) = self.check(&format!("{name}_{i}"), &actual) {
+                writeln!(failures, "at {}: {message}", time.value()).unwrap();
+            }
+        }
+        if !failures.is_empty() {
+            panic!("golden image mismatch\n{failures}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_color_delta() {
+        assert_eq!(color_delta(Rgba([0, 0, 0, 255]), Rgba([0, 0, 0, 255])), 0.);
+        assert!(color_delta(Rgba([0, 0, 0, 255]), Rgba([255, 255, 255, 255])) > 0.9);
+        assert!(color_delta(Rgba([128, 128, 128, 255]), Rgba([129, 128, 128, 255])) < ImageTolerance::default().color_delta);
+        // 完全に透明な色は色成分によらず同じ
+        assert_eq!(color_delta(Rgba([255, 0, 0, 0]), Rgba([0, 0, 255, 0])), 0.);
+        assert!(color_delta(Rgba([255, 0, 0, 255]), Rgba([0, 255, 0, 255])) > 0.5);
+    }
+
+    #[test]
+    fn test_compare_images() {
+        let expected = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 255]));
+        let mut actual = expected.clone();
+        actual.put_pixel(1, 1, Rgba([1, 0, 0, 255]));
+        actual.put_pixel(2, 3, Rgba([255, 255, 255, 255]));
+        let comparison = compare_images(&expected, &actual, &ImageTolerance::default()).unwrap();
+        assert_eq!(comparison.mismatched_pixels, 1);
+        assert_eq!(comparison.total_pixels, 16);
+        assert_eq!(comparison.diff.get_pixel(2, 3), &Rgba([255, 0, 0, 255]));
+        assert_eq!(comparison.diff.get_pixel(1, 1), &Rgba([255, 255, 0, 255]));
+        assert_eq!(comparison.diff.get_pixel(0, 0), &Rgba([230, 230, 230, 255]));
+        assert!(!comparison.is_within(&ImageTolerance::default()));
+        assert!(comparison.is_within(&ImageTolerance { color_delta: 0.05, mismatched_ratio: 1. / 16. }));
+        assert!(!compare_images(&expected, &actual, &ImageTolerance::EXACT).unwrap().is_within(&ImageTolerance::EXACT));
+
+        assert!(compare_images(&expected, &RgbaImage::new(4, 3), &ImageTolerance::default()).is_none());
+    }
+
+    #[test]
+    fn test_golden_images() {
+        const TEST_OUTPUT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test_output/", env!("CARGO_PKG_NAME"));
+        let dir = Path::new(TEST_OUTPUT_DIR).join("golden_images");
+        let _ = fs::remove_dir_all(&dir);
+        let golden = GoldenImages::new(dir.join("reference"), dir.join("output"));
+
+        let image = RgbaImage::from_pixel(4, 4, Rgba([255, 0, 0, 255]));
+        assert!(golden.compare("missing", &image).is_err());
+        assert!(dir.join("output/missing.actual.png").exists());
+
+        fs::create_dir_all(dir.join("reference")).unwrap();
+        image.save(golden.reference_path("red")).unwrap();
+        assert_eq!(golden.compare("red", &image), Ok(()));
+
+        let mut actual = image.clone();
+        actual.put_pixel(0, 0, Rgba([0, 0, 255, 255]));
+        assert!(golden.compare("red", &actual).is_err());
+        let diff = image::open(dir.join("output/red.diff.png")).unwrap().into_rgba8();
+        assert_eq!(diff.get_pixel(0, 0), &Rgba([255, 0, 0, 255]));
+        assert!(dir.join("output/red.expected.png").exists());
+        assert_eq!(golden.with_tolerance(ImageTolerance { color_delta: 0.05, mismatched_ratio: 0.1 }).compare("red", &actual), Ok(()));
+    }
+}
@@ -16,6 +16,9 @@ use std::sync::{atomic, Arc};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
+pub mod golden_audio;
+pub mod golden_image;
+
 #[derive(Default)]
 pub struct TestIdGenerator(AtomicUsize);
 
@@ -167,6 +170,9 @@ where
         let b = b.get();
         (a.left().locked_component_time() == b.left().locked_component_time()).then_some(())?;
         (a.right().locked_component_time() == b.right().locked_component_time()).then_some(())?;
+        (a.frame_rate() == b.frame_rate()).then_some(())?;
+        (a.image_size() == b.image_size()).then_some(())?;
+        (a.pixel_aspect_ratio() == b.pixel_aspect_ratio()).then_some(())?;
         (a.time_of_pin(a.left().id())? == b.time_of_pin(b.left().id())?).then_some(())?;
         (a.time_of_pin(a.right().id())? == b.time_of_pin(b.right().id())?).then_some(())?;
         (a.iter_components().count() == b.iter_components().count()).then_some(())?;
@@ -215,6 +221,7 @@ macro_rules! root_component_class {
         components: [$({
             markers: [$($pin:expr$( => $pin_name:ident)?),*$(,)?]
             $(, processor: $processor:expr)?
+            $(, image_required_params: $image_required_params:expr)?
             $(, fixed_params: [$($fixed_param_name:literal : $fixed_param_type:expr => $fixed_param:expr),*$(,)?])?
             $(, variable_params: [$($variable_param_name:literal : $variable_param_type:expr => $variable_param:expr),*$(,)?])?
             $(,)?
@@ -231,6 +238,7 @@ macro_rules! root_component_class {
             components: [$({
                 markers: [$($pin$( => $pin_name)?),*]
                 $(, processor: $processor)?
+                $(, image_required_params: $image_required_params)?
                 $(, fixed_params: [$($fixed_param_name : $fixed_param_type => $fixed_param),*])?
                 $(, variable_params: [$($variable_param_name : $variable_param_type => $variable_param),*])?
             }$(;$component_name)?),*],
@@ -245,6 +253,7 @@ macro_rules! root_component_class {
         components: [$({
             markers: [$($pin:expr$( => $pin_name:ident)?),*$(,)?]
             $(, processor: $processor:expr)?
+            $(, image_required_params: $image_required_params:expr)?
             $(, fixed_params: [$($fixed_param_name:literal : $fixed_param_type:expr => $fixed_param:expr),*$(,)?])?
             $(, variable_params: [$($variable_param_name:literal : $variable_param_type:expr => $variable_param:expr),*$(,)?])?
             $(,)?
@@ -261,6 +270,7 @@ macro_rules! root_component_class {
             components: [$({
                 markers: [$($pin$( => $pin_name)?),*]
                 $(, processor: $processor)?
+                $(, image_required_params: $image_required_params)?
                 $(, fixed_params: [$($fixed_param_name : $fixed_param_type => $fixed_param),*])?
                 $(, variable_params: [$($variable_param_name : $variable_param_type => $variable_param),*])?
             }$(;$component_name)?),*],
@@ -277,6 +287,7 @@ macro_rules! root_component_class {
         components: [$({
             markers: [$($pin:expr$( => $pin_name:ident)?),*$(,)?]
             $(, processor: $processor:expr)?
+            $(, image_required_params: $image_required_params:expr)?
             $(, fixed_params: [$($fixed_param_name:literal : $fixed_param_type:expr => $fixed_param:expr),*$(,)?])?
             $(, variable_params: [$($variable_param_name:literal : $variable_param_type:expr => $variable_param:expr),*$(,)?])?
         }$(;$component_name:ident)?),*$(,)?],
@@ -317,6 +328,7 @@ macro_rules! root_component_class {
             let marker_left = markers.remove(0);
             let marker_right = markers.pop().unwrap();
             let image_required_params = ::mpdelta_core::component::parameter::ImageRequiredParams::new_default(marker_left.id(), marker_right.id());
+            $(let image_required_params = $image_required_params;)?
             let audio_required_params = ::mpdelta_core::component::parameter::AudioRequiredParams::new_default(marker_left.id(), marker_right.id(), 2);
             let [.., processor] = [::std::sync::Arc::new($crate::NoopProcessor) as ::std::sync::Arc<dyn ::mpdelta_core::component::processor::ComponentProcessorNativeDyn<$t>>, $($processor)?];
             let builder = ::mpdelta_core::component::instance::ComponentInstance::builder(
@@ -1,7 +1,7 @@
 use crate::preview::viewmodel::PreviewViewModel;
 use crate::ImageRegister;
 use egui::load::SizedTexture;
-use egui::{Color32, CornerRadius, Rect, Slider, Stroke, StrokeKind, TextureId, Ui, UiBuilder, Vec2};
+use egui::{Align2, Color32, CornerRadius, FontId, Rect, Slider, Stroke, StrokeKind, TextureId, Ui, UiBuilder, Vec2};
 use mpdelta_core::common::mixed_fraction::MixedFraction;
 use mpdelta_core::component::marker_pin::MarkerTime;
 use mpdelta_core::component::parameter::ParameterValueType;
@@ -47,16 +47,21 @@ impl<T: ParameterValueType, VM: PreviewViewModel<T>> Preview<T, VM> {
         if self.previous_instance.is_some() {
             let Vec2 { x: area_width, y: area_height } = ui.available_size();
             let area_height = area_height - 72.;
-            let (image_width, image_height) = (area_width.min(area_height * 16. / 9.), area_height.min(area_width * 9. / 16.) + 66.);
+            let aspect_ratio = self.view_model.display_aspect_ratio();
+            let (image_width, image_height) = (area_width.min(area_height * aspect_ratio), area_height.min(area_width / aspect_ratio) + 66.);
             let base_pos = ui.cursor().min + Vec2::new(0., 72.);
             ui.allocate_new_ui(
                 UiBuilder::new().max_rect(Rect::from_min_size(base_pos + Vec2::new((area_width - image_width) / 2., (area_height - image_height) / 2.), Vec2::new(image_width, image_height))),
                 |ui| {
                     let image_size = Vec2 { x: image_width, y: image_height - 66. };
                     ui.painter().rect(Rect::from_min_size(ui.cursor().min, image_size), CornerRadius::ZERO, Color32::BLACK, Stroke::default(), StrokeKind::Inside);
+                    let image_rect = Rect::from_min_size(ui.cursor().min, image_size);
                     if let Some(texture_id) = self.previous_preview {
                         ui.image(SizedTexture::new(texture_id, image_size));
                     }
+                    if let Some(value) = self.view_model.selected_instance_value() {
+                        ui.painter().text(image_rect.left_top() + Vec2::splat(4.), Align2::LEFT_TOP, value.as_str(), FontId::monospace(12.), Color32::WHITE);
+                    }
                     ui.horizontal(|ui| {
                         let start = ui.cursor().min.x;
                         if self.view_model.playing() {
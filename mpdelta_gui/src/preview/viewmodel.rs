@@ -2,12 +2,13 @@ use crate::global_ui_state::{GlobalUIEvent, GlobalUIEventHandler, GlobalUIState}
 use crate::viewmodel::ViewModelParams;
 use crate::AudioTypePlayer;
 use arc_swap::ArcSwapOption;
+use crossbeam_utils::atomic::AtomicCell;
 use mpdelta_async_runtime::{AsyncRuntime, JoinHandleWrapper};
 use mpdelta_core::common::mixed_fraction::MixedFraction;
 use mpdelta_core::component::class::ComponentClass;
 use mpdelta_core::component::instance::ComponentInstanceId;
 use mpdelta_core::component::marker_pin::MarkerTime;
-use mpdelta_core::component::parameter::ParameterValueType;
+use mpdelta_core::component::parameter::{Parameter, ParameterType, ParameterValueType};
 use mpdelta_core::core::{EditEventListener, IdGenerator};
 use mpdelta_core::edit::{InstanceEditEvent, RootComponentEditEvent};
 use mpdelta_core::project::RootComponentClassHandle;
@@ -31,18 +32,23 @@ impl<Handle, Image> Default for PreviewImage<Handle, Image> {
 pub trait PreviewViewModel<T: ParameterValueType> {
     type ComponentInstanceHandle: 'static + Send + Sync + Eq + Hash;
     fn get_preview_image(&self) -> PreviewImage<Self::ComponentInstanceHandle, T::Image>;
+    /// プレビュー画像を表示するときの幅/高さ
+    fn display_aspect_ratio(&self) -> f32;
     fn playing(&self) -> bool;
     fn play(&self);
     fn pause(&self);
     fn component_length(&self) -> Option<MarkerTime>;
     fn seek(&self) -> MarkerTime;
     fn set_seek(&self, seek: MarkerTime);
+    /// 選択中のコンポーネントがシーク位置で出力する値 画像や音声を出力しないコンポーネントの確認に使う
+    fn selected_instance_value(&self) -> Option<Arc<String>>;
 }
 
 struct RealTimeRendererHandle<R, T: ParameterValueType> {
     renderer: R,
     render_target_instance: ComponentInstanceId,
     render_target_component_class: RootComponentClassHandle<T>,
+    display_aspect_ratio: f32,
 }
 
 pub struct PreviewViewModelImpl<T: ParameterValueType, Id, GlobalUIState, RealtimeRenderComponent, R, AudioPlayer, G, Runtime, JoinHandle> {
@@ -52,6 +58,9 @@ pub struct PreviewViewModelImpl<T: ParameterValueType, Id, GlobalUIState, Realti
     audio_player: Arc<AudioPlayer>,
     global_ui_state: Arc<GlobalUIState>,
     create_renderer: Mutex<JoinHandleWrapper<JoinHandle>>,
+    selected_instance: AtomicCell<Option<ComponentInstanceId>>,
+    selected_instance_value: Arc<ArcSwapOption<String>>,
+    probe_selected_instance: Mutex<JoinHandleWrapper<JoinHandle>>,
     handle: Runtime,
     guard: OnceLock<G>,
 }
@@ -73,6 +82,10 @@ where
                 self.create_real_time_renderer(root_component_class);
             }
             GlobalUIEvent::SelectRootComponentClass(None) => self.real_time_renderer.store(None),
+            GlobalUIEvent::SelectComponentInstance(target) => {
+                self.selected_instance.store(target);
+                self.selected_instance_value.store(None);
+            }
             _ => {}
         }
     }
@@ -134,6 +147,9 @@ impl<T: ParameterValueType> PreviewViewModelImpl<T, (), (), (), (), (), (), (),
             audio_player: Arc::clone(params.audio_player()),
             global_ui_state: Arc::clone(global_ui_state),
             create_renderer: Mutex::new(handle.spawn(future::ready(()))),
+            selected_instance: AtomicCell::new(None),
+            selected_instance_value: Arc::new(ArcSwapOption::empty()),
+            probe_selected_instance: Mutex::new(handle.spawn(future::ready(()))),
             handle,
             guard: OnceLock::new(),
         });
@@ -174,6 +190,10 @@ where
                 break 'renderer None;
             };
             let class = class.read().await;
+            let display_aspect_ratio = {
+                let item = class.get();
+                item.pixel_aspect_ratio().display_aspect_ratio(item.image_size()) as f32
+            };
             let instance = class.instantiate(&root_component_class.clone().map(|weak| weak as _), &id).await;
             let instance_id = *instance.id();
             match renderer.render_component(Arc::new(instance)).await {
@@ -191,6 +211,7 @@ where
                         renderer,
                         render_target_instance: instance_id,
                         render_target_component_class: root_component_class.clone(),
+                        display_aspect_ratio,
                     }))
                 }
                 Err(err) => {
@@ -221,9 +242,7 @@ where
                     self.global_ui_state.set_component_length(len);
                 }
             }
-            let seek = self.seek();
-            let (i, n) = seek.value().deconstruct_with_round(60);
-            let seek = i as usize * 60 + n as usize;
+            let seek = renderer.frame_rate().frame_round(self.seek().into()).max(0) as usize;
             PreviewImage {
                 instance: Some(*render_target_instance),
                 image: renderer.render_frame(seek).ok(),
@@ -231,6 +250,10 @@ where
         })
     }
 
+    fn display_aspect_ratio(&self) -> f32 {
+        self.real_time_renderer.load().as_deref().map_or(16. / 9., |RealTimeRendererHandle { display_aspect_ratio, .. }| *display_aspect_ratio)
+    }
+
     fn playing(&self) -> bool {
         self.global_ui_state.playing()
     }
@@ -255,4 +278,33 @@ where
         let seek = seek.min(self.global_ui_state.component_length().unwrap_or_else(|| MarkerTime::new(MixedFraction::from_integer(10)).unwrap()));
         self.global_ui_state.set_seek(seek);
     }
+
+    fn selected_instance_value(&self) -> Option<Arc<String>> {
+        let target = self.selected_instance.load()?;
+        let mut probe = self.probe_selected_instance.lock().unwrap();
+        if probe.is_finished() {
+            if let Some(real_time_renderer) = self.real_time_renderer.load_full() {
+                let at = TimelineTime::new(self.seek().value());
+                let selected_instance_value = Arc::clone(&self.selected_instance_value);
+                *probe = self.handle.spawn(async move {
+                    selected_instance_value.store(probe_value(&real_time_renderer.renderer, target, at).await.map(Arc::new));
+                });
+            }
+        }
+        self.selected_instance_value.load_full()
+    }
+}
+
+/// 画像と音声以外の型を順に試し、最初に評価できた値を文字列にする
+async fn probe_value<T: ParameterValueType, R: RealtimeComponentRenderer<T>>(renderer: &R, target: ComponentInstanceId, at: TimelineTime) -> Option<String> {
+    for ty in [ParameterType::String(()), ParameterType::Integer(()), ParameterType::RealNumber(()), ParameterType::Boolean(())] {
+        match renderer.render_param(Some(target), at, ty).await {
+            Ok(Parameter::String(value)) => return Some(format!("String: {value:?}")),
+            Ok(Parameter::Integer(value)) => return Some(format!("Integer: {value}")),
+            Ok(Parameter::RealNumber(value)) => return Some(format!("RealNumber: {value}")),
+            Ok(Parameter::Boolean(value)) => return Some(format!("Boolean: {value}")),
+            _ => {}
+        }
+    }
+    None
 }
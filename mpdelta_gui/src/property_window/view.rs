@@ -4,7 +4,7 @@ use crate::property_window::viewmodel::{ParametersEditSet, PropertyWindowViewMod
 use cgmath::Vector3;
 use egui::scroll_area::ScrollBarVisibility;
 use egui::style::ScrollStyle;
-use egui::{ScrollArea, Sense, Ui, UiBuilder, Vec2};
+use egui::{DragValue, ScrollArea, Sense, Ui, UiBuilder, Vec2};
 use mpdelta_core::component::parameter::value::SingleValueEdit;
 use mpdelta_core::component::parameter::{ImageRequiredParamsTransform, Parameter, ParameterValueFixed, ParameterValueType, VariableParameterValue};
 use std::marker::PhantomData;
@@ -73,6 +73,7 @@ impl<T: ParameterValueType, VM: PropertyWindowViewModel<T>> PropertyWindow<T, VM
                                         times: pin_times.as_ref(),
                                         value: translate_x,
                                         value_range: -3.0..3.0,
+                                        available_easing: self.view_model.available_easing(),
                                         point_per_second,
                                         scroll_offset: &mut self.scroll_offset,
                                     }
@@ -87,6 +88,7 @@ impl<T: ParameterValueType, VM: PropertyWindowViewModel<T>> PropertyWindow<T, VM
                                         times: pin_times.as_ref(),
                                         value: translate_y,
                                         value_range: -3.0..3.0,
+                                        available_easing: self.view_model.available_easing(),
                                         point_per_second,
                                         scroll_offset: &mut self.scroll_offset,
                                     }
@@ -101,6 +103,7 @@ impl<T: ParameterValueType, VM: PropertyWindowViewModel<T>> PropertyWindow<T, VM
                                         times: pin_times.as_ref(),
                                         value: size_x,
                                         value_range: 0.0..2.0,
+                                        available_easing: self.view_model.available_easing(),
                                         point_per_second,
                                         scroll_offset: &mut self.scroll_offset,
                                     }
@@ -115,6 +118,7 @@ impl<T: ParameterValueType, VM: PropertyWindowViewModel<T>> PropertyWindow<T, VM
                                         times: pin_times.as_ref(),
                                         value: size_y,
                                         value_range: 0.0..2.0,
+                                        available_easing: self.view_model.available_easing(),
                                         point_per_second,
                                         scroll_offset: &mut self.scroll_offset,
                                     }
@@ -129,6 +133,7 @@ impl<T: ParameterValueType, VM: PropertyWindowViewModel<T>> PropertyWindow<T, VM
                                         times: pin_times.as_ref(),
                                         value: scale_x,
                                         value_range: 0.0..2.0,
+                                        available_easing: self.view_model.available_easing(),
                                         point_per_second,
                                         scroll_offset: &mut self.scroll_offset,
                                     }
@@ -143,6 +148,7 @@ impl<T: ParameterValueType, VM: PropertyWindowViewModel<T>> PropertyWindow<T, VM
                                         times: pin_times.as_ref(),
                                         value: scale_y,
                                         value_range: 0.0..2.0,
+                                        available_easing: self.view_model.available_easing(),
                                         point_per_second,
                                         scroll_offset: &mut self.scroll_offset,
                                     }
@@ -194,8 +200,28 @@ impl<T: ParameterValueType, VM: PropertyWindowViewModel<T>> PropertyWindow<T, VM
                                             continue;
                                         }
                                     }
-                                    ParameterValueFixed::Integer(_value) => {}
-                                    ParameterValueFixed::RealNumber(_value) => {}
+                                    ParameterValueFixed::Integer(value) => {
+                                        let edit_as_integer = value.edit_value(|v: &mut i64| {
+                                            let before = *v;
+                                            ui.add(DragValue::new(v));
+                                            *v != before
+                                        });
+                                        if let Ok(edit) = edit_as_integer {
+                                            edited |= edit;
+                                            continue;
+                                        }
+                                    }
+                                    ParameterValueFixed::RealNumber(value) => {
+                                        let edit_as_real_number = value.edit_value(|v: &mut f64| {
+                                            let before = *v;
+                                            ui.add(DragValue::new(v).speed(0.1));
+                                            *v != before
+                                        });
+                                        if let Ok(edit) = edit_as_real_number {
+                                            edited |= edit;
+                                            continue;
+                                        }
+                                    }
                                     ParameterValueFixed::Boolean(_value) => {}
                                     ParameterValueFixed::Dictionary(_value) => {}
                                     ParameterValueFixed::Array(_value) => {}
@@ -263,7 +289,7 @@ mod tests {
     use egui::Visuals;
     use egui_image_renderer::FileFormat;
     use mpdelta_core::component::marker_pin::{MarkerPin, MarkerPinId, MarkerTime};
-    use mpdelta_core::component::parameter::value::{DynEditableEasingValue, DynEditableSelfValue, DynEditableSingleValue, EasingValue, LinearEasing};
+    use mpdelta_core::component::parameter::value::{DynEditableEasingValue, DynEditableSelfValue, DynEditableSingleValue, Easing, EasingValue, LinearEasing};
     use mpdelta_core::component::parameter::{ImageRequiredParams, ParameterNullableValue, ParameterValueFixed, ParameterValueType, VariableParameterPriority, VariableParameterValue};
     use mpdelta_core::core::IdGenerator;
     use mpdelta_core::time::TimelineTime;
@@ -306,6 +332,10 @@ mod tests {
                 0.0..1.0
             }
 
+            fn available_easing(&self) -> &[Arc<dyn Easing>] {
+                &[]
+            }
+
             type TimeMap = HashMap<MarkerPinId, TimelineTime>;
 
             fn parameters<R>(&self, f: impl FnOnce(Option<&mut ParametersEditSet<T, Self::TimeMap>>) -> R) -> R {
@@ -1,9 +1,9 @@
 use egui::epaint::{PathShape, PathStroke, RectShape};
 use egui::scroll_area::ScrollBarVisibility;
-use egui::{Color32, CursorIcon, Id, PointerButton, Pos2, Rect, ScrollArea, Sense, Shape, StrokeKind, Ui, UiBuilder, Vec2};
+use egui::{Color32, CursorIcon, DragValue, Id, PointerButton, Pos2, Rect, ScrollArea, Sense, Shape, StrokeKind, Ui, UiBuilder, Vec2};
 use emath::GuiRounding;
 use mpdelta_core::component::marker_pin::MarkerPin;
-use mpdelta_core::component::parameter::value::{EasingValue, EasingValueEdit};
+use mpdelta_core::component::parameter::value::{Easing, EasingValue, EasingValueEdit};
 use mpdelta_core::component::parameter::PinSplitValue;
 use mpdelta_core::project::TimelineTimeOfPin;
 use std::hash::Hash;
@@ -69,6 +69,7 @@ pub struct EasingValueEditorF64<'a, P, H> {
     pub times: &'a P,
     pub value: &'a mut PinSplitValue<Option<EasingValue<f64>>>,
     pub value_range: Range<f64>,
+    pub available_easing: &'a [Arc<dyn Easing>],
     pub point_per_second: f64,
     pub scroll_offset: &'a mut f32,
 }
@@ -100,6 +101,7 @@ where
             times,
             ref mut value,
             ref value_range,
+            available_easing,
             point_per_second,
             ref mut scroll_offset,
         } = self;
@@ -199,6 +201,56 @@ where
                     }
                 }
             }
+            for i in 0..value.len_value() {
+                let (left, _, right) = value.get_value(i).unwrap();
+                let left_time_position = glam::Vec2::new(times.time_of_pin(left).unwrap().value().into_f64() as f32, 1.).dot(time_map);
+                let right_time_position = glam::Vec2::new(times.time_of_pin(right).unwrap().value().into_f64() as f32, 1.).dot(time_map);
+                // 区間の右クリックでイージングを選択する
+                let response = ui.interact(Rect::from_x_y_ranges(left_time_position + slider_width * 2.0..=right_time_position - slider_width * 2., plot_area_rect.y_range()), id.with(("segment", i)), Sense::click());
+                response.context_menu(|ui| {
+                    let Some(easing_value) = value.get_value_mut(i).unwrap() else {
+                        ui.close_menu();
+                        return;
+                    };
+                    let current = easing_value.easing.identifier().into_static();
+                    ScrollArea::vertical().max_height(240.).show(ui, |ui| {
+                        for easing in available_easing {
+                            let identifier = easing.identifier();
+                            if ui.selectable_label(identifier == current, identifier.name.as_ref()).clicked() {
+                                easing_value.easing = Arc::clone(easing);
+                                updated = UpdateStatus::Updated;
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    // ベジェの制御点やばねの強さなど、イージングのパラメータを編集する
+                    let (names, mut values): (Vec<_>, Vec<_>) = easing_value.easing.numeric_parameters().into_iter().unzip();
+                    if names.is_empty() {
+                        return;
+                    }
+                    ui.separator();
+                    let mut edited = None;
+                    for (name, value) in names.into_iter().zip(&mut values) {
+                        ui.horizontal(|ui| {
+                            ui.label(name);
+                            let response = ui.add(DragValue::new(value).speed((value.abs() * 0.01).max(0.01)));
+                            if response.dragged() && !response.drag_stopped() {
+                                if response.changed() {
+                                    edited = Some(UpdateStatus::TemporaryUpdated);
+                                }
+                            } else if response.changed() || response.drag_stopped() {
+                                edited = Some(UpdateStatus::Updated);
+                            }
+                        });
+                    }
+                    if let Some(status) = edited {
+                        if let Some(easing) = easing_value.easing.with_numeric_parameters(&values) {
+                            easing_value.easing = easing;
+                            updated = status;
+                        }
+                    }
+                });
+            }
 
             let mut pins = all_pins;
             let background_pin = (0..value.len_value())
@@ -477,7 +529,8 @@ mod tests {
     use egui::Visuals;
     use egui_image_renderer::FileFormat;
     use mpdelta_core::common::mixed_fraction::MixedFraction;
-    use mpdelta_core::component::parameter::value::{DynEditableEasingValueManager, DynEditableEasingValueMarker, Easing, EasingIdentifier, EasingInput, NamedAny};
+    use mpdelta_core::component::parameter::value::easing::{standard_easings, EasingCurve, EasingDirection, StandardEasing};
+    use mpdelta_core::component::parameter::value::{DynEditableEasingValueManager, DynEditableEasingValueMarker, NamedAny};
     use mpdelta_core::core::IdGenerator;
     use mpdelta_core::time::TimelineTime;
     use mpdelta_core::time_split_value_persistent;
@@ -514,26 +567,7 @@ mod tests {
             }
         }
 
-        struct Easing1;
-        impl Easing for Easing1 {
-            fn identifier(&self) -> EasingIdentifier {
-                todo!()
-            }
-            fn easing(&self, from: EasingInput) -> f64 {
-                let x = 1. - from.value();
-                1. - x * x
-            }
-        }
-        struct Easing2;
-        impl Easing for Easing2 {
-            fn identifier(&self) -> EasingIdentifier {
-                todo!()
-            }
-            fn easing(&self, from: EasingInput) -> f64 {
-                let x = from.value();
-                1. - (x * std::f64::consts::PI / 2.).cos()
-            }
-        }
+        let available_easing = standard_easings().collect::<Vec<_>>();
         let id = TestIdGenerator::new();
         macro_rules! create_editor {
             ($editor:ident) => {
@@ -546,11 +580,11 @@ mod tests {
                 ];
                 let mut value = time_split_value_persistent!(
                     *all_pins[0].id(),
-                    Some(EasingValue::new(LinearEasingF64 { start: 0., end: 1. }, Arc::new(Easing1))),
+                    Some(EasingValue::new(LinearEasingF64 { start: 0., end: 1. }, Arc::new(StandardEasing::new(EasingCurve::Quad, EasingDirection::Out)))),
                     *all_pins[1].id(),
                     None,
                     *all_pins[2].id(),
-                    Some(EasingValue::new(LinearEasingF64 { start: 1., end: 0.5 }, Arc::new(Easing2))),
+                    Some(EasingValue::new(LinearEasingF64 { start: 1., end: 0.5 }, Arc::new(StandardEasing::new(EasingCurve::Sine, EasingDirection::In)))),
                     *all_pins[4].id(),
                 );
                 let mut scroll_offset = 0.;
@@ -562,6 +596,7 @@ mod tests {
                     times: &HashMap::from_iter(all_pins.iter().enumerate().map(|(i, p)| (*p.id(), TimelineTime::new(MixedFraction::from_integer(i as i32))))),
                     value: &mut value,
                     value_range: -0.5..1.5,
+                    available_easing: &available_easing,
                     point_per_second: 150.,
                     scroll_offset: &mut scroll_offset,
                 };
@@ -6,6 +6,7 @@ use arc_swap::{ArcSwap, ArcSwapOption};
 use mpdelta_async_runtime::AsyncRuntime;
 use mpdelta_core::component::instance::ComponentInstanceId;
 use mpdelta_core::component::marker_pin::MarkerPin;
+use mpdelta_core::component::parameter::value::Easing;
 use mpdelta_core::component::parameter::{ImageRequiredParams, ParameterNullableValue, ParameterValueFixed, ParameterValueType, VariableParameterValue};
 use mpdelta_core::core::EditEventListener;
 use mpdelta_core::edit::{InstanceEditCommand, InstanceEditEvent, RootComponentEditEvent};
@@ -43,6 +44,7 @@ pub struct ParametersEditSet<T: ParameterValueType, P> {
 pub trait PropertyWindowViewModel<T: ParameterValueType> {
     fn is_updated_now(&self) -> bool;
     fn selected_instance_at(&self) -> Range<f64>;
+    fn available_easing(&self) -> &[Arc<dyn Easing>];
     type TimeMap: TimelineTimeOfPin;
     fn parameters<R>(&self, f: impl FnOnce(Option<&mut ParametersEditSet<T, Self::TimeMap>>) -> R) -> R;
     fn updated_image_required_params(&self, image_required_params: &ImageRequiredParams);
@@ -97,6 +99,7 @@ pub struct PropertyWindowViewModelImpl<T: ParameterValueType, MessageHandler, Ru
     selected: Arc<StdRwLock<SelectedItem<T>>>,
     selected_instance_at: Arc<ArcSwap<Range<f64>>>,
     parameters: Arc<ArcSwapOption<Mutex<EditSet<T>>>>,
+    available_easing: Arc<[Arc<dyn Easing>]>,
     guard: OnceLock<Guard>,
 }
 
@@ -252,6 +255,7 @@ impl<T: ParameterValueType> PropertyWindowViewModelImpl<T, (), (), ()> {
             selected,
             selected_instance_at,
             parameters,
+            available_easing: Arc::clone(params.available_easing()),
             guard: OnceLock::new(),
         });
         global_ui_state.register_global_ui_event_handler(Arc::clone(&arc));
@@ -276,6 +280,10 @@ where
         (**self.selected_instance_at.load()).clone()
     }
 
+    fn available_easing(&self) -> &[Arc<dyn Easing>] {
+        &self.available_easing
+    }
+
     type TimeMap = RootComponentClassItem<T>;
 
     fn parameters<R>(&self, f: impl FnOnce(Option<&mut ParametersEditSet<T, Self::TimeMap>>) -> R) -> R {
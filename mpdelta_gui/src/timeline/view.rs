@@ -1,12 +1,13 @@
 use crate::timeline::view::range_max::RangeMax;
 use crate::timeline::view::widgets::component_instance_block::{ComponentInstanceBlock, ComponentInstanceEditEvent};
-use crate::timeline::viewmodel::{ComponentClassData, ComponentClassDataList, ComponentInstanceDataList, MarkerLinkDataList, TimelineViewModel};
+use crate::timeline::viewmodel::{ComponentClassData, ComponentClassDataList, ComponentInstanceDataList, MarkerLinkDataList, RootComponentSettings, TimelineViewModel};
 use egui::style::ScrollStyle;
-use egui::{Color32, PointerButton, Pos2, Rect, ScrollArea, Sense, Stroke, Ui, Vec2};
+use egui::{Color32, ComboBox, DragValue, PointerButton, Pos2, Rect, ScrollArea, Sense, Stroke, Ui, Vec2};
 use mpdelta_core::common::mixed_fraction::MixedFraction;
 use mpdelta_core::component::marker_pin::MarkerTime;
 use mpdelta_core::component::parameter::ParameterValueType;
-use mpdelta_core::time::TimelineTime;
+use mpdelta_core::component::processor::{ImageSize, PixelAspectRatio};
+use mpdelta_core::time::{FrameRate, TimelineTime};
 use ordered_float::OrderedFloat;
 use std::collections::HashMap;
 use std::marker::PhantomData;
@@ -16,6 +17,18 @@ use std::{convert, mem};
 mod range_max;
 mod widgets;
 
+const FRAME_RATE_PRESETS: [FrameRate; 6] = [FrameRate::FPS_24, FrameRate::FPS_25, FrameRate::FPS_29_97, FrameRate::FPS_30, FrameRate::FPS_59_94, FrameRate::FPS_60];
+
+const MAX_IMAGE_SIDE: u32 = 16384;
+
+fn frame_rate_text(frame_rate: FrameRate) -> String {
+    if frame_rate.denominator() == 1 {
+        format!("{} fps", frame_rate.numerator())
+    } else {
+        format!("{:.3} fps", frame_rate.into_f64())
+    }
+}
+
 pub struct Timeline<T, VM>
 where
     T: ParameterValueType,
@@ -45,7 +58,50 @@ impl<T: ParameterValueType, VM: TimelineViewModel<T>> Timeline<T, VM> {
         }
     }
 
+    fn root_component_settings_ui(&self, ui: &mut Ui, RootComponentSettings { frame_rate, image_size, pixel_aspect_ratio }: RootComponentSettings) {
+        ui.label("Frame rate");
+        ComboBox::from_id_salt("Timeline-FrameRate").selected_text(frame_rate_text(frame_rate)).show_ui(ui, |ui| {
+            for preset in FRAME_RATE_PRESETS {
+                if ui.selectable_label(preset == frame_rate, frame_rate_text(preset)).clicked() && preset != frame_rate {
+                    self.view_model.edit_frame_rate(preset);
+                }
+            }
+        });
+        let (mut numerator, mut denominator) = (frame_rate.numerator(), frame_rate.denominator());
+        let numerator_changed = ui.add(DragValue::new(&mut numerator).range(1..=u32::MAX)).changed();
+        ui.label("/");
+        let denominator_changed = ui.add(DragValue::new(&mut denominator).range(1..=u32::MAX)).changed();
+        if numerator_changed || denominator_changed {
+            // 約分しても分子か分母が大きすぎる値はFrameRate::newがNoneを返すので無視する
+            if let Some(new_frame_rate) = FrameRate::new(numerator, denominator).filter(|&f| f != frame_rate) {
+                self.view_model.edit_frame_rate(new_frame_rate);
+            }
+        }
+        ui.separator();
+        ui.label("Size");
+        let ImageSize { mut width, mut height } = image_size;
+        let width_changed = ui.add(DragValue::new(&mut width).range(1..=MAX_IMAGE_SIDE)).changed();
+        ui.label("x");
+        let height_changed = ui.add(DragValue::new(&mut height).range(1..=MAX_IMAGE_SIDE)).changed();
+        ui.label("Pixel aspect");
+        let (mut par_numerator, mut par_denominator) = (pixel_aspect_ratio.numerator(), pixel_aspect_ratio.denominator());
+        let par_numerator_changed = ui.add(DragValue::new(&mut par_numerator).range(1..=u32::MAX)).changed();
+        ui.label(":");
+        let par_denominator_changed = ui.add(DragValue::new(&mut par_denominator).range(1..=u32::MAX)).changed();
+        if width_changed || height_changed || par_numerator_changed || par_denominator_changed {
+            let new_image_size = ImageSize { width, height };
+            if let Some(new_pixel_aspect_ratio) = PixelAspectRatio::new(par_numerator, par_denominator) {
+                if new_image_size.is_valid() && (new_image_size, new_pixel_aspect_ratio) != (image_size, pixel_aspect_ratio) {
+                    self.view_model.edit_image_size(new_image_size, new_pixel_aspect_ratio);
+                }
+            }
+        }
+    }
+
     pub fn ui(&mut self, ui: &mut Ui) {
+        if let Some(settings) = self.view_model.root_component_settings() {
+            ui.horizontal(|ui| self.root_component_settings_ui(ui, settings));
+        }
         let mut next_timeline_rect = Rect::from_x_y_ranges(0.0..=0.0, 0.0..=30.0);
         let mut now_dragging = false;
         ui.style_mut().spacing.scroll = ScrollStyle::solid();
@@ -226,6 +282,18 @@ mod tests {
                 None
             }
 
+            fn root_component_settings(&self) -> Option<RootComponentSettings> {
+                Some(RootComponentSettings {
+                    frame_rate: FrameRate::FPS_29_97,
+                    image_size: ImageSize::FULL_HD,
+                    pixel_aspect_ratio: PixelAspectRatio::SQUARE,
+                })
+            }
+
+            fn edit_frame_rate(&self, _frame_rate: FrameRate) {}
+
+            fn edit_image_size(&self, _image_size: ImageSize, _pixel_aspect_ratio: PixelAspectRatio) {}
+
             fn seek(&self) -> MarkerTime {
                 MarkerTime::ZERO
             }
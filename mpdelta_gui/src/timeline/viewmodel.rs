@@ -13,11 +13,12 @@ use mpdelta_core::component::instance::{ComponentInstance, ComponentInstanceId};
 use mpdelta_core::component::link::MarkerLink;
 use mpdelta_core::component::marker_pin::{MarkerPin, MarkerPinId, MarkerTime};
 use mpdelta_core::component::parameter::ParameterValueType;
+use mpdelta_core::component::processor::{ImageSize, PixelAspectRatio};
 use mpdelta_core::core::EditEventListener;
 use mpdelta_core::edit::{InstanceEditCommand, InstanceEditEvent, RootComponentEditCommand, RootComponentEditEvent};
 use mpdelta_core::project::{RootComponentClassHandle, RootComponentClassItem};
 use mpdelta_core::ptr::StaticPointer;
-use mpdelta_core::time::TimelineTime;
+use mpdelta_core::time::{FrameRate, TimelineTime};
 use mpdelta_core::usecase::{GetAvailableComponentClassesUsecase, SubscribeEditEventUsecase};
 use mpdelta_message_router::handler::{IntoAsyncFunctionHandler, IntoFunctionHandler, MessageHandlerBuilder};
 use mpdelta_message_router::{MessageHandler, MessageRouter};
@@ -209,8 +210,19 @@ pub struct ComponentClassDataList<Handle> {
 
 pub type DefaultComponentClassDataList<T> = ComponentClassDataList<StaticPointer<RwLock<dyn ComponentClass<T>>>>;
 
+/// 選択中のRootComponentClassの出力設定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RootComponentSettings {
+    pub frame_rate: FrameRate,
+    pub image_size: ImageSize,
+    pub pixel_aspect_ratio: PixelAspectRatio,
+}
+
 pub trait TimelineViewModel<T: ParameterValueType> {
     fn component_length(&self) -> Option<MarkerTime>;
+    fn root_component_settings(&self) -> Option<RootComponentSettings>;
+    fn edit_frame_rate(&self, frame_rate: FrameRate);
+    fn edit_image_size(&self, image_size: ImageSize, pixel_aspect_ratio: PixelAspectRatio);
     fn seek(&self) -> MarkerTime;
     fn set_seek(&self, seek: MarkerTime);
     fn edit_component_length(&self, length: MarkerTime);
@@ -241,6 +253,7 @@ pub struct TimelineViewModelImpl<T: ParameterValueType, GlobalUIState, MessageHa
     component_classes: Arc<ArcSwap<DefaultComponentClassDataList<T>>>,
     component_instances: Arc<ArcSwap<DefaultComponentInstanceDataList>>,
     marker_links: Arc<ArcSwap<DefaultComponentLinkDataList>>,
+    root_component_settings: Arc<AtomicCell<Option<RootComponentSettings>>>,
     selected_root_component_class: Arc<ArcSwapOption<RootComponentClassHandle<T>>>,
     message_router: MessageRouter<MessageHandler, Runtime>,
     runtime: Runtime,
@@ -259,6 +272,8 @@ pub enum Message<T: ParameterValueType> {
     ConnectMarkerPins(MarkerPinId, MarkerPinId),
     EditMarkerLinkLength(MarkerLink, f64),
     EditComponentLength(MarkerTime),
+    EditFrameRate(FrameRate),
+    EditImageSize(ImageSize, PixelAspectRatio),
     AddMarkerPin(ComponentInstanceId, TimelineTime),
     DeleteMarkerPin(ComponentInstanceId, MarkerPinId),
     LockMarkerPin(ComponentInstanceId, MarkerPinId),
@@ -282,6 +297,8 @@ where
             Message::ConnectMarkerPins(from, to) => Message::ConnectMarkerPins(*from, *to),
             &Message::EditMarkerLinkLength(ref value, length) => Message::EditMarkerLinkLength(value.clone(), length),
             &Message::EditComponentLength(value) => Message::EditComponentLength(value),
+            &Message::EditFrameRate(value) => Message::EditFrameRate(value),
+            &Message::EditImageSize(size, pixel_aspect_ratio) => Message::EditImageSize(size, pixel_aspect_ratio),
             &Message::AddMarkerPin(ref instance, at) => Message::AddMarkerPin(*instance, at),
             Message::DeleteMarkerPin(instance, pin) => Message::DeleteMarkerPin(*instance, *pin),
             Message::LockMarkerPin(instance, pin) => Message::LockMarkerPin(*instance, *pin),
@@ -310,6 +327,8 @@ where
             (Message::ConnectMarkerPins(a, b), Message::ConnectMarkerPins(c, d)) => a == c && b == d,
             (Message::EditMarkerLinkLength(a, al), Message::EditMarkerLinkLength(b, bl)) => a == b && al == bl,
             (Message::EditComponentLength(a), Message::EditComponentLength(b)) => a == b,
+            (Message::EditFrameRate(a), Message::EditFrameRate(b)) => a == b,
+            (Message::EditImageSize(a, ap), Message::EditImageSize(b, bp)) => a == b && ap == bp,
             (Message::AddMarkerPin(a, at), Message::AddMarkerPin(b, bt)) => a == b && at == bt,
             (Message::DeleteMarkerPin(a, ap), Message::DeleteMarkerPin(b, bp)) => a == b && ap == bp,
             (Message::LockMarkerPin(a, ap), Message::LockMarkerPin(b, bp)) => a == b && ap == bp,
@@ -344,9 +363,14 @@ where
     Runtime: AsyncRuntime<()> + Clone,
 {
     fn on_edit(&self, _: &RootComponentClassHandle<T>, _: RootComponentEditEvent) {
-        use_arc!(component_instances = self.component_instances, marker_links = self.marker_links, selected_root_component_class = self.selected_root_component_class);
+        use_arc!(
+            component_instances = self.component_instances,
+            marker_links = self.marker_links,
+            root_component_settings = self.root_component_settings,
+            selected_root_component_class = self.selected_root_component_class
+        );
         let mut task = self.load_timeline_task.lock().unwrap();
-        let future = TimelineViewModelImpl::load_timeline_by_current_root_component_class(component_instances, marker_links, selected_root_component_class);
+        let future = TimelineViewModelImpl::load_timeline_by_current_root_component_class(component_instances, marker_links, root_component_settings, selected_root_component_class);
         if let Some(handle) = task.take() {
             handle.abort();
             *task = Some(self.runtime.spawn(handle.then(|_| future)));
@@ -356,9 +380,14 @@ where
     }
 
     fn on_edit_instance(&self, _: &RootComponentClassHandle<T>, _: &ComponentInstanceId, _: InstanceEditEvent<T>) {
-        use_arc!(component_instances = self.component_instances, marker_links = self.marker_links, selected_root_component_class = self.selected_root_component_class);
+        use_arc!(
+            component_instances = self.component_instances,
+            marker_links = self.marker_links,
+            root_component_settings = self.root_component_settings,
+            selected_root_component_class = self.selected_root_component_class
+        );
         let mut task = self.load_timeline_task.lock().unwrap();
-        let future = TimelineViewModelImpl::load_timeline_by_current_root_component_class(component_instances, marker_links, selected_root_component_class);
+        let future = TimelineViewModelImpl::load_timeline_by_current_root_component_class(component_instances, marker_links, root_component_settings, selected_root_component_class);
         if let Some(handle) = task.take() {
             handle.abort();
             *task = Some(self.runtime.spawn(handle.then(|_| future)));
@@ -379,6 +408,7 @@ impl<T: ParameterValueType> TimelineViewModelImpl<T, (), (), (), (), ()> {
         let selected_components = Arc::new(ArcSwap::new(Arc::new(HashTrieSet::new_sync())));
         let marker_links = Arc::new(ArcSwap::new(Arc::new(MarkerLinkDataList { list: Vec::new() })));
         let component_instances = Arc::new(ArcSwap::new(Arc::new(ComponentInstanceDataList { list: Vec::new() })));
+        let root_component_settings = Arc::new(AtomicCell::new(None));
         let selected_root_component_class = Arc::new(ArcSwapOption::new(None));
         let load_timeline_task = Arc::new(StdMutex::new(None::<JoinHandleWrapper<<P::AsyncRuntime as AsyncRuntime<()>>::JoinHandle>>));
         let message_router = MessageRouter::builder()
@@ -389,11 +419,11 @@ impl<T: ParameterValueType> TimelineViewModelImpl<T, (), (), (), (), ()> {
                     .handle(|handler| {
                         handler.filter_map(|event| if let GlobalUIEvent::SelectRootComponentClass(value) = event { Some(value) } else { None }).handle({
                             let runtime = params.runtime().clone();
-                            use_arc!(selected_root_component_class, component_instances, marker_links, load_timeline_task);
+                            use_arc!(selected_root_component_class, component_instances, marker_links, root_component_settings, load_timeline_task);
                             move |root_component_class| {
-                                use_arc!(selected_root_component_class, component_instances, marker_links);
+                                use_arc!(selected_root_component_class, component_instances, marker_links, root_component_settings);
                                 let mut task = load_timeline_task.lock().unwrap();
-                                let future = Self::load_timeline_by_new_root_component_class(root_component_class, component_instances, marker_links, selected_root_component_class);
+                                let future = Self::load_timeline_by_new_root_component_class(root_component_class, component_instances, marker_links, root_component_settings, selected_root_component_class);
                                 if let Some(handle) = task.take() {
                                     handle.abort();
                                     *task = Some(runtime.spawn(handle.then(|_| future)));
@@ -410,7 +440,14 @@ impl<T: ParameterValueType> TimelineViewModelImpl<T, (), (), (), (), ()> {
                     .filter(|message| {
                         matches!(
                             message,
-                            Message::EditComponentLength(_) | Message::AddComponentInstance(_) | Message::DeleteComponentInstance(_) | Message::EditMarkerLinkLength(_, _) | Message::InsertComponentInstanceTo(_, _) | Message::ConnectMarkerPins(_, _)
+                            Message::EditComponentLength(_)
+                                | Message::EditFrameRate(_)
+                                | Message::EditImageSize(_, _)
+                                | Message::AddComponentInstance(_)
+                                | Message::DeleteComponentInstance(_)
+                                | Message::EditMarkerLinkLength(_, _)
+                                | Message::InsertComponentInstanceTo(_, _)
+                                | Message::ConnectMarkerPins(_, _)
                         )
                     })
                     .handle_async({
@@ -431,6 +468,8 @@ impl<T: ParameterValueType> TimelineViewModelImpl<T, (), (), (), (), ()> {
                                     Message::DeleteComponentInstance(handle) => RootComponentEditCommand::DeleteComponentInstance(handle),
                                     Message::EditMarkerLinkLength(target, len) => RootComponentEditCommand::EditMarkerLinkLength(target, TimelineTime::new(MixedFraction::from_f64(len))),
                                     Message::EditComponentLength(len) => RootComponentEditCommand::EditComponentLength(len),
+                                    Message::EditFrameRate(frame_rate) => RootComponentEditCommand::EditFrameRate(frame_rate),
+                                    Message::EditImageSize(size, pixel_aspect_ratio) => RootComponentEditCommand::EditImageSize(size, pixel_aspect_ratio),
                                     Message::InsertComponentInstanceTo(handle, index) => RootComponentEditCommand::InsertComponentInstanceTo(handle, index),
                                     Message::ConnectMarkerPins(from, to) => RootComponentEditCommand::ConnectMarkerPins(from, to),
                                     _ => unreachable!(),
@@ -502,6 +541,7 @@ impl<T: ParameterValueType> TimelineViewModelImpl<T, (), (), (), (), ()> {
             component_classes,
             component_instances,
             marker_links,
+            root_component_settings,
             selected_root_component_class,
             message_router,
             runtime: params.runtime().clone(),
@@ -518,25 +558,38 @@ impl<T: ParameterValueType> TimelineViewModelImpl<T, (), (), (), (), ()> {
         root_component_class: Option<RootComponentClassHandle<T>>,
         component_instances: Arc<ArcSwap<DefaultComponentInstanceDataList>>,
         marker_links: Arc<ArcSwap<DefaultComponentLinkDataList>>,
+        root_component_settings: Arc<AtomicCell<Option<RootComponentSettings>>>,
         selected_root_component_class: Arc<ArcSwapOption<RootComponentClassHandle<T>>>,
     ) {
         selected_root_component_class.store(root_component_class.clone().map(Arc::new));
-        Self::load_timeline_inner(root_component_class.as_ref(), &component_instances, &marker_links).await;
+        Self::load_timeline_inner(root_component_class.as_ref(), &component_instances, &marker_links, &root_component_settings).await;
     }
 
-    async fn load_timeline_by_current_root_component_class(component_instances: Arc<ArcSwap<DefaultComponentInstanceDataList>>, marker_links: Arc<ArcSwap<DefaultComponentLinkDataList>>, selected_root_component_class: Arc<ArcSwapOption<RootComponentClassHandle<T>>>) {
-        Self::load_timeline_inner(selected_root_component_class.load().as_deref(), &component_instances, &marker_links).await;
+    async fn load_timeline_by_current_root_component_class(
+        component_instances: Arc<ArcSwap<DefaultComponentInstanceDataList>>,
+        marker_links: Arc<ArcSwap<DefaultComponentLinkDataList>>,
+        root_component_settings: Arc<AtomicCell<Option<RootComponentSettings>>>,
+        selected_root_component_class: Arc<ArcSwapOption<RootComponentClassHandle<T>>>,
+    ) {
+        Self::load_timeline_inner(selected_root_component_class.load().as_deref(), &component_instances, &marker_links, &root_component_settings).await;
     }
 
-    async fn load_timeline_inner(root_component_class: Option<&RootComponentClassHandle<T>>, component_instances: &ArcSwap<DefaultComponentInstanceDataList>, marker_links: &ArcSwap<DefaultComponentLinkDataList>) {
+    async fn load_timeline_inner(root_component_class: Option<&RootComponentClassHandle<T>>, component_instances: &ArcSwap<DefaultComponentInstanceDataList>, marker_links: &ArcSwap<DefaultComponentLinkDataList>, root_component_settings: &AtomicCell<Option<RootComponentSettings>>) {
         let Some(root_component_class) = root_component_class else {
+            root_component_settings.store(None);
             return;
         };
         let Some(root_component_class) = root_component_class.upgrade() else {
+            root_component_settings.store(None);
             return;
         };
         let root_component_class = root_component_class.read().await;
         let root_component_class = root_component_class.get();
+        root_component_settings.store(Some(RootComponentSettings {
+            frame_rate: root_component_class.frame_rate(),
+            image_size: root_component_class.image_size(),
+            pixel_aspect_ratio: root_component_class.pixel_aspect_ratio(),
+        }));
         let mut pin_map = HashMap::new();
         let mut list = Vec::new();
         for (i, handle) in root_component_class.iter_components().enumerate() {
@@ -562,6 +615,15 @@ where
     fn component_length(&self) -> Option<MarkerTime> {
         self.global_ui_state.component_length()
     }
+    fn root_component_settings(&self) -> Option<RootComponentSettings> {
+        self.root_component_settings.load()
+    }
+    fn edit_frame_rate(&self, frame_rate: FrameRate) {
+        self.message_router.handle(Message::EditFrameRate(frame_rate));
+    }
+    fn edit_image_size(&self, image_size: ImageSize, pixel_aspect_ratio: PixelAspectRatio) {
+        self.message_router.handle(Message::EditImageSize(image_size, pixel_aspect_ratio));
+    }
     fn seek(&self) -> MarkerTime {
         self.global_ui_state.seek()
     }
@@ -6,10 +6,15 @@ use crate::property_window::view::PropertyWindow;
 use crate::property_window::viewmodel::{PropertyWindowViewModel, PropertyWindowViewModelImpl};
 use crate::timeline::view::Timeline;
 use crate::timeline::viewmodel::{TimelineViewModel, TimelineViewModelImpl};
-use crate::viewmodel::{MainWindowViewModel, MainWindowViewModelImpl, ProjectData, ProjectDataList, RootComponentClassData, RootComponentClassDataList, ViewModelParams};
+use crate::viewmodel::{AudioExportSettings, ExportSettings, MainWindowViewModel, MainWindowViewModelImpl, ProjectData, ProjectDataList, RootComponentClassData, RootComponentClassDataList, ViewModelParams, AUDIO_SAMPLE_RATES};
 use crate::ImageRegister;
-use egui::{Button, Context};
+use egui::{Button, ComboBox, Context, DragValue, ProgressBar, Ui};
 use mpdelta_core::component::parameter::ParameterValueType;
+use mpdelta_core::encode::{EncodePhase, EncodeProgress};
+use mpdelta_multimedia::options_value::{OptionValuesRefMut, ValueTypeF64, ValueTypeI64, ValueTypeString, ValueWithDefault};
+use mpdelta_multimedia::{AudioChannels, AudioCodec, CodecOptions, FileFormat, HasOption, VideoCodec};
+use std::fmt::Display;
+use std::ops::{Bound, RangeInclusive};
 use std::sync::Arc;
 
 pub trait Gui<T> {
@@ -29,6 +34,8 @@ where
     preview: Preview<T, PreviewVM>,
     timeline: Timeline<T, TimelineVM>,
     property_window: PropertyWindow<T, PropertyWindowVM>,
+    export_dialog: Option<(ExportSettings, CodecOptions<VideoCodec>)>,
+    audio_export_dialog: Option<AudioExportSettings>,
 }
 
 pub fn new_gui<T, P>(view_model_params: P) -> MPDeltaGUI<T, impl MainWindowViewModel<T>, impl PreviewViewModel<T>, impl TimelineViewModel<T>, impl PropertyWindowViewModel<T>>
@@ -43,6 +50,8 @@ where
         preview: Preview::new(PreviewViewModelImpl::new(&global_ui_state, &view_model_params)),
         timeline: Timeline::new(TimelineViewModelImpl::new(&global_ui_state, &edit_funnel, &view_model_params)),
         property_window: PropertyWindow::new(PropertyWindowViewModelImpl::new(&global_ui_state, &edit_funnel, &view_model_params)),
+        export_dialog: None,
+        audio_export_dialog: None,
     }
 }
 
@@ -84,7 +93,26 @@ where
                             ui.close_menu();
                         }
                         if ui.button("Encode").clicked() {
-                            self.view_model.encode();
+                            self.export_dialog = self.view_model.export_candidates().first().and_then(|&settings| Some((settings, self.view_model.video_codec_options(settings.video_codec)?)));
+                            if self.export_dialog.is_none() {
+                                eprintln!("no video encoder is available");
+                            }
+                            ui.close_menu();
+                        }
+                        if ui.button("Export Audio").clicked() {
+                            self.audio_export_dialog = self.view_model.audio_export_candidates().first().map(|&(file_format, codec)| AudioExportSettings {
+                                file_format,
+                                codec,
+                                sample_rate: 48_000,
+                                channels: AudioChannels::Stereo,
+                            });
+                            if self.audio_export_dialog.is_none() {
+                                eprintln!("no audio encoder is available");
+                            }
+                            ui.close_menu();
+                        }
+                        if ui.button("Save Frame as PNG").clicked() {
+                            self.view_model.snapshot_frame();
                             ui.close_menu();
                         }
                     });
@@ -98,9 +126,57 @@ where
                             ui.close_menu();
                         }
                     });
+                    if self.view_model.is_encoding() {
+                        let progress = self.view_model.encode_progress();
+                        let fraction = progress.as_ref().map_or(0., EncodeProgress::fraction);
+                        let text = progress.as_ref().map_or_else(|| "Preparing".to_owned(), encode_progress_text);
+                        ui.add(ProgressBar::new(fraction as f32).desired_width(240.).text(text));
+                        if ui.button("Cancel").clicked() {
+                            self.view_model.cancel_encode();
+                        }
+                        ctx.request_repaint();
+                    }
                 });
             });
 
+            if let Some((settings, video_options)) = &mut self.export_dialog {
+                let mut open = true;
+                let mut export = false;
+                egui::Window::new("Encode").open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+                    let video_codec = settings.video_codec;
+                    export_settings_ui(ui, self.view_model.export_candidates(), settings);
+                    // オプションはコーデックごとに異なるので、コーデックを変えたら初期値に戻す
+                    if settings.video_codec != video_codec {
+                        if let Some(options) = self.view_model.video_codec_options(settings.video_codec) {
+                            *video_options = options;
+                        }
+                    }
+                    ui.separator();
+                    codec_options_ui(ui, video_options);
+                    export = ui.button("Export").clicked();
+                });
+                if export {
+                    self.view_model.encode(*settings, video_options.clone());
+                }
+                if export || !open {
+                    self.export_dialog = None;
+                }
+            }
+
+            if let Some(settings) = &mut self.audio_export_dialog {
+                let mut open = true;
+                let mut export = false;
+                egui::Window::new("Export Audio").open(&mut open).collapsible(false).resizable(false).show(ctx, |ui| {
+                    export = audio_export_settings_ui(ui, self.view_model.audio_export_candidates(), settings);
+                });
+                if export {
+                    self.view_model.encode_audio(*settings);
+                }
+                if export || !open {
+                    self.audio_export_dialog = None;
+                }
+            }
+
             egui::TopBottomPanel::top("project_tabs").show(ctx, |ui| {
                 ui.horizontal(|ui| {
                     self.view_model.projects(|&ProjectDataList { ref list, selected }| {
@@ -154,3 +230,187 @@ where
         });
     }
 }
+
+/// ファイル形式とコーデックを選ぶ
+fn export_settings_ui(ui: &mut Ui, candidates: &[ExportSettings], settings: &mut ExportSettings) {
+    let mut distinct = Vec::new();
+    ComboBox::from_label("Format").selected_text(settings.file_format.extension()).show_ui(ui, |ui| {
+        for candidate in candidates {
+            if !distinct.contains(&candidate.file_format) {
+                distinct.push(candidate.file_format);
+                ui.selectable_value(&mut settings.file_format, candidate.file_format, candidate.file_format.extension());
+            }
+        }
+    });
+    let mut distinct = Vec::new();
+    ComboBox::from_label("Video Codec").selected_text(format!("{:?}", settings.video_codec)).show_ui(ui, |ui| {
+        for candidate in candidates.iter().filter(|candidate| candidate.file_format == settings.file_format) {
+            if !distinct.contains(&candidate.video_codec) {
+                distinct.push(candidate.video_codec);
+                ui.selectable_value(&mut settings.video_codec, candidate.video_codec, format!("{:?}", candidate.video_codec));
+            }
+        }
+    });
+    ComboBox::from_label("Audio Codec").selected_text(format!("{:?}", settings.audio_codec)).show_ui(ui, |ui| {
+        for candidate in candidates.iter().filter(|candidate| candidate.file_format == settings.file_format && candidate.video_codec == settings.video_codec) {
+            ui.selectable_value(&mut settings.audio_codec, candidate.audio_codec, format!("{:?}", candidate.audio_codec));
+        }
+    });
+    // ファイル形式や映像コーデックを変えて組み合わせが無効になったら、選べるものに寄せる
+    if !candidates.contains(settings) {
+        let fallback = candidates
+            .iter()
+            .find(|candidate| candidate.file_format == settings.file_format && candidate.video_codec == settings.video_codec)
+            .or_else(|| candidates.iter().find(|candidate| candidate.file_format == settings.file_format && candidate.audio_codec == settings.audio_codec))
+            .or_else(|| candidates.iter().find(|candidate| candidate.file_format == settings.file_format));
+        if let Some(fallback) = fallback {
+            *settings = *fallback;
+        }
+    }
+}
+
+/// コーデック固有のオプションを編集する Defaultのままの項目はエンコーダの既定値を使う
+fn codec_options_ui<Codec: HasOption>(ui: &mut Ui, options: &mut CodecOptions<Codec>) {
+    for (key, value) in options.options_mut() {
+        match value {
+            OptionValuesRefMut::Bool { value } => option_candidates_ui(ui, key, value, [false, true]),
+            OptionValuesRefMut::Int { value, ty: ValueTypeI64::Candidates(candidates) } => option_candidates_ui(ui, key, value, candidates.iter().copied()),
+            OptionValuesRefMut::Int { value, ty } => {
+                let range = match *ty {
+                    ValueTypeI64::Range(min, max) => {
+                        let min = match min {
+                            Bound::Included(min) => min,
+                            Bound::Excluded(min) => min.saturating_add(1),
+                            Bound::Unbounded => i64::MIN,
+                        };
+                        let max = match max {
+                            Bound::Included(max) => max,
+                            Bound::Excluded(max) => max.saturating_sub(1),
+                            Bound::Unbounded => i64::MAX,
+                        };
+                        min..=max
+                    }
+                    _ => i64::MIN..=i64::MAX,
+                };
+                option_drag_value_ui(ui, key, value, range);
+            }
+            OptionValuesRefMut::Float { value, ty: ValueTypeF64::Candidates(candidates) } => option_candidates_ui(ui, key, value, candidates.iter().copied()),
+            OptionValuesRefMut::Float { value, ty } => {
+                let range = match *ty {
+                    ValueTypeF64::Range(min, max) => {
+                        let bound_value = |bound: Bound<f64>, unbounded: f64| match bound {
+                            Bound::Included(value) | Bound::Excluded(value) => value,
+                            Bound::Unbounded => unbounded,
+                        };
+                        bound_value(min, f64::NEG_INFINITY)..=bound_value(max, f64::INFINITY)
+                    }
+                    _ => f64::NEG_INFINITY..=f64::INFINITY,
+                };
+                option_drag_value_ui(ui, key, value, range);
+            }
+            OptionValuesRefMut::String { value, ty: ValueTypeString::Candidates(candidates) } => option_candidates_ui(ui, key, value, candidates.iter().map(|candidate| candidate.clone().into_owned())),
+            OptionValuesRefMut::String { value, ty: ValueTypeString::Any } => {
+                ui.horizontal(|ui| {
+                    let mut specified = matches!(value, ValueWithDefault::Value(_));
+                    if ui.checkbox(&mut specified, key).changed() {
+                        *value = if specified { ValueWithDefault::Value(String::new()) } else { ValueWithDefault::Default };
+                    }
+                    if let ValueWithDefault::Value(value) = value {
+                        ui.text_edit_singleline(value);
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// 候補から選ぶオプション
+fn option_candidates_ui<V: Clone + PartialEq + Display>(ui: &mut Ui, key: &str, value: &mut ValueWithDefault<V>, candidates: impl IntoIterator<Item = V>) {
+    ComboBox::from_label(key).selected_text(option_value_text(value)).show_ui(ui, |ui| {
+        ui.selectable_value(value, ValueWithDefault::Default, "Default");
+        for candidate in candidates {
+            let text = candidate.to_string();
+            ui.selectable_value(value, ValueWithDefault::Value(candidate), text);
+        }
+    });
+}
+
+/// 範囲内の数値を指定するオプション チェックを外すとDefaultに戻す
+fn option_drag_value_ui<V: emath::Numeric>(ui: &mut Ui, key: &str, value: &mut ValueWithDefault<V>, range: RangeInclusive<V>) {
+    ui.horizontal(|ui| {
+        let mut specified = matches!(value, ValueWithDefault::Value(_));
+        if ui.checkbox(&mut specified, key).changed() {
+            *value = if specified { ValueWithDefault::Value(V::from_f64(0f64.clamp(range.start().to_f64(), range.end().to_f64()))) } else { ValueWithDefault::Default };
+        }
+        if let ValueWithDefault::Value(value) = value {
+            ui.add(DragValue::new(value).range(range));
+        }
+    });
+}
+
+fn option_value_text<V: Display>(value: &ValueWithDefault<V>) -> String {
+    match value {
+        ValueWithDefault::Default => "Default".to_owned(),
+        ValueWithDefault::Value(value) => value.to_string(),
+    }
+}
+
+/// 音声のファイル形式、コーデック、サンプルレートとチャンネルを選ぶ 書き出すボタンが押されたらtrueを返す
+fn audio_export_settings_ui(ui: &mut Ui, candidates: &[(FileFormat, AudioCodec)], settings: &mut AudioExportSettings) -> bool {
+    let mut distinct = Vec::new();
+    ComboBox::from_label("Format").selected_text(settings.file_format.extension()).show_ui(ui, |ui| {
+        for &(file_format, _) in candidates {
+            if !distinct.contains(&file_format) {
+                distinct.push(file_format);
+                ui.selectable_value(&mut settings.file_format, file_format, file_format.extension());
+            }
+        }
+    });
+    // WAVではコーデックの違いがビット深度の違いになる
+    let label = if settings.file_format == FileFormat::Wav { "Bit Depth" } else { "Codec" };
+    ComboBox::from_label(label).selected_text(audio_codec_text(settings.codec)).show_ui(ui, |ui| {
+        for &(_, codec) in candidates.iter().filter(|&&(file_format, _)| file_format == settings.file_format) {
+            ui.selectable_value(&mut settings.codec, codec, audio_codec_text(codec));
+        }
+    });
+    if !candidates.contains(&(settings.file_format, settings.codec)) {
+        if let Some(&(_, codec)) = candidates.iter().find(|&&(file_format, _)| file_format == settings.file_format) {
+            settings.codec = codec;
+        }
+    }
+    ComboBox::from_label("Sample Rate").selected_text(format!("{} Hz", settings.sample_rate)).show_ui(ui, |ui| {
+        for sample_rate in AUDIO_SAMPLE_RATES {
+            ui.selectable_value(&mut settings.sample_rate, sample_rate, format!("{sample_rate} Hz"));
+        }
+    });
+    ComboBox::from_label("Channels").selected_text(format!("{:?}", settings.channels)).show_ui(ui, |ui| {
+        for channels in [AudioChannels::Mono, AudioChannels::Stereo] {
+            ui.selectable_value(&mut settings.channels, channels, format!("{channels:?}"));
+        }
+    });
+    ui.button("Export").clicked()
+}
+
+fn audio_codec_text(codec: AudioCodec) -> String {
+    match codec {
+        AudioCodec::PcmS16 => "16-bit".to_owned(),
+        AudioCodec::PcmS24 => "24-bit".to_owned(),
+        AudioCodec::PcmF32 => "32-bit float".to_owned(),
+        codec => format!("{codec:?}"),
+    }
+}
+
+fn encode_progress_text(progress: &EncodeProgress) -> String {
+    match progress.phase {
+        EncodePhase::Audio if progress.total_frames == 0 => "Rendering audio".to_owned(),
+        EncodePhase::Audio => format!("Encoding audio ({:.0}%)", progress.fraction() * 100.),
+        EncodePhase::Video => match progress.eta() {
+            Some(eta) => {
+                let eta = eta.as_secs();
+                format!("{} / {} frames (ETA {}:{:02})", progress.frames_done, progress.total_frames, eta / 60, eta % 60)
+            }
+            None => format!("{} / {} frames", progress.frames_done, progress.total_frames),
+        },
+        EncodePhase::Finalize => "Finalizing".to_owned(),
+    }
+}
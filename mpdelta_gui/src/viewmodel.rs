@@ -4,22 +4,25 @@ use crate::AudioTypePlayer;
 use arc_swap::ArcSwapOption;
 use mpdelta_async_runtime::AsyncRuntime;
 use mpdelta_core::component::class::ComponentClass;
+use mpdelta_core::component::parameter::value::Easing;
 use mpdelta_core::component::parameter::ParameterValueType;
 use mpdelta_core::core::IdGenerator;
+use mpdelta_core::encode::{EncodeMonitor, EncodeProgress};
 use mpdelta_core::project::{ProjectHandle, RootComponentClassHandle};
+use mpdelta_core::time::TimelineTime;
 use mpdelta_core::usecase::{
-    EditUsecase, GetAvailableComponentClassesUsecase, GetLoadedProjectsUsecase, GetRootComponentClassesUsecase, LoadProjectUsecase, NewProjectUsecase, NewRootComponentClassUsecase, RealtimeRenderComponentUsecase, RedoUsecase, RenderWholeComponentUsecase, SetOwnerForRootComponentClassUsecase,
-    SubscribeEditEventUsecase, UndoUsecase, WriteProjectUsecase,
+    EditUsecase, GetAvailableComponentClassesUsecase, GetLoadedProjectsUsecase, GetRootComponentClassesUsecase, LoadProjectUsecase, NewProjectUsecase, NewRootComponentClassUsecase, RealtimeRenderComponentUsecase, RedoUsecase, RenderFrameUsecase, RenderWholeComponentUsecase,
+    SetOwnerForRootComponentClassUsecase, SubscribeEditEventUsecase, UndoUsecase, WriteProjectUsecase,
 };
 use mpdelta_message_router::handler::{IntoAsyncFunctionHandler, IntoAsyncFunctionHandlerSingle, IntoDerefHandler, MessageHandlerBuilder};
 use mpdelta_message_router::{handler, MessageHandler, MessageRouter};
-use mpdelta_multimedia::{AudioCodec, CodecImplement, FileFormat, VideoCodec};
+use mpdelta_multimedia::{AudioChannels, AudioCodec, CodecImplement, CodecOptions, FileFormat, VideoCodec};
 use rfd::AsyncFileDialog;
 use std::borrow::Cow;
 use std::hash::Hash;
 use std::mem;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
 
 pub trait ViewModelParams<T: ParameterValueType> {
     type AsyncRuntime: AsyncRuntime<()> + Clone + 'static;
@@ -39,7 +42,7 @@ pub trait ViewModelParams<T: ParameterValueType> {
     type WriteProject: WriteProjectUsecase<T> + 'static;
     type AudioPlayer: AudioTypePlayer<T::Audio> + 'static;
     type EncoderType: Send + Sync + 'static;
-    type Encode: RenderWholeComponentUsecase<T, Self::EncoderType> + 'static;
+    type Encode: RenderWholeComponentUsecase<T, Self::EncoderType> + RenderFrameUsecase<T, Self::EncoderType> + 'static;
 
     fn runtime(&self) -> &Self::AsyncRuntime;
     fn id_generator(&self) -> &Arc<Self::IdGenerator>;
@@ -60,6 +63,7 @@ pub trait ViewModelParams<T: ParameterValueType> {
     fn available_video_codec(&self) -> &Arc<[CodecImplement<VideoCodec, Self::EncoderType>]>;
     fn available_audio_codec(&self) -> &Arc<[CodecImplement<AudioCodec, Self::EncoderType>]>;
     fn encode(&self) -> &Arc<Self::Encode>;
+    fn available_easing(&self) -> &Arc<[Arc<dyn Easing>]>;
 }
 
 pub struct ViewModelParamsImpl<
@@ -101,6 +105,7 @@ pub struct ViewModelParamsImpl<
     pub available_video_codec: Arc<[CodecImplement<VideoCodec, EncoderType>]>,
     pub available_audio_codec: Arc<[CodecImplement<AudioCodec, EncoderType>]>,
     pub encode: Arc<Encode>,
+    pub available_easing: Arc<[Arc<dyn Easing>]>,
 }
 
 impl<Runtime, Id, Edit, SubscribeEditEvent, GetAvailableComponentClasses, GetLoadedProjects, GetRootComponentClasses, LoadProject, NewProject, NewRootComponentClass, RealtimeRenderComponent, Redo, SetOwnerForRootComponentClass, Undo, WriteProject, AudioPlayer, EncoderType, Encode> Clone
@@ -148,6 +153,7 @@ where
             available_video_codec,
             available_audio_codec,
             encode,
+            available_easing,
         } = self;
         ViewModelParamsImpl {
             runtime: runtime.clone(),
@@ -169,6 +175,7 @@ where
             available_video_codec: Arc::clone(available_video_codec),
             available_audio_codec: Arc::clone(available_audio_codec),
             encode: Arc::clone(encode),
+            available_easing: Arc::clone(available_easing),
         }
     }
 }
@@ -233,7 +240,7 @@ where
     WriteProject: WriteProjectUsecase<T> + 'static,
     AudioPlayer: AudioTypePlayer<T::Audio> + 'static,
     EncoderType: Send + Sync + 'static,
-    Encode: RenderWholeComponentUsecase<T, EncoderType> + 'static,
+    Encode: RenderWholeComponentUsecase<T, EncoderType> + RenderFrameUsecase<T, EncoderType> + 'static,
 {
     type AsyncRuntime = Runtime;
     type IdGenerator = Id;
@@ -311,6 +318,9 @@ where
     fn encode(&self) -> &Arc<Self::Encode> {
         &self.encode
     }
+    fn available_easing(&self) -> &Arc<[Arc<dyn Easing>]> {
+        &self.available_easing
+    }
 }
 
 pub struct ProjectData<Handle> {
@@ -351,6 +361,85 @@ pub struct RootComponentClassDataList<Handle> {
     pub selected: usize,
 }
 
+/// 動画を書き出すときのファイル形式とコーデックの組
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExportSettings {
+    pub file_format: FileFormat,
+    pub video_codec: VideoCodec,
+    pub audio_codec: AudioCodec,
+}
+
+const VIDEO_FILE_FORMATS: [FileFormat; 4] = [FileFormat::Mp4, FileFormat::Webm, FileFormat::Mov, FileFormat::Mkv];
+
+/// 同じ実装で扱える映像と音声のコーデックの組のうち、`settings`に合うものを探す
+fn find_codec_pair<'a, E>(available_video_codec: &'a [CodecImplement<VideoCodec, E>], available_audio_codec: &'a [CodecImplement<AudioCodec, E>], settings: ExportSettings) -> Option<(&'a CodecImplement<VideoCodec, E>, &'a CodecImplement<AudioCodec, E>)> {
+    let ExportSettings { file_format, video_codec, audio_codec } = settings;
+    available_video_codec.iter().filter(|video| video.codec() == video_codec).find_map(|video| {
+        available_audio_codec
+            .iter()
+            .find(|audio| audio.codec() == audio_codec && video.handler().eq(&**audio.handler()) && video.handler().supports(file_format, Some(video_codec), Some(audio_codec)))
+            .map(|audio| (video, audio))
+    })
+}
+
+/// 書き出しダイアログで選べる組み合わせの一覧
+fn export_candidates<E>(available_video_codec: &[CodecImplement<VideoCodec, E>], available_audio_codec: &[CodecImplement<AudioCodec, E>]) -> Vec<ExportSettings> {
+    let mut candidates = Vec::new();
+    for file_format in VIDEO_FILE_FORMATS {
+        for video in available_video_codec {
+            for audio in available_audio_codec {
+                let settings = ExportSettings {
+                    file_format,
+                    video_codec: video.codec(),
+                    audio_codec: audio.codec(),
+                };
+                if !candidates.contains(&settings) && find_codec_pair(available_video_codec, available_audio_codec, settings).is_some() {
+                    candidates.push(settings);
+                }
+            }
+        }
+    }
+    candidates
+}
+
+/// 映像コーデックごとの編集できるオプションの初期値
+fn video_codec_options<E>(available_video_codec: &[CodecImplement<VideoCodec, E>]) -> Vec<(VideoCodec, CodecOptions<VideoCodec>)> {
+    let mut options: Vec<(VideoCodec, CodecOptions<VideoCodec>)> = Vec::new();
+    for video in available_video_codec {
+        if options.iter().all(|&(codec, _)| codec != video.codec()) {
+            options.push((video.codec(), video.default_codec_options()));
+        }
+    }
+    options
+}
+
+/// 音声だけを書き出すときのファイル形式、コーデック、サンプルレートとチャンネル
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioExportSettings {
+    pub file_format: FileFormat,
+    pub codec: AudioCodec,
+    pub sample_rate: u32,
+    pub channels: AudioChannels,
+}
+
+const AUDIO_FILE_FORMATS: [FileFormat; 3] = [FileFormat::Wav, FileFormat::Flac, FileFormat::Mp3];
+
+pub const AUDIO_SAMPLE_RATES: [u32; 4] = [44_100, 48_000, 88_200, 96_000];
+
+/// 音声の書き出しダイアログで選べるファイル形式とコーデックの組の一覧
+fn audio_export_candidates<E>(available_audio_codec: &[CodecImplement<AudioCodec, E>]) -> Vec<(FileFormat, AudioCodec)> {
+    let mut candidates = Vec::new();
+    for file_format in AUDIO_FILE_FORMATS {
+        for audio in available_audio_codec {
+            let candidate = (file_format, audio.codec());
+            if !candidates.contains(&candidate) && audio.handler().supports(file_format, None, Some(audio.codec())) {
+                candidates.push(candidate);
+            }
+        }
+    }
+    candidates
+}
+
 pub trait MainWindowViewModel<T> {
     fn new_project(&self);
     fn open_project(&self);
@@ -363,7 +452,20 @@ pub trait MainWindowViewModel<T> {
     fn root_component_classes<R>(&self, f: impl FnOnce(&RootComponentClassDataList<Self::RootComponentClassHandle>) -> R) -> R;
     fn select_root_component_class(&self, handle: &Self::RootComponentClassHandle);
     fn render_frame<R>(&self, f: impl FnOnce() -> R) -> R;
-    fn encode(&self);
+    fn export_candidates(&self) -> &[ExportSettings];
+    fn video_codec_options(&self, codec: VideoCodec) -> Option<CodecOptions<VideoCodec>>;
+    fn encode(&self, settings: ExportSettings, video_options: CodecOptions<VideoCodec>);
+    fn snapshot_frame(&self);
+    fn audio_export_candidates(&self) -> &[(FileFormat, AudioCodec)];
+    fn encode_audio(&self, settings: AudioExportSettings);
+    fn is_encoding(&self) -> bool;
+    fn encode_progress(&self) -> Option<EncodeProgress>;
+    fn cancel_encode(&self);
+}
+
+struct EncodeTask {
+    monitor: EncodeMonitor,
+    progress: watch::Receiver<Option<EncodeProgress>>,
 }
 
 pub struct MainWindowViewModelImpl<T: ParameterValueType, GlobalUIState, MessageHandler, Runtime> {
@@ -372,6 +474,10 @@ pub struct MainWindowViewModelImpl<T: ParameterValueType, GlobalUIState, Message
     global_ui_state: Arc<GlobalUIState>,
     message_router: MessageRouter<MessageHandler, Runtime>,
     selected_root_component_class: Arc<ArcSwapOption<RootComponentClassHandle<T>>>,
+    encode_task: Arc<ArcSwapOption<EncodeTask>>,
+    export_candidates: Arc<[ExportSettings]>,
+    video_codec_options: Arc<[(VideoCodec, CodecOptions<VideoCodec>)]>,
+    audio_export_candidates: Arc<[(FileFormat, AudioCodec)]>,
 }
 
 #[derive(Debug)]
@@ -380,7 +486,9 @@ pub enum Message<T: ParameterValueType> {
     SelectProject(ProjectHandle<T>),
     NewRootComponentClass,
     SelectRootComponentClass(RootComponentClassHandle<T>),
-    Encode,
+    Encode(ExportSettings, CodecOptions<VideoCodec>),
+    SnapshotFrame,
+    EncodeAudio(AudioExportSettings),
     OpenProject,
     SaveProject,
 }
@@ -395,7 +503,9 @@ where
             Message::SelectProject(value) => Message::SelectProject(value.clone()),
             Message::NewRootComponentClass => Message::NewRootComponentClass,
             Message::SelectRootComponentClass(value) => Message::SelectRootComponentClass(value.clone()),
-            Message::Encode => Message::Encode,
+            Message::Encode(settings, video_options) => Message::Encode(*settings, video_options.clone()),
+            Message::SnapshotFrame => Message::SnapshotFrame,
+            Message::EncodeAudio(value) => Message::EncodeAudio(*value),
             Message::OpenProject => Message::OpenProject,
             Message::SaveProject => Message::SaveProject,
         }
@@ -415,7 +525,9 @@ where
             (Message::SelectProject(a), Message::SelectProject(b)) => a == b,
             (Message::NewRootComponentClass, Message::NewRootComponentClass) => true,
             (Message::SelectRootComponentClass(a), Message::SelectRootComponentClass(b)) => a == b,
-            (Message::Encode, Message::Encode) => true,
+            (Message::Encode(a, a_options), Message::Encode(b, b_options)) => a == b && a_options == b_options,
+            (Message::SnapshotFrame, Message::SnapshotFrame) => true,
+            (Message::EncodeAudio(a), Message::EncodeAudio(b)) => a == b,
             (Message::OpenProject, Message::OpenProject) => true,
             (Message::SaveProject, Message::SaveProject) => true,
             _ => unreachable!(),
@@ -448,6 +560,10 @@ where
             }
         };
         let selected_root_component_class = Arc::new(ArcSwapOption::<RootComponentClassHandle<T>>::empty());
+        let encode_task = Arc::new(ArcSwapOption::<EncodeTask>::empty());
+        let export_candidates = export_candidates(params.available_video_codec(), params.available_audio_codec()).into();
+        let video_codec_options = video_codec_options(params.available_video_codec()).into();
+        let audio_export_candidates = audio_export_candidates(params.available_audio_codec()).into();
         let update_selected_project = Arc::new(handler::handle_async::<_, P::AsyncRuntime, _, _>({
             use_arc!(projects, get_root_component_classes = params.get_root_component_classes(), root_component_classes, global_ui_state);
             move |_project| {
@@ -568,32 +684,47 @@ where
                 })
             })
             .handle(|handler| {
-                handler.filter(|message| *message == Message::Encode).handle_async_single({
+                handler.filter_map(|message| if let Message::Encode(settings, video_options) = message { Some((settings, video_options)) } else { None }).handle_async_single({
                     use_arc!(
                         selected_root_component_class,
+                        encode_task,
                         id = params.id_generator(),
                         available_video_codec = params.available_video_codec(),
                         available_audio_codec = params.available_audio_codec(),
                         encode = params.encode()
                     );
-                    move |_| {
-                        use_arc!(selected_root_component_class, id, available_video_codec, available_audio_codec, encode);
+                    move |(settings, mut video_options): (ExportSettings, CodecOptions<VideoCodec>)| {
+                        use_arc!(selected_root_component_class, encode_task, id, available_video_codec, available_audio_codec, encode);
                         async move {
                             if let Some(root_component_class) = selected_root_component_class.load().as_ref() {
-                                let video_codec = &available_video_codec[0];
-                                let audio_codec = &available_audio_codec[0];
+                                let Some((video_codec, audio_codec)) = find_codec_pair(&available_video_codec, &available_audio_codec, settings) else {
+                                    eprintln!("{:?} with {:?} and {:?} is not available", settings.file_format, settings.video_codec, settings.audio_codec);
+                                    return;
+                                };
                                 let video_codec_handler = video_codec.handler();
-                                assert!(video_codec_handler.eq(&**audio_codec.handler()));
-                                let output_file = AsyncFileDialog::new().add_filter("video", &["mp4"]).save_file().await;
+                                let output_file = AsyncFileDialog::new().add_filter("video", &[settings.file_format.extension()]).save_file().await;
                                 let Some(output_file) = output_file else {
                                     return;
                                 };
-                                let encoder = video_codec_handler.create_encoder(FileFormat::Mp4, Some((video_codec.codec(), video_codec.default_codec_options())), Some((audio_codec.codec(), audio_codec.default_codec_options())), output_file.inner());
                                 let Some(root_component_class_ref) = root_component_class.upgrade() else {
                                     return;
                                 };
+                                let (frame_rate, image_size, pixel_aspect_ratio) = {
+                                    let root_component_class = root_component_class_ref.read().await;
+                                    let item = root_component_class.get();
+                                    (item.frame_rate(), item.image_size(), item.pixel_aspect_ratio())
+                                };
+                                video_options.set_width(image_size.width);
+                                video_options.set_height(image_size.height);
+                                video_options.set_frame_rate(frame_rate.numerator(), frame_rate.denominator());
+                                video_options.set_pixel_aspect_ratio(pixel_aspect_ratio.numerator(), pixel_aspect_ratio.denominator());
+                                let encoder = video_codec_handler.create_encoder(settings.file_format, Some((video_codec.codec(), video_options)), Some((audio_codec.codec(), audio_codec.default_codec_options())), output_file.inner());
                                 let instance = root_component_class_ref.read().await.instantiate(&RootComponentClassHandle::clone(root_component_class).map(|weak| weak as _), &id).await;
-                                if let Err(err) = encode.render_and_encode(Arc::new(instance), encoder).await {
+                                let (monitor, progress) = EncodeMonitor::new();
+                                encode_task.store(Some(Arc::new(EncodeTask { monitor: monitor.clone(), progress })));
+                                let result = encode.render_and_encode(Arc::new(instance), encoder, monitor).await;
+                                encode_task.store(None);
+                                if let Err(err) = result {
                                     eprintln!("failed to encode by {err}");
                                 }
                             }
@@ -601,6 +732,73 @@ where
                     }
                 })
             })
+            .handle(|handler| {
+                handler.filter_map(|message| if let Message::EncodeAudio(settings) = message { Some(settings) } else { None }).handle_async_single({
+                    use_arc!(selected_root_component_class, encode_task, id = params.id_generator(), available_audio_codec = params.available_audio_codec(), encode = params.encode());
+                    move |settings: AudioExportSettings| {
+                        use_arc!(selected_root_component_class, encode_task, id, available_audio_codec, encode);
+                        async move {
+                            let Some(root_component_class) = selected_root_component_class.load_full() else {
+                                return;
+                            };
+                            let AudioExportSettings { file_format, codec, sample_rate, channels } = settings;
+                            let Some(audio_codec) = available_audio_codec.iter().find(|audio_codec| audio_codec.codec() == codec && audio_codec.handler().supports(file_format, None, Some(codec))) else {
+                                eprintln!("{codec:?} encoder is not available");
+                                return;
+                            };
+                            let output_file = AsyncFileDialog::new().add_filter("audio", &[file_format.extension()]).save_file().await;
+                            let Some(output_file) = output_file else {
+                                return;
+                            };
+                            let Some(root_component_class_ref) = root_component_class.upgrade() else {
+                                return;
+                            };
+                            let mut audio_options = audio_codec.default_codec_options();
+                            audio_options.set_sample_rate(sample_rate);
+                            audio_options.set_channels(channels);
+                            let encoder = audio_codec.handler().create_encoder(file_format, None, Some((codec, audio_options)), output_file.inner());
+                            let instance = root_component_class_ref.read().await.instantiate(&RootComponentClassHandle::clone(&root_component_class).map(|weak| weak as _), &id).await;
+                            let (monitor, progress) = EncodeMonitor::new();
+                            encode_task.store(Some(Arc::new(EncodeTask { monitor: monitor.clone(), progress })));
+                            let result = encode.render_and_encode(Arc::new(instance), encoder, monitor).await;
+                            encode_task.store(None);
+                            if let Err(err) = result {
+                                eprintln!("failed to encode audio by {err}");
+                            }
+                        }
+                    }
+                })
+            })
+            .handle(|handler| {
+                handler.filter(|message| *message == Message::SnapshotFrame).handle_async_single({
+                    use_arc!(selected_root_component_class, global_ui_state, id = params.id_generator(), available_video_codec = params.available_video_codec(), encode = params.encode());
+                    move |_| {
+                        use_arc!(selected_root_component_class, global_ui_state, id, available_video_codec, encode);
+                        async move {
+                            let Some(root_component_class) = selected_root_component_class.load_full() else {
+                                return;
+                            };
+                            let Some(png_codec) = available_video_codec.iter().find(|codec| codec.codec() == VideoCodec::Png) else {
+                                eprintln!("png encoder is not available");
+                                return;
+                            };
+                            let at = TimelineTime::new(global_ui_state.seek().value());
+                            let output_file = AsyncFileDialog::new().add_filter("image", &["png"]).save_file().await;
+                            let Some(output_file) = output_file else {
+                                return;
+                            };
+                            let Some(root_component_class_ref) = root_component_class.upgrade() else {
+                                return;
+                            };
+                            let encoder = png_codec.handler().create_encoder(FileFormat::Png, Some((png_codec.codec(), png_codec.default_codec_options())), None, output_file.inner());
+                            let instance = root_component_class_ref.read().await.instantiate(&RootComponentClassHandle::clone(&root_component_class).map(|weak| weak as _), &id).await;
+                            if let Err(err) = encode.render_frame_and_encode(Arc::new(instance), at, encoder).await {
+                                eprintln!("failed to snapshot frame by {err}");
+                            }
+                        }
+                    }
+                })
+            })
             .handle(|handler| {
                 handler
                     .filter(|message| *message == Message::OpenProject)
@@ -658,6 +856,10 @@ where
             global_ui_state: Arc::clone(global_ui_state),
             message_router,
             selected_root_component_class,
+            encode_task,
+            export_candidates,
+            video_codec_options,
+            audio_export_candidates,
         });
         global_ui_state.register_global_ui_event_handler(Arc::clone(&arc));
         arc
@@ -714,7 +916,41 @@ where
         ret
     }
 
-    fn encode(&self) {
-        self.message_router.handle(Message::Encode);
+    fn export_candidates(&self) -> &[ExportSettings] {
+        &self.export_candidates
+    }
+
+    fn video_codec_options(&self, codec: VideoCodec) -> Option<CodecOptions<VideoCodec>> {
+        self.video_codec_options.iter().find(|&&(c, _)| c == codec).map(|(_, options)| options.clone())
+    }
+
+    fn encode(&self, settings: ExportSettings, video_options: CodecOptions<VideoCodec>) {
+        self.message_router.handle(Message::Encode(settings, video_options));
+    }
+
+    fn snapshot_frame(&self) {
+        self.message_router.handle(Message::SnapshotFrame);
+    }
+
+    fn audio_export_candidates(&self) -> &[(FileFormat, AudioCodec)] {
+        &self.audio_export_candidates
+    }
+
+    fn encode_audio(&self, settings: AudioExportSettings) {
+        self.message_router.handle(Message::EncodeAudio(settings));
+    }
+
+    fn is_encoding(&self) -> bool {
+        self.encode_task.load().is_some()
+    }
+
+    fn encode_progress(&self) -> Option<EncodeProgress> {
+        self.encode_task.load().as_ref().and_then(|task| *task.progress.borrow())
+    }
+
+    fn cancel_encode(&self) {
+        if let Some(task) = self.encode_task.load().as_ref() {
+            task.monitor.cancellation_token().cancel();
+        }
     }
 }
@@ -14,6 +14,8 @@ pub enum FileFormat {
     // Video formats
     Mp4,
     Webm,
+    Mov,
+    Mkv,
     // Audio formats
     Mp3,
     Wav,
@@ -26,11 +28,11 @@ pub enum FileFormat {
 
 impl FileFormat {
     pub fn is_video(self) -> bool {
-        matches!(self, FileFormat::Mp4 | FileFormat::Webm)
+        matches!(self, FileFormat::Mp4 | FileFormat::Webm | FileFormat::Mov | FileFormat::Mkv)
     }
 
     pub fn is_audio(self) -> bool {
-        matches!(self, FileFormat::Mp3 | FileFormat::Wav)
+        matches!(self, FileFormat::Mp3 | FileFormat::Wav | FileFormat::Flac)
     }
 
     pub fn is_image(self) -> bool {
@@ -41,6 +43,8 @@ impl FileFormat {
         match self {
             FileFormat::Mp4 => "mp4",
             FileFormat::Webm => "webm",
+            FileFormat::Mov => "mov",
+            FileFormat::Mkv => "mkv",
             FileFormat::Mp3 => "mp3",
             FileFormat::Wav => "wav",
             FileFormat::Flac => "flac",
@@ -54,6 +58,8 @@ impl FileFormat {
         match self {
             FileFormat::Mp4 => c"mp4",
             FileFormat::Webm => c"webm",
+            FileFormat::Mov => c"mov",
+            FileFormat::Mkv => c"mkv",
             FileFormat::Mp3 => c"mp3",
             FileFormat::Wav => c"wav",
             FileFormat::Flac => c"flac",
@@ -62,6 +68,21 @@ impl FileFormat {
             FileFormat::Webp => c"webp",
         }
     }
+
+    /// muxerを指定するときのフォーマット名 拡張子と異なるものがある
+    pub fn format_name(self) -> &'static str {
+        match self {
+            FileFormat::Mkv => "matroska",
+            _ => self.extension(),
+        }
+    }
+
+    pub fn format_name_c(self) -> &'static CStr {
+        match self {
+            FileFormat::Mkv => c"matroska",
+            _ => self.extension_c(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -69,7 +90,12 @@ pub enum VideoCodec {
     H264,
     H265,
     Av1,
+    Vp9,
+    ProRes,
+    Ffv1,
     Png,
+    Jpeg,
+    Webp,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -78,13 +104,35 @@ pub enum AudioCodec {
     Aac,
     Flac,
     Opus,
+    /// 16bit整数のリニアPCM
+    PcmS16,
+    /// 24bit整数のリニアPCM
+    PcmS24,
+    /// 32bit浮動小数点数のリニアPCM
+    PcmF32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AudioChannels {
+    Mono,
+    Stereo,
+}
+
+impl AudioChannels {
+    pub fn count(self) -> usize {
+        match self {
+            AudioChannels::Mono => 1,
+            AudioChannels::Stereo => 2,
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct VideoOption {
     height: u32,
     width: u32,
-    frame_rate: f64,
+    frame_rate: (u32, u32),
+    pixel_aspect_ratio: (u32, u32),
     bit_rate: usize,
     max_bit_rate: usize,
 }
@@ -94,16 +142,18 @@ impl Default for VideoOption {
         VideoOption {
             height: 1080,
             width: 1920,
-            frame_rate: 60.,
+            frame_rate: (60, 1),
+            pixel_aspect_ratio: (1, 1),
             bit_rate: 4_000_000,
             max_bit_rate: 4_000_000,
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct AudioOption {
     sample_rate: u32,
+    channels: AudioChannels,
     bit_rate: usize,
     max_bit_rate: usize,
 }
@@ -112,6 +162,7 @@ impl Default for AudioOption {
     fn default() -> Self {
         AudioOption {
             sample_rate: 44100,
+            channels: AudioChannels::Stereo,
             bit_rate: 192_000,
             max_bit_rate: 192_000,
         }
@@ -119,7 +170,7 @@ impl Default for AudioOption {
 }
 
 pub trait HasOption {
-    type Option: Debug + Default + PartialEq;
+    type Option: Debug + Clone + Default + PartialEq;
 }
 
 impl HasOption for VideoCodec {
@@ -130,7 +181,7 @@ impl HasOption for AudioCodec {
     type Option = AudioOption;
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct CodecOptions<Codec: HasOption> {
     dependent_option: Codec::Option,
     options: IndexMap<Cow<'static, str>, OptionValue>,
@@ -176,11 +227,30 @@ impl CodecOptions<VideoCodec> {
     }
 
     pub fn frame_rate(&self) -> f64 {
+        let (numerator, denominator) = self.dependent_option.frame_rate;
+        numerator as f64 / denominator as f64
+    }
+
+    /// フレームレートを `(分子, 分母)` で返す
+    pub fn frame_rate_fraction(&self) -> (u32, u32) {
         self.dependent_option.frame_rate
     }
 
-    pub fn set_frame_rate(&mut self, frame_rate: f64) {
-        self.dependent_option.frame_rate = frame_rate;
+    pub fn set_frame_rate(&mut self, numerator: u32, denominator: u32) {
+        assert_ne!(numerator, 0);
+        assert_ne!(denominator, 0);
+        self.dependent_option.frame_rate = (numerator, denominator);
+    }
+
+    /// ピクセルアスペクト比を `(分子, 分母)` で返す
+    pub fn pixel_aspect_ratio(&self) -> (u32, u32) {
+        self.dependent_option.pixel_aspect_ratio
+    }
+
+    pub fn set_pixel_aspect_ratio(&mut self, numerator: u32, denominator: u32) {
+        assert_ne!(numerator, 0);
+        assert_ne!(denominator, 0);
+        self.dependent_option.pixel_aspect_ratio = (numerator, denominator);
     }
 
     pub fn bit_rate(&self) -> usize {
@@ -209,6 +279,14 @@ impl CodecOptions<AudioCodec> {
         self.dependent_option.sample_rate = sample_rate;
     }
 
+    pub fn channels(&self) -> AudioChannels {
+        self.dependent_option.channels
+    }
+
+    pub fn set_channels(&mut self, channels: AudioChannels) {
+        self.dependent_option.channels = channels;
+    }
+
     pub fn bit_rate(&self) -> usize {
         self.dependent_option.bit_rate
     }
@@ -0,0 +1,209 @@
+use crate::{as_dictionary, copy_rgba_rows, find_video_encoder, EncoderMessage, FfmpegEncoder, FfmpegError, ImageReadback, AUDIO_QUEUE_CAPACITY, FRAME_QUEUE_CAPACITY};
+use ffmpeg_next::format::Pixel;
+use ffmpeg_next::software::scaling;
+use ffmpeg_next::{frame, Codec, Packet, Rational};
+use mpdelta_core_audio::AudioType;
+use mpdelta_ffmpeg::codec::{codec_supported_pixel_format, new_codec_context_from_codec};
+use mpdelta_multimedia::options_value::{OptionValue, ValueWithDefault};
+use mpdelta_multimedia::{CodecOptions, FileFormat, VideoCodec};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::mpsc::Receiver;
+use std::sync::{mpsc, Arc};
+
+/// ffmpegには渡さず、連番の開始番号に使うオプション
+pub(crate) const START_NUMBER_OPTION: &str = "start_number";
+
+/// ffmpegのimage2と同じく、開始番号の既定値は1
+const DEFAULT_START_NUMBER: u64 = 1;
+
+/// 画像フォーマットに対応する動画コーデック
+pub(crate) fn image_codec(file_format: FileFormat) -> Option<VideoCodec> {
+    match file_format {
+        FileFormat::Png => Some(VideoCodec::Png),
+        FileFormat::Jpeg => Some(VideoCodec::Jpeg),
+        FileFormat::Webp => Some(VideoCodec::Webp),
+        _ => None,
+    }
+}
+
+/// 画像の出力先
+///
+/// ファイル名が`frame_%05d.png`のように`%d`, `%0Nd`を含む場合は連番画像として、含まない場合は1枚の静止画として書き出す
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ImageSequencePattern {
+    directory: PathBuf,
+    prefix: String,
+    suffix: String,
+    digits: Option<usize>,
+}
+
+impl ImageSequencePattern {
+    pub(crate) fn new(path: &Path) -> ImageSequencePattern {
+        let directory = path.parent().map_or_else(PathBuf::new, Path::to_path_buf);
+        let file_name = path.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        match parse_number_placeholder(&file_name) {
+            Some((begin, end, digits)) => ImageSequencePattern {
+                directory,
+                prefix: file_name[..begin].to_owned(),
+                suffix: file_name[end..].to_owned(),
+                digits: Some(digits),
+            },
+            None => ImageSequencePattern {
+                directory,
+                prefix: file_name,
+                suffix: String::new(),
+                digits: None,
+            },
+        }
+    }
+
+    pub(crate) fn is_sequence(&self) -> bool {
+        self.digits.is_some()
+    }
+
+    pub(crate) fn path(&self, number: u64) -> PathBuf {
+        match self.digits {
+            Some(digits) => self.directory.join(format!("{}{number:0digits$}{}", self.prefix, self.suffix)),
+            None => self.directory.join(&self.prefix),
+        }
+    }
+}
+
+/// `%d`, `%05d`の開始位置、終了位置と桁数を返す
+fn parse_number_placeholder(file_name: &str) -> Option<(usize, usize, usize)> {
+    let begin = file_name.find('%')?;
+    let rest = &file_name[begin + 1..];
+    let digits_len = rest.bytes().take_while(u8::is_ascii_digit).count();
+    if rest.as_bytes().get(digits_len) != Some(&b'd') {
+        return None;
+    }
+    let digits = if digits_len == 0 { 0 } else { rest[..digits_len].parse().ok()? };
+    Some((begin, begin + digits_len + 2, digits))
+}
+
+/// アルファチャンネルを保持できるものを優先してピクセルフォーマットを選ぶ
+fn select_image_pixel_format(codec: &Codec) -> Pixel {
+    let formats: Vec<Pixel> = codec_supported_pixel_format(codec).map_or_else(Vec::new, |iter| iter.collect());
+    [Pixel::RGBA, Pixel::YUVA420P].into_iter().find(|format| formats.contains(format)).or_else(|| formats.first().copied()).unwrap_or(Pixel::RGBA)
+}
+
+pub(crate) fn build<R: ImageReadback>(readback: R, video: Option<(VideoCodec, CodecOptions<VideoCodec>)>, pattern: ImageSequencePattern) -> Result<FfmpegEncoder<R::Image>, FfmpegError> {
+    let (codec, options) = video.ok_or(FfmpegError::MissingVideoStream)?;
+    let codec = find_video_encoder(codec).ok_or(ffmpeg_next::Error::EncoderNotFound)?;
+    let start_number = match options.options().get(START_NUMBER_OPTION) {
+        Some(&OptionValue::Int { value: ValueWithDefault::Value(start_number), .. }) => start_number.max(0) as u64,
+        _ => DEFAULT_START_NUMBER,
+    };
+    let (image_sender, image_receiver) = mpsc::sync_channel(FRAME_QUEUE_CAPACITY);
+    let (audio_sender, audio_receiver) = mpsc::sync_channel(AUDIO_QUEUE_CAPACITY);
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let handle = std::thread::spawn(image_encode_thread(readback, codec, options, pattern, start_number, image_receiver, audio_receiver, Arc::clone(&cancelled)));
+    // 中断しても書き出し済みの画像はそのまま残す
+    Ok(FfmpegEncoder {
+        requires_image: true,
+        requires_audio: false,
+        image_sender,
+        audio_sender,
+        handle: Some(handle),
+        cancelled,
+        audio_progress: Default::default(),
+        output_path: None,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn image_encode_thread<R: ImageReadback>(
+    mut readback: R,
+    codec: Codec,
+    options: CodecOptions<VideoCodec>,
+    pattern: ImageSequencePattern,
+    start_number: u64,
+    image_receiver: Receiver<EncoderMessage<R::Image>>,
+    audio_receiver: Receiver<EncoderMessage<AudioType>>,
+    cancelled: Arc<AtomicBool>,
+) -> impl FnOnce() -> Result<(), FfmpegError> + Send + 'static {
+    move || {
+        let mut number = start_number;
+        while let Ok(EncoderMessage::Push(image)) = image_receiver.recv() {
+            // 静止画は最初の1枚だけ書き出す
+            if (!pattern.is_sequence() && number > start_number) || cancelled.load(atomic::Ordering::Acquire) {
+                continue;
+            }
+            let [width, height] = readback.extent(&image);
+            let mut rgba_frame = frame::Video::new(Pixel::RGBA, width, height);
+            readback.read(image, |pixels| copy_rgba_rows(&mut rgba_frame, pixels))?;
+            let encoded = encode_image(codec, &options, &rgba_frame)?;
+            std::fs::write(pattern.path(number), encoded)?;
+            number += 1;
+        }
+        // finishで送られてくるFinishを受け取ってから終了する
+        let _ = audio_receiver.recv();
+        Ok(())
+    }
+}
+
+fn encode_image(codec: Codec, options: &CodecOptions<VideoCodec>, rgba_frame: &frame::Video) -> Result<Vec<u8>, ffmpeg_next::Error> {
+    let (width, height) = (rgba_frame.width(), rgba_frame.height());
+    let format = select_image_pixel_format(&codec);
+    let mut encoder = new_codec_context_from_codec(codec).encoder().video()?;
+    encoder.set_width(width);
+    encoder.set_height(height);
+    encoder.set_format(format);
+    encoder.set_time_base(Rational::new(1, 1));
+    let mut encoder = encoder.open_as_with(codec, as_dictionary(options.options()))?;
+    let mut native_format_frame = frame::Video::new(format, width, height);
+    scaling::Context::get(Pixel::RGBA, width, height, format, width, height, scaling::Flags::BILINEAR)?.run(rgba_frame, &mut native_format_frame)?;
+    native_format_frame.set_pts(Some(0));
+    encoder.send_frame(&native_format_frame)?;
+    encoder.send_eof()?;
+    let mut encoded = Vec::new();
+    let mut packet = Packet::empty();
+    while encoder.receive_packet(&mut packet).is_ok() {
+        encoded.extend_from_slice(packet.data().unwrap_or_default());
+    }
+    Ok(encoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_sequence_pattern() {
+        let pattern = ImageSequencePattern::new(Path::new("out/frame_%05d.png"));
+        assert!(pattern.is_sequence());
+        assert_eq!(pattern.path(1), Path::new("out/frame_00001.png"));
+        assert_eq!(pattern.path(123456), Path::new("out/frame_123456.png"));
+
+        let pattern = ImageSequencePattern::new(Path::new("%d.jpeg"));
+        assert!(pattern.is_sequence());
+        assert_eq!(pattern.path(0), Path::new("0.jpeg"));
+        assert_eq!(pattern.path(42), Path::new("42.jpeg"));
+
+        let pattern = ImageSequencePattern::new(Path::new("out/still.png"));
+        assert!(!pattern.is_sequence());
+        assert_eq!(pattern.path(1), Path::new("out/still.png"));
+        assert_eq!(pattern.path(2), Path::new("out/still.png"));
+
+        // %の後ろがdでなければただの文字として扱う
+        let pattern = ImageSequencePattern::new(Path::new("100%_05x.png"));
+        assert!(!pattern.is_sequence());
+        assert_eq!(pattern.path(1), Path::new("100%_05x.png"));
+    }
+
+    #[test]
+    fn test_encode_image() {
+        ffmpeg_next::init().unwrap();
+        let mut rgba_frame = frame::Video::new(Pixel::RGBA, 33, 17);
+        let stride = rgba_frame.stride(0);
+        for (i, pixel) in rgba_frame.data_mut(0).chunks_exact_mut(4).enumerate() {
+            let x = i % (stride / 4);
+            pixel.copy_from_slice(&[(x * 7) as u8, 128, 255, 200]);
+        }
+        let png = encode_image(find_video_encoder(VideoCodec::Png).unwrap(), &CodecOptions::new(Default::default()), &rgba_frame).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        let jpeg = encode_image(find_video_encoder(VideoCodec::Jpeg).unwrap(), &CodecOptions::new(Default::default()), &rgba_frame).unwrap();
+        assert_eq!(&jpeg[..2], b"\xff\xd8");
+    }
+}
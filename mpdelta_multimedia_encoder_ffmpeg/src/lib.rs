@@ -1,3 +1,4 @@
+use crate::image_sequence::{ImageSequencePattern, START_NUMBER_OPTION};
 use dashmap::DashMap;
 use ffmpeg_next::codec::{Capabilities, Id};
 use ffmpeg_next::encoder::{audio, video};
@@ -7,62 +8,206 @@ use ffmpeg_next::software::{resampling, scaling};
 use ffmpeg_next::{codec, encoder, format, frame, ChannelLayout, Codec, Dictionary, Packet, Rational};
 use indexmap::IndexMap;
 use mpdelta_core::common::mixed_fraction::MixedFraction;
+use mpdelta_core::encode::{EncodeMonitor, EncodePhase, EncodeProgress};
 use mpdelta_core::time::TimelineTime;
 use mpdelta_core_audio::multi_channel_audio::{MultiChannelAudio, MultiChannelAudioMutOp, MultiChannelAudioOp};
-use mpdelta_core_audio::{AudioProvider, AudioType};
-use mpdelta_core_vulkano::ImageType;
+use mpdelta_core_audio::{AudioProvider, AudioType, TrimmedAudio};
 use mpdelta_dsp::Resample;
-use mpdelta_ffmpeg::codec::{codec_supported_pixel_format, codec_supported_sample_format, codec_supported_sample_rate, new_codec_context_from_codec};
+use mpdelta_ffmpeg::codec::{codec_supported_pixel_format, codec_supported_sample_format, codec_supported_sample_rate, new_codec_context_from_codec, pixel_format_bit_depth};
 use mpdelta_ffmpeg::io::FfmpegIoError;
-use mpdelta_multimedia::options_value::{OptionValue, ValueTypeString, ValueWithDefault};
-use mpdelta_multimedia::{AudioCodec, CodecImplement, CodecOptions, FileFormat, MediaCodecImplementHandle, VideoCodec};
+use mpdelta_multimedia::options_value::{OptionValue, ValueTypeF64, ValueTypeI64, ValueTypeString, ValueWithDefault};
+use mpdelta_multimedia::{AudioChannels, AudioCodec, CodecImplement, CodecOptions, FileFormat, MediaCodecImplementHandle, VideoCodec};
 use mpdelta_renderer::{VideoEncoder, VideoEncoderBuilder, VideoEncoderBuilderDyn};
 use std::borrow::Cow;
 use std::fmt::Debug;
 use std::fs::{File, OpenOptions};
 use std::io::{Seek, Write};
-use std::ops::ControlFlow;
-use std::path::Path;
+use std::ops::{ControlFlow, Range, RangeInclusive};
+use std::path::{Path, PathBuf};
 use std::ptr;
+use std::sync::atomic::{self, AtomicBool, AtomicU64};
 use std::sync::mpsc::Receiver;
-use std::sync::{mpsc, Arc, LazyLock};
+use std::sync::{mpsc, Arc, LazyLock, OnceLock};
 use std::thread::JoinHandle;
+use std::time::Instant;
 use thiserror::Error;
-use vulkano::buffer::{BufferCreateInfo, BufferUsage};
-use vulkano::command_buffer::allocator::{CommandBufferAllocator, StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo};
-use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, CopyImageToBufferInfo, PrimaryCommandBufferAbstract};
 use vulkano::device::{Device, Queue};
-use vulkano::memory::allocator::{AllocationCreateInfo, MemoryAllocator, MemoryTypeFilter, StandardMemoryAllocator};
-use vulkano::sync::GpuFuture;
+use vulkano::memory::allocator::StandardMemoryAllocator;
 
-pub struct FfmpegEncoderBuilder {
-    gpu_context: GpuContext,
+pub use readback::{CpuReadback, ImageReadback, VulkanoReadback};
+
+mod image_sequence;
+mod readback;
+
+pub struct FfmpegEncoderBuilder<R = VulkanoReadback> {
+    readback: R,
 }
 
 impl FfmpegEncoderBuilder {
     pub fn new(device: Arc<Device>, queue: Arc<Queue>, memory_allocator: Arc<StandardMemoryAllocator>) -> FfmpegEncoderBuilder {
         FfmpegEncoderBuilder {
-            gpu_context: GpuContext { device, queue, memory_allocator },
+            readback: VulkanoReadback::new(device, queue, memory_allocator),
         }
     }
+}
 
-    pub fn available_video_codec<Encoder: From<FfmpegEncodeSettings<File>>>(self: &Arc<Self>) -> impl IntoIterator<Item = CodecImplement<VideoCodec, Encoder>> {
-        [CodecImplement::new(
-            VideoCodec::H264,
-            IndexMap::from([(
-                Cow::Borrowed("profile"),
-                OptionValue::String {
-                    value: ValueWithDefault::Value(String::from("high")),
-                    ty: ValueTypeString::Candidates(Arc::new([Cow::Borrowed("baseline"), Cow::Borrowed("main"), Cow::Borrowed("high"), Cow::Borrowed("high10"), Cow::Borrowed("high422"), Cow::Borrowed("high444")])),
-                },
-            )]),
-            Arc::clone(self) as Arc<dyn MediaCodecImplementHandle<Encoder>>,
-        )]
+impl FfmpegEncoderBuilder<CpuReadback> {
+    /// GPUを使わずに描画した画像をエンコードする
+    pub fn new_cpu() -> FfmpegEncoderBuilder<CpuReadback> {
+        FfmpegEncoderBuilder { readback: CpuReadback }
     }
+}
 
-    pub fn available_audio_codec<Encoder: From<FfmpegEncodeSettings<File>>>(self: &Arc<Self>) -> impl IntoIterator<Item = CodecImplement<AudioCodec, Encoder>> {
-        [CodecImplement::new(AudioCodec::Aac, IndexMap::new(), Arc::clone(self) as Arc<dyn MediaCodecImplementHandle<Encoder>>)]
+impl<R: ImageReadback> FfmpegEncoderBuilder<R> {
+    pub fn available_video_codec<Encoder: From<FfmpegEncodeSettings<File, R>>>(self: &Arc<Self>) -> impl IntoIterator<Item = CodecImplement<VideoCodec, Encoder>> {
+        // GUIは先頭のものをデフォルトとして使うので、H264を先頭に置く
+        [
+            (
+                VideoCodec::H264,
+                IndexMap::from([
+                    string_option("profile", ValueWithDefault::Value(String::from("high")), &["baseline", "main", "high", "high10", "high422", "high444"]),
+                    x26x_preset_option(),
+                    string_option("tune", ValueWithDefault::Default, &["film", "animation", "grain", "stillimage", "fastdecode", "zerolatency"]),
+                    int_option("crf", 0..=51),
+                ]),
+            ),
+            (
+                VideoCodec::H265,
+                IndexMap::from([
+                    string_option("profile", ValueWithDefault::Default, &["main", "main10", "main12", "main422-10", "main444-8", "main444-10"]),
+                    x26x_preset_option(),
+                    string_option("tune", ValueWithDefault::Default, &["psnr", "ssim", "grain", "zerolatency", "fastdecode", "animation"]),
+                    int_option("crf", 0..=51),
+                    bit_depth_option(&[8, 10, 12]),
+                ]),
+            ),
+            (VideoCodec::Av1, IndexMap::from([int_option("crf", 0..=63), int_option("cpu-used", 0..=8), bit_depth_option(&[8, 10])])),
+            (
+                VideoCodec::Vp9,
+                IndexMap::from([string_option("deadline", ValueWithDefault::Default, &["best", "good", "realtime"]), int_option("crf", 0..=63), int_option("cpu-used", -8..=8), bit_depth_option(&[8, 10, 12])]),
+            ),
+            (VideoCodec::ProRes, IndexMap::from([string_option("profile", ValueWithDefault::Value(String::from("hq")), &["proxy", "lt", "standard", "hq", "4444", "4444xq"])])),
+            (
+                VideoCodec::Ffv1,
+                IndexMap::from([
+                    (
+                        Cow::Borrowed("level"),
+                        OptionValue::Int {
+                            value: ValueWithDefault::Value(3),
+                            ty: ValueTypeI64::Candidates(Arc::new([1, 3])),
+                        },
+                    ),
+                    (Cow::Borrowed("slicecrc"), OptionValue::Bool { value: ValueWithDefault::Default }),
+                    bit_depth_option(&[8, 10, 12, 16]),
+                ]),
+            ),
+            (VideoCodec::Png, IndexMap::from([start_number_option(), int_option("compression_level", 0..=9)])),
+            (VideoCodec::Jpeg, IndexMap::from([start_number_option()])),
+            (
+                VideoCodec::Webp,
+                IndexMap::from([
+                    start_number_option(),
+                    (
+                        Cow::Borrowed("lossless"),
+                        OptionValue::Int {
+                            value: ValueWithDefault::Default,
+                            ty: ValueTypeI64::Candidates(Arc::new([0, 1])),
+                        },
+                    ),
+                    (
+                        Cow::Borrowed("quality"),
+                        OptionValue::Float {
+                            value: ValueWithDefault::Default,
+                            ty: ValueTypeF64::from(0.0..=100.0),
+                        },
+                    ),
+                ]),
+            ),
+        ]
+        .into_iter()
+        .filter(|&(codec, _)| find_video_encoder(codec).is_some())
+        .map(|(codec, options)| CodecImplement::new(codec, options, Arc::clone(self) as Arc<dyn MediaCodecImplementHandle<Encoder>>))
+        .collect::<Vec<_>>()
     }
+
+    pub fn available_audio_codec<Encoder: From<FfmpegEncodeSettings<File, R>>>(self: &Arc<Self>) -> impl IntoIterator<Item = CodecImplement<AudioCodec, Encoder>> {
+        [
+            (AudioCodec::Aac, IndexMap::new()),
+            (
+                AudioCodec::Opus,
+                IndexMap::from([
+                    string_option("vbr", ValueWithDefault::Default, &["off", "on", "constrained"]),
+                    string_option("application", ValueWithDefault::Default, &["voip", "audio", "lowdelay"]),
+                    int_option("compression_level", 0..=10),
+                ]),
+            ),
+            (AudioCodec::Flac, IndexMap::from([int_option("compression_level", 0..=12)])),
+            (AudioCodec::Mp3, IndexMap::from([int_option("compression_level", 0..=9)])),
+            (AudioCodec::PcmS16, IndexMap::new()),
+            (AudioCodec::PcmS24, IndexMap::new()),
+            (AudioCodec::PcmF32, IndexMap::new()),
+        ]
+        .into_iter()
+        .filter(|&(codec, _)| find_audio_encoder(codec).is_some())
+        .map(|(codec, options)| CodecImplement::new(codec, options, Arc::clone(self) as Arc<dyn MediaCodecImplementHandle<Encoder>>))
+        .collect::<Vec<_>>()
+    }
+}
+
+/// ffmpegには渡さず、エンコーダのピクセルフォーマットの選択に使うオプション
+const BIT_DEPTH_OPTION: &str = "bit_depth";
+
+fn string_option(key: &'static str, value: ValueWithDefault<String>, candidates: &[&'static str]) -> (Cow<'static, str>, OptionValue) {
+    (
+        Cow::Borrowed(key),
+        OptionValue::String {
+            value,
+            ty: ValueTypeString::Candidates(candidates.iter().copied().map(Cow::Borrowed).collect()),
+        },
+    )
+}
+
+fn int_option(key: &'static str, range: RangeInclusive<i64>) -> (Cow<'static, str>, OptionValue) {
+    (
+        Cow::Borrowed(key),
+        OptionValue::Int {
+            value: ValueWithDefault::Default,
+            ty: ValueTypeI64::from(range),
+        },
+    )
+}
+
+fn x26x_preset_option() -> (Cow<'static, str>, OptionValue) {
+    string_option("preset", ValueWithDefault::Default, &["ultrafast", "superfast", "veryfast", "faster", "fast", "medium", "slow", "slower", "veryslow", "placebo"])
+}
+
+fn start_number_option() -> (Cow<'static, str>, OptionValue) {
+    (
+        Cow::Borrowed(START_NUMBER_OPTION),
+        OptionValue::Int {
+            value: ValueWithDefault::Default,
+            ty: ValueTypeI64::from(0..),
+        },
+    )
+}
+
+fn bit_depth_option(candidates: &[i64]) -> (Cow<'static, str>, OptionValue) {
+    (
+        Cow::Borrowed(BIT_DEPTH_OPTION),
+        OptionValue::Int {
+            value: ValueWithDefault::Default,
+            ty: ValueTypeI64::Candidates(Arc::from(candidates)),
+        },
+    )
+}
+
+/// エンコーダが対応するピクセルフォーマットのうち、`bit_depth`が指定されていればそれに合うものを選ぶ
+fn select_pixel_format(codec: &Codec, bit_depth: Option<i64>) -> Pixel {
+    let formats: Vec<Pixel> = codec_supported_pixel_format(codec).map_or_else(Vec::new, |iter| iter.collect());
+    bit_depth
+        .and_then(|bit_depth| formats.iter().copied().find(|&format| pixel_format_bit_depth(format).is_some_and(|depth| i64::from(depth) == bit_depth)))
+        .or_else(|| formats.first().copied())
+        .unwrap_or(Pixel::RGBA)
 }
 
 fn find_video_encoder(video: VideoCodec) -> Option<Codec> {
@@ -70,7 +215,12 @@ fn find_video_encoder(video: VideoCodec) -> Option<Codec> {
         VideoCodec::H264 => encoder::find(Id::H264),
         VideoCodec::H265 => encoder::find(Id::H265),
         VideoCodec::Av1 => encoder::find(Id::AV1),
+        VideoCodec::Vp9 => encoder::find(Id::VP9),
+        VideoCodec::ProRes => encoder::find(Id::PRORES),
+        VideoCodec::Ffv1 => encoder::find(Id::FFV1),
         VideoCodec::Png => encoder::find(Id::PNG),
+        VideoCodec::Jpeg => encoder::find(Id::MJPEG),
+        VideoCodec::Webp => encoder::find_by_name("libwebp"),
     }
 }
 
@@ -80,12 +230,22 @@ fn find_audio_encoder(audio: AudioCodec) -> Option<Codec> {
         AudioCodec::Aac => encoder::find(Id::AAC),
         AudioCodec::Flac => encoder::find(Id::FLAC),
         AudioCodec::Opus => encoder::find(Id::OPUS),
+        AudioCodec::PcmS16 => encoder::find(Id::PCM_S16LE),
+        AudioCodec::PcmS24 => encoder::find(Id::PCM_S24LE),
+        AudioCodec::PcmF32 => encoder::find(Id::PCM_F32LE),
+    }
+}
+
+fn channel_layout(channels: AudioChannels) -> ChannelLayout {
+    match channels {
+        AudioChannels::Mono => ChannelLayout::MONO,
+        AudioChannels::Stereo => ChannelLayout::STEREO,
     }
 }
 
 fn as_dictionary(value: &IndexMap<Cow<'static, str>, OptionValue>) -> Dictionary<'static> {
     let mut dictionary = Dictionary::new();
-    for (key, value) in value {
+    for (key, value) in value.iter().filter(|&(key, _)| key != BIT_DEPTH_OPTION && key != START_NUMBER_OPTION) {
         match value {
             OptionValue::Bool { value: ValueWithDefault::Default } | OptionValue::Int { value: ValueWithDefault::Default, .. } | OptionValue::Float { value: ValueWithDefault::Default, .. } | OptionValue::String { value: ValueWithDefault::Default, .. } => {}
             OptionValue::Bool { value: ValueWithDefault::Value(value) } => dictionary.set(key, &value.to_string()),
@@ -97,7 +257,7 @@ fn as_dictionary(value: &IndexMap<Cow<'static, str>, OptionValue>) -> Dictionary
     dictionary
 }
 
-impl<Encoder: From<FfmpegEncodeSettings<File>>> MediaCodecImplementHandle<Encoder> for FfmpegEncoderBuilder {
+impl<Encoder: From<FfmpegEncodeSettings<File, R>>, R: ImageReadback> MediaCodecImplementHandle<Encoder> for FfmpegEncoderBuilder<R> {
     fn eq(&self, rhs: &dyn MediaCodecImplementHandle<Encoder>) -> bool {
         ptr::addr_eq(self, rhs)
     }
@@ -106,6 +266,9 @@ impl<Encoder: From<FfmpegEncodeSettings<File>>> MediaCodecImplementHandle<Encode
         if video.is_none() && audio.is_none() {
             return false;
         }
+        if file_format.is_image() {
+            return audio.is_none() && video.is_some_and(|video| image_sequence::image_codec(file_format) == Some(video) && find_video_encoder(video).is_some());
+        }
 
         static CACHE_VIDEO: LazyLock<DashMap<(FileFormat, VideoCodec), bool>> = LazyLock::new(Default::default);
         static CACHE_AUDIO: LazyLock<DashMap<(FileFormat, AudioCodec), bool>> = LazyLock::new(Default::default);
@@ -136,31 +299,42 @@ impl<Encoder: From<FfmpegEncodeSettings<File>>> MediaCodecImplementHandle<Encode
 
     fn create_encoder(&self, file_format: FileFormat, video: Option<(VideoCodec, CodecOptions<VideoCodec>)>, audio: Option<(AudioCodec, CodecOptions<AudioCodec>)>, output: &Path) -> Encoder {
         assert!(MediaCodecImplementHandle::<Encoder>::supports(self, file_format, video.as_ref().map(|&(codec, _)| codec), audio.as_ref().map(|&(codec, _)| codec)));
-        let output = OpenOptions::new().write(true).create(true).truncate(true).open(output).unwrap();
-        output.set_len(0).unwrap();
+        let output_path = output.to_path_buf();
+        let output = if file_format.is_image() {
+            EncodeOutput::Image(ImageSequencePattern::new(output))
+        } else {
+            match OpenOptions::new().write(true).create(true).truncate(true).open(output).and_then(|output| output.set_len(0).map(|()| output)) {
+                Ok(output) => EncodeOutput::Stream(output),
+                Err(err) => EncodeOutput::OpenFailed(err),
+            }
+        };
         Encoder::from(FfmpegEncodeSettings {
-            gpu_context: self.gpu_context.clone(),
+            readback: self.readback.clone(),
             file_format,
             video,
             audio,
             output: Some(output),
+            output_path: Some(output_path),
         })
     }
 }
 
-#[derive(Debug, Clone)]
-struct GpuContext {
-    device: Arc<Device>,
-    queue: Arc<Queue>,
-    memory_allocator: Arc<StandardMemoryAllocator>,
+/// 画像フォーマットへの出力はファイルを開かず、パスから連番の出力先を決める
+enum EncodeOutput<Output> {
+    Stream(Output),
+    Image(ImageSequencePattern),
+    /// 出力先を開けなかった buildでエラーとして返す
+    OpenFailed(std::io::Error),
 }
 
-pub struct FfmpegEncodeSettings<Output> {
-    gpu_context: GpuContext,
+pub struct FfmpegEncodeSettings<Output, R = VulkanoReadback> {
+    readback: R,
     file_format: FileFormat,
     video: Option<(VideoCodec, CodecOptions<VideoCodec>)>,
     audio: Option<(AudioCodec, CodecOptions<AudioCodec>)>,
-    output: Option<Output>,
+    output: Option<EncodeOutput<Output>>,
+    /// 中断時に削除するファイル
+    output_path: Option<PathBuf>,
 }
 
 #[derive(Debug, Error)]
@@ -169,23 +343,58 @@ pub enum FfmpegError {
     Ffmpeg(#[from] ffmpeg_next::Error),
     #[error("{0}")]
     FfmpegIo(#[from] FfmpegIoError),
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("image output requires a video stream")]
+    MissingVideoStream,
+    #[error("encoder thread panicked")]
+    EncoderThreadPanicked,
+    #[error("pixel aspect ratio {0}/{1} is out of range")]
+    PixelAspectRatioOutOfRange(u32, u32),
+    #[error("failed to remove partial output {}: {1}", .0.display())]
+    RemovePartialOutput(PathBuf, std::io::Error),
+    #[error("failed to read back the rendered image: {0}")]
+    Readback(Box<dyn std::error::Error + Send + Sync>),
 }
 
-impl<Output> VideoEncoderBuilder<ImageType, AudioType> for FfmpegEncodeSettings<Output>
+impl FfmpegError {
+    fn readback(err: impl std::error::Error + Send + Sync + 'static) -> FfmpegError {
+        FfmpegError::Readback(Box::new(err))
+    }
+}
+
+impl<Output, R> VideoEncoderBuilder<R::Image, AudioType> for FfmpegEncodeSettings<Output, R>
 where
     Output: Write + Seek + Send + Sync + 'static,
+    R: ImageReadback,
 {
     type Err = FfmpegError;
-    type Encoder = FfmpegEncoder;
+    type Encoder = FfmpegEncoder<R::Image>;
 
     fn build(&mut self) -> Result<Self::Encoder, Self::Err> {
-        let FfmpegEncodeSettings { gpu_context, file_format, video, audio, output } = self;
-        let mut output = mpdelta_ffmpeg::io::Output::builder().file_type(file_format.extension()).build(output.take().unwrap())?;
+        let FfmpegEncodeSettings {
+            readback,
+            file_format,
+            video,
+            audio,
+            output,
+            output_path,
+        } = self;
+        let output = match output.take().unwrap() {
+            EncodeOutput::Stream(output) => output,
+            EncodeOutput::Image(pattern) => return image_sequence::build(readback.clone(), video.take(), pattern),
+            EncodeOutput::OpenFailed(err) => return Err(err.into()),
+        };
+        let mut output = mpdelta_ffmpeg::io::Output::builder().file_type(file_format.format_name()).build(output)?;
         let global_header = output.format().flags().contains(format::Flags::GLOBAL_HEADER);
         let mut video_stream = None;
         if let Some((codec, options)) = video.take() {
             let codec = find_video_encoder(codec).unwrap();
-            let format = codec_supported_pixel_format(&codec).and_then(|mut iter| iter.next()).unwrap_or(Pixel::RGBA);
+            let bit_depth = match options.options().get(BIT_DEPTH_OPTION) {
+                Some(&OptionValue::Int { value: ValueWithDefault::Value(bit_depth), .. }) => Some(bit_depth),
+                _ => None,
+            };
+            let format = select_pixel_format(&codec, bit_depth);
             let mut ost = output.add_stream(codec)?;
             let mut encoder = new_codec_context_from_codec(codec).encoder().video().unwrap();
             encoder.set_parameters(ost.parameters()).unwrap();
@@ -194,10 +403,16 @@ where
             encoder.set_height(options.height());
             encoder.set_width(options.width());
             encoder.set_format(format);
-            let frame_rate: Rational = options.frame_rate().into();
+            let (frame_rate_numerator, frame_rate_denominator) = options.frame_rate_fraction();
+            let frame_rate = Rational::new(frame_rate_numerator as i32, frame_rate_denominator as i32);
             encoder.set_frame_rate(Some(frame_rate));
             encoder.set_time_base(frame_rate.invert());
             encoder.set_gop((options.frame_rate() * 10.).floor() as u32);
+            let (pixel_aspect_ratio_numerator, pixel_aspect_ratio_denominator) = options.pixel_aspect_ratio();
+            let (Ok(numerator), Ok(denominator)) = (i32::try_from(pixel_aspect_ratio_numerator), i32::try_from(pixel_aspect_ratio_denominator)) else {
+                return Err(FfmpegError::PixelAspectRatioOutOfRange(pixel_aspect_ratio_numerator, pixel_aspect_ratio_denominator));
+            };
+            encoder.set_aspect_ratio(Rational::new(numerator, denominator));
             if global_header {
                 encoder.set_flags(codec::Flags::GLOBAL_HEADER);
             }
@@ -215,7 +430,7 @@ where
             encoder.set_parameters(ost.parameters()).unwrap();
             encoder.set_format(sample_format);
             encoder.set_rate(sample_rate);
-            encoder.set_channel_layout(ChannelLayout::STEREO);
+            encoder.set_channel_layout(channel_layout(options.channels()));
             encoder.set_bit_rate(options.bit_rate());
             encoder.set_max_bit_rate(options.max_bit_rate());
             if global_header {
@@ -227,78 +442,107 @@ where
         }
         let requires_image = video_stream.is_some();
         let requires_audio = audio_stream.is_some();
-        let (image_sender, image_receiver) = mpsc::channel();
-        let (audio_sender, audio_receiver) = mpsc::channel();
-        let handle = std::thread::spawn(encode_thread(gpu_context.clone(), output, video_stream, audio_stream, image_receiver, audio_receiver));
+        let (image_sender, image_receiver) = mpsc::sync_channel(FRAME_QUEUE_CAPACITY);
+        let (audio_sender, audio_receiver) = mpsc::sync_channel(AUDIO_QUEUE_CAPACITY);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let audio_progress = Arc::new(AudioProgress::default());
+        let handle = std::thread::spawn(encode_thread(readback.clone(), output, video_stream, audio_stream, image_receiver, audio_receiver, Arc::clone(&cancelled), Arc::clone(&audio_progress)));
         Ok(FfmpegEncoder {
             requires_image,
             requires_audio,
             image_sender,
             audio_sender,
             handle: Some(handle),
+            cancelled,
+            audio_progress,
+            output_path: output_path.take(),
         })
     }
 }
 
-impl<Output> From<FfmpegEncodeSettings<Output>> for Box<dyn VideoEncoderBuilderDyn<ImageType, AudioType>>
+impl<Output, R> From<FfmpegEncodeSettings<Output, R>> for Box<dyn VideoEncoderBuilderDyn<R::Image, AudioType>>
 where
     Output: Write + Seek + Send + Sync + 'static,
+    R: ImageReadback,
 {
-    fn from(value: FfmpegEncodeSettings<Output>) -> Self {
+    fn from(value: FfmpegEncodeSettings<Output, R>) -> Self {
         Box::new(value)
     }
 }
 
-fn encode_thread<T: Write + Seek + Send + Sync + 'static>(
-    gpu_context: GpuContext,
+/// 音声のエンコードの進捗 エンコードスレッドと共有する
+#[derive(Default)]
+struct AudioProgress {
+    /// エンコードした元の音声のサンプル数
+    samples_done: AtomicU64,
+    /// `set_audio_range`で渡された範囲のサンプル数 分からなければ0
+    total_samples: AtomicU64,
+    monitor: OnceLock<(EncodeMonitor, Instant)>,
+}
+
+impl AudioProgress {
+    /// `samples`だけ進んだことを報告する 中断が要求されていればBreakを返す
+    fn advance(&self, samples: u64) -> ControlFlow<()> {
+        let samples_done = self.samples_done.fetch_add(samples, atomic::Ordering::Relaxed) + samples;
+        let Some((monitor, start)) = self.monitor.get() else {
+            return ControlFlow::Continue(());
+        };
+        if monitor.is_cancelled() {
+            return ControlFlow::Break(());
+        }
+        let total_samples = self.total_samples.load(atomic::Ordering::Relaxed);
+        monitor.report(EncodeProgress {
+            phase: EncodePhase::Audio,
+            frames_done: if total_samples == 0 { samples_done } else { samples_done.min(total_samples) },
+            total_frames: total_samples,
+            elapsed: start.elapsed(),
+        });
+        ControlFlow::Continue(())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn encode_thread<T: Write + Seek + Send + Sync + 'static, R: ImageReadback>(
+    mut readback: R,
     mut output: mpdelta_ffmpeg::io::Output<T>,
     video_stream: Option<(usize, video::Encoder, CodecOptions<VideoCodec>)>,
     audio_stream: Option<(usize, audio::Encoder, CodecOptions<AudioCodec>, bool)>,
-    image_receiver: Receiver<EncoderMessage<ImageType>>,
+    image_receiver: Receiver<EncoderMessage<R::Image>>,
     audio_receiver: Receiver<EncoderMessage<AudioType>>,
-) -> impl FnOnce() + Send + 'static {
+    cancelled: Arc<AtomicBool>,
+    audio_progress: Arc<AudioProgress>,
+) -> impl FnOnce() -> Result<(), FfmpegError> + Send + 'static {
     move || {
-        output.write_header().unwrap();
+        output.write_header()?;
         let mut video_stream = video_stream.map(|(id, mut encoder, options)| {
             let mut rgba_frame = frame::Video::new(Pixel::RGBA, options.width(), options.height());
             let mut encoder_native_format_frame = frame::Video::new(encoder.format(), options.width(), options.height());
             let mut format_conversion_context = scaling::Context::get(Pixel::RGBA, options.width(), options.height(), encoder.format(), options.width(), options.height(), scaling::Flags::FAST_BILINEAR).unwrap();
-            let buffer = vulkano::buffer::Buffer::new_slice::<[u8; 4]>(
-                Arc::clone(&gpu_context.memory_allocator) as Arc<dyn MemoryAllocator>,
-                BufferCreateInfo {
-                    usage: BufferUsage::TRANSFER_DST,
-                    ..BufferCreateInfo::default()
-                },
-                AllocationCreateInfo {
-                    memory_type_filter: MemoryTypeFilter::HOST_RANDOM_ACCESS,
-                    ..AllocationCreateInfo::default()
-                },
-                options.width() as u64 * options.height() as u64,
-            )
-            .unwrap();
-            let command_buffer_allocator = Arc::new(StandardCommandBufferAllocator::new(Arc::clone(&gpu_context.device), StandardCommandBufferAllocatorCreateInfo::default())) as Arc<dyn CommandBufferAllocator>;
             let mut timestamp = 0;
             let mut image_receiver = Some(image_receiver);
             let stream_time_base = output.stream(id).unwrap().time_base();
-            move |video_packet: &mut Packet| -> ControlFlow<()> {
+            let (frame_rate_numerator, frame_rate_denominator) = options.frame_rate_fraction();
+            let encoder_time_base = Rational::new(frame_rate_denominator as i32, frame_rate_numerator as i32);
+            let cancelled = Arc::clone(&cancelled);
+            move |video_packet: &mut Packet| -> Result<ControlFlow<()>, FfmpegError> {
                 loop {
+                    if cancelled.load(atomic::Ordering::Acquire) {
+                        return Ok(ControlFlow::Break(()));
+                    }
                     if encoder.receive_packet(video_packet).is_ok() {
-                        video_packet.rescale_ts(Rational::from(options.frame_rate()).invert(), stream_time_base);
+                        video_packet.rescale_ts(encoder_time_base, stream_time_base);
                         video_packet.set_stream(id);
-                        return ControlFlow::Continue(());
+                        return Ok(ControlFlow::Continue(()));
                     }
                     let Some(image_receiver_ref) = image_receiver.as_ref() else {
-                        return ControlFlow::Break(());
+                        return Ok(ControlFlow::Break(()));
                     };
                     match image_receiver_ref.recv().unwrap() {
-                        EncoderMessage::Push(ImageType(image)) => {
-                            let [width, height, _] = image.extent();
-                            let mut builder = AutoCommandBufferBuilder::primary(Arc::clone(&command_buffer_allocator), 0, CommandBufferUsage::OneTimeSubmit).unwrap();
-                            builder.copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(image, buffer.clone())).unwrap();
-                            builder.build().unwrap().execute(Arc::clone(&gpu_context.queue)).unwrap().then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+                        EncoderMessage::Push(image) => {
+                            let [width, height] = readback.extent(&image);
                             rgba_frame.set_width(width);
                             rgba_frame.set_height(height);
-                            rgba_frame.plane_mut(0).copy_from_slice(&buffer.read().unwrap());
+                            readback.read(image, |pixels| copy_rgba_rows(&mut rgba_frame, pixels))?;
                             format_conversion_context.cached(Pixel::RGBA, width, height, encoder.format(), options.width(), options.height(), scaling::Flags::FAST_BILINEAR);
                             format_conversion_context.run(&rgba_frame, &mut encoder_native_format_frame).unwrap();
                             encoder_native_format_frame.set_pts(Some(timestamp));
@@ -315,11 +559,13 @@ fn encode_thread<T: Write + Seek + Send + Sync + 'static>(
         });
         let mut audio_stream = audio_stream.map(|(id, mut encoder, options, variable_frame_size)| {
             let frame_size = if variable_frame_size { encoder.rate() / 20 } else { encoder.frame_size() };
-            let mut f32_frame = frame::Audio::new(Sample::F32(Type::Planar), frame_size as usize, ChannelLayout::STEREO);
-            let mut encoder_native_format_frame = frame::Audio::new(encoder.format(), frame_size as usize, ChannelLayout::STEREO);
+            let channels = options.channels();
+            let layout = channel_layout(channels);
+            let mut f32_frame = frame::Audio::new(Sample::F32(Type::Planar), frame_size as usize, layout);
+            let mut encoder_native_format_frame = frame::Audio::new(encoder.format(), frame_size as usize, layout);
             f32_frame.set_rate(encoder.rate());
             encoder_native_format_frame.set_rate(encoder.rate());
-            let mut format_conversion_context = resampling::Context::get(Sample::F32(Type::Planar), ChannelLayout::STEREO, encoder.rate(), encoder.format(), ChannelLayout::STEREO, encoder.rate()).unwrap();
+            let mut format_conversion_context = resampling::Context::get(Sample::F32(Type::Planar), layout, encoder.rate(), encoder.format(), layout, encoder.rate()).unwrap();
             let mut audio_buffer = MultiChannelAudio::new(2);
             audio_buffer.resize(frame_size as usize, 0.);
             let mut audio = None;
@@ -328,10 +574,14 @@ fn encode_thread<T: Write + Seek + Send + Sync + 'static>(
             let stream_time_base = output.stream(id).unwrap().time_base();
             let mut next_break = false;
             let mut nb = false;
+            let cancelled = Arc::clone(&cancelled);
             move |audio_packet: &mut Packet| -> ControlFlow<()> {
                 loop {
+                    if cancelled.load(atomic::Ordering::Acquire) {
+                        return ControlFlow::Break(());
+                    }
                     if encoder.receive_packet(audio_packet).is_ok() {
-                        audio_packet.rescale_ts(Rational::new(1, options.sample_rate() as i32), stream_time_base);
+                        audio_packet.rescale_ts(Rational::new(1, encoder.rate() as i32), stream_time_base);
                         audio_packet.set_stream(id);
                         return ControlFlow::Continue(());
                     }
@@ -340,18 +590,25 @@ fn encode_thread<T: Write + Seek + Send + Sync + 'static>(
                     }
                     if audio.is_none() {
                         let EncoderMessage::Push(new_audio) = audio_receiver.recv().unwrap() else {
-                            panic!();
+                            // 音声が渡される前に中断された
+                            assert!(cancelled.load(atomic::Ordering::Acquire));
+                            return ControlFlow::Break(());
                         };
                         let audio_sample_rate = new_audio.sample_rate();
                         let resample = Resample::builder(audio_sample_rate, encoder.rate()).build().unwrap();
-                        audio = Some((new_audio, [resample.clone(), resample]));
+                        audio = Some((new_audio, vec![resample; channels.count()]));
                     }
                     let (audio, resample) = audio.as_mut().unwrap();
                     audio_buffer.resize(frame_size as usize, 0.);
                     let mut offset = 0;
                     loop {
-                        f32_frame.plane_mut::<f32>(0)[offset..].iter_mut().zip(resample[0].by_ref()).for_each(|(frame, sample)| *frame = sample);
-                        offset += f32_frame.plane_mut::<f32>(1)[offset..].iter_mut().zip(resample[1].by_ref()).map(|(frame, sample)| *frame = sample).count();
+                        let written = resample
+                            .iter_mut()
+                            .enumerate()
+                            .map(|(channel, resample)| f32_frame.plane_mut::<f32>(channel)[offset..].iter_mut().zip(resample.by_ref()).map(|(frame, sample)| *frame = sample).count())
+                            .min()
+                            .unwrap_or(0);
+                        offset += written;
                         if offset >= f32_frame.samples() {
                             break;
                         }
@@ -361,13 +618,23 @@ fn encode_thread<T: Write + Seek + Send + Sync + 'static>(
                         }
                         let len = audio.compute_audio(TimelineTime::new(MixedFraction::from_fraction((src_timestamp * frame_size) as i64, audio.sample_rate())), audio_buffer.slice_mut(..).unwrap());
                         src_timestamp += 1;
+                        if audio_progress.advance(len as u64).is_break() {
+                            cancelled.store(true, atomic::Ordering::Release);
+                            return ControlFlow::Break(());
+                        }
                         if len == 0 {
                             resample.iter_mut().for_each(Resample::fill_tail_by_zero);
                             nb = true;
                         } else {
                             let audio = audio_buffer.slice(..len).unwrap();
-                            resample[0].extend(audio.iter().map(|audio| audio[0]));
-                            resample[1].extend(audio.iter().map(|audio| audio[1]));
+                            match resample.as_mut_slice() {
+                                [mono] => mono.extend(audio.iter().map(|audio| (audio[0] + audio[1]) / 2.)),
+                                [left, right] => {
+                                    left.extend(audio.iter().map(|audio| audio[0]));
+                                    right.extend(audio.iter().map(|audio| audio[1]));
+                                }
+                                _ => unreachable!(),
+                            }
                         }
                     }
                     format_conversion_context.run(&f32_frame, &mut encoder_native_format_frame).unwrap();
@@ -380,7 +647,7 @@ fn encode_thread<T: Write + Seek + Send + Sync + 'static>(
         let mut video_packet = video_stream.as_ref().map(|_| Packet::empty());
         let mut audio_packet = audio_stream.as_ref().map(|_| Packet::empty());
         if let Some(video_packet_ref) = &mut video_packet {
-            if let ControlFlow::Break(()) = video_stream.as_mut().unwrap()(video_packet_ref) {
+            if let ControlFlow::Break(()) = video_stream.as_mut().unwrap()(video_packet_ref)? {
                 video_packet = None;
             }
         }
@@ -394,7 +661,7 @@ fn encode_thread<T: Write + Seek + Send + Sync + 'static>(
                 (Some(video_packet_ref), Some(audio_packet_ref)) => {
                     if video_packet_ref.dts().unwrap_or(i64::MAX) <= audio_packet_ref.dts().unwrap_or(i64::MAX) {
                         video_packet_ref.write_interleaved(&mut output).unwrap();
-                        if let ControlFlow::Break(()) = video_stream.as_mut().unwrap()(video_packet_ref) {
+                        if let ControlFlow::Break(()) = video_stream.as_mut().unwrap()(video_packet_ref)? {
                             video_packet = None;
                         }
                     } else {
@@ -406,7 +673,7 @@ fn encode_thread<T: Write + Seek + Send + Sync + 'static>(
                 }
                 (Some(video_packet_ref), None) => {
                     video_packet_ref.write_interleaved(&mut output).unwrap();
-                    if let ControlFlow::Break(()) = video_stream.as_mut().unwrap()(video_packet_ref) {
+                    if let ControlFlow::Break(()) = video_stream.as_mut().unwrap()(video_packet_ref)? {
                         video_packet = None;
                     }
                 }
@@ -419,30 +686,53 @@ fn encode_thread<T: Write + Seek + Send + Sync + 'static>(
                 (None, None) => break,
             }
         }
-        output.write_trailer().unwrap();
+        output.write_trailer()?;
+        Ok(())
     }
 }
 
+/// 行の間に隙間のないRGBA8の画素を`frame`の幅で区切り、ストライドに合わせて書き込む
+fn copy_rgba_rows(frame: &mut frame::Video, pixels: &[u8]) {
+    let row_len = frame.width() as usize * 4;
+    let stride = frame.stride(0);
+    let data = frame.data_mut(0);
+    for (y, row) in pixels.chunks_exact(row_len).enumerate() {
+        data[y * stride..][..row_len].copy_from_slice(row);
+    }
+}
+
+/// エンコードスレッドに渡してまだ処理されていないフレームの上限 これを超えるとpush_frameが待つ
+const FRAME_QUEUE_CAPACITY: usize = 4;
+
+/// 音声はPushとFinishの2つしか送らない
+const AUDIO_QUEUE_CAPACITY: usize = 2;
+
 enum EncoderMessage<T> {
     Push(T),
     Finish,
 }
 
-pub struct FfmpegEncoder {
+pub struct FfmpegEncoder<Image> {
     requires_image: bool,
     requires_audio: bool,
-    image_sender: mpsc::Sender<EncoderMessage<ImageType>>,
-    audio_sender: mpsc::Sender<EncoderMessage<AudioType>>,
-    handle: Option<JoinHandle<()>>,
+    image_sender: mpsc::SyncSender<EncoderMessage<Image>>,
+    audio_sender: mpsc::SyncSender<EncoderMessage<AudioType>>,
+    handle: Option<JoinHandle<Result<(), FfmpegError>>>,
+    cancelled: Arc<AtomicBool>,
+    audio_progress: Arc<AudioProgress>,
+    output_path: Option<PathBuf>,
 }
 
-impl VideoEncoder<ImageType, AudioType> for FfmpegEncoder {
+impl<Image: Send + 'static> VideoEncoder<Image, AudioType> for FfmpegEncoder<Image> {
+    type Err = FfmpegError;
+
     fn requires_image(&self) -> bool {
         self.requires_image
     }
 
-    fn push_frame(&mut self, frame: ImageType) {
-        self.image_sender.send(EncoderMessage::Push(frame)).unwrap();
+    fn push_frame(&mut self, frame: Image) {
+        // エンコードスレッドがエラーで終了していればfinishでそのエラーを返す
+        let _ = self.image_sender.send(EncoderMessage::Push(frame));
     }
 
     fn requires_audio(&self) -> bool {
@@ -450,13 +740,40 @@ impl VideoEncoder<ImageType, AudioType> for FfmpegEncoder {
     }
 
     fn set_audio(&mut self, audio: AudioType) {
-        self.audio_sender.send(EncoderMessage::Push(audio)).unwrap();
+        let _ = self.audio_sender.send(EncoderMessage::Push(audio));
+    }
+
+    fn set_audio_range(&mut self, audio: AudioType, range: Range<TimelineTime>) {
+        let total_samples = (range.end.value().into_f64() - range.start.value().into_f64()) * audio.sample_rate() as f64;
+        self.audio_progress.total_samples.store(total_samples.ceil().max(0.) as u64, atomic::Ordering::Relaxed);
+        self.set_audio(AudioType::new(TrimmedAudio::new(audio, range)));
+    }
+
+    fn finish(&mut self) -> Result<(), FfmpegError> {
+        // エンコードスレッドが先に終了していれば送れないが、結果はjoinで受け取る
+        let _ = self.image_sender.send(EncoderMessage::Finish);
+        let _ = self.audio_sender.send(EncoderMessage::Finish);
+        let Some(handle) = self.handle.take() else {
+            return Ok(());
+        };
+        handle.join().unwrap_or(Err(FfmpegError::EncoderThreadPanicked))
     }
 
-    fn finish(&mut self) {
-        self.image_sender.send(EncoderMessage::Finish).unwrap();
-        self.audio_sender.send(EncoderMessage::Finish).unwrap();
-        self.handle.take().unwrap().join().unwrap();
+    fn set_monitor(&mut self, monitor: EncodeMonitor) {
+        let _ = self.audio_progress.monitor.set((monitor, Instant::now()));
+    }
+
+    fn cancel(&mut self) -> Result<(), FfmpegError> {
+        self.cancelled.store(true, atomic::Ordering::Release);
+        // 中断したので、エンコードスレッドの結果は使わない
+        let _ = self.finish();
+        let Some(path) = self.output_path.take() else {
+            return Ok(());
+        };
+        match std::fs::remove_file(&path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(FfmpegError::RemovePartialOutput(path, err)),
+            _ => Ok(()),
+        }
     }
 }
 
@@ -468,10 +785,13 @@ mod tests {
     use std::io;
     use std::io::{Cursor, IoSlice, SeekFrom};
     use std::sync::Mutex;
-    use vulkano::command_buffer::ClearColorImageInfo;
+    use vulkano::command_buffer::allocator::{CommandBufferAllocator, StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo};
+    use vulkano::command_buffer::{AutoCommandBufferBuilder, ClearColorImageInfo, CommandBufferUsage, PrimaryCommandBufferAbstract};
     use vulkano::format::{ClearColorValue, Format};
     use vulkano::image::{Image, ImageCreateInfo, ImageUsage};
     use vulkano::instance::InstanceCreateInfo;
+    use vulkano::memory::allocator::{AllocationCreateInfo, MemoryAllocator};
+    use vulkano::sync::GpuFuture;
     use vulkano::Version;
     use vulkano_util::context::{VulkanoConfig, VulkanoContext};
 
@@ -544,6 +864,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_select_pixel_format() {
+        ffmpeg_next::init().unwrap();
+        let ffv1 = encoder::find(Id::FFV1).unwrap();
+        for bit_depth in [8, 10, 12, 16] {
+            assert_eq!(pixel_format_bit_depth(select_pixel_format(&ffv1, Some(bit_depth))), Some(bit_depth as u32));
+        }
+        assert_eq!(select_pixel_format(&ffv1, None), codec_supported_pixel_format(&ffv1).unwrap().next().unwrap());
+        // 対応するものがなければ先頭のもの
+        assert_eq!(select_pixel_format(&ffv1, Some(3)), select_pixel_format(&ffv1, None));
+    }
+
+    #[test]
+    fn test_mastering_format_supports() {
+        ffmpeg_next::init().unwrap();
+        assert!(mpdelta_ffmpeg::supports(FileFormat::Mkv, Id::FFV1));
+        assert!(mpdelta_ffmpeg::supports(FileFormat::Mkv, Id::FLAC));
+        assert!(mpdelta_ffmpeg::supports(FileFormat::Mov, Id::PRORES));
+        assert!(mpdelta_ffmpeg::supports(FileFormat::Webm, Id::VP9));
+        assert!(mpdelta_ffmpeg::supports(FileFormat::Webm, Id::OPUS));
+        assert!(!mpdelta_ffmpeg::supports(FileFormat::Webm, Id::H264));
+    }
+
     #[test]
     fn test_encode_mp4_h264_aac() {
         ffmpeg_next::init().unwrap();
@@ -561,21 +904,18 @@ mod tests {
         let mut video_options = CodecOptions::new(IndexMap::new());
         video_options.set_height(1080);
         video_options.set_width(1920);
-        video_options.set_frame_rate(TEST_VIDEO_FRAME_RATE);
+        video_options.set_frame_rate(TEST_VIDEO_FRAME_RATE as u32, 1);
         let mut audio_options = CodecOptions::new(Default::default());
         audio_options.set_sample_rate(48_000);
         audio_options.set_bit_rate(192_000);
         audio_options.set_max_bit_rate(192_000);
         let mut encoder = FfmpegEncodeSettings {
-            gpu_context: GpuContext {
-                device: Arc::clone(vulkano_context.device()),
-                queue: Arc::clone(vulkano_context.graphics_queue()),
-                memory_allocator: Arc::clone(vulkano_context.memory_allocator()),
-            },
+            readback: VulkanoReadback::new(Arc::clone(vulkano_context.device()), Arc::clone(vulkano_context.graphics_queue()), Arc::clone(vulkano_context.memory_allocator())),
             file_format: FileFormat::Mp4,
             video: Some((VideoCodec::H264, video_options)),
             audio: Some((AudioCodec::Aac, audio_options)),
-            output: Some(WriteWrapper(Arc::clone(&output))),
+            output: Some(EncodeOutput::Stream(WriteWrapper(Arc::clone(&output)))),
+            output_path: None,
         };
         let mut encoder = encoder.build().unwrap();
         assert!(encoder.requires_audio());
@@ -610,46 +950,100 @@ mod tests {
         for i in 0..(TEST_VIDEO_FRAME_RATE * TEST_VIDEO_LENGTH).round() as usize {
             let f = ((i * 2) as f64 / TEST_VIDEO_FRAME_RATE).floor() as usize & 0b111;
             let f = f ^ (f >> 1);
-            encoder.push_frame(ImageType(Arc::clone(&images[f])));
+            encoder.push_frame(mpdelta_core_vulkano::ImageType(Arc::clone(&images[f])));
         }
-        encoder.finish();
+        encoder.finish().unwrap();
         std::fs::write(test_output_dir.join("test_encode_mp4_h264_aac.mp4"), output.lock().unwrap().get_ref()).unwrap();
     }
 
+    #[test]
+    fn test_encode_mkv_ffv1_cpu() {
+        ffmpeg_next::init().unwrap();
+        let output = Arc::new(Mutex::new(Cursor::new(Vec::new())));
+        let mut video_options = CodecOptions::new(IndexMap::new());
+        video_options.set_width(64);
+        video_options.set_height(48);
+        video_options.set_frame_rate(TEST_VIDEO_FRAME_RATE as u32, 1);
+        let mut encoder = FfmpegEncodeSettings {
+            readback: CpuReadback,
+            file_format: FileFormat::Mkv,
+            video: Some((VideoCodec::Ffv1, video_options)),
+            audio: None,
+            output: Some(EncodeOutput::Stream(WriteWrapper(Arc::clone(&output)))),
+            output_path: None,
+        };
+        let mut encoder = encoder.build().unwrap();
+        assert!(!encoder.requires_audio());
+        assert!(encoder.requires_image());
+        for i in 0..16u8 {
+            encoder.push_frame(mpdelta_core_cpu::ImageType::filled(64, 48, [i * 16, 255 - i * 16, 128, 255]));
+        }
+        encoder.finish().unwrap();
+        let output = output.lock().unwrap();
+        // EBMLヘッダ
+        assert_eq!(&output.get_ref()[..4], b"\x1a\x45\xdf\xa3");
+    }
+
     #[test]
     fn test_encode_flac() {
         ffmpeg_next::init().unwrap();
         const TEST_OUTPUT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test_output/", env!("CARGO_PKG_NAME"));
         let test_output_dir = Path::new(TEST_OUTPUT_DIR);
         std::fs::create_dir_all(test_output_dir).unwrap();
-        let vulkano_context = Arc::new(VulkanoContext::new(VulkanoConfig {
-            instance_create_info: InstanceCreateInfo {
-                max_api_version: Some(Version::V1_2),
-                ..InstanceCreateInfo::default()
-            },
-            ..VulkanoConfig::default()
-        }));
         let output = Arc::new(Mutex::new(Cursor::new(Vec::new())));
         let mut audio_options = CodecOptions::new(Default::default());
         audio_options.set_sample_rate(48_000);
         audio_options.set_bit_rate(192_000);
         audio_options.set_max_bit_rate(192_000);
         let mut encoder = FfmpegEncodeSettings {
-            gpu_context: GpuContext {
-                device: Arc::clone(vulkano_context.device()),
-                queue: Arc::clone(vulkano_context.graphics_queue()),
-                memory_allocator: Arc::clone(vulkano_context.memory_allocator()),
-            },
+            readback: CpuReadback,
             file_format: FileFormat::Flac,
             video: None,
             audio: Some((AudioCodec::Flac, audio_options)),
-            output: Some(WriteWrapper(Arc::clone(&output))),
+            output: Some(EncodeOutput::Stream(WriteWrapper(Arc::clone(&output)))),
+            output_path: None,
         };
         let mut encoder = encoder.build().unwrap();
         assert!(encoder.requires_audio());
         assert!(!encoder.requires_image());
         encoder.set_audio(AudioType::new(TestAudio));
-        encoder.finish();
+        encoder.finish().unwrap();
         std::fs::write(test_output_dir.join("test_encode_flac.flac"), output.lock().unwrap().get_ref()).unwrap();
     }
+
+    #[test]
+    fn test_encode_wav_pcm_s24_mono() {
+        ffmpeg_next::init().unwrap();
+        const TEST_OUTPUT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test_output/", env!("CARGO_PKG_NAME"));
+        let test_output_dir = Path::new(TEST_OUTPUT_DIR);
+        std::fs::create_dir_all(test_output_dir).unwrap();
+        let output = Arc::new(Mutex::new(Cursor::new(Vec::new())));
+        let mut audio_options = CodecOptions::new(Default::default());
+        audio_options.set_sample_rate(22_050);
+        audio_options.set_channels(AudioChannels::Mono);
+        audio_options.set_bit_rate(192_000);
+        audio_options.set_max_bit_rate(192_000);
+        let mut encoder = FfmpegEncodeSettings {
+            readback: CpuReadback,
+            file_format: FileFormat::Wav,
+            video: None,
+            audio: Some((AudioCodec::PcmS24, audio_options)),
+            output: Some(EncodeOutput::Stream(WriteWrapper(Arc::clone(&output)))),
+            output_path: None,
+        };
+        let mut encoder = encoder.build().unwrap();
+        assert!(encoder.requires_audio());
+        assert!(!encoder.requires_image());
+        encoder.set_audio(AudioType::new(TestAudio));
+        encoder.finish().unwrap();
+        let output = output.lock().unwrap();
+        let wav = output.get_ref();
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(&wav[8..12], b"WAVE");
+        // fmtチャンクのチャンネル数, サンプルレート, ビット深度
+        assert_eq!(u16::from_le_bytes([wav[22], wav[23]]), 1);
+        assert_eq!(u32::from_le_bytes([wav[24], wav[25], wav[26], wav[27]]), 22_050);
+        assert_eq!(u16::from_le_bytes([wav[34], wav[35]]), 24);
+        std::fs::write(test_output_dir.join("test_encode_wav_pcm_s24_mono.wav"), wav).unwrap();
+    }
 }
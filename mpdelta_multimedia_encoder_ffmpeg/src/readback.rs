@@ -0,0 +1,100 @@
+use crate::FfmpegError;
+use std::sync::Arc;
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::command_buffer::allocator::{CommandBufferAllocator, StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, CopyImageToBufferInfo, PrimaryCommandBufferAbstract};
+use vulkano::device::{Device, Queue};
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryAllocator, MemoryTypeFilter, StandardMemoryAllocator};
+use vulkano::sync::GpuFuture;
+
+/// 描画された画像をエンコードスレッドでRGBA8の画素として読み出す
+///
+/// エンコードスレッドごとに複製して使う
+pub trait ImageReadback: Clone + Send + Sync + 'static {
+    type Image: Send + 'static;
+    /// 画像の幅と高さ
+    fn extent(&self, image: &Self::Image) -> [u32; 2];
+    /// 行の間に隙間を空けずに並べた画素を`f`に渡す
+    fn read<R>(&mut self, image: Self::Image, f: impl FnOnce(&[u8]) -> R) -> Result<R, FfmpegError>;
+}
+
+/// GPU上の画像をホストから読めるバッファにコピーして読む
+#[derive(Clone)]
+pub struct VulkanoReadback {
+    queue: Arc<Queue>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    command_buffer_allocator: Arc<dyn CommandBufferAllocator>,
+    /// 前のフレームで使ったバッファ 大きさが同じなら使い回す
+    buffer: Option<Subbuffer<[u8]>>,
+}
+
+impl VulkanoReadback {
+    pub fn new(device: Arc<Device>, queue: Arc<Queue>, memory_allocator: Arc<StandardMemoryAllocator>) -> VulkanoReadback {
+        VulkanoReadback {
+            queue,
+            memory_allocator,
+            command_buffer_allocator: Arc::new(StandardCommandBufferAllocator::new(device, StandardCommandBufferAllocatorCreateInfo::default())),
+            buffer: None,
+        }
+    }
+}
+
+impl ImageReadback for VulkanoReadback {
+    type Image = mpdelta_core_vulkano::ImageType;
+
+    fn extent(&self, image: &mpdelta_core_vulkano::ImageType) -> [u32; 2] {
+        let [width, height, _] = image.0.extent();
+        [width, height]
+    }
+
+    fn read<R>(&mut self, mpdelta_core_vulkano::ImageType(image): mpdelta_core_vulkano::ImageType, f: impl FnOnce(&[u8]) -> R) -> Result<R, FfmpegError> {
+        let [width, height, _] = image.extent();
+        let len = width as u64 * height as u64 * 4;
+        let buffer = match self.buffer.take() {
+            Some(buffer) if buffer.len() == len => buffer,
+            _ => Buffer::new_slice::<u8>(
+                Arc::clone(&self.memory_allocator) as Arc<dyn MemoryAllocator>,
+                BufferCreateInfo {
+                    usage: BufferUsage::TRANSFER_DST,
+                    ..BufferCreateInfo::default()
+                },
+                AllocationCreateInfo {
+                    memory_type_filter: MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                    ..AllocationCreateInfo::default()
+                },
+                len,
+            )
+            .map_err(FfmpegError::readback)?,
+        };
+        let mut builder = AutoCommandBufferBuilder::primary(Arc::clone(&self.command_buffer_allocator), self.queue.queue_family_index(), CommandBufferUsage::OneTimeSubmit).map_err(FfmpegError::readback)?;
+        builder.copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(image, buffer.clone())).map_err(FfmpegError::readback)?;
+        builder
+            .build()
+            .map_err(FfmpegError::readback)?
+            .execute(Arc::clone(&self.queue))
+            .map_err(FfmpegError::readback)?
+            .then_signal_fence_and_flush()
+            .map_err(FfmpegError::readback)?
+            .wait(None)
+            .map_err(FfmpegError::readback)?;
+        let result = f(&buffer.read().map_err(FfmpegError::readback)?);
+        self.buffer = Some(buffer);
+        Ok(result)
+    }
+}
+
+/// メモリ上の画像はそのまま読む
+#[derive(Clone)]
+pub struct CpuReadback;
+
+impl ImageReadback for CpuReadback {
+    type Image = mpdelta_core_cpu::ImageType;
+
+    fn extent(&self, image: &mpdelta_core_cpu::ImageType) -> [u32; 2] {
+        [image.0.width(), image.0.height()]
+    }
+
+    fn read<R>(&mut self, image: mpdelta_core_cpu::ImageType, f: impl FnOnce(&[u8]) -> R) -> Result<R, FfmpegError> {
+        Ok(f(image.0.as_raw()))
+    }
+}
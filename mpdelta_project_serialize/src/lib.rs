@@ -210,8 +210,20 @@ mod tests {
         AudioRequiredParamsForSerialize, ComponentInstanceForSerialize, De, EasingValueForSerialize, ImageRequiredParamsForSerialize, ImageRequiredParamsTransformForSerialize, ParameterNullableValueForSerialize, ParameterValueFixedForSerialize, ProjectForSerialize, RootComponentClassForSerialize,
         Ser, SerDeSelect, UnDeserialized, VariableParameterValueForSerialize, Vector3ParamsForSerialize,
     };
-    use mpdelta_core::component::parameter::value::{DynEditableEasingValueMarker, DynEditableSingleValue, DynEditableSingleValueMarker};
+    use mpdelta_core::common::mixed_fraction::MixedFraction;
+    use mpdelta_core::component::instance::ComponentInstance;
+    use mpdelta_core::component::link::MarkerLink;
+    use mpdelta_core::component::marker_pin::{MarkerPin, MarkerTime};
+    use mpdelta_core::component::parameter::value::{DynEditableEasingValueManager, DynEditableEasingValueMarker, DynEditableSingleValue, DynEditableSingleValueManager, DynEditableSingleValueMarker, Easing};
+    use mpdelta_core::component::parameter::{ParameterNullableValue, ParameterType, VariableParameterValue};
+    use mpdelta_core::project::{Project, RootComponentClass, RootComponentClassItemWrite};
+    use mpdelta_core::ptr::StaticPointerOwned;
+    use mpdelta_core::time::TimelineTime;
     use proptest::{prop_assert_eq, proptest};
+    use rpds::Vector;
+    use std::borrow::Cow;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use uuid::Uuid;
 
     fn vector3_params_into(params: Vector3ParamsForSerialize<Ser>) -> Vector3ParamsForSerialize<De> {
         params.map(|VariableParameterValueForSerialize { params, components, priority }| VariableParameterValueForSerialize {
@@ -303,13 +315,14 @@ mod tests {
     }
 
     fn easing_value_into<V>(value: EasingValueForSerialize<V, Ser>) -> EasingValueForSerialize<V, De> {
-        let EasingValueForSerialize { value, easing } = value;
+        let EasingValueForSerialize { value, easing, easing_parameters } = value;
         EasingValueForSerialize {
             value: UnDeserialized {
                 tag: value.0.manager().identifier().into_static(),
                 value: serde_json::to_value(value.0).unwrap(),
             },
             easing,
+            easing_parameters,
         }
     }
 
@@ -323,8 +336,8 @@ mod tests {
             ParameterNullableValueForSerialize::Integer(value) => ParameterNullableValueForSerialize::Integer(value.map_value(|value| value.map(easing_value_into))),
             ParameterNullableValueForSerialize::RealNumber(value) => ParameterNullableValueForSerialize::RealNumber(value.map_value(|value| value.map(easing_value_into))),
             ParameterNullableValueForSerialize::Boolean(value) => ParameterNullableValueForSerialize::Boolean(value.map_value(|value| value.map(easing_value_into))),
-            ParameterNullableValueForSerialize::Dictionary(value) => ParameterNullableValueForSerialize::Dictionary(value),
-            ParameterNullableValueForSerialize::Array(value) => ParameterNullableValueForSerialize::Array(value),
+            ParameterNullableValueForSerialize::Dictionary(value) => ParameterNullableValueForSerialize::Dictionary(value.map_value(|value| value.map(easing_value_into))),
+            ParameterNullableValueForSerialize::Array(value) => ParameterNullableValueForSerialize::Array(value.map_value(|value| value.map(easing_value_into))),
             ParameterNullableValueForSerialize::ComponentClass(value) => ParameterNullableValueForSerialize::ComponentClass(value),
         }
     }
@@ -362,12 +375,23 @@ mod tests {
     }
 
     fn root_component_class_into<T: ParameterValueType>(component: RootComponentClassForSerialize<T, Ser>) -> RootComponentClassForSerialize<T, De> {
-        let RootComponentClassForSerialize { id, components, links, length } = component;
+        let RootComponentClassForSerialize {
+            id,
+            components,
+            links,
+            length,
+            frame_rate,
+            image_size,
+            pixel_aspect_ratio,
+        } = component;
         RootComponentClassForSerialize {
             id,
             components: components.into_iter().map(component_instance_into).collect(),
             links,
             length,
+            frame_rate,
+            image_size,
+            pixel_aspect_ratio,
         }
     }
 
@@ -395,6 +419,136 @@ mod tests {
         type ComponentClass = ();
     }
 
+    #[derive(Default)]
+    struct CountIdGenerator(AtomicU64);
+
+    impl IdGenerator for CountIdGenerator {
+        fn generate_new(&self) -> Uuid {
+            Uuid::from_u128(self.0.fetch_add(1, Ordering::Relaxed) as u128)
+        }
+    }
+
+    /// 何も読み込めないLoader
+    #[derive(Clone)]
+    struct EmptyLoader;
+
+    #[async_trait]
+    impl<V> ValueManagerLoader<V> for EmptyLoader {
+        async fn get_available_single_value(&self) -> Cow<[Arc<dyn DynEditableSingleValueManager<V>>]> {
+            Cow::Borrowed(&[])
+        }
+
+        async fn single_value_by_identifier(&self, _: DynEditableSingleValueIdentifier<'_>) -> Option<Arc<dyn DynEditableSingleValueManager<V>>> {
+            None
+        }
+
+        async fn get_available_easing_value(&self) -> Cow<[Arc<dyn DynEditableEasingValueManager<V>>]> {
+            Cow::Borrowed(&[])
+        }
+
+        async fn easing_value_by_identifier(&self, _: DynEditableEasingValueIdentifier<'_>) -> Option<Arc<dyn DynEditableEasingValueManager<V>>> {
+            None
+        }
+    }
+
+    #[async_trait]
+    impl EasingLoader for EmptyLoader {
+        async fn get_available_easing(&self) -> Cow<[Arc<dyn Easing>]> {
+            Cow::Borrowed(&[])
+        }
+
+        async fn easing_by_identifier(&self, _: EasingIdentifier<'_>) -> Option<Arc<dyn Easing>> {
+            None
+        }
+    }
+
+    #[async_trait]
+    impl ComponentClassLoader<T> for EmptyLoader {
+        async fn get_available_component_classes(&self) -> Cow<[StaticPointer<RwLock<dyn ComponentClass<T>>>]> {
+            Cow::Borrowed(&[])
+        }
+
+        async fn component_class_by_identifier(&self, _: ComponentClassIdentifier<'_>) -> Option<StaticPointer<RwLock<dyn ComponentClass<T>>>> {
+            None
+        }
+    }
+
+    struct EmptyLoaders;
+
+    impl ParameterValueType for EmptyLoaders {
+        type Image = EmptyLoader;
+        type Audio = EmptyLoader;
+        type Binary = EmptyLoader;
+        type String = EmptyLoader;
+        type Integer = EmptyLoader;
+        type RealNumber = EmptyLoader;
+        type Boolean = EmptyLoader;
+        type Dictionary = EmptyLoader;
+        type Array = EmptyLoader;
+        type ComponentClass = EmptyLoader;
+    }
+
+    #[tokio::test]
+    async fn test_serialize_deserialize_component_class_parameter() {
+        let id = Arc::new(CountIdGenerator::default());
+        let project_id = id.generate_new();
+        let project = Project::<T>::new_empty(project_id);
+        let project_ref = StaticPointerOwned::reference(&project).clone();
+        let (template_id, root_id) = (id.generate_new(), id.generate_new());
+        let template = RootComponentClass::new_empty(template_id, project_ref.clone(), project_id, &*id);
+        let root = RootComponentClass::new_empty(root_id, project_ref.clone(), project_id, &*id);
+        let template_ptr: StaticPointer<RwLock<dyn ComponentClass<T>>> = StaticPointerOwned::reference(&template).clone().map(|c| c as _);
+        {
+            // rootに、templateを参照するComponentClass型のvariable parameterを持つコンポーネントを置く
+            let processor = template.read().await.processor();
+            let read = root.read().await;
+            let mut item = read.get_mut().await;
+            let root_left = *item.left().id();
+            let left = MarkerPin::new(id.generate_new(), MarkerTime::ZERO);
+            let right = MarkerPin::new_unlocked(id.generate_new());
+            let (left_id, right_id) = (*left.id(), *right.id());
+            let component = ComponentInstance::builder(template_ptr.clone(), left, right, Vec::new(), processor)
+                .variable_parameters(
+                    vec![("template".to_owned(), ParameterType::ComponentClass(()))],
+                    Vector::new_sync().push_back(VariableParameterValue::new(ParameterNullableValue::ComponentClass(Some(template_ptr.clone())))),
+                )
+                .build(&*id);
+            item.add_component(component);
+            item.add_link(MarkerLink::new(root_left, left_id, TimelineTime::new(MixedFraction::from_integer(1))));
+            item.add_link(MarkerLink::new(left_id, right_id, TimelineTime::new(MixedFraction::from_integer(2))));
+            let time_map = mpdelta_differential::collect_cached_time(&*item).unwrap();
+            RootComponentClassItemWrite::commit_changes(item, time_map);
+        }
+        project.write().await.add_children(&project_ref, [template, root]).await;
+
+        let values = ParameterAllValues::<EmptyLoaders> {
+            image: EmptyLoader,
+            audio: EmptyLoader,
+            binary: EmptyLoader,
+            string: EmptyLoader,
+            integer: EmptyLoader,
+            real_number: EmptyLoader,
+            boolean: EmptyLoader,
+            dictionary: EmptyLoader,
+            array: EmptyLoader,
+            component_class: EmptyLoader,
+        };
+        let serializer = MPDeltaProjectSerializer::new::<T>(Handle::current(), id, EmptyLoader, values, EmptyLoader, EmptyLoader);
+        let mut data = Vec::new();
+        serializer.serialize_project(&project_ref, &mut data).await.unwrap();
+        let project = serializer.deserialize_project(data.as_slice()).await.unwrap();
+
+        let project = project.read().await;
+        let find = |class_id: Uuid| project.children().iter().find(|&class| class.try_read().unwrap().id() == class_id).unwrap();
+        let template_ptr: StaticPointer<RwLock<dyn ComponentClass<T>>> = StaticPointerOwned::reference(find(template_id)).clone().map(|c| c as _);
+        let root = find(root_id).read().await;
+        let item = root.get();
+        let [component] = item.iter_components().collect::<Vec<_>>()[..] else { panic!() };
+        assert_eq!(component.component_class(), &template_ptr);
+        let [param] = component.variable_parameters().iter().collect::<Vec<_>>()[..] else { panic!() };
+        assert!(matches!(&param.params, ParameterNullableValue::ComponentClass(Some(class)) if class == &template_ptr));
+    }
+
     proptest! {
         #[test]
         fn test_serialize_deserialize_project(project in serde_v0::proptest_arbitrary::project::<T>()) {
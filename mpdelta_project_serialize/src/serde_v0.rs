@@ -9,14 +9,14 @@ use mpdelta_core::component::link::MarkerLink;
 use mpdelta_core::component::marker_pin::{MarkerPin, MarkerPinId, MarkerTime};
 use mpdelta_core::component::parameter::value::{DynEditableEasingValue, DynEditableEasingValueIdentifier, DynEditableEasingValueMarker, DynEditableSingleValue, DynEditableSingleValueIdentifier, DynEditableSingleValueMarker, EasingIdentifier, EasingValue};
 use mpdelta_core::component::parameter::{
-    AbstractFile, AudioRequiredParams, BlendMode, CompositeOperation, ImageRequiredParams, ImageRequiredParamsTransform, Never, Parameter, ParameterAllValues, ParameterNullableValue, ParameterValueFixed, ParameterValueRaw, ParameterValueType, ValueRaw, VariableParameterPriority,
-    VariableParameterValue, Vector3Params,
+    AbstractFile, AudioRequiredParams, BlendMode, CompositeOperation, ImageRequiredParams, ImageRequiredParamsTransform, Parameter, ParameterAllValues, ParameterNullableValue, ParameterValueFixed, ParameterValueRaw, ParameterValueType, ValueRaw, VariableParameterPriority, VariableParameterValue,
+    Vector3Params,
 };
-use mpdelta_core::component::processor::ComponentProcessor;
+use mpdelta_core::component::processor::{ComponentProcessor, ImageSize, PixelAspectRatio};
 use mpdelta_core::core::{ComponentClassLoader, EasingLoader, IdGenerator, ValueManagerLoader};
 use mpdelta_core::project::{Project, ProjectHandleOwned, RootComponentClass, RootComponentClassHandle, RootComponentClassHandleOwned, RootComponentClassItemWrite};
 use mpdelta_core::ptr::{StaticPointer, StaticPointerOwned};
-use mpdelta_core::time::TimelineTime;
+use mpdelta_core::time::{FrameRate, TimelineTime};
 use rayon::prelude::{IndexedParallelIterator, IntoParallelRefIterator, ParallelBridge, ParallelIterator};
 use rpds::Vector;
 use serde::de::DeserializeOwned;
@@ -212,14 +212,14 @@ impl<T: ParameterValueType, S: SerDeSelect> ParameterValueType for NullableValue
     type Integer = PinSplitValueForSerialize<Option<EasingValueForSerialize<i64, S>>>;
     type RealNumber = PinSplitValueForSerialize<Option<EasingValueForSerialize<f64, S>>>;
     type Boolean = PinSplitValueForSerialize<Option<EasingValueForSerialize<bool, S>>>;
-    type Dictionary = Never;
-    type Array = Never;
-    type ComponentClass = Option<()>;
+    type Dictionary = PinSplitValueForSerialize<Option<EasingValueForSerialize<HashMap<String, ParameterValueRaw<T::Image, T::Audio>>, S>>>;
+    type Array = PinSplitValueForSerialize<Option<EasingValueForSerialize<Vec<ParameterValueRaw<T::Image, T::Audio>>, S>>>;
+    type ComponentClass = Option<ComponentClassIdentifier<'static>>;
 }
 
 pub type ParameterNullableValueForSerialize<T, S> = Parameter<NullableValueForSerialize<T, S>>;
 
-fn nullable_value_for_serialize<T: ParameterValueType>(value: &ParameterNullableValue<T>, pin_map: &HashMap<MarkerPinId, MarkerPinHandleForSerialize>) -> ParameterNullableValueForSerialize<T, Ser> {
+fn nullable_value_for_serialize<T: ParameterValueType>(value: &ParameterNullableValue<T>, pin_map: &HashMap<MarkerPinId, MarkerPinHandleForSerialize>, class_map: &HashMap<StaticPointer<RwLock<dyn ComponentClass<T>>>, ComponentClassIdentifier<'static>>) -> ParameterNullableValueForSerialize<T, Ser> {
     match value {
         ParameterNullableValue::None => ParameterNullableValueForSerialize::None,
         ParameterNullableValue::Image(value) => ParameterNullableValueForSerialize::Image(value.map_time_value_to_normal(|pin| pin_map[pin], |value| value.as_ref().map(EasingValueForSerialize::from))),
@@ -229,15 +229,9 @@ fn nullable_value_for_serialize<T: ParameterValueType>(value: &ParameterNullable
         ParameterNullableValue::Integer(value) => ParameterNullableValueForSerialize::Integer(value.map_time_value_to_normal(|pin| pin_map[pin], |value| value.as_ref().map(EasingValueForSerialize::from))),
         ParameterNullableValue::RealNumber(value) => ParameterNullableValueForSerialize::RealNumber(value.map_time_value_to_normal(|pin| pin_map[pin], |value| value.as_ref().map(EasingValueForSerialize::from))),
         ParameterNullableValue::Boolean(value) => ParameterNullableValueForSerialize::Boolean(value.map_time_value_to_normal(|pin| pin_map[pin], |value| value.as_ref().map(EasingValueForSerialize::from))),
-        ParameterNullableValue::Dictionary(value) => {
-            let _: &Never = value;
-            unreachable!()
-        }
-        ParameterNullableValue::Array(value) => {
-            let _: &Never = value;
-            unreachable!()
-        }
-        &ParameterNullableValue::ComponentClass(value) => ParameterNullableValueForSerialize::ComponentClass(value),
+        ParameterNullableValue::Dictionary(value) => ParameterNullableValueForSerialize::Dictionary(value.map_time_value_to_normal(|pin| pin_map[pin], |value| value.as_ref().map(EasingValueForSerialize::from))),
+        ParameterNullableValue::Array(value) => ParameterNullableValueForSerialize::Array(value.map_time_value_to_normal(|pin| pin_map[pin], |value| value.as_ref().map(EasingValueForSerialize::from))),
+        ParameterNullableValue::ComponentClass(value) => ParameterNullableValueForSerialize::ComponentClass(value.as_ref().map(|class| class_map[class].clone())),
     }
 }
 
@@ -301,11 +295,13 @@ pub struct EasingValueForSerialize<Value: 'static, S: SerDeSelect> {
     pub value: S::T<DynEditableEasingValue<Value>>,
     #[serde(rename = "e")]
     pub easing: EasingIdentifier<'static>,
+    #[serde(rename = "ep", default, skip_serializing_if = "Option::is_none")]
+    pub easing_parameters: Option<serde_json::Value>,
 }
 
 impl<Value: 'static, S: SerDeSelect> PartialEq for EasingValueForSerialize<Value, S> {
     fn eq(&self, other: &Self) -> bool {
-        self.value == other.value && self.easing == other.easing
+        self.value == other.value && self.easing == other.easing && self.easing_parameters == other.easing_parameters
     }
 }
 
@@ -316,14 +312,18 @@ where
     S::T<DynEditableEasingValue<Value>>: Debug,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("EasingValueForSerialize").field("value", &self.value).field("easing", &self.easing).finish()
+        f.debug_struct("EasingValueForSerialize").field("value", &self.value).field("easing", &self.easing).field("easing_parameters", &self.easing_parameters).finish()
     }
 }
 
 impl<Value, S: SerDeSelect> Clone for EasingValueForSerialize<Value, S> {
     fn clone(&self) -> Self {
-        let EasingValueForSerialize { value, easing } = self;
-        EasingValueForSerialize { value: value.clone(), easing: easing.clone() }
+        let EasingValueForSerialize { value, easing, easing_parameters } = self;
+        EasingValueForSerialize {
+            value: value.clone(),
+            easing: easing.clone(),
+            easing_parameters: easing_parameters.clone(),
+        }
     }
 }
 
@@ -332,6 +332,7 @@ impl<'a, Value> From<&'a EasingValue<Value>> for EasingValueForSerialize<Value,
         EasingValueForSerialize {
             value: Wrapper(value.value.clone()),
             easing: value.easing.identifier().into_static(),
+            easing_parameters: value.easing.parameters().map(|parameters| serde_json::to_value(parameters).expect("easing parameters should be serializable")),
         }
     }
 }
@@ -436,6 +437,12 @@ pub struct RootComponentClassForSerialize<T: ParameterValueType, S: SerDeSelect>
     pub links: Vec<MarkerLinkForSerialize>,
     #[serde(rename = "l")]
     pub length: MarkerTime,
+    #[serde(rename = "fr", default)]
+    pub frame_rate: FrameRate,
+    #[serde(rename = "is", default)]
+    pub image_size: ImageSize,
+    #[serde(rename = "par", default)]
+    pub pixel_aspect_ratio: PixelAspectRatio,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -446,8 +453,18 @@ pub struct ProjectForSerialize<T: ParameterValueType, S: SerDeSelect> {
     pub components: Vec<RootComponentClassForSerialize<T, S>>,
 }
 
+macro_rules! deserialize_easing {
+    ($easing_loader:expr, $easing:expr, $easing_parameters:expr) => {{
+        let easing = $easing_loader.easing_by_identifier($easing.as_ref()).await.ok_or(DeserializeError::UnknownEasing($easing))?;
+        match $easing_parameters {
+            Some(parameters) => easing.with_parameters(&mut <dyn erased_serde::Deserializer>::erase(parameters)).map_err(DeserializeError::ValueDeserializationError)?,
+            None => easing,
+        }
+    }};
+}
+
 macro_rules! deserialize_easing_value {
-    ($easing_value_loader:expr, $easing_loader:expr, $value:expr, $easing:expr) => {
+    ($easing_value_loader:expr, $easing_loader:expr, $value:expr, $easing:expr, $easing_parameters:expr) => {
         EasingValue {
             value: $easing_value_loader
                 .easing_value_by_identifier($value.tag.as_ref())
@@ -455,7 +472,7 @@ macro_rules! deserialize_easing_value {
                 .ok_or(DeserializeError::UnknownEasingValue($value.tag))?
                 .deserialize(&mut <dyn erased_serde::Deserializer>::erase($value.value))
                 .map_err(DeserializeError::ValueDeserializationError)?,
-            easing: $easing_loader.easing_by_identifier($easing.as_ref()).await.ok_or(DeserializeError::UnknownEasing($easing))?,
+            easing: deserialize_easing!($easing_loader, $easing, $easing_parameters),
         }
     };
 }
@@ -466,8 +483,8 @@ macro_rules! deserialize_pin_split_value {
             .try_map_time_value_async_to_persistent(
                 |time| future::ready($pins_map.get(&time).cloned().ok_or(DeserializeError::UnknownPin(time))),
                 |value| async move {
-                    if let Some(EasingValueForSerialize { value, easing }) = value {
-                        Ok(Some(deserialize_easing_value!($easing_value_loader, $easing_loader, value, easing)))
+                    if let Some(EasingValueForSerialize { value, easing, easing_parameters }) = value {
+                        Ok(Some(deserialize_easing_value!($easing_value_loader, $easing_loader, value, easing, easing_parameters)))
                     } else {
                         Ok(None)
                     }
@@ -506,6 +523,16 @@ impl<T: ParameterValueType> RootComponentClassForSerialize<T, Ser> {
             })
             .try_collect::<Vec<_>>()
             .await?;
+        let variable_parameter_classes = stream::iter(value.iter_components().flat_map(|component| component.variable_parameters().iter()).filter_map(|param| param.params.as_component_class()?.clone()))
+            .then(|class| async move {
+                let Some(class_ref) = class.upgrade() else {
+                    return Err(SerializeError::InvalidComponentClassHandle(class));
+                };
+                let identifier = class_ref.read().await.identifier().into_static();
+                Ok((class, identifier))
+            })
+            .try_collect::<HashMap<_, _>>()
+            .await?;
         runtime
             .spawn_blocking(move || {
                 let component_map = value.iter_components().enumerate().map(|(component, c)| (*c.id(), ComponentInstanceHandleForSerialize { component })).collect::<HashMap<_, _>>();
@@ -595,7 +622,7 @@ impl<T: ParameterValueType> RootComponentClassForSerialize<T, Ser> {
                                     .map(|value| {
                                         let &VariableParameterValue { ref params, ref components, priority } = value;
                                         VariableParameterValueForSerialize {
-                                            params: nullable_value_for_serialize(params, &pin_map),
+                                            params: nullable_value_for_serialize(params, &pin_map, &variable_parameter_classes),
                                             components: components.iter().map(|c| component_map[c]).collect(),
                                             priority,
                                         }
@@ -631,6 +658,9 @@ impl<T: ParameterValueType> RootComponentClassForSerialize<T, Ser> {
                     components: components?,
                     links,
                     length: value.length(),
+                    frame_rate: value.frame_rate(),
+                    image_size: value.image_size(),
+                    pixel_aspect_ratio: value.pixel_aspect_ratio(),
                 })
             })
             .await
@@ -658,8 +688,19 @@ impl<T: ParameterValueType> RootComponentClassForSerialize<T, De> {
         Id: IdGenerator + Clone + 'static,
     {
         let mut slot = slot.get_mut().await;
-        let RootComponentClassForSerialize { id: _, components, links, length } = self;
+        let RootComponentClassForSerialize {
+            id: _,
+            components,
+            links,
+            length,
+            frame_rate,
+            image_size,
+            pixel_aspect_ratio,
+        } = self;
         slot.set_length(length);
+        slot.set_frame_rate(frame_rate);
+        slot.set_image_size(image_size);
+        slot.set_pixel_aspect_ratio(pixel_aspect_ratio);
         let (all_pins, pins_map) = components.iter().enumerate().fold(
             (
                 Vec::with_capacity(components.len()),
@@ -758,15 +799,13 @@ impl<T: ParameterValueType> RootComponentClassForSerialize<T, De> {
                                 ParameterNullableValueForSerialize::Integer(value) => deserialize_pin_split_value!(value, pins_map, class_loader.value_managers.integer, class_loader.easing_manager).map(ParameterNullableValue::Integer),
                                 ParameterNullableValueForSerialize::RealNumber(value) => deserialize_pin_split_value!(value, pins_map, class_loader.value_managers.real_number, class_loader.easing_manager).map(ParameterNullableValue::RealNumber),
                                 ParameterNullableValueForSerialize::Boolean(value) => deserialize_pin_split_value!(value, pins_map, class_loader.value_managers.boolean, class_loader.easing_manager).map(ParameterNullableValue::Boolean),
-                                ParameterNullableValueForSerialize::Dictionary(value) => {
-                                    let _: Never = value;
-                                    unreachable!()
-                                }
-                                ParameterNullableValueForSerialize::Array(value) => {
-                                    let _: Never = value;
-                                    unreachable!()
+                                ParameterNullableValueForSerialize::Dictionary(value) => deserialize_pin_split_value!(value, pins_map, class_loader.value_managers.dictionary, class_loader.easing_manager).map(ParameterNullableValue::Dictionary),
+                                ParameterNullableValueForSerialize::Array(value) => deserialize_pin_split_value!(value, pins_map, class_loader.value_managers.array, class_loader.easing_manager).map(ParameterNullableValue::Array),
+                                ParameterNullableValueForSerialize::ComponentClass(None) => Ok(ParameterNullableValue::ComponentClass(None)),
+                                ParameterNullableValueForSerialize::ComponentClass(Some(class)) => {
+                                    let class_ptr = class_loader.component_class_by_identifier(class.as_ref()).await;
+                                    class_ptr.map(|class_ptr| ParameterNullableValue::ComponentClass(Some(class_ptr))).ok_or(DeserializeError::UnknownComponentClass(class))
                                 }
-                                ParameterNullableValueForSerialize::ComponentClass(value) => Ok(ParameterNullableValue::ComponentClass(value)),
                             };
                             Ok::<_, DeserializeError>((params?, components, priority))
                         })
@@ -850,7 +889,7 @@ impl<T: ParameterValueType> RootComponentClassForSerialize<T, De> {
                                             .try_map_time_value_async_to_persistent(
                                                 |time| future::ready(pins_map.get(&time).cloned().ok_or(DeserializeError::UnknownPin(time))),
                                                 |value| async move {
-                                                    let EasingValueForSerialize { value, easing } = value;
+                                                    let EasingValueForSerialize { value, easing, easing_parameters } = value;
                                                     Ok(EasingValue {
                                                         value: class_loader
                                                             .quaternion_manager
@@ -859,7 +898,7 @@ impl<T: ParameterValueType> RootComponentClassForSerialize<T, De> {
                                                             .ok_or(DeserializeError::UnknownEasingValue(value.tag))?
                                                             .deserialize(&mut <dyn erased_serde::Deserializer>::erase(value.value))
                                                             .map_err(DeserializeError::ValueDeserializationError)?,
-                                                        easing: class_loader.easing_manager.easing_by_identifier(easing.as_ref()).await.ok_or(DeserializeError::UnknownEasing(easing))?,
+                                                        easing: deserialize_easing!(class_loader.easing_manager, easing, easing_parameters),
                                                     })
                                                 },
                                             )
@@ -888,8 +927,8 @@ impl<T: ParameterValueType> RootComponentClassForSerialize<T, De> {
                             .try_map_time_value_async_to_persistent(
                                 |time| future::ready(pins_map.get(&time).cloned().ok_or(DeserializeError::UnknownPin(time))),
                                 |value| async move {
-                                    let EasingValueForSerialize { value, easing } = value;
-                                    Ok(deserialize_easing_value!(class_loader.value_managers.real_number, class_loader.easing_manager, value, easing))
+                                    let EasingValueForSerialize { value, easing, easing_parameters } = value;
+                                    Ok(deserialize_easing_value!(class_loader.value_managers.real_number, class_loader.easing_manager, value, easing, easing_parameters))
                                 },
                             )
                             .await?;
@@ -915,8 +954,8 @@ impl<T: ParameterValueType> RootComponentClassForSerialize<T, De> {
                                     .try_map_time_value_async_to_persistent(
                                         |time| future::ready(pins_map.get(&time).cloned().ok_or(DeserializeError::UnknownPin(time))),
                                         |value| async move {
-                                            if let Some(EasingValueForSerialize { value, easing }) = value {
-                                                Ok(Some(deserialize_easing_value!(class_loader.value_managers.real_number, class_loader.easing_manager, value, easing)))
+                                            if let Some(EasingValueForSerialize { value, easing, easing_parameters }) = value {
+                                                Ok(Some(deserialize_easing_value!(class_loader.value_managers.real_number, class_loader.easing_manager, value, easing, easing_parameters)))
                                             } else {
                                                 Ok(None)
                                             }
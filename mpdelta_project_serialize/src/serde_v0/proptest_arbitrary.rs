@@ -11,6 +11,8 @@ use mpdelta_core::component::parameter::value::{
     DynEditableEasingValue, DynEditableEasingValueIdentifier, DynEditableEasingValueManager, DynEditableEasingValueMarker, DynEditableSingleValue, DynEditableSingleValueIdentifier, DynEditableSingleValueManager, DynEditableSingleValueMarker, EasingIdentifier, NamedAny,
 };
 use mpdelta_core::component::parameter::{BlendMode, CompositeOperation, ParameterValueType, VariableParameterPriority};
+use mpdelta_core::component::processor::{ImageSize, PixelAspectRatio};
+use mpdelta_core::time::FrameRate;
 use proptest::array::{uniform3, uniform4};
 use proptest::collection::vec;
 use proptest::option::of;
@@ -256,10 +258,19 @@ pub fn easing_value<V: Send + Sync + Clone + 'static>() -> impl Strategy<Value =
             ),
         ),
     ));
-    (value, any::<EasingIdentifier>()).prop_map(|(value, easing)| EasingValueForSerialize { value, easing })
+    let easing_parameters = of((any::<i32>(), any::<String>()).prop_map(|(a, b)| serde_json::json!({ "a": a, "b": b })));
+    (value, any::<EasingIdentifier>(), easing_parameters).prop_map(|(value, easing, easing_parameters)| EasingValueForSerialize { value, easing, easing_parameters })
 }
 
 pub fn parameter_nullable_values<T: ParameterValueType>() -> impl Strategy<Value = ParameterNullableValueForSerialize<T, Ser>> {
+    // TupleUnionは10要素までなので、ComponentClassだけ外側で合わせる
+    TupleUnion::new((
+        (10, Arc::new(parameter_nullable_values_except_component_class())),
+        (1, Arc::new(of(any::<ComponentClassIdentifier>()).prop_map(ParameterNullableValueForSerialize::ComponentClass))),
+    ))
+}
+
+fn parameter_nullable_values_except_component_class<T: ParameterValueType>() -> impl Strategy<Value = ParameterNullableValueForSerialize<T, Ser>> {
     TupleUnion::new((
         (1, Arc::new(Just(ParameterNullableValueForSerialize::None))),
         (1, Arc::new(TimeSplitValue::strategy_from(any::<MarkerPinHandleForSerialize>(), of(easing_value()), 1..10).prop_map(ParameterNullableValueForSerialize::Image))),
@@ -269,6 +280,8 @@ pub fn parameter_nullable_values<T: ParameterValueType>() -> impl Strategy<Value
         (1, Arc::new(TimeSplitValue::strategy_from(any::<MarkerPinHandleForSerialize>(), of(easing_value()), 1..10).prop_map(ParameterNullableValueForSerialize::Integer))),
         (1, Arc::new(TimeSplitValue::strategy_from(any::<MarkerPinHandleForSerialize>(), of(easing_value()), 1..10).prop_map(ParameterNullableValueForSerialize::RealNumber))),
         (1, Arc::new(TimeSplitValue::strategy_from(any::<MarkerPinHandleForSerialize>(), of(easing_value()), 1..10).prop_map(ParameterNullableValueForSerialize::Boolean))),
+        (1, Arc::new(TimeSplitValue::strategy_from(any::<MarkerPinHandleForSerialize>(), of(easing_value()), 1..10).prop_map(ParameterNullableValueForSerialize::Dictionary))),
+        (1, Arc::new(TimeSplitValue::strategy_from(any::<MarkerPinHandleForSerialize>(), of(easing_value()), 1..10).prop_map(ParameterNullableValueForSerialize::Array))),
     ))
 }
 
@@ -300,7 +313,17 @@ pub fn component_instance<T: ParameterValueType>() -> impl Strategy<Value = Comp
 }
 
 pub fn root_component_class<T: Debug + ParameterValueType>() -> impl Strategy<Value = RootComponentClassForSerialize<T, Ser>> {
-    (any::<u128>(), vec(component_instance(), 0..10), vec(any::<MarkerLinkForSerialize>(), 0..10), any::<MarkerTime>()).prop_map(|(id, components, links, length)| RootComponentClassForSerialize { id: Uuid::from_u128(id), components, links, length })
+    (any::<u128>(), vec(component_instance(), 0..10), vec(any::<MarkerLinkForSerialize>(), 0..10), any::<MarkerTime>(), any::<FrameRate>(), any::<ImageSize>(), any::<PixelAspectRatio>()).prop_map(|(id, components, links, length, frame_rate, image_size, pixel_aspect_ratio)| {
+        RootComponentClassForSerialize {
+            id: Uuid::from_u128(id),
+            components,
+            links,
+            length,
+            frame_rate,
+            image_size,
+            pixel_aspect_ratio,
+        }
+    })
 }
 
 pub fn project<T: Debug + ParameterValueType>() -> impl Strategy<Value = ProjectForSerialize<T, Ser>> {
@@ -0,0 +1,179 @@
+use crate::ImageSizeRequest;
+use cgmath::{Matrix3, Matrix4, SquareMatrix, Vector3, Vector4};
+use mpdelta_core::component::parameter::ImageRequiredParamsTransformFixed;
+use std::cmp::Ordering;
+
+fn move_mat(pos: Vector3<f64>) -> Matrix4<f64> {
+    Matrix4::from_cols(Vector4::unit_x(), Vector4::unit_y(), Vector4::unit_z(), pos.extend(1.))
+}
+
+fn scale_mat(scale: Vector3<f64>) -> Matrix4<f64> {
+    Matrix4::from_diagonal(scale.extend(1.))
+}
+
+/// 頂点(x, y) ∈ [-1, 1]^2の四角形に貼った画像をclip空間へ移す変換行列を返す
+///
+/// `image_extent`は貼る画像の幅と高さ
+/// Freeの4隅が退化していて変換が求まらない場合はNoneを返す
+pub fn image_transform_mat(transform: &ImageRequiredParamsTransformFixed, image_extent: [u32; 2], size_request: ImageSizeRequest) -> Option<Matrix4<f64>> {
+    match *transform {
+        ImageRequiredParamsTransformFixed::Params {
+            size,
+            scale,
+            translate,
+            rotate,
+            scale_center,
+            rotate_center,
+        } => {
+            let image_width = size_request.width.ceil() as f64;
+            let image_height = size_request.height.ceil() as f64;
+            let [extent_width, extent_height] = image_extent.map(f64::from);
+            let image_native_size = match (image_width * size.x * extent_height).partial_cmp(&(image_height * size.y * extent_width)).unwrap() {
+                Ordering::Greater => (image_height * size.y * extent_width / extent_height, image_height * size.y),
+                Ordering::Equal => (image_width * size.x, image_height * size.y),
+                Ordering::Less => (image_width * size.x, image_width * size.x * extent_height / extent_width),
+            };
+            Some(
+                scale_mat(Vector3::new(image_native_size.0 / size_request.width as f64, image_native_size.1 / size_request.height as f64, 1.))
+                    * move_mat(-scale_center)
+                    * scale_mat(scale)
+                    * move_mat(scale_center)
+                    * move_mat(-rotate_center)
+                    * Matrix4::from(rotate)
+                    * move_mat(rotate_center)
+                    * move_mat(translate),
+            )
+        }
+        ImageRequiredParamsTransformFixed::Free { left_top, right_top, left_bottom, right_bottom } => free_transform_mat(left_top, right_top, left_bottom, right_bottom),
+    }
+}
+
+/// 出力画像の左上を(0, 0)、右下を(1, 1)とする座標で与えた4隅に画像を貼る射影変換行列を返す
+///
+/// 射影成分をclip空間のwに入れるので、uvは透視補正されて補間される
+/// 4隅が退化している場合や、凹んでいたり辺が交差していたりして画像の内側でwが正にならない場合はNoneを返す
+pub fn free_transform_mat(left_top: Vector3<f64>, right_top: Vector3<f64>, left_bottom: Vector3<f64>, right_bottom: Vector3<f64>) -> Option<Matrix4<f64>> {
+    // ref: Paul S. Heckbert, "Fundamentals of Texture Mapping and Image Warping" (square to quadrilateral)
+    let ndc = |p: Vector3<f64>| (p.x * 2. - 1., p.y * 2. - 1.);
+    let (x0, y0) = ndc(left_top);
+    let (x1, y1) = ndc(right_top);
+    let (x2, y2) = ndc(right_bottom);
+    let (x3, y3) = ndc(left_bottom);
+    let (dx1, dx2, dx3) = (x1 - x2, x3 - x2, x0 - x1 + x2 - x3);
+    let (dy1, dy2, dy3) = (y1 - y2, y3 - y2, y0 - y1 + y2 - y3);
+    let (g, h) = if dx3.abs() <= f64::EPSILON && dy3.abs() <= f64::EPSILON {
+        (0., 0.)
+    } else {
+        let det = dx1 * dy2 - dx2 * dy1;
+        if det.abs() <= f64::EPSILON {
+            return None;
+        }
+        ((dx3 * dy2 - dx2 * dy3) / det, (dx1 * dy3 - dx3 * dy1) / det)
+    };
+    // unit square (u, v) -> NDC (X, Y, W)
+    let square_to_quad = Matrix3::new(x1 - x0 + g * x1, y1 - y0 + g * y1, g, x3 - x0 + h * x3, y3 - y0 + h * y3, h, x0, y0, 1.);
+    // 頂点 (x, y) ∈ [-1, 1]^2 -> uv
+    let vertex_to_square = Matrix3::new(0.5, 0., 0., 0., 0.5, 0., 0.5, 0.5, 1.);
+    let m = square_to_quad * vertex_to_square;
+    let det = m.determinant();
+    if !det.is_finite() || det.abs() <= f64::EPSILON {
+        return None;
+    }
+    // wは頂点座標の1次式なので、4隅で正なら画像全体で正になる
+    // 凸でない四角形では地平線が画像を横切り、反転したりでたらめな位置に射影されたりする
+    if [(-1., -1.), (1., -1.), (-1., 1.), (1., 1.)].into_iter().any(|(x, y)| m.x.z * x + m.y.z * y + m.z.z <= f64::EPSILON) {
+        return None;
+    }
+    Some(Matrix4::new(m.x.x, m.x.y, 0., m.x.z, m.y.x, m.y.y, 0., m.y.z, 0., 0., 1., 0., m.z.x, m.z.y, 0., m.z.z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{One, Quaternion, Rad, Rotation3};
+
+    fn project(mat: &Matrix4<f64>, x: f64, y: f64) -> [f64; 2] {
+        let p = mat * Vector4::new(x, y, 0., 1.);
+        assert!(p.w > 0.);
+        [p.x / p.w, p.y / p.w]
+    }
+
+    fn assert_near(actual: [f64; 2], expected: [f64; 2]) {
+        assert!((actual[0] - expected[0]).abs() < 1e-9 && (actual[1] - expected[1]).abs() < 1e-9, "actual: {actual:?}, expected: {expected:?}");
+    }
+
+    #[test]
+    fn test_free_transform_mat() {
+        // 画面全体に貼ると恒等変換
+        let mat = free_transform_mat(Vector3::new(0., 0., 0.), Vector3::new(1., 0., 0.), Vector3::new(0., 1., 0.), Vector3::new(1., 1., 0.)).unwrap();
+        for (x, y) in [(-1., -1.), (1., -1.), (-1., 1.), (1., 1.), (0.25, -0.5)] {
+            assert_near(project(&mat, x, y), [x, y]);
+        }
+
+        // 台形に貼ると各頂点が指定した4隅へ移り、画像の中心は(アフィン変換と違い)対角線の交点へ移る
+        let left_top = Vector3::new(0.25, 0., 0.);
+        let right_top = Vector3::new(0.75, 0., 0.);
+        let left_bottom = Vector3::new(0., 1., 0.);
+        let right_bottom = Vector3::new(1., 1., 0.);
+        let mat = free_transform_mat(left_top, right_top, left_bottom, right_bottom).unwrap();
+        assert_near(project(&mat, -1., -1.), [-0.5, -1.]);
+        assert_near(project(&mat, 1., -1.), [0.5, -1.]);
+        assert_near(project(&mat, -1., 1.), [-1., 1.]);
+        assert_near(project(&mat, 1., 1.), [1., 1.]);
+        assert_near(project(&mat, 0., 0.), [0., -1. / 3.]);
+
+        // 左右反転した凸四角形は貼れる
+        let mat = free_transform_mat(Vector3::new(1., 0., 0.), Vector3::new(0., 0., 0.), Vector3::new(1., 1., 0.), Vector3::new(0., 1., 0.)).unwrap();
+        assert_near(project(&mat, -1., -1.), [1., -1.]);
+        assert_near(project(&mat, 0.5, 0.), [-0.5, 0.]);
+
+        // 退化した4隅
+        assert!(free_transform_mat(Vector3::new(0.5, 0.5, 0.), Vector3::new(0.5, 0.5, 0.), Vector3::new(0.5, 0.5, 0.), Vector3::new(0.5, 0.5, 0.)).is_none());
+
+        // 凹んだ4隅
+        assert!(free_transform_mat(Vector3::new(0., 0., 0.), Vector3::new(1., 0., 0.), Vector3::new(0., 1., 0.), Vector3::new(0.25, 0.25, 0.)).is_none());
+
+        // 辺が交差した4隅
+        assert!(free_transform_mat(Vector3::new(0., 0., 0.), Vector3::new(1., 0., 0.), Vector3::new(1., 1., 0.), Vector3::new(0., 1., 0.)).is_none());
+    }
+
+    #[test]
+    fn test_image_transform_mat() {
+        let size_request = ImageSizeRequest { width: 200., height: 100. };
+        let params = |scale: Vector3<f64>, translate: Vector3<f64>, rotate: Quaternion<f64>| ImageRequiredParamsTransformFixed::Params {
+            size: Vector3::new(1., 1., 1.),
+            scale,
+            translate,
+            rotate,
+            scale_center: Vector3::new(0., 0., 0.),
+            rotate_center: Vector3::new(0., 0., 0.),
+        };
+
+        // 出力と同じアスペクト比の画像は画面全体に貼られる
+        let mat = image_transform_mat(&params(Vector3::new(1., 1., 1.), Vector3::new(0., 0., 0.), Quaternion::one()), [20, 10], size_request).unwrap();
+        assert_near(project(&mat, -1., -1.), [-1., -1.]);
+        assert_near(project(&mat, 1., 1.), [1., 1.]);
+
+        // 正方形の画像は高さに合わせて縮められる
+        let mat = image_transform_mat(&params(Vector3::new(1., 1., 1.), Vector3::new(0., 0., 0.), Quaternion::one()), [1, 1], size_request).unwrap();
+        assert_near(project(&mat, -1., -1.), [-0.5, -1.]);
+        assert_near(project(&mat, 1., 1.), [0.5, 1.]);
+
+        // 移動してから拡大縮小する
+        let mat = image_transform_mat(&params(Vector3::new(0.5, 0.5, 1.), Vector3::new(0.5, 0., 0.), Quaternion::one()), [1, 1], size_request).unwrap();
+        assert_near(project(&mat, -1., -1.), [-0.125, -0.5]);
+        assert_near(project(&mat, 1., 1.), [0.375, 0.5]);
+
+        // z軸回りに90度回転
+        let mat = image_transform_mat(&params(Vector3::new(1., 1., 1.), Vector3::new(0., 0., 0.), Quaternion::from_angle_z(Rad(std::f64::consts::FRAC_PI_2))), [1, 1], size_request).unwrap();
+        assert_near(project(&mat, 1., 0.), [0., 1.]);
+
+        let free = ImageRequiredParamsTransformFixed::Free {
+            left_top: Vector3::new(0.5, 0.5, 0.),
+            right_top: Vector3::new(0.5, 0.5, 0.),
+            left_bottom: Vector3::new(0.5, 0.5, 0.),
+            right_bottom: Vector3::new(0.5, 0.5, 0.),
+        };
+        assert!(image_transform_mat(&free, [1, 1], size_request).is_none());
+    }
+}
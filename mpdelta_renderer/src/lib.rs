@@ -1,18 +1,19 @@
 use crate::heartbeat::{HeartbeatController, HeartbeatMonitor};
 use crate::lazy_init::LazyInit;
-use crate::render::Renderer;
+use crate::render::{top_level_image_size, Renderer};
 use arc_swap::ArcSwap;
 use async_trait::async_trait;
 use crossbeam_utils::atomic::AtomicCell;
-use futures::FutureExt;
-use mpdelta_core::common::mixed_fraction::MixedFraction;
+use futures::future::Either;
+use futures::{FutureExt, StreamExt};
 use mpdelta_core::component::instance::{ComponentInstance, ComponentInstanceId};
 use mpdelta_core::component::link::MarkerLink;
 use mpdelta_core::component::marker_pin::{MarkerPinId, MarkerTime};
-use mpdelta_core::component::parameter::{ImageRequiredParamsFixed, ImageRequiredParamsTransformFixed, Parameter, ParameterSelect, ParameterType, ParameterValueType};
-use mpdelta_core::component::processor::{DynGatherNativeParameter, ProcessorCache};
+use mpdelta_core::component::parameter::{ImageRequiredParamsFixed, ImageRequiredParamsTransformFixed, Parameter, ParameterSelect, ParameterType, ParameterValueRaw, ParameterValueType};
+use mpdelta_core::component::processor::{DynGatherNativeParameter, ImageSize, ProcessorCache};
 use mpdelta_core::core::{ComponentEncoder, ComponentRendererBuilder};
-use mpdelta_core::time::TimelineTime;
+use mpdelta_core::encode::{EncodeMonitor, EncodePhase, EncodeProgress};
+use mpdelta_core::time::{FrameRate, TimelineTime};
 use mpdelta_core::usecase::RealtimeComponentRenderer;
 use mpdelta_differential::CollectCachedTimeError;
 use rpds::{RedBlackTreeMap, RedBlackTreeMapSync};
@@ -21,15 +22,23 @@ use std::error::Error;
 use std::fmt::{Debug, Display, Formatter};
 use std::future::Future;
 use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::num::NonZeroUsize;
 use std::ops::{Deref, DerefMut, Range};
+use std::panic;
+use std::pin::Pin;
 use std::sync::{Arc, RwLock as StdRwLock};
+use std::task::{Context, Poll};
+use std::time::Instant;
 use thiserror::Error;
 use tokio::runtime::Handle;
 use tokio::sync::mpsc::error::SendError;
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::oneshot;
+use tokio::task::{JoinError, JoinHandle};
 
 mod heartbeat;
+mod image_transform;
 mod invalidate_range;
 mod lazy_init;
 mod render;
@@ -37,6 +46,7 @@ mod render;
 mod tests;
 mod time_stretch;
 
+pub use image_transform::{free_transform_mat, image_transform_mat};
 pub use invalidate_range::InvalidateRange;
 pub use time_stretch::{GlobalTime, LocalTime, TimeStretch, TimeStretchSegment};
 
@@ -90,12 +100,38 @@ impl Error for DynError {
     }
 }
 
+/// エンコード時に先行して並列に描画するフレーム数の設定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodePipelineConfig {
+    /// 同時に描画するフレーム数の上限
+    pub concurrency: usize,
+    /// 描画済みでエンコード待ちのフレームが使ってよいメモリ量の上限(バイト)
+    pub memory_budget: usize,
+}
+
+impl EncodePipelineConfig {
+    /// 1フレームあたり`frame_bytes`バイトのとき、同時に保持するフレーム数 最低でも1
+    pub fn frames_in_flight(&self, frame_bytes: usize) -> usize {
+        self.concurrency.min(self.memory_budget / frame_bytes.max(1)).max(1)
+    }
+}
+
+impl Default for EncodePipelineConfig {
+    fn default() -> Self {
+        EncodePipelineConfig {
+            concurrency: std::thread::available_parallelism().map_or(4, NonZeroUsize::get),
+            memory_budget: 1 << 30,
+        }
+    }
+}
+
 pub struct MPDeltaRendererBuilder<C, ImageCombinerBuilder, AudioCombinerBuilder, Cache> {
     controller_builder: Arc<C>,
     image_combiner_builder: Arc<ImageCombinerBuilder>,
     audio_combiner_builder: Arc<AudioCombinerBuilder>,
     cache: Cache,
     runtime: Handle,
+    encode_pipeline: EncodePipelineConfig,
 }
 
 impl<C, ImageCombinerBuilder, AudioCombinerBuilder, Cache> MPDeltaRendererBuilder<C, ImageCombinerBuilder, AudioCombinerBuilder, Cache> {
@@ -106,6 +142,91 @@ impl<C, ImageCombinerBuilder, AudioCombinerBuilder, Cache> MPDeltaRendererBuilde
             audio_combiner_builder,
             cache,
             runtime,
+            encode_pipeline: EncodePipelineConfig::default(),
+        }
+    }
+
+    pub fn with_encode_pipeline(self, encode_pipeline: EncodePipelineConfig) -> MPDeltaRendererBuilder<C, ImageCombinerBuilder, AudioCombinerBuilder, Cache> {
+        MPDeltaRendererBuilder { encode_pipeline, ..self }
+    }
+}
+
+/// dropされたときにタスクを中断するJoinHandle
+struct AbortOnDrop<T>(JoinHandle<T>);
+
+impl<T> Future for AbortOnDrop<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx)
+    }
+}
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// 最後まで書き出す前にdropされたとき(パニックやFutureの破棄)にエンコーダをcancelする
+///
+/// cancelのエラーを返したいときは`cancel`を直接呼ぶ
+struct CancelOnDrop<E, Image, Audio>
+where
+    E: VideoEncoder<Image, Audio>,
+{
+    encoder: E,
+    armed: bool,
+    _phantom: PhantomData<fn(Image, Audio)>,
+}
+
+impl<E, Image, Audio> CancelOnDrop<E, Image, Audio>
+where
+    E: VideoEncoder<Image, Audio>,
+{
+    fn new(encoder: E) -> CancelOnDrop<E, Image, Audio> {
+        CancelOnDrop { encoder, armed: true, _phantom: PhantomData }
+    }
+
+    fn cancel(&mut self) -> Result<(), E::Err> {
+        self.armed = false;
+        self.encoder.cancel()
+    }
+
+    /// 書き出しが終わったので、dropしてもcancelしないようにする
+    fn disarm(&mut self) {
+        self.armed = false;
+    }
+}
+
+impl<E, Image, Audio> Deref for CancelOnDrop<E, Image, Audio>
+where
+    E: VideoEncoder<Image, Audio>,
+{
+    type Target = E;
+
+    fn deref(&self) -> &E {
+        &self.encoder
+    }
+}
+
+impl<E, Image, Audio> DerefMut for CancelOnDrop<E, Image, Audio>
+where
+    E: VideoEncoder<Image, Audio>,
+{
+    fn deref_mut(&mut self) -> &mut E {
+        &mut self.encoder
+    }
+}
+
+impl<E, Image, Audio> Drop for CancelOnDrop<E, Image, Audio>
+where
+    E: VideoEncoder<Image, Audio>,
+{
+    fn drop(&mut self) {
+        if self.armed {
+            // 呼び出し元に返す先がないので、ここでのエラーは捨てる
+            let _ = self.encoder.cancel();
         }
     }
 }
@@ -125,21 +246,18 @@ where
     async fn create_renderer(&self, component: Arc<ComponentInstance<T>>) -> Result<Self::Renderer, Self::Err> {
         let (controller, loop_heartbeat) = heartbeat::heartbeat();
         let images = Arc::new(ArcSwap::new(Arc::new(RedBlackTreeMap::new_sync())));
-        let (sender, component_length, future) = rendering_loop(
-            component.clone(),
-            &*self.controller_builder,
-            Arc::clone(&self.image_combiner_builder),
-            Arc::clone(&self.audio_combiner_builder),
-            self.cache.clone(),
-            Handle::current(),
-            controller,
-            Arc::clone(&images),
-        );
+        let image_size = top_level_image_size(&component).await;
+        let renderer = Arc::new(Renderer::new(component.clone(), image_size, Handle::current(), Arc::clone(&self.image_combiner_builder), Arc::clone(&self.audio_combiner_builder), self.cache.clone()));
+        // rendering loopが起動するまでの間もプレビューが正しいフレーム番号を計算できるよう、先に求めておく
+        let frame_rate = Arc::new(AtomicCell::new(renderer.frame_rate().await.unwrap_or_default()));
+        let (sender, component_length, future) = rendering_loop(renderer, component.clone(), Arc::clone(&self.controller_builder), Handle::current(), controller, Arc::clone(&images), Arc::clone(&frame_rate));
         let component_natural_length = AtomicCell::new(component_length);
         self.runtime.spawn(future);
         Ok(MPDeltaRenderer {
             component,
+            image_size,
             component_natural_length,
+            frame_rate,
             controller_builder: Arc::clone(&self.controller_builder),
             image_combiner_builder: Arc::clone(&self.image_combiner_builder),
             audio_combiner_builder: Arc::clone(&self.audio_combiner_builder),
@@ -158,6 +276,8 @@ pub enum EncodeError<E> {
     RenderError(#[from] RenderError),
     #[error("encoder error: {0}")]
     EncoderError(E),
+    #[error("encode cancelled")]
+    Cancelled,
 }
 
 impl<E> Debug for EncodeError<E>
@@ -168,6 +288,7 @@ where
         match self {
             EncodeError::RenderError(e) => f.debug_tuple("RenderError").field(e).finish(),
             EncodeError::EncoderError(e) => f.debug_tuple("EncoderError").field(e).finish(),
+            EncodeError::Cancelled => f.write_str("Cancelled"),
         }
     }
 }
@@ -183,32 +304,128 @@ where
 {
     type Err = EncodeError<Encoder::Err>;
 
-    async fn render_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, mut encoder: Encoder) -> Result<(), Self::Err>
+    async fn render_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, encoder: Encoder, monitor: EncodeMonitor) -> Result<(), Self::Err>
     where
         'life0: 'async_trait,
     {
-        let mut encoder = encoder.build().map_err(EncodeError::EncoderError)?;
-        let renderer = Arc::new(Renderer::new(component.clone(), self.runtime.clone(), Arc::clone(&self.image_combiner_builder), Arc::clone(&self.audio_combiner_builder), self.cache.clone()));
-        let length = renderer.component_length();
-        if encoder.requires_audio() {
-            match renderer.render(TimelineTime::ZERO, ParameterType::Audio(())).await {
-                Ok(Parameter::Audio(value)) => encoder.set_audio(value),
-                Ok(other) => {
-                    return Err(RenderError::OutputTypeMismatch {
-                        component: *component.id(),
-                        expect: Parameter::Audio(()),
-                        actual: other.select(),
+        self.render_range_and_encode(component, TimelineTime::ZERO..TimelineTime::MAX, encoder, monitor).await
+    }
+
+    async fn render_range_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, range: Range<TimelineTime>, mut encoder: Encoder, monitor: EncodeMonitor) -> Result<(), Self::Err>
+    where
+        'life0: 'async_trait,
+    {
+        let start_time = Instant::now();
+        let report = |phase, frames_done, total_frames| {
+            monitor.report(EncodeProgress {
+                phase,
+                frames_done,
+                total_frames,
+                elapsed: start_time.elapsed(),
+            })
+        };
+        let mut encoder = CancelOnDrop::new(encoder.build().map_err(EncodeError::EncoderError)?);
+        // どこで失敗しても書きかけの出力が残らないように、エラーはまとめて受けてcancelする
+        let result = async {
+            let image_size = top_level_image_size(&component).await;
+            let renderer = Arc::new(Renderer::new(component.clone(), image_size, self.runtime.clone(), Arc::clone(&self.image_combiner_builder), Arc::clone(&self.audio_combiner_builder), self.cache.clone()));
+            let length = TimelineTime::from(renderer.component_length());
+            let range = range.start.max(TimelineTime::ZERO)..range.end.min(length);
+            if range.start >= range.end {
+                return encoder.finish().map_err(EncodeError::EncoderError);
+            }
+            let video = if encoder.requires_image() {
+                let frame_rate = renderer.frame_rate().await?;
+                Some((frame_rate, frame_rate.frame_round(range.start)..frame_rate.frame_round(range.end)))
+            } else {
+                None
+            };
+            if encoder.requires_audio() {
+                report(EncodePhase::Audio, 0, 0);
+                if video.is_none() {
+                    encoder.set_monitor(monitor.clone());
+                }
+                // 映像と長さが揃うように、映像があればフレームの境界に合わせる
+                let audio_range = match &video {
+                    Some((frame_rate, frames)) => frame_rate.time_of_frame(frames.start)..frame_rate.time_of_frame(frames.end),
+                    None => range.clone(),
+                };
+                match renderer.render(TimelineTime::ZERO, ParameterType::Audio(())).await? {
+                    Parameter::Audio(value) => encoder.set_audio_range(value, audio_range),
+                    other => {
+                        return Err(RenderError::OutputTypeMismatch {
+                            component: *component.id(),
+                            expect: Parameter::Audio(()),
+                            actual: other.select(),
+                        }
+                        .into());
+                    }
+                }
+            }
+            let mut length_frames = 0;
+            if let Some((frame_rate, frames)) = video {
+                length_frames = (frames.end - frames.start) as u64;
+                report(EncodePhase::Video, 0, length_frames);
+                let frames_in_flight = self.encode_pipeline.frames_in_flight(image_size.width as usize * image_size.height as usize * 4);
+                // 先のフレームを並列に描画しつつ、順番通りにエンコーダへ渡す
+                let mut rendered_frames = futures::stream::iter(frames.clone()).map(|f| AbortOnDrop(self.runtime.spawn(renderer.render(frame_rate.time_of_frame(f), ParameterType::Image(()))))).buffered(frames_in_flight);
+                for f in frames.clone() {
+                    // 描画を待っている間に中断されてもすぐに抜ける
+                    let rendered = tokio::select! {
+                        biased;
+                        _ = monitor.cancelled() => return Err(EncodeError::Cancelled),
+                        rendered = rendered_frames.next() => rendered.expect("rendered_frames yields as many items as frames"),
+                    };
+                    let rendered = match rendered {
+                        Ok(rendered) => rendered,
+                        Err(err) => panic::resume_unwind(err.into_panic()),
+                    };
+                    match rendered? {
+                        Parameter::Image(value) => encoder.push_frame(value),
+                        other => {
+                            return Err(RenderError::OutputTypeMismatch {
+                                component: *component.id(),
+                                expect: Parameter::Image(()),
+                                actual: other.select(),
+                            }
+                            .into());
+                        }
                     }
-                    .into());
+                    report(EncodePhase::Video, (f - frames.start + 1) as u64, length_frames);
                 }
-                Err(err) => return Err(err.into()),
             }
+            if monitor.is_cancelled() {
+                return Err(EncodeError::Cancelled);
+            }
+            if length_frames > 0 {
+                report(EncodePhase::Finalize, length_frames, length_frames);
+            }
+            encoder.finish().map_err(EncodeError::EncoderError)?;
+            // 音声だけのときはfinishの中でエンコードするので、その間に中断されていることがある
+            if monitor.is_cancelled() {
+                return Err(EncodeError::Cancelled);
+            }
+            Ok(())
         }
-        if encoder.requires_image() {
-            let (i, n) = length.value().deconstruct_with_round(60);
-            let length_frames = i as i64 * 60 + n as i64;
-            for f in 0..length_frames {
-                match renderer.render(TimelineTime::new(MixedFraction::from_fraction(f, 60)), ParameterType::Image(())).await {
+        .await;
+        if let Err(err) = result {
+            encoder.cancel().map_err(EncodeError::EncoderError)?;
+            return Err(err);
+        }
+        encoder.disarm();
+        Ok(())
+    }
+
+    async fn render_frame_and_encode<'life0, 'async_trait>(&'life0 self, component: Arc<ComponentInstance<T>>, at: TimelineTime, mut encoder: Encoder) -> Result<(), Self::Err>
+    where
+        'life0: 'async_trait,
+    {
+        let mut encoder = CancelOnDrop::new(encoder.build().map_err(EncodeError::EncoderError)?);
+        let result = async {
+            let image_size = top_level_image_size(&component).await;
+            let renderer = Renderer::new(component.clone(), image_size, self.runtime.clone(), Arc::clone(&self.image_combiner_builder), Arc::clone(&self.audio_combiner_builder), self.cache.clone());
+            if encoder.requires_image() {
+                match renderer.render(at, ParameterType::Image(())).await {
                     Ok(Parameter::Image(value)) => encoder.push_frame(value),
                     Ok(other) => {
                         return Err(RenderError::OutputTypeMismatch {
@@ -218,24 +435,24 @@ where
                         }
                         .into());
                     }
-                    Err(err) => return Err(err.into()),
-                }
-                if f % 60 == 59 {
-                    println!("{} of {length_frames} frames rendered", f + 1);
+                    Err(err) => return Err(EncodeError::RenderError(err)),
                 }
             }
-            println!("{length_frames} for {length_frames} frames rendered");
+            encoder.finish().map_err(EncodeError::EncoderError)
+        }
+        .await;
+        if let Err(err) = result {
+            encoder.cancel().map_err(EncodeError::EncoderError)?;
+            return Err(err);
         }
-        println!("waiting for encode");
-        encoder.finish();
-        println!("encode finished");
+        encoder.disarm();
         Ok(())
     }
 }
 
 pub trait VideoEncoderBuilder<Image, Audio>: Send + Sync {
     type Err: Error + Send + 'static;
-    type Encoder: VideoEncoder<Image, Audio>;
+    type Encoder: VideoEncoder<Image, Audio, Err = Self::Err>;
     fn build(&mut self) -> Result<Self::Encoder, Self::Err>;
 }
 
@@ -252,7 +469,7 @@ where
 }
 
 pub trait VideoEncoderBuilderDyn<Image, Audio>: Send + Sync {
-    fn build_dyn(&mut self) -> Result<Box<dyn VideoEncoder<Image, Audio>>, Box<dyn Error + Send + 'static>>;
+    fn build_dyn(&mut self) -> Result<Box<dyn VideoEncoder<Image, Audio, Err = DynError>>, Box<dyn Error + Send + 'static>>;
 }
 
 impl<Image, Audio, O> VideoEncoderBuilderDyn<Image, Audio> for O
@@ -260,17 +477,59 @@ where
     O: VideoEncoderBuilder<Image, Audio>,
     O::Encoder: 'static,
 {
-    fn build_dyn(&mut self) -> Result<Box<dyn VideoEncoder<Image, Audio>>, Box<dyn Error + Send + 'static>> {
+    fn build_dyn(&mut self) -> Result<Box<dyn VideoEncoder<Image, Audio, Err = DynError>>, Box<dyn Error + Send + 'static>> {
         match self.build() {
-            Ok(encoder) => Ok(Box::new(encoder)),
+            Ok(encoder) => Ok(Box::new(DynVideoEncoder(encoder))),
             Err(err) => Err(Box::new(err)),
         }
     }
 }
 
+/// エラーの型を`DynError`に揃えるためのラッパー
+struct DynVideoEncoder<E>(E);
+
+impl<Image, Audio, E> VideoEncoder<Image, Audio> for DynVideoEncoder<E>
+where
+    E: VideoEncoder<Image, Audio>,
+{
+    type Err = DynError;
+
+    fn requires_image(&self) -> bool {
+        self.0.requires_image()
+    }
+
+    fn push_frame(&mut self, frame: Image) {
+        self.0.push_frame(frame)
+    }
+
+    fn requires_audio(&self) -> bool {
+        self.0.requires_audio()
+    }
+
+    fn set_audio(&mut self, audio: Audio) {
+        self.0.set_audio(audio)
+    }
+
+    fn set_audio_range(&mut self, audio: Audio, range: Range<TimelineTime>) {
+        self.0.set_audio_range(audio, range)
+    }
+
+    fn finish(&mut self) -> Result<(), Self::Err> {
+        self.0.finish().map_err(|err| DynError(Box::new(err)))
+    }
+
+    fn set_monitor(&mut self, monitor: EncodeMonitor) {
+        self.0.set_monitor(monitor)
+    }
+
+    fn cancel(&mut self) -> Result<(), Self::Err> {
+        self.0.cancel().map_err(|err| DynError(Box::new(err)))
+    }
+}
+
 impl<Image, Audio> VideoEncoderBuilder<Image, Audio> for dyn VideoEncoderBuilderDyn<Image, Audio> {
     type Err = DynError;
-    type Encoder = Box<dyn VideoEncoder<Image, Audio>>;
+    type Encoder = Box<dyn VideoEncoder<Image, Audio, Err = DynError>>;
 
     fn build(&mut self) -> Result<Self::Encoder, Self::Err> {
         self.build_dyn().map_err(DynError)
@@ -278,11 +537,19 @@ impl<Image, Audio> VideoEncoderBuilder<Image, Audio> for dyn VideoEncoderBuilder
 }
 
 pub trait VideoEncoder<Image, Audio>: Send + Sync {
+    type Err: Error + Send + 'static;
     fn requires_image(&self) -> bool;
     fn push_frame(&mut self, frame: Image);
     fn requires_audio(&self) -> bool;
     fn set_audio(&mut self, audio: Audio);
-    fn finish(&mut self);
+    /// 音声のうち`range`の範囲だけを、`range.start`を先頭としてエンコードする
+    fn set_audio_range(&mut self, audio: Audio, range: Range<TimelineTime>);
+    /// 残りを書き出して終了する エンコード中に起きたエラーはここで返す
+    fn finish(&mut self) -> Result<(), Self::Err>;
+    /// 音声だけを書き出すとき、`finish`の中で行う音声のエンコードの進捗の報告先と中断要求の確認先
+    fn set_monitor(&mut self, _: EncodeMonitor) {}
+    /// エンコードを中断し、途中までの出力を破棄する 破棄できなかったときはそのエラーを返す
+    fn cancel(&mut self) -> Result<(), Self::Err>;
 }
 
 impl<Image, Audio, O> VideoEncoder<Image, Audio> for O
@@ -290,6 +557,8 @@ where
     O: DerefMut + Send + Sync,
     O::Target: VideoEncoder<Image, Audio>,
 {
+    type Err = <O::Target as VideoEncoder<Image, Audio>>::Err;
+
     fn requires_image(&self) -> bool {
         self.deref().requires_image()
     }
@@ -306,14 +575,36 @@ where
         self.deref_mut().set_audio(audio)
     }
 
-    fn finish(&mut self) {
+    fn set_audio_range(&mut self, audio: Audio, range: Range<TimelineTime>) {
+        self.deref_mut().set_audio_range(audio, range)
+    }
+
+    fn finish(&mut self) -> Result<(), Self::Err> {
         self.deref_mut().finish()
     }
+
+    fn set_monitor(&mut self, monitor: EncodeMonitor) {
+        self.deref_mut().set_monitor(monitor)
+    }
+
+    fn cancel(&mut self) -> Result<(), Self::Err> {
+        self.deref_mut().cancel()
+    }
 }
 
 enum RenderingMessage<T: ParameterValueType> {
-    RequestRenderFrame { frame: usize },
-    RequestConstructAudio { ret: oneshot::Sender<RenderResult<T::Audio>> },
+    RequestRenderFrame {
+        frame: usize,
+    },
+    RequestConstructAudio {
+        ret: oneshot::Sender<RenderResult<T::Audio>>,
+    },
+    RequestRenderParam {
+        target: Option<ComponentInstanceId>,
+        at: TimelineTime,
+        ty: ParameterType,
+        ret: oneshot::Sender<RenderResult<ParameterValueRaw<T::Image, T::Audio>>>,
+    },
 }
 
 pub enum RenderingControllerItem {
@@ -323,7 +614,7 @@ pub enum RenderingControllerItem {
 
 pub trait MPDeltaRenderingControllerBuilder: Send + Sync {
     type Controller<F: Fn(RenderingControllerItem) + Send + Sync + 'static>: MPDeltaRenderingController;
-    fn create<F: Fn(RenderingControllerItem) + Send + Sync + 'static>(&self, f: F) -> Self::Controller<F>;
+    fn create<F: Fn(RenderingControllerItem) + Send + Sync + 'static>(&self, frame_rate: FrameRate, f: F) -> Self::Controller<F>;
 }
 
 pub trait MPDeltaRenderingController: Send + Sync + 'static {
@@ -332,36 +623,37 @@ pub trait MPDeltaRenderingController: Send + Sync + 'static {
 
 type Images<T> = RedBlackTreeMapSync<usize, LazyInit<Result<<T as ParameterValueType>::Image, RenderError>>>;
 
-// TODO: あとでなんとかするかも
-#[allow(clippy::too_many_arguments)]
+type LoopRenderer<T, ImageCombinerBuilder, AudioCombinerBuilder, Cache> = Renderer<T, Arc<ImageCombinerBuilder>, Arc<AudioCombinerBuilder>, Cache>;
+
 fn rendering_loop<T, C, ImageCombinerBuilder, AudioCombinerBuilder, Cache>(
+    renderer: Arc<LoopRenderer<T, ImageCombinerBuilder, AudioCombinerBuilder, Cache>>,
     component: Arc<ComponentInstance<T>>,
-    controller_builder: &C,
-    image_combiner_builder: Arc<ImageCombinerBuilder>,
-    audio_combiner_builder: Arc<AudioCombinerBuilder>,
-    cache: Cache,
+    controller_builder: Arc<C>,
     runtime: Handle,
     heartbeat_controller: HeartbeatController,
     images: Arc<ArcSwap<Images<T>>>,
+    frame_rate: Arc<AtomicCell<FrameRate>>,
 ) -> (UnboundedSender<RenderingMessage<T>>, MarkerTime, impl Future<Output = ()> + Send + 'static)
 where
     T: ParameterValueType + 'static,
-    C: MPDeltaRenderingControllerBuilder,
+    C: MPDeltaRenderingControllerBuilder + 'static,
     ImageCombinerBuilder: CombinerBuilder<T::Image, Request = ImageCombinerRequest, Param = ImageCombinerParam> + 'static,
     AudioCombinerBuilder: CombinerBuilder<T::Audio, Request = AudioCombinerRequest, Param = AudioCombinerParam> + 'static,
     Cache: ProcessorCache + 'static,
 {
     let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
     let (controller_sender, mut controller_receiver) = tokio::sync::mpsc::unbounded_channel();
-    let renderer = Arc::new(Renderer::new(component.clone(), runtime.clone(), image_combiner_builder, audio_combiner_builder, cache));
     let component_length = renderer.component_length();
-    let controller = controller_builder.create(move |message| {
-        let _ = controller_sender.send(message);
-    });
     images.store(Arc::new(RedBlackTreeMap::new_sync()));
     #[allow(unreachable_code)] // heartbeat_controllerはdropされるときの通知を担当するので、panicしない場合ずっとdropされずにいなければならない
     let future = async move {
         let _heartbeat_controller = heartbeat_controller;
+        // フレームレートはRootComponentClassの設定なので、processしてみないとわからない
+        let current_frame_rate = renderer.frame_rate().await.unwrap_or_default();
+        frame_rate.store(current_frame_rate);
+        let controller = controller_builder.create(current_frame_rate, move |message| {
+            let _ = controller_sender.send(message);
+        });
         loop {
             tokio::select! {
                 message = receiver.recv() => {
@@ -384,6 +676,25 @@ where
                                 let _ = ret.send(result);
                             });
                         }
+                        RenderingMessage::RequestRenderParam { target, at, ty, ret } => {
+                            let render = match target {
+                                Some(target) => Either::Left(renderer.render_component(target, at, ty)),
+                                None => Either::Right(renderer.render(at, ty)),
+                            };
+                            let component = target.unwrap_or(*component.id());
+                            runtime.spawn(async move {
+                                let result = match render.await {
+                                    Ok(value) if value.select() == ty.select() => Ok(value),
+                                    Ok(value) => Err(RenderError::OutputTypeMismatch {
+                                        component,
+                                        expect: ty.select(),
+                                        actual: value.select(),
+                                    }),
+                                    Err(e) => Err(e),
+                                };
+                                let _ = ret.send(result);
+                            });
+                        }
                     }
                 }
                 Some(message) = controller_receiver.recv() => {
@@ -391,7 +702,7 @@ where
                         RenderingControllerItem::RequestRender {frame} => {
                             let renderer = Arc::clone(&renderer);
                             let component_id = *component.id();
-                            let result = LazyInit::new(renderer.render(TimelineTime::new(MixedFraction::from_fraction(frame as i64, 60)), ParameterType::Image(()))
+                            let result = LazyInit::new(renderer.render(current_frame_rate.time_of_frame(frame as i64), ParameterType::Image(()))
                                     .map(move |result| match result {
                                         Ok(Parameter::Image(value)) => Ok(value),
                                         Ok(value) => Err(RenderError::OutputTypeMismatch {
@@ -419,7 +730,9 @@ where
 
 pub struct MPDeltaRenderer<T: ParameterValueType, C, ImageCombinerBuilder, AudioCombinerBuilder, Cache> {
     component: Arc<ComponentInstance<T>>,
+    image_size: ImageSize,
     component_natural_length: AtomicCell<MarkerTime>,
+    frame_rate: Arc<AtomicCell<FrameRate>>,
     controller_builder: Arc<C>,
     image_combiner_builder: Arc<ImageCombinerBuilder>,
     audio_combiner_builder: Arc<AudioCombinerBuilder>,
@@ -446,12 +759,16 @@ pub enum RenderError {
     InvalidVariableParameter { component: ComponentInstanceId, index: usize },
     #[error("time {at:?} is out of range {range:?}")]
     RenderTargetTimeOutOfRange { component: ComponentInstanceId, range: Range<TimelineTime>, at: TimelineTime },
+    #[error("component class referenced by {0:?} contains itself")]
+    RecursiveComponentClass(ComponentInstanceId),
     #[error("required type value is not provided")]
     NotProvided,
     #[error("timeout")]
     Timeout,
     #[error("unsupported parameter type")]
     UnsupportedParameterType,
+    #[error("rendering task was aborted")]
+    Aborted,
     #[error("{0}")]
     UnknownError(#[from] Arc<dyn Error + Send + Sync + 'static>),
 }
@@ -475,9 +792,11 @@ impl Debug for RenderError {
             RenderError::InvalidMarkerLink(l) => f.debug_tuple("InvalidMarkerLink").field(l).finish(),
             RenderError::InvalidVariableParameter { component, index } => f.debug_struct("InvalidVariableParameter").field("component", component).field("index", index).finish(),
             RenderError::RenderTargetTimeOutOfRange { component, range, at } => f.debug_struct("FrameOutOfRange").field("component", component).field("range", range).field("at", at).finish(),
+            RenderError::RecursiveComponentClass(c) => f.debug_tuple("RecursiveComponentClass").field(c).finish(),
             RenderError::NotProvided => f.debug_struct("NotProvided").finish(),
             RenderError::Timeout => f.debug_struct("Timeout").finish(),
             RenderError::UnsupportedParameterType => f.debug_struct("UnsupportedParameterType").finish(),
+            RenderError::Aborted => f.debug_struct("Aborted").finish(),
             RenderError::UnknownError(error) => f.debug_tuple("UnknownError").field(error).finish(),
         }
     }
@@ -497,14 +816,62 @@ impl Clone for RenderError {
             RenderError::InvalidMarkerLink(handle) => RenderError::InvalidMarkerLink(handle.clone()),
             RenderError::InvalidVariableParameter { component, index } => RenderError::InvalidVariableParameter { component: *component, index: *index },
             RenderError::RenderTargetTimeOutOfRange { component, range, at } => RenderError::RenderTargetTimeOutOfRange { component: *component, range: range.clone(), at: *at },
+            RenderError::RecursiveComponentClass(handle) => RenderError::RecursiveComponentClass(*handle),
             RenderError::NotProvided => RenderError::NotProvided,
             RenderError::Timeout => RenderError::Timeout,
             RenderError::UnsupportedParameterType => RenderError::UnsupportedParameterType,
+            RenderError::Aborted => RenderError::Aborted,
             RenderError::UnknownError(error) => RenderError::UnknownError(error.clone()),
         }
     }
 }
 
+impl<T, C, ImageCombinerBuilder, AudioCombinerBuilder, Cache> MPDeltaRenderer<T, C, ImageCombinerBuilder, AudioCombinerBuilder, Cache>
+where
+    T: ParameterValueType + 'static,
+    C: MPDeltaRenderingControllerBuilder + 'static,
+    ImageCombinerBuilder: CombinerBuilder<T::Image, Request = ImageCombinerRequest, Param = ImageCombinerParam> + 'static,
+    AudioCombinerBuilder: CombinerBuilder<T::Audio, Request = AudioCombinerRequest, Param = AudioCombinerParam> + 'static,
+    Cache: ProcessorCache + Clone + 'static,
+{
+    fn new_renderer(&self) -> Arc<LoopRenderer<T, ImageCombinerBuilder, AudioCombinerBuilder, Cache>> {
+        Arc::new(Renderer::new(self.component.clone(), self.image_size, self.runtime.clone(), Arc::clone(&self.image_combiner_builder), Arc::clone(&self.audio_combiner_builder), self.cache.clone()))
+    }
+
+    /// rendering loopにメッセージを送る
+    ///
+    /// rendering loopが止まっていた場合は起動しなおしてから送る
+    fn send_message(&self, message: RenderingMessage<T>) {
+        let mut message = Some(message);
+        loop {
+            match self.loop_sender.load().send(message.take().unwrap()) {
+                Ok(()) => break,
+                Err(SendError(failed_message)) => {
+                    message = Some(failed_message);
+                    let mut loop_heartbeat = self.loop_heartbeat.write().unwrap();
+                    if loop_heartbeat.is_live() {
+                        continue;
+                    }
+                    let (heartbeat_controller, new_monitor) = heartbeat::heartbeat();
+                    let (new_loop_sender, component_length, fut) = rendering_loop(
+                        self.new_renderer(),
+                        self.component.clone(),
+                        Arc::clone(&self.controller_builder),
+                        self.runtime.clone(),
+                        heartbeat_controller,
+                        Arc::clone(&self.images),
+                        Arc::clone(&self.frame_rate),
+                    );
+                    self.component_natural_length.store(component_length);
+                    *loop_heartbeat = new_monitor;
+                    self.runtime.spawn(fut);
+                    self.loop_sender.store(Arc::new(new_loop_sender));
+                }
+            };
+        }
+    }
+}
+
 impl<T, C, ImageCombinerBuilder, AudioCombinerBuilder, Cache> RealtimeComponentRenderer<T> for MPDeltaRenderer<T, C, ImageCombinerBuilder, AudioCombinerBuilder, Cache>
 where
     T: ParameterValueType + 'static,
@@ -519,6 +886,10 @@ where
         Some(self.component_natural_length.load())
     }
 
+    fn frame_rate(&self) -> FrameRate {
+        self.frame_rate.load()
+    }
+
     fn render_frame(&self, frame: usize) -> Result<T::Image, Self::Err> {
         let result = self.images.load().get(&frame).and_then(|image| image.get().as_deref().cloned()).unwrap_or(Err(RenderError::Timeout));
         if !self.loop_heartbeat.read().unwrap().is_live() {
@@ -528,14 +899,13 @@ where
             }
             let (heartbeat_controller, new_monitor) = heartbeat::heartbeat();
             let (new_loop_sender, component_length, fut) = rendering_loop(
+                self.new_renderer(),
                 self.component.clone(),
-                &*self.controller_builder,
-                Arc::clone(&self.image_combiner_builder),
-                Arc::clone(&self.audio_combiner_builder),
-                self.cache.clone(),
+                Arc::clone(&self.controller_builder),
                 self.runtime.clone(),
                 heartbeat_controller,
                 Arc::clone(&self.images),
+                Arc::clone(&self.frame_rate),
             );
             self.component_natural_length.store(component_length);
             self.runtime.spawn(fut);
@@ -553,35 +923,7 @@ where
     async fn mix_audio(&self, _offset: usize, _length: usize) -> Result<T::Audio, Self::Err> {
         loop {
             let (sender, receiver) = oneshot::channel();
-            let mut message = Some(RenderingMessage::RequestConstructAudio { ret: sender });
-            loop {
-                match self.loop_sender.load().send(message.take().unwrap()) {
-                    Ok(()) => break,
-                    Err(SendError(failed_message)) => {
-                        message = Some(failed_message);
-                        let mut loop_heartbeat = self.loop_heartbeat.write().unwrap();
-                        if loop_heartbeat.is_live() {
-                            continue;
-                        }
-                        let (heartbeat_controller, new_monitor) = heartbeat::heartbeat();
-                        dbg!();
-                        let (new_loop_sender, component_length, fut) = rendering_loop(
-                            self.component.clone(),
-                            &*self.controller_builder,
-                            Arc::clone(&self.image_combiner_builder),
-                            Arc::clone(&self.audio_combiner_builder),
-                            self.cache.clone(),
-                            self.runtime.clone(),
-                            heartbeat_controller,
-                            Arc::clone(&self.images),
-                        );
-                        self.component_natural_length.store(component_length);
-                        *loop_heartbeat = new_monitor;
-                        self.runtime.spawn(fut);
-                        self.loop_sender.store(Arc::new(new_loop_sender));
-                    }
-                };
-            }
+            self.send_message(RenderingMessage::RequestConstructAudio { ret: sender });
             let result = receiver.await;
             match result {
                 Ok(Ok(result)) => break Ok(result),
@@ -594,8 +936,15 @@ where
         }
     }
 
-    fn render_param(&self, _param: Parameter<ParameterSelect>) -> Result<Parameter<T>, Self::Err> {
-        todo!()
+    async fn render_param(&self, target: Option<ComponentInstanceId>, at: TimelineTime, ty: ParameterType) -> Result<ParameterValueRaw<T::Image, T::Audio>, Self::Err> {
+        match ty {
+            ParameterType::Binary(_) | ParameterType::String(_) | ParameterType::Integer(_) | ParameterType::RealNumber(_) | ParameterType::Boolean(_) | ParameterType::Dictionary(_) | ParameterType::Array(_) => {}
+            ParameterType::None | ParameterType::Image(_) | ParameterType::Audio(_) | ParameterType::ComponentClass(_) => return Err(RenderError::UnsupportedParameterType),
+        }
+        let (sender, receiver) = oneshot::channel();
+        self.send_message(RenderingMessage::RequestRenderParam { target, at, ty, ret: sender });
+        // 描画タスクがpanicすると結果を返さずにsenderがdropされる
+        receiver.await.unwrap_or(Err(RenderError::Aborted))
     }
 }
 
@@ -22,7 +22,7 @@ use mpdelta_core::component::processor::{
 };
 use mpdelta_core::core::IdGenerator;
 use mpdelta_core::ptr::{StaticPointer, StaticPointerOwned};
-use mpdelta_core::time::TimelineTime;
+use mpdelta_core::time::{FrameRate, TimelineTime};
 use rpds::VectorSync;
 use std::any::Any;
 use std::borrow::Cow;
@@ -93,8 +93,13 @@ struct EvaluationContext<T: ParameterValueType, ImageCombinerBuilder, AudioCombi
     variable_parameters_placeholder_owned: Box<[StaticPointerOwned<RwLock<dyn ComponentClass<T>>>]>,
     fixed_parameter_component_map: HashMap<ParameterComponentMapKey<T>, ParameterValueRaw<T::Image, T::Audio>>,
     variable_parameter_component_map: HashMap<ParameterComponentMapKey<T>, ParameterGatherNativeProcessorParam<T::Image, T::Audio>>,
+    /// このコンテキストを展開するまでに通ったComponentClass 自己参照の検出に使う
+    ancestor_classes: Arc<[StaticPointer<RwLock<dyn ComponentClass<T>>>]>,
 }
 
+/// Componentの入れ子の最大の深さ 参照をたどって無限に展開されるのを防ぐ
+const MAX_COMPONENT_NESTING_DEPTH: usize = 64;
+
 struct ParameterComponentMapKey<T>(*const dyn ComponentProcessorNativeDyn<T>);
 
 // SAFETY: これはアドレス値の比較のためにのみ用いるため安全
@@ -228,6 +233,27 @@ macro_rules! make_map {
     };
 }
 
+/// NativeProcessorに渡すvariable parameterはAudioを含められないので、Dictionary/Arrayの中身も含めて取り除く
+fn without_audio<Image, Audio>(value: ParameterValueRaw<Image, Audio>) -> RenderResult<ParameterValueRaw<Image, Never>>
+where
+    Image: Send + Sync + Clone + 'static,
+    Audio: Send + Sync + Clone + 'static,
+{
+    match value {
+        Parameter::None => Ok(Parameter::None),
+        Parameter::Image(value) => Ok(Parameter::Image(value)),
+        Parameter::Audio(_) => Err(RenderError::UnsupportedParameterType),
+        Parameter::Binary(value) => Ok(Parameter::Binary(value)),
+        Parameter::String(value) => Ok(Parameter::String(value)),
+        Parameter::Integer(value) => Ok(Parameter::Integer(value)),
+        Parameter::RealNumber(value) => Ok(Parameter::RealNumber(value)),
+        Parameter::Boolean(value) => Ok(Parameter::Boolean(value)),
+        Parameter::Dictionary(value) => value.into_iter().map(|(key, value)| Ok((key, without_audio(value)?))).collect::<RenderResult<_>>().map(Parameter::Dictionary),
+        Parameter::Array(value) => value.into_iter().map(without_audio).collect::<RenderResult<_>>().map(Parameter::Array),
+        Parameter::ComponentClass(value) => Ok(Parameter::ComponentClass(value)),
+    }
+}
+
 struct ParameterForComponent<T> {
     component_class_owned: StaticPointerOwned<RwLock<dyn ComponentClass<T>>>,
     parameter: Arc<dyn ComponentProcessorNativeDyn<T>>,
@@ -369,9 +395,13 @@ where
                 self.combine_by_replace(ParameterType::RealNumber(()), value, components, invalidate_ranges, priority, at, make_map!(into_real_number), Default::default).await?,
             )),
             ParameterNullableValue::Boolean(value) => Ok(Parameter::Boolean(self.combine_by_replace(ParameterType::Boolean(()), value, components, invalidate_ranges, priority, at, make_map!(into_boolean), Default::default).await?)),
-            ParameterNullableValue::Dictionary(_) => unimplemented!(),
-            ParameterNullableValue::Array(_) => unimplemented!(),
-            ParameterNullableValue::ComponentClass(_) => unimplemented!(),
+            ParameterNullableValue::Dictionary(value) => without_audio(Parameter::Dictionary(
+                self.combine_by_replace(ParameterType::Dictionary(Vec::new()), value, components, invalidate_ranges, priority, at, make_map!(into_dictionary), Default::default).await?,
+            )),
+            ParameterNullableValue::Array(value) => without_audio(Parameter::Array(
+                self.combine_by_replace(ParameterType::Array(Box::new(Parameter::None)), value, components, invalidate_ranges, priority, at, make_map!(into_array), Default::default).await?,
+            )),
+            ParameterNullableValue::ComponentClass(_) => Ok(Parameter::ComponentClass(())),
         }
     }
 
@@ -387,10 +417,13 @@ where
         let &VariableParameterValue { ref params, ref components, priority } = param;
         macro_rules! make_param {
             ($parameter_type:ident, $value:expr, $into:ident) => {
+                make_param!($parameter_type, ParameterType::$parameter_type(()), $value, $into)
+            };
+            ($parameter_type:ident, $ty:expr, $value:expr, $into:ident) => {
                 Ok(ParameterGatherNativeProcessorParam::<T::Image, T::Audio>::$parameter_type(DynGatherNativeParameter::new(CombineByReplaceParam {
                     eval_ctx: Arc::clone(self),
                     time_map: Arc::clone(invert_time_map),
-                    parameter_type: ParameterType::$parameter_type(()),
+                    parameter_type: $ty,
                     value: $value.clone(),
                     components: components.clone(),
                     invalidate_ranges: Arc::clone(invalidate_ranges),
@@ -426,9 +459,9 @@ where
             ParameterNullableValue::Integer(value) => make_param!(Integer, value, into_integer),
             ParameterNullableValue::RealNumber(value) => make_param!(RealNumber, value, into_real_number),
             ParameterNullableValue::Boolean(value) => make_param!(Boolean, value, into_boolean),
-            ParameterNullableValue::Dictionary(_) => unimplemented!(),
-            ParameterNullableValue::Array(_) => unimplemented!(),
-            ParameterNullableValue::ComponentClass(_) => unimplemented!(),
+            ParameterNullableValue::Dictionary(value) => make_param!(Dictionary, ParameterType::Dictionary(Vec::new()), value, into_dictionary),
+            ParameterNullableValue::Array(value) => make_param!(Array, ParameterType::Array(Box::new(Parameter::None)), value, into_array),
+            ParameterNullableValue::ComponentClass(_) => Ok(Parameter::ComponentClass(DynGatherNativeParameter::new(ConstantParam(())))),
         }
     }
 
@@ -683,6 +716,19 @@ where
     }
 }
 
+#[derive(Clone)]
+struct ConstantParam<V>(V);
+
+impl<V> GatherNativeParameter<V> for ConstantParam<V>
+where
+    V: Clone + Send + Sync + 'static,
+{
+    type Err = RenderError;
+    async fn get_param(&self, _: TimelineTime) -> Result<V, Self::Err> {
+        Ok(self.0.clone())
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct CachePointer(*const (dyn Any + Send + Sync + 'static));
 
@@ -702,6 +748,7 @@ enum ComponentRendererState<T: ParameterValueType, ImageCombinerBuilder, AudioCo
     Components {
         components: Arc<[(ComponentInstanceId, Arc<ComponentInvalidateRange>)]>,
         image_size: ImageSize,
+        frame_rate: FrameRate,
         time_map: Arc<TimeStretch<GlobalTime, LocalTime>>,
         invert_time_map: Arc<TimeStretch<LocalTime, GlobalTime>>,
         inner_evaluation_context: Arc<EvaluationContext<T, ImageCombinerBuilder, AudioCombinerBuilder, Cache>>,
@@ -750,6 +797,172 @@ where
         }
     }
 
+    async fn load_state(&self, eval_ctx: &Arc<EvaluationContext<T, ImageCombinerBuilder, AudioCombinerBuilder, Cache>>, invalidate_range: &ComponentInvalidateRange) -> RenderResult<Guard<Arc<ComponentRendererState<T, ImageCombinerBuilder, AudioCombinerBuilder, Cache>>>> {
+        loop {
+            let state = self.state.load();
+            if let ComponentRendererState::New(_) = &**state {
+                let write_guard = self.state_lock.lock().await;
+                let ComponentRendererState::New(component) = &**self.state.load() else {
+                    continue;
+                };
+                let time_map = Arc::new(TimeStretch::new(component.marker_left(), component.markers(), component.marker_right(), &eval_ctx.time_map));
+                let invert_time_map = Arc::new(time_map.invert().unwrap());
+
+                let new_state: ComponentRendererState<T, ImageCombinerBuilder, AudioCombinerBuilder, Cache> = match component.processor() {
+                    ComponentProcessorWrapper::Component(processor) => {
+                        let component_class = component.component_class();
+                        let referenced_classes = component
+                            .variable_parameters()
+                            .iter()
+                            .filter_map(|param| match &param.params {
+                                ParameterNullableValue::ComponentClass(Some(class)) => Some(class.clone()),
+                                _ => None,
+                            })
+                            .collect::<Vec<_>>();
+                        if eval_ctx.ancestor_classes.len() >= MAX_COMPONENT_NESTING_DEPTH || referenced_classes.iter().any(|class| class == component_class || eval_ctx.ancestor_classes.contains(class)) {
+                            return Err(RenderError::RecursiveComponentClass(*component.id()));
+                        }
+                        let interprocess_pins = component.interprocess_pins();
+                        let interprocess_pins = iter::once(component.marker_left())
+                            .chain(component.markers())
+                            .chain(iter::once(component.marker_right()))
+                            .filter_map(|p| interprocess_pins.contains(p.id()).then_some(*p.id()))
+                            .collect::<Vec<_>>();
+                        let transform = component.image_required_params().map(|params| (&*params.transform, &invalidate_range.image_required_params.as_ref().unwrap().transform));
+                        let fixed_parameters = eval_fixed_parameters(component.fixed_parameters()).collect::<Box<[_]>>();
+                        let component_length = processor.natural_length(&fixed_parameters, &interprocess_pins).await.into();
+                        let variable_parameters = stream::iter(component.variable_parameters().iter().zip(&invalidate_range.variable_parameters))
+                            .then(|(p, invalidate_ranges)| eval_ctx.variable_parameter_for_gather_native(transform, component_length, &time_map, &invert_time_map, p, invalidate_ranges))
+                            .try_collect::<Vec<_>>()
+                            .await?;
+                        let (fixed_parameters_placeholder_owned, fixed_parameter_processors): (Vec<_>, Vec<_>) = component
+                            .fixed_parameters_type()
+                            .iter()
+                            .map(|(_, ty)| eval_ctx.variable_parameter_for_component(ty.select()))
+                            .map(|ParameterForComponent { component_class_owned, parameter }| (component_class_owned, parameter))
+                            .unzip();
+                        let (variable_parameters_placeholder_owned, variable_parameter_processors): (Vec<_>, Vec<_>) = component
+                            .variable_parameters_type()
+                            .iter()
+                            .map(|(_, ty)| eval_ctx.variable_parameter_for_component(ty.select()))
+                            .map(|ParameterForComponent { component_class_owned, parameter }| (component_class_owned, parameter))
+                            .unzip();
+                        let fixed_parameters_placeholder = fixed_parameters_placeholder_owned.iter().map(StaticPointerOwned::reference).cloned().collect::<Vec<_>>();
+                        // ComponentClass型のvariable parameterは、placeholderではなく参照先のComponentClassそのものを渡す
+                        let variable_parameters_placeholder = variable_parameters_placeholder_owned
+                            .iter()
+                            .map(StaticPointerOwned::reference)
+                            .zip(component.variable_parameters().iter())
+                            .map(|(placeholder, param)| match &param.params {
+                                ParameterNullableValue::ComponentClass(Some(class)) => class.clone(),
+                                _ => placeholder.clone(),
+                            })
+                            .collect::<Vec<_>>();
+
+                        let variable_parameter_component_map = variable_parameter_processors.into_iter().zip(variable_parameters).map(|(p, param)| (ParameterComponentMapKey::new(&p), param)).collect();
+                        let fixed_parameter_component_map = fixed_parameter_processors.into_iter().zip(&fixed_parameters).map(|(p, param)| (ParameterComponentMapKey::new(&p), param.clone())).collect();
+
+                        let result = processor.process(&fixed_parameters, &fixed_parameters_placeholder, &interprocess_pins, &variable_parameters_placeholder, component.variable_parameters_type()).await;
+                        let inner_time_map = mpdelta_differential::collect_cached_time(&*result)?;
+                        let (component_ids, component_map): (Vec<_>, HashMap<_, _>) = result.components_dyn().map(|component| (*component.id(), (*component.id(), Arc::new(ComponentRenderer::new(Arc::clone(component)))))).unzip();
+                        let component_invalidate_range = collect_invalidate_range(&component_ids, &component_map, &inner_time_map);
+                        let inner_eval_ctx = EvaluationContext {
+                            render_ctx: Arc::clone(&eval_ctx.render_ctx),
+                            components: component_map,
+                            image_size: result.default_image_size(),
+                            time_map: Arc::new(inner_time_map),
+                            fixed_parameters_placeholder_owned: fixed_parameters_placeholder_owned.into_boxed_slice(),
+                            variable_parameters_placeholder_owned: variable_parameters_placeholder_owned.into_boxed_slice(),
+                            fixed_parameter_component_map,
+                            variable_parameter_component_map,
+                            ancestor_classes: eval_ctx.ancestor_classes.iter().chain(iter::once(component_class)).chain(&referenced_classes).cloned().collect(),
+                        };
+                        ComponentRendererState::Components {
+                            components: component_ids.into_iter().zip(component_invalidate_range).collect(),
+                            image_size: result.default_image_size(),
+                            frame_rate: result.frames_per_second(),
+                            time_map,
+                            invert_time_map,
+                            inner_evaluation_context: Arc::new(inner_eval_ctx),
+                        }
+                    }
+                    ComponentProcessorWrapper::Native(processor) => {
+                        if let Some(fixed_param) = eval_ctx.fixed_parameter_component_map.get(&ParameterComponentMapKey::new(processor)) {
+                            ComponentRendererState::FixedParameter {
+                                time_map,
+                                invert_time_map,
+                                fixed_param: fixed_param.clone(),
+                            }
+                        } else if let Some(variable_param) = eval_ctx.variable_parameter_component_map.get(&ParameterComponentMapKey::new(processor)) {
+                            ComponentRendererState::VariableParameter {
+                                time_map,
+                                invert_time_map,
+                                variable_param: variable_param.clone(),
+                            }
+                        } else {
+                            let interprocess_pins = component.interprocess_pins();
+                            let interprocess_pins = iter::once(component.marker_left())
+                                .chain(component.markers())
+                                .chain(iter::once(component.marker_right()))
+                                .filter(|p| interprocess_pins.contains(p.id()))
+                                .map(|pin| time_map.map(GlobalTime::new(eval_ctx.time_map[pin.id()])).unwrap())
+                                .map(LocalTime::time)
+                                .collect::<Vec<_>>();
+                            let fixed_parameters = eval_fixed_parameters(component.fixed_parameters()).collect::<Box<[_]>>();
+                            let whole_component_cache_key = processor.whole_component_cache_key(&fixed_parameters, &interprocess_pins);
+                            ComponentRendererState::Native {
+                                processor: Arc::clone(processor),
+                                time_map,
+                                invert_time_map,
+                                fixed_parameters,
+                                interprocess_pins: interprocess_pins.into(),
+                                whole_component_cache_key,
+                            }
+                        }
+                    }
+                    ComponentProcessorWrapper::GatherNative(processor) => {
+                        let interprocess_pins = component.interprocess_pins();
+                        let interprocess_pins = iter::once(component.marker_left())
+                            .chain(component.markers())
+                            .chain(iter::once(component.marker_right()))
+                            .filter(|p| interprocess_pins.contains(p.id()))
+                            .map(|pin| time_map.map(GlobalTime::new(eval_ctx.time_map[pin.id()])).unwrap())
+                            .map(LocalTime::time)
+                            .collect::<Vec<_>>();
+                        let fixed_parameters = eval_fixed_parameters(component.fixed_parameters()).collect::<Box<[_]>>();
+                        let whole_component_cache_key = processor.whole_component_cache_key(&fixed_parameters, &interprocess_pins);
+                        let mut whole_component_cache = OptionFuture::from(whole_component_cache_key.as_ref().map(|key| eval_ctx.render_ctx.cache.get(key))).await.flatten();
+                        let whole_component_cache_ptr = whole_component_cache.as_ref().map(Arc::as_ptr).map(CachePointer);
+                        let component_length = processor.natural_length(&fixed_parameters, &mut whole_component_cache).await.unwrap_or(MarkerTime::new(MixedFraction::MAX).unwrap());
+                        if let (Some(whole_component_cache_key), Some(whole_component_cache)) = (&whole_component_cache_key, whole_component_cache) {
+                            if whole_component_cache_ptr.is_none_or(|p| p != CachePointer(Arc::as_ptr(&whole_component_cache))) {
+                                eval_ctx.render_ctx.cache.insert(Arc::clone(whole_component_cache_key), whole_component_cache).await;
+                            }
+                        }
+                        let transform = component.image_required_params().map(|params| (&*params.transform, &invalidate_range.image_required_params.as_ref().unwrap().transform));
+                        let variable_parameters = stream::iter(component.variable_parameters().iter().zip(&invalidate_range.variable_parameters))
+                            .then(|(p, invalidate_ranges)| eval_ctx.variable_parameter_for_gather_native(transform, component_length.into(), &time_map, &invert_time_map, p, invalidate_ranges))
+                            .try_collect::<Vec<_>>()
+                            .await?;
+                        ComponentRendererState::GatherNative {
+                            processor: Arc::clone(processor),
+                            time_map,
+                            invert_time_map,
+                            fixed_parameters,
+                            interprocess_pins: interprocess_pins.into(),
+                            variable_parameters: variable_parameters.into(),
+                            whole_component_cache_key,
+                        }
+                    }
+                };
+                self.state.store(Arc::new(new_state));
+                drop(write_guard);
+            } else {
+                return Ok(state);
+            };
+        }
+    }
+
     // 'staticであることを明示したい
     #[allow(clippy::manual_async_fn)]
     fn render(
@@ -780,146 +993,7 @@ where
                     eval_ctx.eval_audio_required_params(self.component.audio_required_params().unwrap(), &invalidate_range.audio_required_params.as_ref().unwrap(), &$time_map)
                 };
             }
-            let state: Guard<Arc<ComponentRendererState<T, ImageCombinerBuilder, AudioCombinerBuilder, Cache>>> = loop {
-                let state = self.state.load();
-                if let ComponentRendererState::New(_) = &**state {
-                    let write_guard = self.state_lock.lock().await;
-                    let ComponentRendererState::New(component) = &**self.state.load() else {
-                        continue;
-                    };
-                    let time_map = Arc::new(TimeStretch::new(component.marker_left(), component.markers(), component.marker_right(), &eval_ctx.time_map));
-                    let invert_time_map = Arc::new(time_map.invert().unwrap());
-
-                    let new_state: ComponentRendererState<T, ImageCombinerBuilder, AudioCombinerBuilder, Cache> = match component.processor() {
-                        ComponentProcessorWrapper::Component(processor) => {
-                            let interprocess_pins = component.interprocess_pins();
-                            let interprocess_pins = iter::once(component.marker_left())
-                                .chain(component.markers())
-                                .chain(iter::once(component.marker_right()))
-                                .filter_map(|p| interprocess_pins.contains(p.id()).then_some(*p.id()))
-                                .collect::<Vec<_>>();
-                            let transform = component.image_required_params().map(|params| (&*params.transform, &invalidate_range.image_required_params.as_ref().unwrap().transform));
-                            let fixed_parameters = eval_fixed_parameters(component.fixed_parameters()).collect::<Box<[_]>>();
-                            let component_length = processor.natural_length(&fixed_parameters, &interprocess_pins).await.into();
-                            let variable_parameters = stream::iter(component.variable_parameters().iter().zip(&invalidate_range.variable_parameters))
-                                .then(|(p, invalidate_ranges)| eval_ctx.variable_parameter_for_gather_native(transform, component_length, &time_map, &invert_time_map, p, invalidate_ranges))
-                                .try_collect::<Vec<_>>()
-                                .await?;
-                            let (fixed_parameters_placeholder_owned, fixed_parameter_processors): (Vec<_>, Vec<_>) = component
-                                .fixed_parameters_type()
-                                .iter()
-                                .map(|(_, ty)| eval_ctx.variable_parameter_for_component(ty.select()))
-                                .map(|ParameterForComponent { component_class_owned, parameter }| (component_class_owned, parameter))
-                                .unzip();
-                            let (variable_parameters_placeholder_owned, variable_parameter_processors): (Vec<_>, Vec<_>) = component
-                                .variable_parameters_type()
-                                .iter()
-                                .map(|(_, ty)| eval_ctx.variable_parameter_for_component(ty.select()))
-                                .map(|ParameterForComponent { component_class_owned, parameter }| (component_class_owned, parameter))
-                                .unzip();
-                            let fixed_parameters_placeholder = fixed_parameters_placeholder_owned.iter().map(StaticPointerOwned::reference).cloned().collect::<Vec<_>>();
-                            let variable_parameters_placeholder = variable_parameters_placeholder_owned.iter().map(StaticPointerOwned::reference).cloned().collect::<Vec<_>>();
-
-                            let variable_parameter_component_map = variable_parameter_processors.into_iter().zip(variable_parameters).map(|(p, param)| (ParameterComponentMapKey::new(&p), param)).collect();
-                            let fixed_parameter_component_map = fixed_parameter_processors.into_iter().zip(&fixed_parameters).map(|(p, param)| (ParameterComponentMapKey::new(&p), param.clone())).collect();
-
-                            let result = processor.process(&fixed_parameters, &fixed_parameters_placeholder, &interprocess_pins, &variable_parameters_placeholder, component.variable_parameters_type()).await;
-                            let inner_time_map = mpdelta_differential::collect_cached_time(&*result)?;
-                            let (component_ids, component_map): (Vec<_>, HashMap<_, _>) = result.components_dyn().map(|component| (*component.id(), (*component.id(), Arc::new(ComponentRenderer::new(Arc::clone(component)))))).unzip();
-                            let component_invalidate_range = collect_invalidate_range(&component_ids, &component_map, &inner_time_map);
-                            let inner_eval_ctx = EvaluationContext {
-                                render_ctx: Arc::clone(&eval_ctx.render_ctx),
-                                components: component_map,
-                                image_size: result.default_image_size(),
-                                time_map: Arc::new(inner_time_map),
-                                fixed_parameters_placeholder_owned: fixed_parameters_placeholder_owned.into_boxed_slice(),
-                                variable_parameters_placeholder_owned: variable_parameters_placeholder_owned.into_boxed_slice(),
-                                fixed_parameter_component_map,
-                                variable_parameter_component_map,
-                            };
-                            ComponentRendererState::Components {
-                                components: component_ids.into_iter().zip(component_invalidate_range).collect(),
-                                image_size: result.default_image_size(),
-                                time_map,
-                                invert_time_map,
-                                inner_evaluation_context: Arc::new(inner_eval_ctx),
-                            }
-                        }
-                        ComponentProcessorWrapper::Native(processor) => {
-                            if let Some(fixed_param) = eval_ctx.fixed_parameter_component_map.get(&ParameterComponentMapKey::new(processor)) {
-                                ComponentRendererState::FixedParameter {
-                                    time_map,
-                                    invert_time_map,
-                                    fixed_param: fixed_param.clone(),
-                                }
-                            } else if let Some(variable_param) = eval_ctx.variable_parameter_component_map.get(&ParameterComponentMapKey::new(processor)) {
-                                ComponentRendererState::VariableParameter {
-                                    time_map,
-                                    invert_time_map,
-                                    variable_param: variable_param.clone(),
-                                }
-                            } else {
-                                let interprocess_pins = component.interprocess_pins();
-                                let interprocess_pins = iter::once(component.marker_left())
-                                    .chain(component.markers())
-                                    .chain(iter::once(component.marker_right()))
-                                    .filter(|p| interprocess_pins.contains(p.id()))
-                                    .map(|pin| time_map.map(GlobalTime::new(eval_ctx.time_map[pin.id()])).unwrap())
-                                    .map(LocalTime::time)
-                                    .collect::<Vec<_>>();
-                                let fixed_parameters = eval_fixed_parameters(component.fixed_parameters()).collect::<Box<[_]>>();
-                                let whole_component_cache_key = processor.whole_component_cache_key(&fixed_parameters, &interprocess_pins);
-                                ComponentRendererState::Native {
-                                    processor: Arc::clone(processor),
-                                    time_map,
-                                    invert_time_map,
-                                    fixed_parameters,
-                                    interprocess_pins: interprocess_pins.into(),
-                                    whole_component_cache_key,
-                                }
-                            }
-                        }
-                        ComponentProcessorWrapper::GatherNative(processor) => {
-                            let interprocess_pins = component.interprocess_pins();
-                            let interprocess_pins = iter::once(component.marker_left())
-                                .chain(component.markers())
-                                .chain(iter::once(component.marker_right()))
-                                .filter(|p| interprocess_pins.contains(p.id()))
-                                .map(|pin| time_map.map(GlobalTime::new(eval_ctx.time_map[pin.id()])).unwrap())
-                                .map(LocalTime::time)
-                                .collect::<Vec<_>>();
-                            let fixed_parameters = eval_fixed_parameters(component.fixed_parameters()).collect::<Box<[_]>>();
-                            let whole_component_cache_key = processor.whole_component_cache_key(&fixed_parameters, &interprocess_pins);
-                            let mut whole_component_cache = OptionFuture::from(whole_component_cache_key.as_ref().map(|key| eval_ctx.render_ctx.cache.get(key))).await.flatten();
-                            let whole_component_cache_ptr = whole_component_cache.as_ref().map(Arc::as_ptr).map(CachePointer);
-                            let component_length = processor.natural_length(&fixed_parameters, &mut whole_component_cache).await.unwrap_or(MarkerTime::new(MixedFraction::MAX).unwrap());
-                            if let (Some(whole_component_cache_key), Some(whole_component_cache)) = (&whole_component_cache_key, whole_component_cache) {
-                                if whole_component_cache_ptr.is_none_or(|p| p != CachePointer(Arc::as_ptr(&whole_component_cache))) {
-                                    eval_ctx.render_ctx.cache.insert(Arc::clone(whole_component_cache_key), whole_component_cache).await;
-                                }
-                            }
-                            let transform = component.image_required_params().map(|params| (&*params.transform, &invalidate_range.image_required_params.as_ref().unwrap().transform));
-                            let variable_parameters = stream::iter(component.variable_parameters().iter().zip(&invalidate_range.variable_parameters))
-                                .then(|(p, invalidate_ranges)| eval_ctx.variable_parameter_for_gather_native(transform, component_length.into(), &time_map, &invert_time_map, p, invalidate_ranges))
-                                .try_collect::<Vec<_>>()
-                                .await?;
-                            ComponentRendererState::GatherNative {
-                                processor: Arc::clone(processor),
-                                time_map,
-                                invert_time_map,
-                                fixed_parameters,
-                                interprocess_pins: interprocess_pins.into(),
-                                variable_parameters: variable_parameters.into(),
-                                whole_component_cache_key,
-                            }
-                        }
-                    };
-                    self.state.store(Arc::new(new_state));
-                    drop(write_guard);
-                } else {
-                    break state;
-                };
-            };
+            let state = self.load_state(&eval_ctx, &invalidate_range).await?;
             match &**state {
                 ComponentRendererState::New(_) => {
                     unreachable!()
@@ -927,6 +1001,7 @@ where
                 ComponentRendererState::Components {
                     components,
                     image_size,
+                    frame_rate: _,
                     time_map,
                     invert_time_map,
                     inner_evaluation_context,
@@ -1004,9 +1079,9 @@ where
                         ParameterType::Integer(_) => unwrap_iter!(Integer),
                         ParameterType::RealNumber(_) => unwrap_iter!(RealNumber),
                         ParameterType::Boolean(_) => unwrap_iter!(Boolean),
-                        ParameterType::Dictionary(_) => unimplemented!(),
-                        ParameterType::Array(_) => unimplemented!(),
-                        ParameterType::ComponentClass(_) => unimplemented!(),
+                        ParameterType::Dictionary(_) => unwrap_iter!(Dictionary),
+                        ParameterType::Array(_) => unwrap_iter!(Array),
+                        ParameterType::ComponentClass(_) => unwrap_iter!(ComponentClass),
                     }
                 }
                 ComponentRendererState::Native {
@@ -1117,9 +1192,11 @@ where
                     variable_parameters,
                     whole_component_cache_key,
                 } => {
+                    let local_time_range = invert_time_map.left().time()..invert_time_map.right().time();
                     let parameters = NativeGatherProcessorInput {
                         fixed_parameters,
                         interprocess_pins,
+                        local_time_range: &local_time_range,
                         variable_parameters,
                         variable_parameter_type: self.component.variable_parameters_type(),
                     };
@@ -1260,7 +1337,8 @@ where
     AudioCombinerBuilder: CombinerBuilder<T::Audio, Request = AudioCombinerRequest, Param = AudioCombinerParam> + 'static,
     Cache: ProcessorCache + 'static,
 {
-    pub fn new(component: Arc<ComponentInstance<T>>, runtime: Handle, image_combiner_builder: ImageCombinerBuilder, audio_combiner_builder: AudioCombinerBuilder, cache: Cache) -> Renderer<T, ImageCombinerBuilder, AudioCombinerBuilder, Cache> {
+    /// `image_size`は`component`自身を描画するときの画像の大きさ 普通は[`top_level_image_size`]で求める
+    pub fn new(component: Arc<ComponentInstance<T>>, image_size: ImageSize, runtime: Handle, image_combiner_builder: ImageCombinerBuilder, audio_combiner_builder: AudioCombinerBuilder, cache: Cache) -> Renderer<T, ImageCombinerBuilder, AudioCombinerBuilder, Cache> {
         let renderer = Arc::new(ComponentRenderer::new(component));
         let invalidate_range = Arc::new(ComponentInvalidateRange::new_default(&renderer.component));
         let marker_left_time = renderer.component.marker_left().locked_component_time().unwrap().into();
@@ -1273,12 +1351,13 @@ where
                 cache,
             }),
             components: HashMap::from([(*renderer.component.id(), Arc::clone(&renderer))]),
-            image_size: ImageSize { width: 1920, height: 1080 },
+            image_size,
             time_map: Arc::new(HashMap::from([(*renderer.component.marker_left().id(), marker_left_time), (*renderer.component.marker_right().id(), marker_right_time)])),
             fixed_parameters_placeholder_owned: Box::new([]),
             variable_parameters_placeholder_owned: Box::new([]),
             fixed_parameter_component_map: Default::default(),
             variable_parameter_component_map: Default::default(),
+            ancestor_classes: Arc::new([]),
         });
         Renderer {
             eval_ctx,
@@ -1292,19 +1371,78 @@ where
         self.length
     }
 
+    pub fn image_size(&self) -> ImageSize {
+        self.eval_ctx.image_size
+    }
+
+    pub async fn frame_rate(&self) -> RenderResult<FrameRate> {
+        let state = self.renderer.load_state(&self.eval_ctx, &self.invalidate_range).await?;
+        match &**state {
+            ComponentRendererState::Components { frame_rate, .. } => Ok(*frame_rate),
+            _ => Ok(FrameRate::default()),
+        }
+    }
+
     pub fn render(&self, at: TimelineTime, ty: ParameterType) -> impl Future<Output = RenderResult<ParameterValueRaw<T::Image, T::Audio>>> + Send + 'static {
-        Arc::clone(&self.renderer).render(Arc::clone(&self.eval_ctx), Arc::clone(&self.invalidate_range), GlobalTime::new(at), ty).map_ok(|result| match result {
-            Parameter::None => Parameter::None,
-            Parameter::Image((value, _)) => Parameter::Image(value),
-            Parameter::Audio((value, _)) => Parameter::Audio(value),
-            Parameter::Binary(value) => Parameter::Binary(value),
-            Parameter::String(value) => Parameter::String(value),
-            Parameter::Integer(value) => Parameter::Integer(value),
-            Parameter::RealNumber(value) => Parameter::RealNumber(value),
-            Parameter::Boolean(value) => Parameter::Boolean(value),
-            Parameter::Dictionary(value) => Parameter::Dictionary(value),
-            Parameter::Array(value) => Parameter::Array(value),
-            Parameter::ComponentClass(value) => Parameter::ComponentClass(value),
-        })
+        Arc::clone(&self.renderer).render(Arc::clone(&self.eval_ctx), Arc::clone(&self.invalidate_range), GlobalTime::new(at), ty).map_ok(strip_render_output)
+    }
+
+    /// レンダリング対象のコンポーネントの直下にある`target`を評価する `at`はレンダリング対象のコンポーネント上の時刻
+    pub fn render_component(&self, target: ComponentInstanceId, at: TimelineTime, ty: ParameterType) -> impl Future<Output = RenderResult<ParameterValueRaw<T::Image, T::Audio>>> + Send + 'static {
+        let renderer = Arc::clone(&self.renderer);
+        let eval_ctx = Arc::clone(&self.eval_ctx);
+        let invalidate_range = Arc::clone(&self.invalidate_range);
+        let length = self.length;
+        async move {
+            let state = renderer.load_state(&eval_ctx, &invalidate_range).await?;
+            let ComponentRendererState::Components { components, time_map, inner_evaluation_context, .. } = &**state else {
+                return Err(RenderError::InvalidComponent(target));
+            };
+            let Some((_, target_invalidate_range)) = components.iter().find(|(id, _)| *id == target) else {
+                return Err(RenderError::InvalidComponent(target));
+            };
+            let Some(target_renderer) = inner_evaluation_context.components.get(&target) else {
+                return Err(RenderError::InvalidComponent(target));
+            };
+            let Some(local_at) = time_map.map(GlobalTime::new(at)) else {
+                return Err(RenderError::RenderTargetTimeOutOfRange {
+                    component: *renderer.component.id(),
+                    range: TimelineTime::ZERO..TimelineTime::new(length.value()),
+                    at,
+                });
+            };
+            let result = Arc::clone(target_renderer).render(Arc::clone(inner_evaluation_context), Arc::clone(target_invalidate_range), GlobalTime::new(local_at.time()), ty).await?;
+            Ok(strip_render_output(result))
+        }
+    }
+}
+
+/// 一番外側で描画するコンポーネントの画像の大きさ
+///
+/// ルートコンポーネントのようにパラメータを持たずにComponentsLinksPairを返すものはそのdefault_image_sizeを、それ以外はFULL_HDを使う
+pub async fn top_level_image_size<T: ParameterValueType>(component: &ComponentInstance<T>) -> ImageSize {
+    match component.processor() {
+        ComponentProcessorWrapper::Component(processor) if component.fixed_parameters().is_empty() && component.variable_parameters().is_empty() => processor.process(&[], &[], &[], &[], &[]).await.default_image_size(),
+        _ => ImageSize::FULL_HD,
+    }
+}
+
+fn strip_render_output<Image, Audio>(output: Parameter<RenderOutput<Image, Audio>>) -> ParameterValueRaw<Image, Audio>
+where
+    Image: Send + Sync + Clone + 'static,
+    Audio: Send + Sync + Clone + 'static,
+{
+    match output {
+        Parameter::None => Parameter::None,
+        Parameter::Image((value, _)) => Parameter::Image(value),
+        Parameter::Audio((value, _)) => Parameter::Audio(value),
+        Parameter::Binary(value) => Parameter::Binary(value),
+        Parameter::String(value) => Parameter::String(value),
+        Parameter::Integer(value) => Parameter::Integer(value),
+        Parameter::RealNumber(value) => Parameter::RealNumber(value),
+        Parameter::Boolean(value) => Parameter::Boolean(value),
+        Parameter::Dictionary(value) => Parameter::Dictionary(value),
+        Parameter::Array(value) => Parameter::Array(value),
+        Parameter::ComponentClass(value) => Parameter::ComponentClass(value),
     }
 }
@@ -1,16 +1,25 @@
 use super::*;
 use mpdelta_core::common::mixed_fraction::MixedFraction;
 use mpdelta_core::component::class::ComponentClass;
-use mpdelta_core::component::parameter::{ParameterNullableValue, ParameterValueRaw, ParameterValueType, VariableParameterValue};
-use mpdelta_core::component::processor::{CacheKey, ComponentProcessor, ComponentProcessorNative, ComponentProcessorNativeDyn, NativeProcessorInput, NativeProcessorRequest};
-use mpdelta_core::ptr::StaticPointerOwned;
-use mpdelta_core::time::TimelineTime;
+use mpdelta_core::component::link::MarkerLink;
+use mpdelta_core::component::marker_pin::MarkerPin;
+use mpdelta_core::component::parameter::value::{DynEditablePlainValue, EasingValue, LinearEasing, PlainValue};
+use mpdelta_core::component::parameter::{AudioRequiredParams, ImageRequiredParams};
+use mpdelta_core::component::parameter::{Never, ParameterNullableValue, ParameterValueRaw, ParameterValueType, VariableParameterValue};
+use mpdelta_core::component::processor::{CacheKey, ComponentProcessor, ComponentProcessorComponent, ComponentProcessorNative, ComponentProcessorNativeDyn, ComponentProcessorWrapper, ComponentsLinksPair, NativeProcessorInput, NativeProcessorRequest};
+use mpdelta_core::core::IdGenerator;
+use mpdelta_core::project::{RootComponentClass, RootComponentClassItemWrite};
+use mpdelta_core::ptr::{StaticPointer, StaticPointerOwned};
+use mpdelta_core::time::{FrameRate, TimelineTime};
 use mpdelta_core::{mfrac, time_split_value_persistent};
 use mpdelta_core_test_util::{root_component_class, TestIdGenerator};
+use rpds::VectorSync;
 use std::any::Any;
+use std::collections::BTreeMap;
 use std::future;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::sync::RwLock;
 
 struct T;
 impl ParameterValueType for T {
@@ -61,7 +70,7 @@ impl ComponentProcessorNative<T> for Processor {
     }
 
     async fn supports_output_type(&self, _: &[ParameterValueRaw<<T as ParameterValueType>::Image, <T as ParameterValueType>::Audio>], out: Parameter<ParameterSelect>, _: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> bool {
-        out.equals_type(&Parameter::<ParameterSelect>::Image(()))
+        out.equals_type(&Parameter::<ParameterSelect>::Image(())) || out.equals_type(&Parameter::<ParameterSelect>::Integer(()))
     }
 
     async fn process(
@@ -72,9 +81,193 @@ impl ComponentProcessorNative<T> for Processor {
         _: &mut Option<Arc<Self::WholeComponentCacheValue>>,
         _: &mut Option<Arc<Self::FramedCacheValue>>,
     ) -> ParameterValueRaw<<T as ParameterValueType>::Image, <T as ParameterValueType>::Audio> {
-        assert!(request.equals_type(&Parameter::<ParameterSelect>::Image(())));
-        Parameter::Image(vec![time.value()])
+        match request {
+            Parameter::Image(_) => Parameter::Image(vec![time.value()]),
+            Parameter::Integer(_) => {
+                let (i, n) = time.value().deconstruct_with_round(60);
+                Parameter::Integer(i as i64 * 60 + n as i64)
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// variable parameterのDictionaryとArrayをそのまま使って出力する
+struct CollectionProcessor;
+
+#[async_trait]
+impl ComponentProcessor<T> for CollectionProcessor {
+    async fn fixed_parameter_types(&self) -> &[(String, ParameterType)] {
+        &[]
+    }
+
+    async fn update_variable_parameter(&self, _: &[ParameterValueRaw<<T as ParameterValueType>::Image, <T as ParameterValueType>::Audio>], _: &mut Vec<(String, ParameterType)>) {}
+
+    async fn num_interprocess_pins(&self, _: &[ParameterValueRaw<<T as ParameterValueType>::Image, <T as ParameterValueType>::Audio>]) -> usize {
+        0
+    }
+}
+
+#[async_trait]
+impl ComponentProcessorNative<T> for CollectionProcessor {
+    type WholeComponentCacheKey = ();
+    type WholeComponentCacheValue = ();
+    type FramedCacheKey = ();
+    type FramedCacheValue = ();
+
+    fn whole_component_cache_key(&self, _: &[ParameterValueRaw<<T as ParameterValueType>::Image, <T as ParameterValueType>::Audio>], _: &[TimelineTime]) -> Option<Self::WholeComponentCacheKey> {
+        None
+    }
+
+    fn framed_cache_key(&self, _: NativeProcessorInput<'_, T>, _: TimelineTime, _: Parameter<ParameterSelect>) -> Option<Self::FramedCacheKey> {
+        None
+    }
+
+    async fn natural_length(&self, _: &[ParameterValueRaw<<T as ParameterValueType>::Image, <T as ParameterValueType>::Audio>], _: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> Option<MarkerTime> {
+        None
+    }
+
+    async fn supports_output_type(&self, _: &[ParameterValueRaw<<T as ParameterValueType>::Image, <T as ParameterValueType>::Audio>], out: Parameter<ParameterSelect>, _: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> bool {
+        matches!(out, Parameter::Integer(_) | Parameter::Dictionary(_) | Parameter::Array(_))
+    }
+
+    async fn process(
+        &self,
+        parameters: NativeProcessorInput<'_, T>,
+        _: TimelineTime,
+        request: Parameter<NativeProcessorRequest>,
+        _: &mut Option<Arc<Self::WholeComponentCacheValue>>,
+        _: &mut Option<Arc<Self::FramedCacheValue>>,
+    ) -> ParameterValueRaw<<T as ParameterValueType>::Image, <T as ParameterValueType>::Audio> {
+        let [Parameter::Dictionary(weights), Parameter::Array(items)] = parameters.variable_parameters else { unreachable!() };
+        let integer = |value: &ParameterValueRaw<Vec<MixedFraction>, Never>| *value.as_integer().unwrap();
+        match request {
+            Parameter::Integer(_) => Parameter::Integer(weights.values().map(integer).sum::<i64>() + items.iter().map(integer).sum::<i64>()),
+            Parameter::Dictionary(_) => Parameter::Dictionary(weights.iter().map(|(key, value)| (key.clone(), Parameter::Integer(integer(value)))).collect()),
+            Parameter::Array(_) => Parameter::Array(items.iter().map(|value| Parameter::Integer(integer(value) * 2)).collect()),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Integerを出力しようとするとpanicする
+struct PanicProcessor;
+
+#[async_trait]
+impl ComponentProcessor<T> for PanicProcessor {
+    async fn fixed_parameter_types(&self) -> &[(String, ParameterType)] {
+        &[]
+    }
+
+    async fn update_variable_parameter(&self, _: &[ParameterValueRaw<<T as ParameterValueType>::Image, <T as ParameterValueType>::Audio>], _: &mut Vec<(String, ParameterType)>) {}
+
+    async fn num_interprocess_pins(&self, _: &[ParameterValueRaw<<T as ParameterValueType>::Image, <T as ParameterValueType>::Audio>]) -> usize {
+        0
+    }
+}
+
+#[async_trait]
+impl ComponentProcessorNative<T> for PanicProcessor {
+    type WholeComponentCacheKey = ();
+    type WholeComponentCacheValue = ();
+    type FramedCacheKey = ();
+    type FramedCacheValue = ();
+
+    fn whole_component_cache_key(&self, _: &[ParameterValueRaw<<T as ParameterValueType>::Image, <T as ParameterValueType>::Audio>], _: &[TimelineTime]) -> Option<Self::WholeComponentCacheKey> {
+        None
+    }
+
+    fn framed_cache_key(&self, _: NativeProcessorInput<'_, T>, _: TimelineTime, _: Parameter<ParameterSelect>) -> Option<Self::FramedCacheKey> {
+        None
+    }
+
+    async fn natural_length(&self, _: &[ParameterValueRaw<<T as ParameterValueType>::Image, <T as ParameterValueType>::Audio>], _: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> Option<MarkerTime> {
+        None
+    }
+
+    async fn supports_output_type(&self, _: &[ParameterValueRaw<<T as ParameterValueType>::Image, <T as ParameterValueType>::Audio>], out: Parameter<ParameterSelect>, _: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> bool {
+        matches!(out, Parameter::Integer(_))
+    }
+
+    async fn process(
+        &self,
+        _: NativeProcessorInput<'_, T>,
+        _: TimelineTime,
+        _: Parameter<NativeProcessorRequest>,
+        _: &mut Option<Arc<Self::WholeComponentCacheValue>>,
+        _: &mut Option<Arc<Self::FramedCacheValue>>,
+    ) -> ParameterValueRaw<<T as ParameterValueType>::Image, <T as ParameterValueType>::Audio> {
+        panic!("PanicProcessor always panics")
+    }
+}
+
+/// ComponentClass型のvariable parameterで指定されたComponentClassの中身をそのまま展開する
+struct TemplateProcessor;
+
+#[async_trait]
+impl ComponentProcessor<T> for TemplateProcessor {
+    async fn fixed_parameter_types(&self) -> &[(String, ParameterType)] {
+        &[]
+    }
+
+    async fn update_variable_parameter(&self, _: &[ParameterValueRaw<<T as ParameterValueType>::Image, <T as ParameterValueType>::Audio>], _: &mut Vec<(String, ParameterType)>) {}
+
+    async fn num_interprocess_pins(&self, _: &[ParameterValueRaw<<T as ParameterValueType>::Image, <T as ParameterValueType>::Audio>]) -> usize {
+        0
+    }
+}
+
+#[async_trait]
+impl ComponentProcessorComponent<T> for TemplateProcessor {
+    async fn natural_length(&self, _: &[ParameterValueRaw<<T as ParameterValueType>::Image, <T as ParameterValueType>::Audio>], _: &[MarkerPinId]) -> MarkerTime {
+        MarkerTime::new(mfrac!(4)).unwrap()
     }
+
+    async fn process(
+        &self,
+        _: &[ParameterValueRaw<<T as ParameterValueType>::Image, <T as ParameterValueType>::Audio>],
+        _: &[StaticPointer<RwLock<dyn ComponentClass<T>>>],
+        _: &[MarkerPinId],
+        variable_parameters: &[StaticPointer<RwLock<dyn ComponentClass<T>>>],
+        _: &[(String, ParameterType)],
+    ) -> Arc<dyn ComponentsLinksPair<T>> {
+        let [template] = variable_parameters else { unreachable!() };
+        let template = template.upgrade().unwrap();
+        let ComponentProcessorWrapper::Component(processor) = template.read().await.processor() else { unreachable!() };
+        processor.process(&[], &[], &[], &[], &[]).await
+    }
+}
+
+/// `root`の左端から1の位置に、`template`を展開する長さ4のコンポーネントを置く
+async fn add_template_component(root: &StaticPointerOwned<RwLock<RootComponentClass<T>>>, template: StaticPointer<RwLock<dyn ComponentClass<T>>>, id: &TestIdGenerator) -> ComponentInstanceId {
+    let read = root.read().await;
+    let mut item = read.get_mut().await;
+    let (root_left, root_right) = (*item.left().id(), *item.right().id());
+    let left = MarkerPin::new(id.generate_new(), MarkerTime::ZERO);
+    let right = MarkerPin::new_unlocked(id.generate_new());
+    let (left_id, right_id) = (*left.id(), *right.id());
+    let component = ComponentInstance::builder(
+        StaticPointer::<RwLock<mpdelta_core_test_util::NoopComponentClass>>::new().map(|c| c as _),
+        left,
+        right,
+        Vec::new(),
+        Arc::new(TemplateProcessor) as Arc<dyn ComponentProcessorComponent<T>>,
+    )
+    .image_required_params(ImageRequiredParams::new_default(&left_id, &right_id))
+    .audio_required_params(AudioRequiredParams::new_default(&left_id, &right_id, 2))
+    .variable_parameters(
+        vec![("template".to_owned(), ParameterType::ComponentClass(()))],
+        VectorSync::new_sync().push_back(VariableParameterValue::new(ParameterNullableValue::ComponentClass(Some(template)))),
+    )
+    .build(id);
+    let component_id = *component.id();
+    item.add_component(component);
+    item.add_link(MarkerLink::new(root_left, left_id, TimelineTime::new(mfrac!(1))));
+    item.add_link(MarkerLink::new(left_id, right_id, TimelineTime::new(mfrac!(4))));
+    item.add_link(MarkerLink::new(right_id, root_right, TimelineTime::new(mfrac!(1))));
+    let time_map = mpdelta_differential::collect_cached_time(&*item).unwrap();
+    RootComponentClassItemWrite::commit_changes(item, time_map);
+    component_id
 }
 
 struct VecCombinerBuilder;
@@ -108,6 +301,42 @@ impl Combiner<Vec<MixedFraction>> for VecCombiner {
     }
 }
 
+/// 先のフレームほど早く描画が終わるように、時刻が早いほど長く待ってから結合結果を返す
+struct ReverseDelayCombinerBuilder;
+
+impl CombinerBuilder<Vec<MixedFraction>> for ReverseDelayCombinerBuilder {
+    type Request = ImageCombinerRequest;
+    type Param = ImageCombinerParam;
+    type Combiner = ReverseDelayCombiner;
+
+    fn new_combiner(&self, _: Self::Request) -> Self::Combiner {
+        ReverseDelayCombiner(VecCombiner { data: Vec::new() })
+    }
+}
+
+struct ReverseDelayCombiner(VecCombiner);
+
+impl Combiner<Vec<MixedFraction>> for ReverseDelayCombiner {
+    type Param = ImageCombinerParam;
+
+    fn add(&mut self, data: Vec<MixedFraction>, param: Self::Param) {
+        self.0.add(data, param);
+    }
+
+    fn collect<'async_trait>(self) -> impl Future<Output = Vec<MixedFraction>> + Send + 'async_trait
+    where
+        Self: 'async_trait,
+        Vec<MixedFraction>: 'async_trait,
+    {
+        let data = self.0.data;
+        let delay = data.first().map_or(0., |time| (1. - time.into_f64()).max(0.));
+        async move {
+            tokio::time::sleep(Duration::from_secs_f64(delay / 10.)).await;
+            data
+        }
+    }
+}
+
 struct NoopAudioCombiner;
 
 impl CombinerBuilder<()> for NoopAudioCombiner {
@@ -139,7 +368,7 @@ struct NoopRenderingControllerBuilder;
 impl MPDeltaRenderingControllerBuilder for NoopRenderingControllerBuilder {
     type Controller<F: Fn(RenderingControllerItem) + Send + Sync + 'static> = NoopRenderingController<F>;
 
-    fn create<F: Fn(RenderingControllerItem) + Send + Sync + 'static>(&self, f: F) -> Self::Controller<F> {
+    fn create<F: Fn(RenderingControllerItem) + Send + Sync + 'static>(&self, _: FrameRate, f: F) -> Self::Controller<F> {
         NoopRenderingController(f)
     }
 }
@@ -167,6 +396,92 @@ impl ProcessorCache for NoopProcessorCache {
 
 struct NoopRenderingController<F>(F);
 
+/// エンコーダが受け取ったもの
+#[derive(Default)]
+struct EncodedRecord {
+    frames: Vec<Vec<MixedFraction>>,
+    audio_range: Option<Range<TimelineTime>>,
+    monitor: Option<EncodeMonitor>,
+    finished: bool,
+    cancelled: bool,
+}
+
+#[derive(Debug, Error)]
+#[error("encoder failed")]
+struct EncoderFailed;
+
+/// 受け取ったフレームと音声の範囲を記録するエンコーダ
+#[derive(Clone, Default)]
+struct RecordingEncoder {
+    requires_image: bool,
+    requires_audio: bool,
+    fail_on_finish: bool,
+    /// 途中までの出力を破棄できなかったことにする
+    fail_on_cancel: bool,
+    /// finishの中で中断を要求する
+    cancel_in_finish: bool,
+    record: Arc<Mutex<EncodedRecord>>,
+}
+
+impl VideoEncoderBuilder<Vec<MixedFraction>, ()> for RecordingEncoder {
+    type Err = EncoderFailed;
+    type Encoder = RecordingEncoder;
+
+    fn build(&mut self) -> Result<Self::Encoder, Self::Err> {
+        Ok(self.clone())
+    }
+}
+
+impl VideoEncoder<Vec<MixedFraction>, ()> for RecordingEncoder {
+    type Err = EncoderFailed;
+
+    fn requires_image(&self) -> bool {
+        self.requires_image
+    }
+
+    fn push_frame(&mut self, frame: Vec<MixedFraction>) {
+        self.record.lock().unwrap().frames.push(frame);
+    }
+
+    fn requires_audio(&self) -> bool {
+        self.requires_audio
+    }
+
+    fn set_audio(&mut self, _: ()) {}
+
+    fn set_audio_range(&mut self, _: (), range: Range<TimelineTime>) {
+        self.record.lock().unwrap().audio_range = Some(range);
+    }
+
+    fn finish(&mut self) -> Result<(), Self::Err> {
+        let mut record = self.record.lock().unwrap();
+        record.finished = true;
+        if self.cancel_in_finish {
+            if let Some(monitor) = &record.monitor {
+                monitor.cancellation_token().cancel();
+            }
+        }
+        if self.fail_on_finish {
+            Err(EncoderFailed)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn set_monitor(&mut self, monitor: EncodeMonitor) {
+        self.record.lock().unwrap().monitor = Some(monitor);
+    }
+
+    fn cancel(&mut self) -> Result<(), Self::Err> {
+        self.record.lock().unwrap().cancelled = true;
+        if self.fail_on_cancel {
+            Err(EncoderFailed)
+        } else {
+            Ok(())
+        }
+    }
+}
+
 impl<F: Fn(RenderingControllerItem) + Send + Sync + 'static> MPDeltaRenderingController for NoopRenderingController<F> {
     fn on_request_render(&self, frame: usize) {
         self.0(RenderingControllerItem::RequestRender { frame });
@@ -247,3 +562,435 @@ async fn test_render() {
     assert_eq!(render_frame!(180), vec![mfrac!(180, 60)]);
     assert_eq!(render_frame!(181), vec![mfrac!(182, 60)]);
 }
+
+#[tokio::test]
+async fn test_frame_rate_before_rendering_loop() {
+    let id = TestIdGenerator::new();
+    root_component_class! {
+        root; <T>; id;
+        components: [],
+        links: [],
+    }
+    {
+        let read = root.read().await;
+        let mut item = read.get_mut().await;
+        item.set_frame_rate(FrameRate::FPS_24);
+        let time_map = mpdelta_differential::collect_cached_time(&*item).unwrap();
+        mpdelta_core::project::RootComponentClassItemWrite::commit_changes(item, time_map);
+    }
+    let instance = root.read().await.instantiate(&StaticPointerOwned::reference(&root).clone().map(|c| c as _), &id).await;
+    let renderer_builder = MPDeltaRendererBuilder::new(Arc::new(VecCombinerBuilder), Arc::new(NoopRenderingControllerBuilder), Arc::new(NoopAudioCombiner), NoopProcessorCache, Handle::current());
+    let renderer = renderer_builder.create_renderer(Arc::new(instance)).await.unwrap();
+    // rendering loopが一度も動いていなくてもプロジェクトのフレームレートを返す
+    assert_eq!(renderer.frame_rate(), FrameRate::FPS_24);
+}
+
+#[tokio::test]
+async fn test_top_level_image_size() {
+    let id = TestIdGenerator::new();
+    root_component_class! {
+        root; <T>; id;
+        components: [],
+        links: [],
+    }
+    let image_size = ImageSize { width: 3840, height: 2160 };
+    {
+        let read = root.read().await;
+        let mut item = read.get_mut().await;
+        item.set_image_size(image_size);
+        let time_map = mpdelta_differential::collect_cached_time(&*item).unwrap();
+        mpdelta_core::project::RootComponentClassItemWrite::commit_changes(item, time_map);
+    }
+    let instance = Arc::new(root.read().await.instantiate(&StaticPointerOwned::reference(&root).clone().map(|c| c as _), &id).await);
+    // エンコード時のフレームの大きさの見積もりにも、ルートコンポーネントの設定を使う
+    assert_eq!(render::top_level_image_size(&instance).await, image_size);
+    let renderer = render::Renderer::new(Arc::clone(&instance), render::top_level_image_size(&instance).await, Handle::current(), Arc::new(VecCombinerBuilder), Arc::new(NoopAudioCombiner), NoopProcessorCache);
+    assert_eq!(renderer.image_size(), image_size);
+    // ComponentsLinksPairを返さないものはFULL_HDとして扱う
+    let processor = Arc::new(Processor) as Arc<dyn ComponentProcessorNativeDyn<T>>;
+    root_component_class! {
+        other; <T>; id;
+        left: left,
+        right: right,
+        components: [
+            {
+                markers: [marker!(locked: 0) => l1, marker!() => r1],
+                processor: processor.clone()
+            }; c1,
+        ],
+        links: [
+            left = 1 => l1,
+            l1 = 2 => r1,
+            r1 = 1 => right,
+        ],
+    }
+    let component = other.read().await.get().component(&c1).unwrap().clone();
+    assert_eq!(render::top_level_image_size(&component).await, ImageSize::FULL_HD);
+}
+
+#[tokio::test]
+async fn test_render_param() {
+    let processor = Arc::new(Processor) as Arc<dyn ComponentProcessorNativeDyn<T>>;
+    let id = TestIdGenerator::new();
+    root_component_class! {
+        root; <T>; id;
+        left: left,
+        right: right,
+        components: [
+            {
+                markers: [marker!(locked: 0) => l1, marker!() => r1],
+                processor: processor.clone()
+            }; c1,
+        ],
+        links: [
+            left = 1 => l1,
+            l1 = 2 => r1,
+            r1 = 1 => right,
+        ],
+    }
+    let instance = root.read().await.instantiate(&StaticPointerOwned::reference(&root).clone().map(|c| c as _), &id).await;
+    let renderer_builder = MPDeltaRendererBuilder::new(Arc::new(VecCombinerBuilder), Arc::new(NoopRenderingControllerBuilder), Arc::new(NoopAudioCombiner), NoopProcessorCache, Handle::current());
+    let root_instance_id = *instance.id();
+    let renderer = renderer_builder.create_renderer(Arc::new(instance)).await.unwrap();
+
+    assert!(matches!(renderer.render_param(None, TimelineTime::new(mfrac!(2)), ParameterType::Integer(())).await, Ok(Parameter::Integer(60))));
+    assert!(matches!(renderer.render_param(None, TimelineTime::new(mfrac!(5, 2)), ParameterType::Integer(())).await, Ok(Parameter::Integer(90))));
+    // コンポーネントの範囲外ではデフォルト値になる
+    assert!(matches!(renderer.render_param(None, TimelineTime::new(mfrac!(1, 2)), ParameterType::Integer(())).await, Ok(Parameter::Integer(0))));
+    // 出力できるコンポーネントがなければデフォルト値になる
+    assert!(matches!(renderer.render_param(None, TimelineTime::new(mfrac!(2)), ParameterType::Boolean(())).await, Ok(Parameter::Boolean(false))));
+    assert!(matches!(renderer.render_param(None, TimelineTime::new(mfrac!(2)), ParameterType::Image(())).await, Err(RenderError::UnsupportedParameterType)));
+
+    // 直下のコンポーネントを指定したときは、そのコンポーネントの出力だけを評価する
+    assert!(matches!(renderer.render_param(Some(c1), TimelineTime::new(mfrac!(2)), ParameterType::Integer(())).await, Ok(Parameter::Integer(60))));
+    assert!(matches!(renderer.render_param(Some(c1), TimelineTime::new(mfrac!(1, 2)), ParameterType::Integer(())).await, Err(RenderError::RenderTargetTimeOutOfRange { .. })));
+    assert!(matches!(renderer.render_param(Some(c1), TimelineTime::new(mfrac!(2)), ParameterType::Boolean(())).await, Err(RenderError::NotProvided)));
+    // 直下にないコンポーネントは指定できない
+    assert!(matches!(renderer.render_param(Some(root_instance_id), TimelineTime::new(mfrac!(2)), ParameterType::Integer(())).await, Err(RenderError::InvalidComponent(c)) if c == root_instance_id));
+}
+
+#[tokio::test]
+async fn test_render_param_collection() {
+    let processor = Arc::new(CollectionProcessor) as Arc<dyn ComponentProcessorNativeDyn<T>>;
+    let id = TestIdGenerator::new();
+    let weights = BTreeMap::from([("a".to_owned(), PlainValue::Integer(1)), ("b".to_owned(), PlainValue::Integer(2))]);
+    let items = vec![PlainValue::Integer(10), PlainValue::Integer(20)];
+    root_component_class! {
+        root; <T>; id;
+        left: left,
+        right: right,
+        components: [
+            {
+                markers: [marker!(locked: 0) => l1, marker!() => r1],
+                processor: processor.clone(),
+                variable_params: [
+                    "weights": ParameterType::Dictionary(vec![("a".to_owned(), ParameterType::Integer(())), ("b".to_owned(), ParameterType::Integer(()))])
+                        => VariableParameterValue::new(ParameterNullableValue::Dictionary(time_split_value_persistent![l1, Some(EasingValue::new(DynEditablePlainValue::<_, Vec<MixedFraction>, ()>::new(weights), Arc::new(LinearEasing))), r1])),
+                    "items": ParameterType::Array(Box::new(ParameterType::Integer(())))
+                        => VariableParameterValue::new(ParameterNullableValue::Array(time_split_value_persistent![l1, Some(EasingValue::new(DynEditablePlainValue::<_, Vec<MixedFraction>, ()>::new(items), Arc::new(LinearEasing))), r1])),
+                ]
+            }; c1,
+        ],
+        links: [
+            left = 1 => l1,
+            l1 = 2 => r1,
+            r1 = 1 => right,
+        ],
+    }
+    let instance = root.read().await.instantiate(&StaticPointerOwned::reference(&root).clone().map(|c| c as _), &id).await;
+    let renderer_builder = MPDeltaRendererBuilder::new(Arc::new(VecCombinerBuilder), Arc::new(NoopRenderingControllerBuilder), Arc::new(NoopAudioCombiner), NoopProcessorCache, Handle::current());
+    let renderer = renderer_builder.create_renderer(Arc::new(instance)).await.unwrap();
+    let at = TimelineTime::new(mfrac!(2));
+
+    assert!(matches!(renderer.render_param(None, at, ParameterType::Integer(())).await, Ok(Parameter::Integer(33))));
+    let Ok(Parameter::Dictionary(weights)) = renderer.render_param(None, at, ParameterType::Dictionary(Vec::new())).await else { panic!() };
+    assert_eq!(weights.len(), 2);
+    assert_eq!(weights["a"].as_integer(), Some(&1));
+    assert_eq!(weights["b"].as_integer(), Some(&2));
+    let Ok(Parameter::Array(items)) = renderer.render_param(Some(c1), at, ParameterType::Array(Box::new(Parameter::None))).await else { panic!() };
+    assert_eq!(items.iter().map(|item| item.as_integer().copied()).collect::<Vec<_>>(), [Some(20), Some(40)]);
+    // 範囲外では空のDictionaryになる
+    let Ok(Parameter::Dictionary(weights)) = renderer.render_param(None, TimelineTime::new(mfrac!(1, 2)), ParameterType::Dictionary(Vec::new())).await else {
+        panic!()
+    };
+    assert!(weights.is_empty());
+}
+
+#[tokio::test]
+async fn test_render_param_panicked() {
+    let processor = Arc::new(PanicProcessor) as Arc<dyn ComponentProcessorNativeDyn<T>>;
+    let id = TestIdGenerator::new();
+    root_component_class! {
+        root; <T>; id;
+        left: left,
+        right: right,
+        components: [
+            {
+                markers: [marker!(locked: 0) => l1, marker!() => r1],
+                processor: processor.clone()
+            }; c1,
+        ],
+        links: [
+            left = 1 => l1,
+            l1 = 2 => r1,
+            r1 = 1 => right,
+        ],
+    }
+    let instance = root.read().await.instantiate(&StaticPointerOwned::reference(&root).clone().map(|c| c as _), &id).await;
+    let renderer_builder = MPDeltaRendererBuilder::new(Arc::new(VecCombinerBuilder), Arc::new(NoopRenderingControllerBuilder), Arc::new(NoopAudioCombiner), NoopProcessorCache, Handle::current());
+    let renderer = renderer_builder.create_renderer(Arc::new(instance)).await.unwrap();
+
+    // 描画タスクがpanicしても待ち続けずにエラーを返す
+    let result = tokio::time::timeout(Duration::from_secs(10), renderer.render_param(Some(c1), TimelineTime::new(mfrac!(2)), ParameterType::Integer(()))).await;
+    assert!(matches!(result, Ok(Err(RenderError::Aborted))));
+}
+
+#[tokio::test]
+async fn test_render_component_class_parameter() {
+    let processor = Arc::new(Processor) as Arc<dyn ComponentProcessorNativeDyn<T>>;
+    let id = TestIdGenerator::new();
+    root_component_class! {
+        template; <T>; id;
+        left: left,
+        right: right,
+        components: [
+            {
+                markers: [marker!(locked: 0) => l1, marker!() => r1],
+                processor: processor.clone()
+            },
+        ],
+        links: [
+            left = 1 => l1,
+            l1 = 2 => r1,
+            r1 = 1 => right,
+        ],
+    }
+    root_component_class! {
+        root; <T>; id;
+        components: [],
+        links: [],
+    }
+    let template_ptr = StaticPointerOwned::reference(&template).clone().map(|c| c as _);
+    let c1 = add_template_component(&root, template_ptr, &id).await;
+    let instance = root.read().await.instantiate(&StaticPointerOwned::reference(&root).clone().map(|c| c as _), &id).await;
+    let renderer_builder = MPDeltaRendererBuilder::new(Arc::new(VecCombinerBuilder), Arc::new(NoopRenderingControllerBuilder), Arc::new(NoopAudioCombiner), NoopProcessorCache, Handle::current());
+    let renderer = renderer_builder.create_renderer(Arc::new(instance)).await.unwrap();
+
+    // templateの中身が、コンポーネントの位置にずらして評価される
+    assert!(matches!(renderer.render_param(None, TimelineTime::new(mfrac!(3)), ParameterType::Integer(())).await, Ok(Parameter::Integer(60))));
+    assert!(matches!(renderer.render_param(Some(c1), TimelineTime::new(mfrac!(7, 2)), ParameterType::Integer(())).await, Ok(Parameter::Integer(90))));
+    assert!(matches!(renderer.render_param(None, TimelineTime::new(mfrac!(3, 2)), ParameterType::Integer(())).await, Ok(Parameter::Integer(0))));
+}
+
+#[tokio::test]
+async fn test_render_recursive_component_class_parameter() {
+    let id = TestIdGenerator::new();
+    root_component_class! {
+        root; <T>; id;
+        components: [],
+        links: [],
+    }
+    // 自分自身を展開するコンポーネントを置く
+    let root_ptr = StaticPointerOwned::reference(&root).clone().map(|c| c as _);
+    let c1 = add_template_component(&root, root_ptr, &id).await;
+    let instance = root.read().await.instantiate(&StaticPointerOwned::reference(&root).clone().map(|c| c as _), &id).await;
+    let renderer_builder = MPDeltaRendererBuilder::new(Arc::new(VecCombinerBuilder), Arc::new(NoopRenderingControllerBuilder), Arc::new(NoopAudioCombiner), NoopProcessorCache, Handle::current());
+    let renderer = renderer_builder.create_renderer(Arc::new(instance)).await.unwrap();
+
+    assert!(matches!(renderer.render_param(None, TimelineTime::new(mfrac!(3)), ParameterType::Integer(())).await, Err(RenderError::RecursiveComponentClass(c)) if c == c1));
+}
+
+#[tokio::test]
+async fn test_render_frame_and_encode() {
+    let processor = Arc::new(Processor) as Arc<dyn ComponentProcessorNativeDyn<T>>;
+    let id = TestIdGenerator::new();
+    root_component_class! {
+        root; <T>; id;
+        left: left,
+        right: right,
+        components: [
+            {
+                markers: [marker!(locked: 0) => l1, marker!() => r1],
+                processor: processor.clone()
+            },
+        ],
+        links: [
+            left = 1 => l1,
+            l1 = 2 => r1,
+            r1 = 1 => right,
+        ],
+    }
+    let instance = Arc::new(root.read().await.instantiate(&StaticPointerOwned::reference(&root).clone().map(|c| c as _), &id).await);
+    let renderer_builder = MPDeltaRendererBuilder::new(Arc::new(VecCombinerBuilder), Arc::new(NoopRenderingControllerBuilder), Arc::new(NoopAudioCombiner), NoopProcessorCache, Handle::current());
+
+    let encoder = RecordingEncoder { requires_image: true, ..RecordingEncoder::default() };
+    renderer_builder.render_frame_and_encode(Arc::clone(&instance), TimelineTime::new(mfrac!(3, 2)), encoder.clone()).await.unwrap();
+    let record = encoder.record.lock().unwrap();
+    assert_eq!(record.frames, vec![vec![mfrac!(1, 2)]]);
+    assert!(record.finished);
+    assert!(!record.cancelled);
+    drop(record);
+
+    // 画像を必要としないエンコーダには何も渡さずに終了する
+    let encoder = RecordingEncoder::default();
+    renderer_builder.render_frame_and_encode(Arc::clone(&instance), TimelineTime::new(mfrac!(3, 2)), encoder.clone()).await.unwrap();
+    let record = encoder.record.lock().unwrap();
+    assert!(record.frames.is_empty());
+    assert!(record.finished);
+    drop(record);
+
+    // 終了時のエラーはそのまま返す
+    let encoder = RecordingEncoder {
+        requires_image: true,
+        fail_on_finish: true,
+        ..RecordingEncoder::default()
+    };
+    let result = renderer_builder.render_frame_and_encode(Arc::clone(&instance), TimelineTime::new(mfrac!(3, 2)), encoder.clone()).await;
+    assert!(matches!(result, Err(EncodeError::EncoderError(EncoderFailed))));
+    // 失敗したときは途中までの出力を破棄する
+    assert!(encoder.record.lock().unwrap().cancelled);
+}
+
+#[tokio::test]
+async fn test_render_range_and_encode_unaligned_range() {
+    let processor = Arc::new(Processor) as Arc<dyn ComponentProcessorNativeDyn<T>>;
+    let id = TestIdGenerator::new();
+    root_component_class! {
+        root; <T>; id;
+        left: left,
+        right: right,
+        components: [
+            {
+                markers: [marker!(locked: 0) => l1, marker!() => r1],
+                processor: processor.clone()
+            },
+        ],
+        links: [
+            left = 1 => l1,
+            l1 = 2 => r1,
+            r1 = 1 => right,
+        ],
+    }
+    let instance = Arc::new(root.read().await.instantiate(&StaticPointerOwned::reference(&root).clone().map(|c| c as _), &id).await);
+    let renderer_builder = MPDeltaRendererBuilder::new(Arc::new(VecCombinerBuilder), Arc::new(NoopRenderingControllerBuilder), Arc::new(NoopAudioCombiner), NoopProcessorCache, Handle::current());
+
+    // 60fpsのフレームの境界からずれた範囲を指定する
+    let encoder = RecordingEncoder {
+        requires_image: true,
+        requires_audio: true,
+        ..RecordingEncoder::default()
+    };
+    let range = TimelineTime::new(mfrac!(101, 100))..TimelineTime::new(mfrac!(301, 200));
+    let (monitor, _progress) = EncodeMonitor::new();
+    renderer_builder.render_range_and_encode(Arc::clone(&instance), range, encoder.clone(), monitor).await.unwrap();
+    let record = encoder.record.lock().unwrap();
+    // 1.01sに最も近い61フレーム目から、1.505sに最も近い90フレーム目の手前まで
+    assert_eq!(record.frames.len(), 29);
+    assert_eq!(record.frames.first(), Some(&vec![mfrac!(1, 60)]));
+    assert_eq!(record.frames.last(), Some(&vec![mfrac!(29, 60)]));
+    // 音声は描画したフレームと同じ範囲になる
+    assert_eq!(record.audio_range, Some(TimelineTime::new(mfrac!(61, 60))..TimelineTime::new(mfrac!(90, 60))));
+    // 映像があれば音声の進捗は描画側で報告する
+    assert!(record.monitor.is_none());
+    assert!(record.finished);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_render_and_encode_frames_in_order() {
+    let processor = Arc::new(Processor) as Arc<dyn ComponentProcessorNativeDyn<T>>;
+    let id = TestIdGenerator::new();
+    root_component_class! {
+        root; <T>; id;
+        left: left,
+        right: right,
+        components: [
+            {
+                markers: [marker!(locked: 0) => l1, marker!() => r1],
+                processor: processor.clone()
+            },
+        ],
+        links: [
+            left = 1 => l1,
+            l1 = 0.5 => r1,
+            r1 = 1 => right,
+        ],
+    }
+    let instance = Arc::new(root.read().await.instantiate(&StaticPointerOwned::reference(&root).clone().map(|c| c as _), &id).await);
+    let renderer_builder = MPDeltaRendererBuilder::new(Arc::new(ReverseDelayCombinerBuilder), Arc::new(NoopRenderingControllerBuilder), Arc::new(NoopAudioCombiner), NoopProcessorCache, Handle::current()).with_encode_pipeline(EncodePipelineConfig { concurrency: 8, memory_budget: usize::MAX });
+
+    // 後のフレームほど先に描画し終わるが、エンコーダには時刻の順に渡す
+    let encoder = RecordingEncoder { requires_image: true, ..RecordingEncoder::default() };
+    let (monitor, _progress) = EncodeMonitor::new();
+    let range = TimelineTime::new(mfrac!(1))..TimelineTime::new(mfrac!(3, 2));
+    renderer_builder.render_range_and_encode(Arc::clone(&instance), range.clone(), encoder.clone(), monitor).await.unwrap();
+    let record = encoder.record.lock().unwrap();
+    assert_eq!(record.frames, (0..30).map(|f| vec![mfrac!(f, 60)]).collect::<Vec<_>>());
+    assert!(!record.cancelled);
+    drop(record);
+
+    // 描画を待っている間に中断されたら、残りのフレームを待たずに途中までの出力を破棄する
+    let encoder = RecordingEncoder { requires_image: true, ..RecordingEncoder::default() };
+    let (monitor, _progress) = EncodeMonitor::new();
+    monitor.cancellation_token().cancel();
+    let result = renderer_builder.render_range_and_encode(Arc::clone(&instance), range, encoder.clone(), monitor).await;
+    assert!(matches!(result, Err(EncodeError::Cancelled)));
+    let record = encoder.record.lock().unwrap();
+    assert!(record.frames.is_empty());
+    assert!(!record.finished);
+    assert!(record.cancelled);
+}
+
+#[tokio::test]
+async fn test_render_and_encode_audio_only_cancelled_in_finish() {
+    let id = TestIdGenerator::new();
+    root_component_class! {
+        root; <T>; id;
+        left: left,
+        right: right,
+        components: [],
+        links: [
+            left = 2 => right,
+        ],
+    }
+    let instance = Arc::new(root.read().await.instantiate(&StaticPointerOwned::reference(&root).clone().map(|c| c as _), &id).await);
+    let renderer_builder = MPDeltaRendererBuilder::new(Arc::new(VecCombinerBuilder), Arc::new(NoopRenderingControllerBuilder), Arc::new(NoopAudioCombiner), NoopProcessorCache, Handle::current());
+
+    // 音声だけのエンコーダにはモニタを渡し、finishの中での中断も中断として扱う
+    let encoder = RecordingEncoder {
+        requires_audio: true,
+        cancel_in_finish: true,
+        ..RecordingEncoder::default()
+    };
+    let (monitor, _progress) = EncodeMonitor::new();
+    let result = renderer_builder.render_and_encode(Arc::clone(&instance), encoder.clone(), monitor).await;
+    assert!(matches!(result, Err(EncodeError::Cancelled)));
+    let record = encoder.record.lock().unwrap();
+    assert!(record.monitor.is_some());
+    assert!(record.finished);
+    assert!(record.cancelled);
+    drop(record);
+
+    // 途中までの出力を破棄できなければ、そのエラーを返す
+    let encoder = RecordingEncoder {
+        requires_audio: true,
+        cancel_in_finish: true,
+        fail_on_cancel: true,
+        ..RecordingEncoder::default()
+    };
+    let (monitor, _progress) = EncodeMonitor::new();
+    let result = renderer_builder.render_and_encode(Arc::clone(&instance), encoder.clone(), monitor).await;
+    assert!(matches!(result, Err(EncodeError::EncoderError(EncoderFailed))));
+    assert!(encoder.record.lock().unwrap().cancelled);
+}
+
+#[test]
+fn test_encode_pipeline_frames_in_flight() {
+    let config = EncodePipelineConfig { concurrency: 8, memory_budget: 1920 * 1080 * 4 * 3 };
+    assert_eq!(config.frames_in_flight(1920 * 1080 * 4), 3);
+    assert_eq!(config.frames_in_flight(1280 * 720 * 4), 6);
+    assert_eq!(config.frames_in_flight(16 * 16 * 4), 8);
+    // 1フレームも予算に収まらなくても止まらないように1は確保する
+    assert_eq!(config.frames_in_flight(7680 * 4320 * 4), 1);
+    assert_eq!(EncodePipelineConfig { concurrency: 0, ..config }.frames_in_flight(16), 1);
+    assert_eq!(config.frames_in_flight(0), 8);
+}
@@ -1,3 +1,4 @@
+use mpdelta_core::time::FrameRate;
 use mpdelta_renderer::{MPDeltaRenderingController, MPDeltaRenderingControllerBuilder, RenderingControllerItem};
 use std::collections::BTreeSet;
 use std::sync::Mutex;
@@ -14,31 +15,43 @@ impl LookaheadRenderingControllerBuilder {
 impl MPDeltaRenderingControllerBuilder for LookaheadRenderingControllerBuilder {
     type Controller<F: Fn(RenderingControllerItem) + Send + Sync + 'static> = LookaheadRenderingController<F>;
 
-    fn create<F: Fn(RenderingControllerItem) + Send + Sync + 'static>(&self, f: F) -> Self::Controller<F> {
-        LookaheadRenderingController::new(f)
+    fn create<F: Fn(RenderingControllerItem) + Send + Sync + 'static>(&self, frame_rate: FrameRate, f: F) -> Self::Controller<F> {
+        LookaheadRenderingController::new(frame_rate, f)
     }
 }
 
 pub struct LookaheadRenderingController<F> {
     f: F,
+    behind_frames: usize,
+    ahead_frames: usize,
     in_cache: Mutex<BTreeSet<usize>>,
 }
 
 impl<F> LookaheadRenderingController<F> {
-    fn new(f: F) -> LookaheadRenderingController<F> {
-        LookaheadRenderingController { f, in_cache: Mutex::new(BTreeSet::new()) }
+    fn new(frame_rate: FrameRate, f: F) -> LookaheadRenderingController<F> {
+        // 後ろ1/3秒分を残し、前2/3秒分を先読みする
+        let numerator = frame_rate.numerator() as u64;
+        let denominator = frame_rate.denominator() as u64;
+        let behind_frames = numerator.div_ceil(denominator * 3) as usize;
+        let ahead_frames = (numerator * 2).div_ceil(denominator * 3) as usize;
+        LookaheadRenderingController {
+            f,
+            behind_frames,
+            ahead_frames,
+            in_cache: Mutex::new(BTreeSet::new()),
+        }
     }
 }
 
 impl<F: Fn(RenderingControllerItem) + Send + Sync + 'static> MPDeltaRenderingController for LookaheadRenderingController<F> {
     fn on_request_render(&self, frame: usize) {
         let mut in_cache = self.in_cache.lock().unwrap();
-        let remove_frames = in_cache.range(..frame.saturating_sub(20)).copied().collect::<Vec<_>>();
+        let remove_frames = in_cache.range(..frame.saturating_sub(self.behind_frames)).copied().collect::<Vec<_>>();
         remove_frames.iter().copied().for_each(|frame| (self.f)(RenderingControllerItem::RemoveCache { frame }));
         remove_frames.iter().for_each(|frame| {
             in_cache.remove(frame);
         });
-        let new_frames = (frame..).take(40).filter(|f| !in_cache.contains(f)).collect::<Vec<_>>();
+        let new_frames = (frame..).take(self.ahead_frames).filter(|f| !in_cache.contains(f)).collect::<Vec<_>>();
         new_frames.iter().copied().for_each(|frame| (self.f)(RenderingControllerItem::RequestRender { frame }));
         in_cache.extend(new_frames);
     }
@@ -55,7 +68,7 @@ mod tests {
         let remove_frames = Arc::new(Mutex::new(Vec::new()));
         let af = Arc::clone(&add_frames);
         let rf = Arc::clone(&remove_frames);
-        let controller = LookaheadRenderingController::new(move |item| match item {
+        let controller = LookaheadRenderingController::new(FrameRate::FPS_60, move |item| match item {
             RenderingControllerItem::RequestRender { frame } => {
                 add_frames.lock().unwrap().push(frame);
             }
@@ -73,4 +86,26 @@ mod tests {
         assert_eq!(*af.lock().unwrap(), (0..75).collect::<Vec<_>>());
         assert_eq!(*rf.lock().unwrap(), (0..15).collect::<Vec<_>>());
     }
+
+    #[test]
+    fn test_lookahead_rendering_controller_frame_rate() {
+        let add_frames = Arc::new(Mutex::new(Vec::new()));
+        let remove_frames = Arc::new(Mutex::new(Vec::new()));
+        let af = Arc::clone(&add_frames);
+        let rf = Arc::clone(&remove_frames);
+        let controller = LookaheadRenderingController::new(FrameRate::FPS_29_97, move |item| match item {
+            RenderingControllerItem::RequestRender { frame } => {
+                add_frames.lock().unwrap().push(frame);
+            }
+            RenderingControllerItem::RemoveCache { frame } => {
+                remove_frames.lock().unwrap().push(frame);
+            }
+        });
+        controller.on_request_render(0);
+        assert_eq!(*af.lock().unwrap(), (0..20).collect::<Vec<_>>());
+        assert_eq!(*rf.lock().unwrap(), []);
+        controller.on_request_render(15);
+        assert_eq!(*af.lock().unwrap(), (0..35).collect::<Vec<_>>());
+        assert_eq!(*rf.lock().unwrap(), (0..5).collect::<Vec<_>>());
+    }
 }
@@ -7,7 +7,9 @@ use mpdelta_core::common::time_split_value_persistent::TimeSplitValuePersistent;
 use mpdelta_core::component::instance::{ComponentInstance, ComponentInstanceId};
 use mpdelta_core::component::link::MarkerLink;
 use mpdelta_core::component::marker_pin::{MarkerPin, MarkerPinId, MarkerTime};
-use mpdelta_core::component::parameter::{AudioRequiredParams, ImageRequiredParams, ImageRequiredParamsTransform, Never, Parameter, ParameterNullableValue, ParameterValueType, PinSplitValue, SingleChannelVolume, VariableParameterValue, Vector3Params};
+use mpdelta_core::component::parameter::value::DynEditableSingleValueMarker;
+use mpdelta_core::component::parameter::{AudioRequiredParams, ImageRequiredParams, ImageRequiredParamsTransform, Parameter, ParameterNullableValue, ParameterValueFixed, ParameterValueRaw, ParameterValueType, PinSplitValue, SingleChannelVolume, VariableParameterValue, Vector3Params};
+use mpdelta_core::component::processor::ComponentProcessor;
 use mpdelta_core::core::{EditEventListener, Editor, IdGenerator};
 use mpdelta_core::edit::{InstanceEditCommand, InstanceEditEvent, RootComponentEditCommand, RootComponentEditEvent};
 use mpdelta_core::project::{RootComponentClassHandle, RootComponentClassItemWrite};
@@ -73,6 +75,8 @@ pub enum ProjectEditError {
     CannotSplitForAvoidFloating,
     #[error("marker link not found")]
     MarkerLinkNotFound,
+    #[error("invalid image size")]
+    InvalidImageSize,
     #[error("{0}")]
     CollectCachedTimeError(#[from] CollectCachedTimeError),
 }
@@ -322,6 +326,32 @@ where
                 self.edit_event_listeners.iter().for_each(|listener| listener.on_edit(target_ref, RootComponentEditEvent::ConnectMarkerPins(&from, &to)));
                 Ok(ProjectEditLog::Unimplemented)
             }
+            RootComponentEditCommand::EditFrameRate(frame_rate) => {
+                {
+                    let mut item = target.get_mut().await;
+                    item.set_frame_rate(frame_rate);
+                    let time_map = mpdelta_differential::collect_cached_time(&*item)?;
+                    RootComponentClassItemWrite::commit_changes(item, time_map);
+                }
+
+                self.edit_event_listeners.iter().for_each(|listener| listener.on_edit(target_ref, RootComponentEditEvent::EditFrameRate(frame_rate)));
+                Ok(ProjectEditLog::Unimplemented)
+            }
+            RootComponentEditCommand::EditImageSize(image_size, pixel_aspect_ratio) => {
+                if !image_size.is_valid() {
+                    return Err(ProjectEditError::InvalidImageSize);
+                }
+                {
+                    let mut item = target.get_mut().await;
+                    item.set_image_size(image_size);
+                    item.set_pixel_aspect_ratio(pixel_aspect_ratio);
+                    let time_map = mpdelta_differential::collect_cached_time(&*item)?;
+                    RootComponentClassItemWrite::commit_changes(item, time_map);
+                }
+
+                self.edit_event_listeners.iter().for_each(|listener| listener.on_edit(target_ref, RootComponentEditEvent::EditImageSize(image_size, pixel_aspect_ratio)));
+                Ok(ProjectEditLog::Unimplemented)
+            }
         }
     }
 
@@ -344,6 +374,7 @@ where
                         }
                         *slot = value.clone();
                     }
+                    update_variable_parameter_types(component).await;
 
                     let time_map = mpdelta_differential::collect_cached_time(&*item)?;
                     RootComponentClassItemWrite::commit_changes(item, time_map);
@@ -385,14 +416,8 @@ where
                                 Parameter::Integer(value) => all_valid_pins(value, &pins)?,
                                 Parameter::RealNumber(value) => all_valid_pins(value, &pins)?,
                                 Parameter::Boolean(value) => all_valid_pins(value, &pins)?,
-                                Parameter::Dictionary(value) => {
-                                    let _: &Never = value;
-                                    unreachable!();
-                                }
-                                Parameter::Array(value) => {
-                                    let _: &Never = value;
-                                    unreachable!();
-                                }
+                                Parameter::Dictionary(value) => all_valid_pins(value, &pins)?,
+                                Parameter::Array(value) => all_valid_pins(value, &pins)?,
                                 Parameter::ComponentClass(_) => {}
                             }
 
@@ -880,14 +905,8 @@ where
                             Parameter::Integer(value) => remove_pin(value, &pin),
                             Parameter::RealNumber(value) => remove_pin(value, &pin),
                             Parameter::Boolean(value) => remove_pin(value, &pin),
-                            Parameter::Dictionary(value) => {
-                                let _: &mut Never = value;
-                                unreachable!();
-                            }
-                            Parameter::Array(value) => {
-                                let _: &mut Never = value;
-                                unreachable!();
-                            }
+                            Parameter::Dictionary(value) => remove_pin(value, &pin),
+                            Parameter::Array(value) => remove_pin(value, &pin),
                             Parameter::ComponentClass(_) => {}
                         }
                     }
@@ -1104,9 +1123,9 @@ where
                                 ParameterNullableValue::Integer(value) => ParameterNullableValue::Integer(split_time_split_value(value, &right_pins, &pin, &cloned_pin_weak)),
                                 ParameterNullableValue::RealNumber(value) => ParameterNullableValue::RealNumber(split_time_split_value(value, &right_pins, &pin, &cloned_pin_weak)),
                                 ParameterNullableValue::Boolean(value) => ParameterNullableValue::Boolean(split_time_split_value(value, &right_pins, &pin, &cloned_pin_weak)),
-                                &mut ParameterNullableValue::Dictionary(value) => ParameterNullableValue::Dictionary(value),
-                                &mut ParameterNullableValue::Array(value) => ParameterNullableValue::Array(value),
-                                &mut ParameterNullableValue::ComponentClass(value) => ParameterNullableValue::ComponentClass(value),
+                                ParameterNullableValue::Dictionary(value) => ParameterNullableValue::Dictionary(split_time_split_value(value, &right_pins, &pin, &cloned_pin_weak)),
+                                ParameterNullableValue::Array(value) => ParameterNullableValue::Array(split_time_split_value(value, &right_pins, &pin, &cloned_pin_weak)),
+                                ParameterNullableValue::ComponentClass(value) => ParameterNullableValue::ComponentClass(value.clone()),
                             };
                             VariableParameterValue { params, components: components.clone(), priority }
                         })
@@ -1168,3 +1187,57 @@ where
         }
     }
 }
+
+/// 固定パラメータの変更で可変パラメータの種類が変わった場合に、可変パラメータをそれに合わせる
+///
+/// 名前と型が同じ可変パラメータは値を引き継ぎ、それ以外は値のない状態で追加する
+async fn update_variable_parameter_types<T: ParameterValueType>(component: &mut ComponentInstance<T>) {
+    let fixed_parameters = component
+        .fixed_parameters()
+        .iter()
+        .map(|value| match value {
+            ParameterValueFixed::None => ParameterValueRaw::None,
+            ParameterValueFixed::Image(value) => ParameterValueRaw::Image(value.get_value()),
+            ParameterValueFixed::Audio(value) => ParameterValueRaw::Audio(value.get_value()),
+            ParameterValueFixed::Binary(value) => ParameterValueRaw::Binary(value.get_value()),
+            ParameterValueFixed::String(value) => ParameterValueRaw::String(value.get_value()),
+            ParameterValueFixed::Integer(value) => ParameterValueRaw::Integer(value.get_value()),
+            ParameterValueFixed::RealNumber(value) => ParameterValueRaw::RealNumber(value.get_value()),
+            ParameterValueFixed::Boolean(value) => ParameterValueRaw::Boolean(value.get_value()),
+            ParameterValueFixed::Dictionary(value) => ParameterValueRaw::Dictionary(value.get_value()),
+            ParameterValueFixed::Array(value) => ParameterValueRaw::Array(value.get_value()),
+            ParameterValueFixed::ComponentClass(()) => ParameterValueRaw::ComponentClass(()),
+        })
+        .collect::<Vec<_>>();
+    let mut types = Vec::new();
+    component.processor().update_variable_parameter(&fixed_parameters, &mut types).await;
+    let current_types = component.variable_parameters_type();
+    if types.len() == current_types.len() && types.iter().zip(current_types).all(|((name, ty), (current_name, current_ty))| name == current_name && ty.select() == current_ty.select()) {
+        return;
+    }
+    let (left, right) = (*component.marker_left().id(), *component.marker_right().id());
+    let values = types
+        .iter()
+        .map(|(name, ty)| {
+            let current = current_types.iter().position(|(current_name, current_ty)| current_name == name && current_ty.select() == ty.select());
+            if let Some(value) = current.and_then(|i| component.variable_parameters().get(i)) {
+                return value.clone();
+            }
+            let params = match ty.select() {
+                Parameter::None => ParameterNullableValue::None,
+                Parameter::Image(()) => ParameterNullableValue::Image(TimeSplitValuePersistent::new(left, None, right)),
+                Parameter::Audio(()) => ParameterNullableValue::Audio(TimeSplitValuePersistent::new(left, None, right)),
+                Parameter::Binary(()) => ParameterNullableValue::Binary(TimeSplitValuePersistent::new(left, None, right)),
+                Parameter::String(()) => ParameterNullableValue::String(TimeSplitValuePersistent::new(left, None, right)),
+                Parameter::Integer(()) => ParameterNullableValue::Integer(TimeSplitValuePersistent::new(left, None, right)),
+                Parameter::RealNumber(()) => ParameterNullableValue::RealNumber(TimeSplitValuePersistent::new(left, None, right)),
+                Parameter::Boolean(()) => ParameterNullableValue::Boolean(TimeSplitValuePersistent::new(left, None, right)),
+                Parameter::Dictionary(()) => ParameterNullableValue::Dictionary(TimeSplitValuePersistent::new(left, None, right)),
+                Parameter::Array(()) => ParameterNullableValue::Array(TimeSplitValuePersistent::new(left, None, right)),
+                Parameter::ComponentClass(()) => ParameterNullableValue::ComponentClass(None),
+            };
+            VariableParameterValue::new(params)
+        })
+        .collect();
+    component.set_variable_parameters(types, values);
+}
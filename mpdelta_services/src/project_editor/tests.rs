@@ -1,11 +1,19 @@
-use crate::project_editor::ProjectEditor;
-use mpdelta_core::component::parameter::ParameterValueType;
-use mpdelta_core::core::Editor;
+use crate::project_editor::{update_variable_parameter_types, ProjectEditor};
+use async_trait::async_trait;
+use mpdelta_core::common::time_split_value_persistent::TimeSplitValuePersistent;
+use mpdelta_core::component::instance::ComponentInstance;
+use mpdelta_core::component::marker_pin::{MarkerPin, MarkerTime};
+use mpdelta_core::component::parameter::{Parameter, ParameterNullableValue, ParameterSelect, ParameterType, ParameterValueRaw, ParameterValueType, VariableParameterPriority, VariableParameterValue};
+use mpdelta_core::component::processor::{ComponentProcessor, ComponentProcessorNative, ComponentProcessorNativeDyn, ImageSize, NativeProcessorInput, NativeProcessorRequest, PixelAspectRatio};
+use mpdelta_core::core::{Editor, IdGenerator};
 use mpdelta_core::edit::{InstanceEditCommand, RootComponentEditCommand};
 use mpdelta_core::mfrac;
-use mpdelta_core::time::TimelineTime;
-use mpdelta_core_test_util::{assert_eq_root_component_class, root_component_class, TestIdGenerator};
+use mpdelta_core::ptr::StaticPointer;
+use mpdelta_core::time::{FrameRate, TimelineTime};
+use mpdelta_core_test_util::{assert_eq_root_component_class, root_component_class, NoopComponentClass, TestIdGenerator};
+use rpds::Vector;
 use std::sync::Arc;
+use tokio::sync::RwLock;
 
 struct T;
 
@@ -209,6 +217,55 @@ async fn test_connect_marker_pins() {
     assert_eq_root_component_class(&edit_target, &expect).await;
 }
 
+#[tokio::test]
+async fn test_edit_frame_rate() {
+    let id = Arc::new(TestIdGenerator::new());
+    let editor = ProjectEditor::new(Arc::clone(&id));
+    root_component_class! {
+        edit_target; <T>; id;
+        left: left,
+        components: [
+            { markers: [marker!(locked: 0) => l1, marker!() => r1] },
+        ],
+        links: [
+            left = mfrac!(1) => l1,
+            l1 = mfrac!(1) => r1,
+        ],
+    }
+    assert_eq!(edit_target.read().await.get().frame_rate(), FrameRate::FPS_60);
+    editor.edit(edit_target.as_ref(), RootComponentEditCommand::EditFrameRate(FrameRate::FPS_29_97)).await.unwrap();
+    assert_eq!(edit_target.read().await.get().frame_rate(), FrameRate::FPS_29_97);
+    assert_eq!(edit_target.read().await.get().time_of_pin(&r1), Some(TimelineTime::new(mfrac!(2))));
+}
+
+#[tokio::test]
+async fn test_edit_image_size() {
+    let id = Arc::new(TestIdGenerator::new());
+    let editor = ProjectEditor::new(Arc::clone(&id));
+    root_component_class! {
+        edit_target; <T>; id;
+        left: left,
+        components: [
+            { markers: [marker!(locked: 0) => l1, marker!() => r1] },
+        ],
+        links: [
+            left = mfrac!(1) => l1,
+            l1 = mfrac!(1) => r1,
+        ],
+    }
+    assert_eq!(edit_target.read().await.get().image_size(), ImageSize { width: 1920, height: 1080 });
+    assert_eq!(edit_target.read().await.get().pixel_aspect_ratio(), PixelAspectRatio::SQUARE);
+    let vertical = ImageSize { width: 1080, height: 1920 };
+    let anamorphic = PixelAspectRatio::new(4, 3).unwrap();
+    editor.edit(edit_target.as_ref(), RootComponentEditCommand::EditImageSize(vertical, anamorphic)).await.unwrap();
+    assert_eq!(edit_target.read().await.get().image_size(), vertical);
+    assert_eq!(edit_target.read().await.get().pixel_aspect_ratio(), anamorphic);
+    assert_eq!(edit_target.read().await.get().time_of_pin(&r1), Some(TimelineTime::new(mfrac!(2))));
+
+    assert!(editor.edit(edit_target.as_ref(), RootComponentEditCommand::EditImageSize(ImageSize { width: 0, height: 1080 }, PixelAspectRatio::SQUARE)).await.is_err());
+    assert_eq!(edit_target.read().await.get().image_size(), vertical);
+}
+
 #[tokio::test]
 async fn test_lock_marker_pin() {
     let id = Arc::new(TestIdGenerator::new());
@@ -320,3 +377,115 @@ async fn test_split_at_pin() {
     }
     editor.edit_instance(edit_target.as_ref(), &c1, InstanceEditCommand::SplitAtPin(m)).await.unwrap_err();
 }
+
+/// 固定パラメータによらず、決まった可変パラメータの種類を返す
+struct VariableParameterTypes(Vec<(String, ParameterType)>);
+
+#[async_trait]
+impl ComponentProcessor<T> for VariableParameterTypes {
+    async fn fixed_parameter_types(&self) -> &[(String, ParameterType)] {
+        &[]
+    }
+
+    async fn update_variable_parameter(&self, _: &[ParameterValueRaw<(), ()>], variable_parameters: &mut Vec<(String, ParameterType)>) {
+        *variable_parameters = self.0.clone();
+    }
+
+    async fn num_interprocess_pins(&self, _: &[ParameterValueRaw<(), ()>]) -> usize {
+        0
+    }
+}
+
+#[async_trait]
+impl ComponentProcessorNative<T> for VariableParameterTypes {
+    type WholeComponentCacheKey = ();
+    type WholeComponentCacheValue = ();
+    type FramedCacheKey = ();
+    type FramedCacheValue = ();
+
+    fn whole_component_cache_key(&self, _: &[ParameterValueRaw<(), ()>], _: &[TimelineTime]) -> Option<Self::WholeComponentCacheKey> {
+        unimplemented!()
+    }
+
+    fn framed_cache_key(&self, _: NativeProcessorInput<'_, T>, _: TimelineTime, _: Parameter<ParameterSelect>) -> Option<Self::FramedCacheKey> {
+        unimplemented!()
+    }
+
+    async fn natural_length(&self, _: &[ParameterValueRaw<(), ()>], _: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> Option<MarkerTime> {
+        unimplemented!()
+    }
+
+    async fn supports_output_type(&self, _: &[ParameterValueRaw<(), ()>], _: Parameter<ParameterSelect>, _: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> bool {
+        unimplemented!()
+    }
+
+    async fn process(&self, _: NativeProcessorInput<'_, T>, _: TimelineTime, _: Parameter<NativeProcessorRequest>, _: &mut Option<Arc<Self::WholeComponentCacheValue>>, _: &mut Option<Arc<Self::FramedCacheValue>>) -> ParameterValueRaw<(), ()> {
+        unimplemented!()
+    }
+}
+
+#[tokio::test]
+async fn test_update_variable_parameter_types() {
+    let id = TestIdGenerator::new();
+    // 可変パラメータの種類が`current`のコンポーネントを、プロセッサが`updated`を返すときの種類に合わせる
+    // 元からある値は`PrioritizeComponent`にしておき、新しく作られた値と区別する
+    let update = |current: Vec<(&str, ParameterType)>, updated: Vec<(&str, ParameterType)>| {
+        let left = MarkerPin::new(id.generate_new(), MarkerTime::ZERO);
+        let right = MarkerPin::new(id.generate_new(), MarkerTime::new(mfrac!(1)).unwrap());
+        let values = current
+            .iter()
+            .map(|(_, ty)| {
+                let params = match ty {
+                    Parameter::String(()) => ParameterNullableValue::<T>::String(TimeSplitValuePersistent::new(*left.id(), None, *right.id())),
+                    Parameter::Integer(()) => ParameterNullableValue::<T>::Integer(TimeSplitValuePersistent::new(*left.id(), None, *right.id())),
+                    _ => unreachable!(),
+                };
+                VariableParameterValue {
+                    params,
+                    components: Vector::new_sync(),
+                    priority: VariableParameterPriority::PrioritizeComponent,
+                }
+            })
+            .collect();
+        let types = |types: Vec<(&str, ParameterType)>| types.into_iter().map(|(name, ty)| (name.to_owned(), ty)).collect::<Vec<_>>();
+        let processor = Arc::new(VariableParameterTypes(types(updated)));
+        let mut component = ComponentInstance::builder(StaticPointer::<RwLock<NoopComponentClass>>::new().map(|c| c as _), left, right, Vec::new(), processor as Arc<dyn ComponentProcessorNativeDyn<T>>)
+            .variable_parameters(types(current), values)
+            .build(&id);
+        async move {
+            update_variable_parameter_types(&mut component).await;
+            component
+        }
+    };
+    let summary = |component: &ComponentInstance<T>| {
+        assert_eq!(component.variable_parameters_type().len(), component.variable_parameters().len());
+        component
+            .variable_parameters_type()
+            .iter()
+            .zip(component.variable_parameters().iter())
+            .map(|((name, ty), value)| {
+                assert_eq!(ty.select(), value.params.select());
+                (name.clone(), ty.clone(), value.priority)
+            })
+            .collect::<Vec<_>>()
+    };
+    use VariableParameterPriority::{PrioritizeComponent as Kept, PrioritizeManually as Reset};
+    let string = || -> ParameterType { Parameter::String(()) };
+    let integer = || -> ParameterType { Parameter::Integer(()) };
+
+    // 種類が変わらなければ値はそのまま
+    let component = update(vec![("a", string()), ("b", integer())], vec![("a", string()), ("b", integer())]).await;
+    assert_eq!(summary(&component), [("a".to_owned(), string(), Kept), ("b".to_owned(), integer(), Kept)]);
+
+    // 追加されたパラメータは値のない状態で加わる
+    let component = update(vec![("a", string())], vec![("a", string()), ("b", integer())]).await;
+    assert_eq!(summary(&component), [("a".to_owned(), string(), Kept), ("b".to_owned(), integer(), Reset)]);
+
+    // 削除されたパラメータの値は捨て、残りは位置が変わっても引き継ぐ
+    let component = update(vec![("a", string()), ("b", integer())], vec![("b", integer())]).await;
+    assert_eq!(summary(&component), [("b".to_owned(), integer(), Kept)]);
+
+    // 型が変わったパラメータは値のない状態に戻す
+    let component = update(vec![("a", string()), ("b", integer())], vec![("a", integer()), ("b", integer())]).await;
+    assert_eq!(summary(&component), [("a".to_owned(), integer(), Reset), ("b".to_owned(), integer(), Kept)]);
+}
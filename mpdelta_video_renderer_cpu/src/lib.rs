@@ -0,0 +1,500 @@
+use cgmath::{Matrix3, Matrix4, SquareMatrix, Vector3, Vector4};
+use glam::Vec4;
+use mpdelta_core::component::parameter::ImageRequiredParamsFixed;
+use mpdelta_core_cpu::{ImageType, RgbaImage};
+use mpdelta_renderer::{image_transform_mat, Combiner, CombinerBuilder, ImageCombinerParam, ImageCombinerRequest, ImageSizeRequest};
+use rayon::prelude::{IndexedParallelIterator, ParallelIterator, ParallelSliceMut};
+use shader_composite_operation::blend::mix_blended_color;
+use shader_composite_operation::composite::composite;
+use std::future::Future;
+
+/// 出力画像のピクセルから、そこに貼られる画像の色を求める
+///
+/// mpdelta_video_renderer_vulkanoのtexture_drawingシェーダを逆向きにたどる
+struct TextureMapping<'a> {
+    image: &'a RgbaImage,
+    transform: Matrix4<f64>,
+    // NDC (X, Y, 1) -> 頂点 (x, y, 1)の斉次座標
+    inverse: Matrix3<f64>,
+    viewport: ImageSizeRequest,
+}
+
+impl<'a> TextureMapping<'a> {
+    fn new(image: &'a RgbaImage, transform: Matrix4<f64>, viewport: ImageSizeRequest) -> Option<TextureMapping<'a>> {
+        // 頂点のzは常に0なので、clip空間の(x, y, w)は3x3の行列で表せる
+        let m = Matrix3::new(transform.x.x, transform.x.y, transform.x.w, transform.y.x, transform.y.y, transform.y.w, transform.w.x, transform.w.y, transform.w.w);
+        let inverse = m.invert()?;
+        Some(TextureMapping { image, transform, inverse, viewport })
+    }
+
+    fn sample(&self, x: usize, y: usize) -> Option<Vec4> {
+        let ndc = Vector3::new((x as f64 + 0.5) / self.viewport.width as f64 * 2. - 1., (y as f64 + 0.5) / self.viewport.height as f64 * 2. - 1., 1.);
+        let vertex = self.inverse * ndc;
+        let (vx, vy) = (vertex.x / vertex.z, vertex.y / vertex.z);
+        if !(-1. ..=1.).contains(&vx) || !(-1. ..=1.).contains(&vy) {
+            return None;
+        }
+        // GPUと同じく、wが正でない点と深度が[0, 1]の外にある点はクリップする
+        let clip = self.transform * Vector4::new(vx, vy, 0., 1.);
+        if clip.w <= 0. || clip.z < 0. || clip.z > clip.w {
+            return None;
+        }
+        Some(sample_bilinear(self.image, (vx + 1.) / 2., (vy + 1.) / 2.))
+    }
+}
+
+/// `SamplerCreateInfo::simple_repeat_linear_no_mipmap`と同じく、範囲外を繰り返してバイリニア補間する
+fn sample_bilinear(image: &RgbaImage, u: f64, v: f64) -> Vec4 {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return Vec4::ZERO;
+    }
+    let x = u * width as f64 - 0.5;
+    let y = v * height as f64 - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = ((x - x0) as f32, (y - y0) as f32);
+    let texel = |x: f64, y: f64| {
+        let x = (x as i64).rem_euclid(width as i64) as u32;
+        let y = (y as i64).rem_euclid(height as i64) as u32;
+        Vec4::from_array(image.get_pixel(x, y).0.map(|c| c as f32 / 255.))
+    };
+    let top = texel(x0, y0).lerp(texel(x0 + 1., y0), fx);
+    let bottom = texel(x0, y0 + 1.).lerp(texel(x0 + 1., y0 + 1.), fx);
+    top.lerp(bottom, fy)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImageCombinerBuilder {}
+
+impl ImageCombinerBuilder {
+    pub fn new() -> ImageCombinerBuilder {
+        ImageCombinerBuilder {}
+    }
+}
+
+impl CombinerBuilder<ImageType> for ImageCombinerBuilder {
+    type Request = ImageCombinerRequest;
+    type Param = ImageCombinerParam;
+    type Combiner = ImageCombiner;
+
+    fn new_combiner(&self, request: Self::Request) -> Self::Combiner {
+        ImageCombiner {
+            image_size_request: request.size_request,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+pub struct ImageCombiner {
+    image_size_request: ImageSizeRequest,
+    buffer: Vec<(ImageType, ImageRequiredParamsFixed)>,
+}
+
+impl ImageCombiner {
+    fn combine(self) -> ImageType {
+        let ImageCombiner { image_size_request, buffer } = self;
+        let image_width = image_size_request.width.ceil() as u32;
+        let image_height = image_size_request.height.ceil() as u32;
+        if image_width == 0 || image_height == 0 {
+            return ImageType::from(RgbaImage::new(image_width, image_height));
+        }
+        let mut result = vec![Vec4::ZERO; image_width as usize * image_height as usize];
+        for (ImageType(image), image_param) in buffer {
+            let Some(transform_mat) = image_transform_mat(&image_param.transform, [image.width(), image.height()], image_size_request) else {
+                continue;
+            };
+            // 変換が退化している場合は何も描かれないが、合成はGPUと同じく透明なsourceとして行う
+            let mapping = TextureMapping::new(&image, transform_mat, image_size_request);
+            let opacity = image_param.opacity.value() as f32;
+            let blend = image_param.blend_mode as u32;
+            let operation = image_param.composite_operation as u32;
+            result.par_chunks_mut(image_width as usize).enumerate().for_each(|(y, row)| {
+                for (x, dest) in row.iter_mut().enumerate() {
+                    let src = mapping.as_ref().and_then(|mapping| mapping.sample(x, y)).unwrap_or(Vec4::ZERO);
+                    let src_alpha = src.w * opacity;
+                    let c_s = mix_blended_color(blend, dest.truncate(), dest.w, src.truncate());
+                    *dest = composite(operation, c_s.extend(src_alpha), *dest);
+                }
+            });
+        }
+        let data = result.into_iter().flat_map(|color| (color.clamp(Vec4::ZERO, Vec4::ONE) * 255.).round().to_array().map(|c| c as u8)).collect();
+        ImageType::from(RgbaImage::from_raw(image_width, image_height, data).unwrap())
+    }
+}
+
+impl Combiner<ImageType> for ImageCombiner {
+    type Param = ImageCombinerParam;
+
+    fn add(&mut self, data: ImageType, param: Self::Param) {
+        self.buffer.push((data, param))
+    }
+
+    fn collect<'async_trait>(self) -> impl Future<Output = ImageType> + Send + 'async_trait
+    where
+        Self: 'async_trait,
+        ImageType: 'async_trait,
+    {
+        // rayonでの合成が終わるまで非同期ランタイムのスレッドを塞がないようにする
+        async move { tokio::task::spawn_blocking(move || self.combine()).await.unwrap() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use cgmath::{One, Quaternion};
+    use mpdelta_audio_mixer::MPDeltaAudioMixerBuilder;
+    use mpdelta_component_sine_audio::SineAudio;
+    use mpdelta_core::common::time_split_value_persistent::TimeSplitValuePersistent;
+    use mpdelta_core::component::marker_pin::{MarkerPinId, MarkerTime};
+    use mpdelta_core::component::parameter::value::{DynEditableLerpEasingValue, EasingValue, LinearEasing};
+    use mpdelta_core::component::parameter::{BlendMode, CompositeOperation, ImageRequiredParams, ImageRequiredParamsTransform, ImageRequiredParamsTransformFixed, Opacity, Parameter, ParameterSelect, ParameterType, ParameterValueRaw, ParameterValueType, VariableParameterValue};
+    use mpdelta_core::component::processor::{ComponentProcessor, ComponentProcessorNative, ComponentProcessorNativeDyn, ImageSize, NativeProcessorInput, NativeProcessorRequest};
+    use mpdelta_core::core::ComponentEncoder;
+    use mpdelta_core::encode::EncodeMonitor;
+    use mpdelta_core::mfrac;
+    use mpdelta_core::project::RootComponentClassItemWrite;
+    use mpdelta_core::ptr::StaticPointerOwned;
+    use mpdelta_core::time::TimelineTime;
+    use mpdelta_core_audio::AudioType;
+    use mpdelta_core_test_util::golden_audio::{AudioTolerance, GoldenAudio};
+    use mpdelta_core_test_util::golden_image::GoldenImages;
+    use mpdelta_core_test_util::{root_component_class, TestIdGenerator};
+    use mpdelta_processor_cache_moka::MokaCache;
+    use mpdelta_renderer::{MPDeltaRendererBuilder, VideoEncoder, VideoEncoderBuilder};
+    use mpdelta_rendering_controller::LookaheadRenderingControllerBuilder;
+    use std::convert::Infallible;
+    use std::ops::Range;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+    use tokio::runtime::Handle;
+
+    fn param(scale: f64, opacity: Opacity, blend_mode: BlendMode, composite_operation: CompositeOperation) -> ImageRequiredParamsFixed {
+        ImageRequiredParamsFixed {
+            transform: ImageRequiredParamsTransformFixed::Params {
+                size: Vector3::new(1., 1., 1.),
+                scale: Vector3::new(scale, scale, 1.),
+                translate: Vector3::new(0., 0., 0.),
+                rotate: Quaternion::one(),
+                scale_center: Vector3::new(0., 0., 0.),
+                rotate_center: Vector3::new(0., 0., 0.),
+            },
+            background_color: [0; 4],
+            opacity,
+            blend_mode,
+            composite_operation,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_image_combiner() {
+        let image_combiner_builder = ImageCombinerBuilder::new();
+        let size = ImageSizeRequest { width: 24., height: 24. };
+
+        let ImageType(image) = image_combiner_builder.new_combiner(ImageCombinerRequest::from(size)).collect().await;
+        assert_eq!(image.dimensions(), (24, 24));
+        assert!(image.pixels().all(|p| p.0 == [0; 4]));
+
+        // 赤の上に半透明の緑を中央1/3だけ重ねる
+        let mut image_combiner = image_combiner_builder.new_combiner(ImageCombinerRequest::from(size));
+        image_combiner.add(ImageType::filled(1, 1, [255, 0, 0, 255]), param(1., Opacity::OPAQUE, BlendMode::Normal, CompositeOperation::SourceOver));
+        image_combiner.add(ImageType::filled(1, 1, [0, 255, 0, 255]), param(1. / 3., Opacity::new(0.5).unwrap(), BlendMode::Normal, CompositeOperation::SourceOver));
+        let ImageType(image) = image_combiner.collect().await;
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(23, 23).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(12, 12).0, [128, 128, 0, 255]);
+        assert_eq!(image.get_pixel(7, 12).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(8, 12).0, [128, 128, 0, 255]);
+
+        // 乗算
+        let mut image_combiner = image_combiner_builder.new_combiner(ImageCombinerRequest::from(size));
+        image_combiner.add(ImageType::filled(1, 1, [255, 128, 0, 255]), param(1., Opacity::OPAQUE, BlendMode::Normal, CompositeOperation::SourceOver));
+        image_combiner.add(ImageType::filled(1, 1, [128, 255, 255, 255]), param(1., Opacity::OPAQUE, BlendMode::Multiply, CompositeOperation::SourceOver));
+        let ImageType(image) = image_combiner.collect().await;
+        assert_eq!(image.get_pixel(5, 5).0, [128, 128, 0, 255]);
+
+        // 画像が描かれていない範囲も透明なsourceとして合成される
+        let mut image_combiner = image_combiner_builder.new_combiner(ImageCombinerRequest::from(size));
+        image_combiner.add(ImageType::filled(1, 1, [255, 0, 0, 255]), param(1., Opacity::OPAQUE, BlendMode::Normal, CompositeOperation::SourceOver));
+        image_combiner.add(ImageType::filled(1, 1, [0, 0, 255, 255]), param(1. / 3., Opacity::OPAQUE, BlendMode::Normal, CompositeOperation::SourceIn));
+        let ImageType(image) = image_combiner.collect().await;
+        assert_eq!(image.get_pixel(0, 0).0, [0; 4]);
+        assert_eq!(image.get_pixel(12, 12).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_sample_bilinear() {
+        let image = RgbaImage::from_fn(2, 1, |x, _| if x == 0 { [0, 0, 0, 255].into() } else { [255, 255, 255, 255].into() });
+        assert_eq!(sample_bilinear(&image, 0.25, 0.5), Vec4::new(0., 0., 0., 1.));
+        assert_eq!(sample_bilinear(&image, 0.75, 0.5), Vec4::ONE);
+        assert!(sample_bilinear(&image, 0.5, 0.5).abs_diff_eq(Vec4::new(0.5, 0.5, 0.5, 1.), 1e-6));
+        // 端は反対側と補間される
+        assert!(sample_bilinear(&image, 0., 0.5).abs_diff_eq(Vec4::new(0.5, 0.5, 0.5, 1.), 1e-6));
+    }
+
+    struct T;
+
+    impl ParameterValueType for T {
+        type Image = ImageType;
+        type Audio = AudioType;
+        type Binary = ();
+        type String = ();
+        type Integer = ();
+        type RealNumber = ();
+        type Boolean = ();
+        type Dictionary = ();
+        type Array = ();
+        type ComponentClass = ();
+    }
+
+    struct SolidColor(ImageType);
+
+    #[async_trait]
+    impl ComponentProcessor<T> for SolidColor {
+        async fn fixed_parameter_types(&self) -> &[(String, ParameterType)] {
+            &[]
+        }
+
+        async fn update_variable_parameter(&self, _: &[ParameterValueRaw<ImageType, AudioType>], _: &mut Vec<(String, ParameterType)>) {}
+
+        async fn num_interprocess_pins(&self, _: &[ParameterValueRaw<ImageType, AudioType>]) -> usize {
+            0
+        }
+    }
+
+    #[async_trait]
+    impl ComponentProcessorNative<T> for SolidColor {
+        type WholeComponentCacheKey = ();
+        type WholeComponentCacheValue = ();
+        type FramedCacheKey = ();
+        type FramedCacheValue = ();
+
+        fn whole_component_cache_key(&self, _: &[ParameterValueRaw<ImageType, AudioType>], _: &[TimelineTime]) -> Option<Self::WholeComponentCacheKey> {
+            None
+        }
+
+        fn framed_cache_key(&self, _: NativeProcessorInput<'_, T>, _: TimelineTime, _: Parameter<ParameterSelect>) -> Option<Self::FramedCacheKey> {
+            None
+        }
+
+        async fn natural_length(&self, _: &[ParameterValueRaw<ImageType, AudioType>], _: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> Option<MarkerTime> {
+            None
+        }
+
+        async fn supports_output_type(&self, _: &[ParameterValueRaw<ImageType, AudioType>], out: Parameter<ParameterSelect>, _: &mut Option<Arc<Self::WholeComponentCacheValue>>) -> bool {
+            out.equals_type(&Parameter::<ParameterSelect>::Image(()))
+        }
+
+        async fn process(&self, _: NativeProcessorInput<'_, T>, _: TimelineTime, _: Parameter<NativeProcessorRequest>, _: &mut Option<Arc<Self::WholeComponentCacheValue>>, _: &mut Option<Arc<Self::FramedCacheValue>>) -> ParameterValueRaw<ImageType, AudioType> {
+            Parameter::Image(self.0.clone())
+        }
+    }
+
+    /// 1フレームだけ受け取るエンコーダ
+    struct CaptureFrame(Arc<Mutex<Option<ImageType>>>);
+
+    impl VideoEncoderBuilder<ImageType, AudioType> for CaptureFrame {
+        type Err = Infallible;
+        type Encoder = CaptureFrame;
+
+        fn build(&mut self) -> Result<Self::Encoder, Self::Err> {
+            Ok(CaptureFrame(Arc::clone(&self.0)))
+        }
+    }
+
+    impl VideoEncoder<ImageType, AudioType> for CaptureFrame {
+        type Err = Infallible;
+
+        fn requires_image(&self) -> bool {
+            true
+        }
+
+        fn push_frame(&mut self, frame: ImageType) {
+            *self.0.lock().unwrap() = Some(frame);
+        }
+
+        fn requires_audio(&self) -> bool {
+            false
+        }
+
+        fn set_audio(&mut self, _: AudioType) {}
+
+        fn set_audio_range(&mut self, _: AudioType, _: Range<TimelineTime>) {}
+
+        fn finish(&mut self) -> Result<(), Self::Err> {
+            Ok(())
+        }
+
+        fn cancel(&mut self) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    fn image_params(left: MarkerPinId, right: MarkerPinId, scale: f64, translate: f64, opacity: (f64, f64), blend_mode: BlendMode) -> ImageRequiredParams {
+        let mut params = ImageRequiredParams::new_default(&left, &right);
+        let constant = |value: f64| VariableParameterValue::new(TimeSplitValuePersistent::new(left, Some(EasingValue::new(DynEditableLerpEasingValue((value, value)), Arc::new(LinearEasing))), right));
+        let ImageRequiredParamsTransform::Params { scale: s, translate: t, .. } = Arc::make_mut(&mut params.transform) else {
+            unreachable!();
+        };
+        *s = Arc::new(Vector3 {
+            x: constant(scale),
+            y: constant(scale),
+            z: constant(1.),
+        });
+        *t = Arc::new(Vector3 {
+            x: constant(translate),
+            y: constant(translate),
+            z: constant(0.),
+        });
+        params.opacity = TimeSplitValuePersistent::new(left, EasingValue::new(DynEditableLerpEasingValue(opacity), Arc::new(LinearEasing)), right);
+        params.blend_mode = TimeSplitValuePersistent::new(left, blend_mode, right);
+        params
+    }
+
+    #[tokio::test]
+    async fn test_golden_layers() {
+        let solid = |color| Arc::new(SolidColor(ImageType::filled(1, 1, color))) as Arc<dyn ComponentProcessorNativeDyn<T>>;
+        let id = TestIdGenerator::new();
+        root_component_class! {
+            root; <T>; id;
+            left: left,
+            components: [
+                {
+                    markers: [marker!(locked: 0) => l1, marker!() => r1],
+                    processor: solid([255, 128, 0, 255])
+                },
+                {
+                    markers: [marker!(locked: 0) => l2, marker!() => r2],
+                    processor: solid([128, 255, 255, 255]),
+                    image_required_params: image_params(l2, r2, 0.5, 0., (1., 1.), BlendMode::Multiply)
+                },
+                {
+                    markers: [marker!(locked: 0) => l3, marker!() => r3],
+                    processor: solid([0, 255, 0, 255]),
+                    image_required_params: image_params(l3, r3, 0.25, 0., (0., 1.), BlendMode::Normal)
+                },
+                {
+                    markers: [marker!(locked: 0) => l4, marker!() => r4],
+                    processor: solid([0, 0, 255, 255]),
+                    image_required_params: image_params(l4, r4, 0.25, -2., (1., 1.), BlendMode::Screen)
+                },
+            ],
+            links: [
+                left = 0 => l1,
+                l1 = 2 => r1,
+                left = 0 => l2,
+                l2 = 2 => r2,
+                left = 0 => l3,
+                l3 = 2 => r3,
+                left = 0 => l4,
+                l4 = 2 => r4,
+            ],
+        }
+        {
+            let read = root.read().await;
+            let mut item = read.get_mut().await;
+            item.set_image_size(ImageSize { width: 24, height: 24 });
+            let time_map = mpdelta_differential::collect_cached_time(&*item).unwrap();
+            RootComponentClassItemWrite::commit_changes(item, time_map);
+        }
+        let instance = Arc::new(root.read().await.instantiate(&StaticPointerOwned::reference(&root).clone().map(|c| c as _), &id).await);
+        let renderer_builder = MPDeltaRendererBuilder::new(Arc::new(ImageCombinerBuilder::new()), Arc::new(LookaheadRenderingControllerBuilder::new()), Arc::new(MPDeltaAudioMixerBuilder::new()), MokaCache::new(), Handle::current());
+
+        const TEST_OUTPUT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test_output/", env!("CARGO_PKG_NAME"));
+        let golden = GoldenImages::new(concat!(env!("CARGO_MANIFEST_DIR"), "/golden"), Path::new(TEST_OUTPUT_DIR).join("golden"));
+        // 下から順に、橙の背景、中央1/2に乗算、中央1/4に不透明度が0から1へ変わる緑、左上にスクリーン
+        golden
+            .assert_frames("layers", [TimelineTime::new(mfrac!(1, 2)), TimelineTime::new(mfrac!(3, 2))], |at| {
+                let instance = Arc::clone(&instance);
+                let renderer_builder = &renderer_builder;
+                async move {
+                    let frame = Arc::new(Mutex::new(None));
+                    renderer_builder.render_frame_and_encode(instance, at, CaptureFrame(Arc::clone(&frame))).await.unwrap();
+                    let ImageType(image) = frame.lock().unwrap().take().unwrap();
+                    Arc::unwrap_or_clone(image)
+                }
+            })
+            .await;
+    }
+
+    /// 音声だけを受け取るエンコーダ
+    struct CaptureAudio(Arc<Mutex<Option<(AudioType, Range<TimelineTime>)>>>);
+
+    impl VideoEncoderBuilder<ImageType, AudioType> for CaptureAudio {
+        type Err = Infallible;
+        type Encoder = CaptureAudio;
+
+        fn build(&mut self) -> Result<Self::Encoder, Self::Err> {
+            Ok(CaptureAudio(Arc::clone(&self.0)))
+        }
+    }
+
+    impl VideoEncoder<ImageType, AudioType> for CaptureAudio {
+        type Err = Infallible;
+
+        fn requires_image(&self) -> bool {
+            false
+        }
+
+        fn push_frame(&mut self, _: ImageType) {}
+
+        fn requires_audio(&self) -> bool {
+            true
+        }
+
+        fn set_audio(&mut self, _: AudioType) {}
+
+        fn set_audio_range(&mut self, audio: AudioType, range: Range<TimelineTime>) {
+            *self.0.lock().unwrap() = Some((audio, range));
+        }
+
+        fn finish(&mut self) -> Result<(), Self::Err> {
+            Ok(())
+        }
+
+        fn cancel(&mut self) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_golden_sine_stretch() {
+        let sine = || Arc::new(SineAudio::new()) as Arc<dyn ComponentProcessorNativeDyn<T>>;
+        let id = TestIdGenerator::new();
+        root_component_class! {
+            root; <T>; id;
+            left: left,
+            components: [
+                {
+                    markers: [marker!(locked: 0) => l1, marker!() => r1],
+                    processor: sine()
+                },
+                {
+                    markers: [marker!(locked: 0) => l2, marker!(locked: mfrac!(1, 20)) => r2],
+                    processor: sine()
+                },
+            ],
+            links: [
+                left = mfrac!(1, 40) => l1,
+                l1 = mfrac!(3, 40) => r1,
+                left = mfrac!(1, 8) => l2,
+                l2 = mfrac!(1, 10) => r2,
+            ],
+        }
+        let instance = Arc::new(root.read().await.instantiate(&StaticPointerOwned::reference(&root).clone().map(|c| c as _), &id).await);
+        let renderer_builder = MPDeltaRendererBuilder::new(Arc::new(ImageCombinerBuilder::new()), Arc::new(LookaheadRenderingControllerBuilder::new()), Arc::new(MPDeltaAudioMixerBuilder::new()), MokaCache::new(), Handle::current());
+        let captured = Arc::new(Mutex::new(None));
+        renderer_builder
+            .render_range_and_encode(instance, TimelineTime::ZERO..TimelineTime::new(mfrac!(1, 4)), CaptureAudio(Arc::clone(&captured)), EncodeMonitor::default())
+            .await
+            .unwrap();
+        let (audio, range) = captured.lock().unwrap().take().unwrap();
+
+        const TEST_OUTPUT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test_output/", env!("CARGO_PKG_NAME"));
+        // 0.025s-0.1sにそのままの440Hz、0.125s-0.225sに半分の速さで再生した220Hz 境界はどちらも波形の0を通る
+        GoldenAudio::new(concat!(env!("CARGO_MANIFEST_DIR"), "/golden"), Path::new(TEST_OUTPUT_DIR).join("golden"))
+            .with_tolerance(AudioTolerance { peak: 0.02, rms: 0.002 })
+            .assert_range("sine_stretch", audio, range);
+    }
+}
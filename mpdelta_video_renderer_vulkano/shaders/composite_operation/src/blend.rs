@@ -0,0 +1,238 @@
+// ref: https://www.w3.org/TR/compositing-1/#blending
+use glam::Vec3;
+
+/// `B(Cb, Cs)` for the mode numbered as `mpdelta_core::component::parameter::BlendMode`.
+///
+/// `backdrop` and `source` are non-premultiplied colors in `[0, 1]`.
+#[inline(always)]
+pub fn blend(mode: u32, backdrop: Vec3, source: Vec3) -> Vec3 {
+    // see mpdelta_core::component::parameter::BlendMode
+    match mode {
+        1/* Multiply */ => multiply(backdrop, source),
+        2/* Screen */ => screen(backdrop, source),
+        3/* Overlay */ => overlay(backdrop, source),
+        4/* Darken */ => darken(backdrop, source),
+        5/* Lighten */ => lighten(backdrop, source),
+        6/* ColorDodge */ => color_dodge(backdrop, source),
+        7/* ColorBurn */ => color_burn(backdrop, source),
+        8/* HardLight */ => hard_light(backdrop, source),
+        9/* SoftLight */ => soft_light(backdrop, source),
+        10/* Difference */ => difference(backdrop, source),
+        11/* Exclusion */ => exclusion(backdrop, source),
+        12/* Hue */ => hue(backdrop, source),
+        13/* Saturation */ => saturation(backdrop, source),
+        14/* Color */ => color(backdrop, source),
+        15/* Luminosity */ => luminosity(backdrop, source),
+        _/* Normal */ => normal(backdrop, source),
+    }
+}
+
+/// source colorにbackdropを混ぜたもの `(1 - αb) * Cs + αb * B(Cb, Cs)` を返す
+#[inline(always)]
+pub fn mix_blended_color(mode: u32, backdrop: Vec3, backdrop_alpha: f32, source: Vec3) -> Vec3 {
+    (1. - backdrop_alpha) * source + backdrop_alpha * blend(mode, backdrop, source)
+}
+
+#[inline(always)]
+pub fn normal(_cb: Vec3, cs: Vec3) -> Vec3 {
+    cs
+}
+
+#[inline(always)]
+pub fn multiply(cb: Vec3, cs: Vec3) -> Vec3 {
+    cb * cs
+}
+
+#[inline(always)]
+pub fn screen(cb: Vec3, cs: Vec3) -> Vec3 {
+    cb + cs - cb * cs
+}
+
+#[inline(always)]
+pub fn overlay(cb: Vec3, cs: Vec3) -> Vec3 {
+    hard_light(cs, cb)
+}
+
+#[inline(always)]
+pub fn darken(cb: Vec3, cs: Vec3) -> Vec3 {
+    cb.min(cs)
+}
+
+#[inline(always)]
+pub fn lighten(cb: Vec3, cs: Vec3) -> Vec3 {
+    cb.max(cs)
+}
+
+#[inline(always)]
+pub fn color_dodge(cb: Vec3, cs: Vec3) -> Vec3 {
+    let dodged = (cb / (Vec3::ONE - cs).max(Vec3::splat(f32::MIN_POSITIVE))).min(Vec3::ONE);
+    Vec3::select(cb.cmple(Vec3::ZERO), Vec3::ZERO, Vec3::select(cs.cmpge(Vec3::ONE), Vec3::ONE, dodged))
+}
+
+#[inline(always)]
+pub fn color_burn(cb: Vec3, cs: Vec3) -> Vec3 {
+    let burned = Vec3::ONE - ((Vec3::ONE - cb) / cs.max(Vec3::splat(f32::MIN_POSITIVE))).min(Vec3::ONE);
+    Vec3::select(cb.cmpge(Vec3::ONE), Vec3::ONE, Vec3::select(cs.cmple(Vec3::ZERO), Vec3::ZERO, burned))
+}
+
+#[inline(always)]
+pub fn hard_light(cb: Vec3, cs: Vec3) -> Vec3 {
+    let cs2 = cs * 2.;
+    Vec3::select(cs.cmple(Vec3::splat(0.5)), multiply(cb, cs2), screen(cb, cs2 - Vec3::ONE))
+}
+
+#[inline(always)]
+pub fn soft_light(cb: Vec3, cs: Vec3) -> Vec3 {
+    let d = Vec3::select(cb.cmple(Vec3::splat(0.25)), ((16. * cb - Vec3::splat(12.)) * cb + Vec3::splat(4.)) * cb, cb.powf(0.5));
+    let darker = cb - (Vec3::ONE - 2. * cs) * cb * (Vec3::ONE - cb);
+    let lighter = cb + (2. * cs - Vec3::ONE) * (d - cb);
+    Vec3::select(cs.cmple(Vec3::splat(0.5)), darker, lighter)
+}
+
+#[inline(always)]
+pub fn difference(cb: Vec3, cs: Vec3) -> Vec3 {
+    (cb - cs).abs()
+}
+
+#[inline(always)]
+pub fn exclusion(cb: Vec3, cs: Vec3) -> Vec3 {
+    cb + cs - 2. * cb * cs
+}
+
+#[inline(always)]
+pub fn hue(cb: Vec3, cs: Vec3) -> Vec3 {
+    set_lum(set_sat(cs, sat(cb)), lum(cb))
+}
+
+#[inline(always)]
+pub fn saturation(cb: Vec3, cs: Vec3) -> Vec3 {
+    set_lum(set_sat(cb, sat(cs)), lum(cb))
+}
+
+#[inline(always)]
+pub fn color(cb: Vec3, cs: Vec3) -> Vec3 {
+    set_lum(cs, lum(cb))
+}
+
+#[inline(always)]
+pub fn luminosity(cb: Vec3, cs: Vec3) -> Vec3 {
+    set_lum(cb, lum(cs))
+}
+
+#[inline(always)]
+fn lum(c: Vec3) -> f32 {
+    c.dot(Vec3::new(0.3, 0.59, 0.11))
+}
+
+#[inline(always)]
+fn clip_color(c: Vec3) -> Vec3 {
+    let l = lum(c);
+    let n = c.min_element();
+    let x = c.max_element();
+    let c = if n < 0. { Vec3::splat(l) + (c - Vec3::splat(l)) * l / (l - n) } else { c };
+    if x > 1. {
+        Vec3::splat(l) + (c - Vec3::splat(l)) * (1. - l) / (x - l)
+    } else {
+        c
+    }
+}
+
+#[inline(always)]
+fn set_lum(c: Vec3, l: f32) -> Vec3 {
+    clip_color(c + Vec3::splat(l - lum(c)))
+}
+
+#[inline(always)]
+fn sat(c: Vec3) -> f32 {
+    c.max_element() - c.min_element()
+}
+
+#[inline(always)]
+fn set_sat(c: Vec3, s: f32) -> Vec3 {
+    // Cmin -> 0, Cmax -> s, Cmid -> (Cmid - Cmin) * s / (Cmax - Cmin) を成分ごとにまとめて計算する
+    let min = c.min_element();
+    let max = c.max_element();
+    if max > min {
+        (c - Vec3::splat(min)) * s / (max - min)
+    } else {
+        Vec3::ZERO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_color_eq(actual: Vec3, expected: Vec3) {
+        assert!(actual.abs_diff_eq(expected, 1e-4), "actual: {actual:?}, expected: {expected:?}");
+    }
+
+    #[test]
+    fn test_separable_blend_modes() {
+        let cb = Vec3::new(0.2, 0.5, 0.8);
+        let cs = Vec3::new(0.6, 0.3, 0.1);
+        assert_color_eq(blend(0, cb, cs), Vec3::new(0.6, 0.3, 0.1));
+        assert_color_eq(blend(1, cb, cs), Vec3::new(0.12, 0.15, 0.08));
+        assert_color_eq(blend(2, cb, cs), Vec3::new(0.68, 0.65, 0.82));
+        assert_color_eq(blend(3, cb, cs), Vec3::new(0.24, 0.3, 0.64));
+        assert_color_eq(blend(4, cb, cs), Vec3::new(0.2, 0.3, 0.1));
+        assert_color_eq(blend(5, cb, cs), Vec3::new(0.6, 0.5, 0.8));
+        assert_color_eq(blend(6, cb, cs), Vec3::new(0.5, 0.5 / 0.7, 0.8 / 0.9));
+        assert_color_eq(blend(7, cb, cs), Vec3::ZERO);
+        assert_color_eq(blend(8, cb, cs), Vec3::new(0.36, 0.3, 0.16));
+        assert_color_eq(blend(9, cb, cs), Vec3::new(0.2496, 0.4, 0.672));
+        assert_color_eq(blend(10, cb, cs), Vec3::new(0.4, 0.2, 0.7));
+        assert_color_eq(blend(11, cb, cs), Vec3::new(0.56, 0.5, 0.74));
+    }
+
+    #[test]
+    fn test_separable_blend_modes_edge_cases() {
+        // ColorDodge: Cb == 0 なら 0, Cs == 1 なら 1
+        assert_color_eq(color_dodge(Vec3::new(0., 0.5, 1.), Vec3::new(1., 1., 0.5)), Vec3::new(0., 1., 1.));
+        // ColorBurn: Cb == 1 なら 1, Cs == 0 なら 0
+        assert_color_eq(color_burn(Vec3::new(1., 0.5, 0.), Vec3::new(0., 0., 0.5)), Vec3::new(1., 0., 0.));
+        assert_color_eq(color_burn(Vec3::splat(0.8), Vec3::splat(0.5)), Vec3::splat(0.6));
+        // SoftLight: Cb > 0.25 の lighter 側は sqrt(Cb) を使う
+        assert_color_eq(soft_light(Vec3::splat(0.64), Vec3::splat(1.)), Vec3::splat(0.8));
+        assert_color_eq(soft_light(Vec3::splat(0.64), Vec3::splat(0.)), Vec3::splat(0.64 - 0.64 * 0.36));
+        // HardLight: Cs == 0.5 は Multiply 側
+        assert_color_eq(hard_light(Vec3::splat(0.4), Vec3::splat(0.5)), Vec3::splat(0.4));
+    }
+
+    #[test]
+    fn test_non_separable_blend_modes() {
+        let red = Vec3::new(1., 0., 0.);
+        let gray = Vec3::splat(0.5);
+        let teal = Vec3::new(0.2, 0.6, 0.6);
+
+        assert!((lum(red) - 0.3).abs() < 1e-6);
+        assert!((sat(teal) - 0.4).abs() < 1e-6);
+
+        // 無彩色のsourceのhueを乗せると彩度0になり、backdropの輝度だけが残る
+        assert_color_eq(blend(12, teal, gray), Vec3::splat(lum(teal)));
+        // 無彩色のbackdropには彩度を乗せられない
+        assert_color_eq(blend(13, gray, teal), gray);
+        // Colorはbackdropの輝度を保ちつつsourceのhue/saturationを使う
+        let c = blend(14, gray, red);
+        assert!((lum(c) - 0.5).abs() < 1e-4);
+        assert_color_eq(c, Vec3::new(1., 0.5 - 0.3 / 0.7 * 0.5, 0.5 - 0.3 / 0.7 * 0.5));
+        // Luminosityはsourceの輝度をbackdropに適用する
+        let c = blend(15, red, gray);
+        assert!((lum(c) - 0.5).abs() < 1e-4);
+        assert_color_eq(c, Vec3::new(1., 0.5 - 0.3 / 0.7 * 0.5, 0.5 - 0.3 / 0.7 * 0.5));
+        // Hue: sourceのhueをbackdropのsaturation/luminosityで
+        let c = blend(12, teal, red);
+        assert!((lum(c) - lum(teal)).abs() < 1e-4);
+        assert!((sat(c) - sat(teal)).abs() < 1e-4);
+        assert!(c.x > c.y && (c.y - c.z).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_mix_blended_color() {
+        let cb = Vec3::new(0.2, 0.5, 0.8);
+        let cs = Vec3::new(0.6, 0.3, 0.1);
+        assert_color_eq(mix_blended_color(1, cb, 0., cs), cs);
+        assert_color_eq(mix_blended_color(1, cb, 1., cs), multiply(cb, cs));
+        assert_color_eq(mix_blended_color(1, cb, 0.5, cs), (cs + multiply(cb, cs)) / 2.);
+    }
+}
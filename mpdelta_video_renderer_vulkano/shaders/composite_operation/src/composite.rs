@@ -0,0 +1,115 @@
+// ref: https://www.w3.org/TR/compositing-1/#porterduffcompositingoperators
+use glam::{Vec3, Vec4};
+
+/// Porter-Duff合成の係数 `(Fa, Fb)` を返す
+///
+/// `operation` is numbered as `mpdelta_core::component::parameter::CompositeOperation`.
+#[inline(always)]
+pub fn composite_factors(operation: u32, source_alpha: f32, backdrop_alpha: f32) -> (f32, f32) {
+    let a_s = source_alpha;
+    let a_b = backdrop_alpha;
+    // see mpdelta_core::component::parameter::CompositeOperation
+    match operation {
+        0/* Clear */ => (0., 0.),
+        1/* Copy */ => (1., 0.),
+        2/* Destination */ => (0., 1.),
+        4/* DestinationOver */ => (1. - a_b, 1.),
+        5/* SourceIn */ => (a_b, 0.),
+        6/* DestinationIn */ => (0., a_s),
+        7/* SourceOut */ => (1. - a_b, 0.),
+        8/* DestinationOut */ => (0., 1. - a_s),
+        9/* SourceAtop */ => (a_b, 1. - a_s),
+        10/* DestinationAtop */ => (1. - a_b, a_s),
+        11/* XOR */ => (1. - a_b, 1. - a_s),
+        12/* Lighter */ => (1., 1.),
+        _/* SourceOver */ => (1., 1. - a_s),
+    }
+}
+
+/// sourceをbackdropに合成した結果を返す
+///
+/// `source` and `backdrop` are non-premultiplied RGBA, and so is the result.
+#[inline(always)]
+pub fn composite(operation: u32, source: Vec4, backdrop: Vec4) -> Vec4 {
+    let c_s = Vec3::new(source.x, source.y, source.z);
+    let a_s = source.w;
+    let c_b = Vec3::new(backdrop.x, backdrop.y, backdrop.z);
+    let a_b = backdrop.w;
+    let (fa, fb) = composite_factors(operation, a_s, a_b);
+    let co = a_s * fa * c_s + a_b * fb * c_b;
+    let ao = (a_s * fa + a_b * fb).clamp(0.0, 1.0);
+    if ao == 0.0 {
+        Vec4::ZERO
+    } else {
+        (co / ao).extend(ao)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_composite_factors() {
+        let a_s = 0.25;
+        let a_b = 0.75;
+        let table = [
+            (0, (0., 0.)),
+            (1, (1., 0.)),
+            (2, (0., 1.)),
+            (3, (1., 0.75)),
+            (4, (0.25, 1.)),
+            (5, (0.75, 0.)),
+            (6, (0., 0.25)),
+            (7, (0.25, 0.)),
+            (8, (0., 0.75)),
+            (9, (0.75, 0.75)),
+            (10, (0.25, 0.25)),
+            (11, (0.25, 0.75)),
+            (12, (1., 1.)),
+        ];
+        for (operation, expected) in table {
+            assert_eq!(composite_factors(operation, a_s, a_b), expected, "operation: {operation}");
+        }
+    }
+
+    #[test]
+    fn test_composite() {
+        let red = Vec4::new(1., 0., 0., 1.);
+        let half_blue = Vec4::new(0., 0., 1., 0.5);
+        let transparent = Vec4::ZERO;
+        let table = [
+            // (operation, source, backdrop, expected)
+            (0, red, half_blue, Vec4::ZERO),
+            (1, half_blue, red, half_blue),
+            (1, transparent, red, Vec4::ZERO),
+            (2, red, half_blue, half_blue),
+            (3, red, half_blue, red),
+            (3, half_blue, red, Vec4::new(0.5, 0., 0.5, 1.)),
+            (3, transparent, half_blue, half_blue),
+            (4, red, half_blue, Vec4::new(0.5, 0., 0.5, 1.)),
+            (4, red, transparent, red),
+            (5, red, half_blue, Vec4::new(1., 0., 0., 0.5)),
+            (5, red, transparent, Vec4::ZERO),
+            (6, half_blue, red, Vec4::new(1., 0., 0., 0.5)),
+            (6, transparent, red, Vec4::ZERO),
+            (7, red, half_blue, Vec4::new(1., 0., 0., 0.5)),
+            (7, red, red, Vec4::ZERO),
+            (8, half_blue, red, Vec4::new(1., 0., 0., 0.5)),
+            (8, red, half_blue, Vec4::ZERO),
+            (8, transparent, red, red),
+            (9, half_blue, red, Vec4::new(0.5, 0., 0.5, 1.)),
+            (9, red, transparent, Vec4::ZERO),
+            (10, red, half_blue, Vec4::new(0.5, 0., 0.5, 1.)),
+            (10, half_blue, red, Vec4::new(1., 0., 0., 0.5)),
+            (11, red, half_blue, Vec4::new(1., 0., 0., 0.5)),
+            (11, red, red, Vec4::ZERO),
+            (12, half_blue, Vec4::new(0., 0., 1., 0.5), Vec4::new(0., 0., 1., 1.)),
+            (12, red, half_blue, Vec4::new(1., 0., 0.5, 1.)),
+        ];
+        for (operation, source, backdrop, expected) in table {
+            let actual = composite(operation, source, backdrop);
+            assert!(actual.abs_diff_eq(expected, 1e-6), "operation: {operation}, source: {source:?}, backdrop: {backdrop:?}, actual: {actual:?}, expected: {expected:?}");
+        }
+    }
+}
@@ -3,6 +3,9 @@
 #![deny(warnings)]
 use bytemuck_derive::{Pod, Zeroable};
 
+pub mod blend;
+pub mod composite;
+
 pub const BLOCK_SIZE: u32 = 32;
 
 #[repr(C)]
@@ -16,6 +19,8 @@ pub struct CompositeOperationConstant {
 
 #[cfg(feature = "shader")]
 pub mod shader {
+    use crate::blend::mix_blended_color;
+    use crate::composite::composite;
     use crate::CompositeOperationConstant;
     use spirv_std::glam::{UVec3, Vec3Swizzles, Vec4, Vec4Swizzles};
     use spirv_std::{spirv, Image};
@@ -32,54 +37,11 @@ pub mod shader {
             return;
         }
         let stencil: u32 = stencil.read(id.xy());
-        if stencil == 0 {
-            return;
-        }
         let dest_color: Vec4 = result_image.read(id.xy());
-        let src_color: Vec4 = image.read(id.xy());
-        let c_s = src_color.xyz();
-        let a_s = src_color.w;
-        let c_b = dest_color.xyz();
-        let a_b = dest_color.w;
-        // see mpdelta_core::component::parameter::BlendMode
-        let co = match constant.blend {
-            1/* Multiply */ => c_s,
-            2/* Screen */ => c_s,
-            3/* Overlay */ => c_s,
-            4/* Darken */ => c_s,
-            5/* Lighten */ => c_s,
-            6/* ColorDodge */ => c_s,
-            7/* ColorBurn */ => c_s,
-            8/* HardLight */ => c_s,
-            9/* SoftLight */ => c_s,
-            10/* Difference */ => c_s,
-            11/* Exclusion */ => c_s,
-            12/* Hue */ => c_s,
-            13/* Saturation */ => c_s,
-            14/* Color */ => c_s,
-            15/* Luminosity */ => c_s,
-            _/* Normal */ => c_s,
-        };
-        let c_s = (1. - a_b) * c_s + a_b * co;
-        // see mpdelta_core::component::parameter::CompositeOperation
-        let (fa, fb) = match constant.composite {
-            0/* Clear */ => (1., 1. - a_s),
-            1/* Copy */ => (1., 1. - a_s),
-            2/* Destination */ => (1., 1. - a_s),
-            4/* DestinationOver */ => (1., 1. - a_s),
-            5/* SourceIn */ => (1., 1. - a_s),
-            6/* DestinationIn */ => (1., 1. - a_s),
-            7/* SourceOut */ => (1., 1. - a_s),
-            8/* DestinationOut */ => (1., 1. - a_s),
-            9/* SourceAtop */ => (1., 1. - a_s),
-            10/* DestinationAtop */ => (1., 1. - a_s),
-            11/* XOR */ => (1., 1. - a_s),
-            12/* Lighter */ => (1., 1. - a_s),
-            _/* SourceOver */ => (1., 1. - a_s),
-        };
-        let co = a_s * fa * c_s + a_b * fb * c_b;
-        let ao = (a_s * fa + a_b * fb).clamp(0.0, 1.0);
-        let result_color = if ao == 0.0 { Vec4::new(0., 0., 0., 0.) } else { (co / ao).extend(ao) };
+        // 画像が描かれていない範囲は透明なsourceとして合成する(Clear/Copy/SourceIn等は範囲外のbackdropにも作用する)
+        let src_color: Vec4 = if stencil == 0 { Vec4::ZERO } else { image.read(id.xy()) };
+        let c_s = mix_blended_color(constant.blend, dest_color.xyz(), dest_color.w, src_color.xyz());
+        let result_color = composite(constant.composite, c_s.extend(src_color.w), dest_color);
         unsafe { result_image.write(id.xy(), result_color) };
     }
 }
@@ -1,13 +1,12 @@
-use cgmath::{Matrix4, SquareMatrix, Vector3, Vector4};
+use cgmath::{Matrix4, Vector4};
 use futures::future::FutureExt;
 use glam::{Mat4, Vec4};
-use mpdelta_core::component::parameter::{ImageRequiredParamsFixed, ImageRequiredParamsTransformFixed};
+use mpdelta_core::component::parameter::ImageRequiredParamsFixed;
 use mpdelta_core_vulkano::ImageType;
-use mpdelta_renderer::{Combiner, CombinerBuilder, ImageCombinerParam, ImageCombinerRequest, ImageSizeRequest};
+use mpdelta_renderer::{image_transform_mat, Combiner, CombinerBuilder, ImageCombinerParam, ImageCombinerRequest, ImageSizeRequest};
 use shader_composite_operation::CompositeOperationConstant;
 use shader_texture_drawing::TextureDrawingConstant;
 use smallvec::smallvec;
-use std::cmp::Ordering;
 use std::future::Future;
 use std::sync::Arc;
 use vulkano::command_buffer::allocator::{CommandBufferAllocator, StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo};
@@ -130,14 +129,6 @@ impl SharedResource {
     }
 }
 
-fn move_mat(pos: Vector3<f64>) -> Matrix4<f64> {
-    Matrix4::from_cols(Vector4::unit_x(), Vector4::unit_y(), Vector4::unit_z(), pos.extend(1.))
-}
-
-fn scale_mat(scale: Vector3<f64>) -> Matrix4<f64> {
-    Matrix4::from_diagonal(scale.extend(1.))
-}
-
 fn vec4_into_glam(vec: Vector4<f64>) -> Vec4 {
     Vec4::new(vec.x as f32, vec.y as f32, vec.z as f32, vec.w as f32)
 }
@@ -276,35 +267,8 @@ impl Combiner<ImageType> for ImageCombiner {
         )
         .unwrap();
         for (ImageType(image), image_param) in buffer {
-            let transform_mat = match image_param.transform {
-                ImageRequiredParamsTransformFixed::Params {
-                    size,
-                    scale,
-                    translate,
-                    rotate,
-                    scale_center,
-                    rotate_center,
-                } => {
-                    let image_native_size = match (image_width as f64 * size.x * image.extent()[1] as f64).partial_cmp(&(image_height as f64 * size.y * image.extent()[0] as f64)).unwrap() {
-                        Ordering::Greater => (image_height as f64 * size.y * image.extent()[0] as f64 / image.extent()[1] as f64, image_height as f64 * size.y),
-                        Ordering::Equal => (image_width as f64 * size.x, image_height as f64 * size.y),
-                        Ordering::Less => (image_width as f64 * size.x, image_width as f64 * size.x * image.extent()[1] as f64 / image.extent()[0] as f64),
-                    };
-                    scale_mat(Vector3::new(image_native_size.0 / image_size_request.width as f64, image_native_size.1 / image_size_request.height as f64, 1.))
-                        * move_mat(-scale_center)
-                        * scale_mat(scale)
-                        * move_mat(scale_center)
-                        * move_mat(-rotate_center)
-                        * Matrix4::from(rotate)
-                        * move_mat(rotate_center)
-                        * move_mat(translate)
-                }
-                ImageRequiredParamsTransformFixed::Free {
-                    left_top: _,
-                    right_top: _,
-                    left_bottom: _,
-                    right_bottom: _,
-                } => todo!(),
+            let Some(transform_mat) = image_transform_mat(&image_param.transform, [image.extent()[0], image.extent()[1]], image_size_request) else {
+                continue;
             };
             let transform_matrix = mat4_into_glam(transform_mat);
             // imageを空間に貼る
@@ -429,7 +393,8 @@ impl Combiner<ImageType> for ImageCombiner {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cgmath::{Quaternion, Zero};
+    use cgmath::{Quaternion, Vector3, Zero};
+    use mpdelta_core::component::parameter::ImageRequiredParamsTransformFixed;
     use vulkano::instance::InstanceCreateInfo;
     use vulkano::Version;
     use vulkano_util::context::{VulkanoConfig, VulkanoContext};